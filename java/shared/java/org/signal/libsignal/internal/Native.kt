@@ -357,6 +357,8 @@ internal object Native {
   public external fun ConnectionProxyConfig_Destroy(handle: ObjectHandle): Unit
   @JvmStatic @Throws(Exception::class)
   public external fun ConnectionProxyConfig_new(scheme: String, host: String, port: Int, username: String?, password: String?): ObjectHandle
+  @JvmStatic @Throws(Exception::class)
+  public external fun ConnectionProxyConfig_new_domain_front(host: String, port: Int, sni: String, httpHost: String?): ObjectHandle
 
   @JvmStatic @Throws(Exception::class)
   public external fun CreateCallLinkCredentialPresentation_CheckValidContents(presentationBytes: ByteArray): Unit
@@ -620,6 +620,7 @@ type NativeFunctions = {
   TokioAsyncContext_new: () => TokioAsyncContext;
   TokioAsyncContext_cancel: (context: Wrapper<TokioAsyncContext>, rawCancellationId: bigint) => void;
   ConnectionProxyConfig_new: (scheme: string, host: string, port: number, username: string | null, password: string | null) => ConnectionProxyConfig;
+  ConnectionProxyConfig_new_domain_front: (host: string, port: number, sni: string, httpHost: string | null) => ConnectionProxyConfig;
   ConnectionManager_new: (environment: number, userAgent: string, remoteConfig: Wrapper<BridgedStringMap>, buildVariant: number) => ConnectionManager;
   ConnectionManager_set_proxy: (connectionManager: Wrapper<ConnectionManager>, proxy: Wrapper<ConnectionProxyConfig>) => void;
   ConnectionManager_set_invalid_proxy: (connectionManager: Wrapper<ConnectionManager>) => void;
@@ -1172,6 +1173,7 @@ const { registerErrors,
   TokioAsyncContext_new,
   TokioAsyncContext_cancel,
   ConnectionProxyConfig_new,
+  ConnectionProxyConfig_new_domain_front,
   ConnectionManager_new,
   ConnectionManager_set_proxy,
   ConnectionManager_set_invalid_proxy,
@@ -1726,6 +1728,7 @@ export { registerErrors,
   TokioAsyncContext_new,
   TokioAsyncContext_cancel,
   ConnectionProxyConfig_new,
+  ConnectionProxyConfig_new_domain_front,
   ConnectionManager_new,
   ConnectionManager_set_proxy,
   ConnectionManager_set_invalid_proxy,
@@ -1895,7 +1898,7 @@ export interface RegisterAccountResponse { readonly __type: unique symbol; }
 export interface RegistrationAccountAttributes { readonly __type: unique symbol; }
 export interface BackupStoreResponse { readonly __type: unique symbol; }
 export interface BackupRestoreResponse { readonly __type: unique symbol; }
-export const NetRemoteConfigKeys = ['chatRequestConnectionCheckTimeoutMillis', 'chatPermessageDeflate', 'disableNagleAlgorithm', ] as const;
+export const NetRemoteConfigKeys = ['chatRequestConnectionCheckTimeoutMillis', 'chatPermessageDeflate', 'disableNagleAlgorithm', 'dnsServiceBindings', ] as const;
 export interface TokioAsyncContext { readonly __type: unique symbol; }
 export interface ConnectionManager { readonly __type: unique symbol; }
 export interface ConnectionProxyConfig { readonly __type: unique symbol; }
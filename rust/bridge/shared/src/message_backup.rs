@@ -98,6 +98,7 @@ async fn MessageBackupValidator_Validate(
                 let ReadResult {
                     result,
                     found_unknown_fields,
+                    migrations: _,
                 } = reader.validate_all().await;
 
                 (result.err().map(Into::into), found_unknown_fields)
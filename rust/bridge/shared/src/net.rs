@@ -4,6 +4,7 @@
 //
 
 use std::num::NonZeroU16;
+use std::sync::Arc;
 
 use libsignal_bridge_macros::bridge_fn;
 pub use libsignal_bridge_types::net::remote_config::RemoteConfigKey;
@@ -12,8 +13,13 @@ pub use libsignal_bridge_types::net::{
 };
 use libsignal_net::chat::ConnectionInfo;
 use libsignal_net::connect_state::infer_proxy_mode_for_config;
+use libsignal_net::infra::certs::RootCertificates;
 use libsignal_net::infra::errors::LogSafeDisplay;
-use libsignal_net::infra::route::ConnectionProxyConfig;
+use libsignal_net::infra::host::Host;
+use libsignal_net::infra::route::{
+    ConnectionProxyConfig, DomainFront, DomainFrontTransport, PluggableTransportProxy,
+};
+use nonzero_ext::nonzero;
 
 use crate::support::*;
 use crate::*;
@@ -37,27 +43,7 @@ fn ConnectionProxyConfig_new(
     username: Option<String>,
     password: Option<String>,
 ) -> Result<ConnectionProxyConfig, std::io::Error> {
-    // We take port as an i32 because Java 'short' is signed and thus can't represent all port
-    // numbers, and we want too-large port numbers to be handled the same way as 0. However, we
-    // *also* want to have a representation that means "no port provided". We'll use something
-    // unlikely for anyone to have typed manually, especially in decimal: `i32::MIN`. (We're not
-    // using 0 as the placeholder because an explicitly-specified zero should be diagnosed as
-    // invalid.)
-    let port = if port == i32::MIN {
-        None
-    } else {
-        Some(
-            u16::try_from(port)
-                .ok()
-                .and_then(NonZeroU16::new)
-                .ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::InvalidInput,
-                        format!("invalid port '{port}'"),
-                    )
-                })?,
-        )
-    };
+    let port = proxy_port_from_bridge(port)?;
 
     let auth = match (username, password) {
         (None, None) => None,
@@ -90,6 +76,59 @@ fn ConnectionProxyConfig_new(
     })
 }
 
+/// Creates a proxy config that connects through a CDN edge at `host`, presenting `sni` in the TLS
+/// handshake and `http_host` (if present) as the HTTP Host.
+#[bridge_fn]
+fn ConnectionProxyConfig_new_domain_front(
+    host: String,
+    port: i32,
+    sni: String,
+    http_host: Option<String>,
+) -> Result<ConnectionProxyConfig, std::io::Error> {
+    let port = proxy_port_from_bridge(port)?;
+    if host.is_empty() || sni.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "domain fronts need a host and an SNI",
+        ));
+    }
+
+    let front = DomainFront {
+        sni: sni.into(),
+        http_host: http_host.map(Into::into),
+        // CDN edges present certificates for the front, not for Signal.
+        root_certs: Some(RootCertificates::Native),
+    };
+    Ok(PluggableTransportProxy {
+        proxy_host: Host::parse_as_ip_or_domain(&host),
+        proxy_port: port.unwrap_or(nonzero!(443u16)),
+        transport: Arc::new(DomainFrontTransport::new(front)),
+    }
+    .into())
+}
+
+fn proxy_port_from_bridge(port: i32) -> Result<Option<NonZeroU16>, std::io::Error> {
+    // We take port as an i32 because Java 'short' is signed and thus can't represent all port
+    // numbers, and we want too-large port numbers to be handled the same way as 0. However, we
+    // *also* want to have a representation that means "no port provided". We'll use something
+    // unlikely for anyone to have typed manually, especially in decimal: `i32::MIN`. (We're not
+    // using 0 as the placeholder because an explicitly-specified zero should be diagnosed as
+    // invalid.)
+    if port == i32::MIN {
+        return Ok(None);
+    }
+    u16::try_from(port)
+        .ok()
+        .and_then(NonZeroU16::new)
+        .map(Some)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid port '{port}'"),
+            )
+        })
+}
+
 bridge_handle_fns!(ConnectionManager, clone = false);
 
 #[bridge_fn]
@@ -169,4 +208,36 @@ mod test {
         ConnectionProxyConfig_new(scheme.to_owned(), "host".to_owned(), 80, None, None)
             .expect("valid")
     }
+
+    #[test]
+    fn connection_proxy_config_domain_front() {
+        let config = ConnectionProxyConfig_new_domain_front(
+            "cdn.example".to_owned(),
+            i32::MIN,
+            "front.example".to_owned(),
+            Some("chat.example".to_owned()),
+        )
+        .expect("valid");
+        let ConnectionProxyConfig::Pluggable(proxy) = config else {
+            panic!("unexpected config: {config:?}");
+        };
+        assert_eq!(proxy.proxy_host, Host::Domain("cdn.example".into()));
+        assert_eq!(proxy.proxy_port, nonzero!(443u16));
+        assert_eq!(
+            proxy.transport.domain_front(),
+            Some(&DomainFront {
+                sni: "front.example".into(),
+                http_host: Some("chat.example".into()),
+                root_certs: Some(RootCertificates::Native),
+            })
+        );
+
+        ConnectionProxyConfig_new_domain_front(
+            "cdn.example".to_owned(),
+            0,
+            "front.example".to_owned(),
+            None,
+        )
+        .expect_err("invalid port");
+    }
 }
@@ -29,6 +29,7 @@ async fn ComparableBackup_ReadUnencrypted(
     let ReadResult {
         result,
         found_unknown_fields,
+        migrations: _,
     } = reader.read_all().await;
 
     match result {
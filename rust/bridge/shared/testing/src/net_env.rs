@@ -30,6 +30,7 @@ fn localhost_test_domain_config_with_port_and_cert(
             cert: RootCertificates::FromDer(std::borrow::Cow::Owned(root_certificate_der.to_vec())),
             http_version: Some(HttpVersion::Http1_1),
             min_tls_version: None,
+            ech_config_list: None,
             confirmation_header_name: None,
             proxy: None,
         },
@@ -157,6 +157,9 @@ impl ConnectionManager {
         let transport_connector =
             std::sync::Mutex::new(TcpSslConnector::new_direct(dns_resolver.clone()));
         let remote_config = RemoteConfig::new(remote_config, build_variant);
+        dns_resolver.set_service_bindings_enabled(
+            remote_config.is_enabled(RemoteConfigKey::EnableDnsServiceBindings),
+        );
         let endpoints = std::sync::Mutex::new(
             EndpointConnections::new(&env, false, EnforceMinimumTls::Yes).into(),
         );
@@ -168,7 +171,7 @@ impl ConnectionManager {
             connect: ConnectState::new_with_transport_connector(
                 SUGGESTED_CONNECT_CONFIG,
                 PreconnectingFactory::new(
-                    DefaultConnectorFactory,
+                    DefaultConnectorFactory::default(),
                     SUGGESTED_TLS_PRECONNECT_LIFETIME,
                 ),
             ),
@@ -220,8 +223,11 @@ impl ConnectionManager {
         remote_config: HashMap<String, Arc<str>>,
         build_variant: BuildVariant,
     ) {
-        *self.remote_config.lock().expect("not poisoned") =
-            RemoteConfig::new(remote_config, build_variant);
+        let remote_config = RemoteConfig::new(remote_config, build_variant);
+        self.dns_resolver.set_service_bindings_enabled(
+            remote_config.is_enabled(RemoteConfigKey::EnableDnsServiceBindings),
+        );
+        *self.remote_config.lock().expect("not poisoned") = remote_config;
     }
 
     fn tcp_nagle_override(&self) -> OverrideNagleAlgorithm {
@@ -99,6 +99,8 @@ pub enum RemoteConfigKey {
     EnableChatPermessageDeflate => "chatPermessageDeflate",
     /// Whether to disable the Nagle algorithm (sets TCP_NODELAY).
     DisableNagleAlgorithm => "disableNagleAlgorithm",
+    /// Whether to look up HTTPS DNS records, which carry the configs for Encrypted Client Hello.
+    EnableDnsServiceBindings => "dnsServiceBindings",
 }
 }
 
@@ -3,16 +3,18 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::path::PathBuf;
 use std::process::ExitCode;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use clap_stdin::FileOrStdin;
 use futures::future::Either;
 use libsignal_message_backup::FoundUnknownField;
+use libsignal_message_backup::args::parse_hex_bytes;
 use libsignal_message_backup::backup::{CompletedBackup, PartialBackup, Purpose, ValidateOnly};
 use libsignal_message_backup::frame::{FramesReader, ReaderFactory as _};
 use libsignal_message_backup::parse::VarintDelimitedReader;
-use libsignal_message_backup::scramble::Scrambler;
+use libsignal_message_backup::scramble::{MappingTable, ScrambleCategories, Scrambler};
 use libsignal_message_backup::unknown::VisitUnknownFieldsExt as _;
 
 #[path = "../src/bin/support/mod.rs"]
@@ -36,6 +38,47 @@ struct CliArgs {
 
     #[command(flatten)]
     key_args: KeyArgs,
+
+    /// derive replacement identifiers from this key (hex), so that they are consistent across
+    /// backups scrambled with the same key
+    #[arg(long, value_parser=parse_hex_bytes::<32>)]
+    pseudonymization_key: Option<[u8; 32]>,
+
+    /// leave this kind of data unscrambled (may be repeated)
+    #[arg(long, value_enum)]
+    keep: Vec<Category>,
+
+    /// load previously-chosen replacements from this JSON file
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    load_mapping: Option<PathBuf>,
+
+    /// save all chosen replacements to this JSON file
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    save_mapping: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Category {
+    TextBodies,
+    Names,
+    PhoneNumbers,
+    Usernames,
+    AttachmentLocators,
+}
+
+fn categories_to_scramble(keep: &[Category]) -> ScrambleCategories {
+    let mut categories = ScrambleCategories::ALL;
+    for category in keep {
+        let flag = match category {
+            Category::TextBodies => &mut categories.text_bodies,
+            Category::Names => &mut categories.names,
+            Category::PhoneNumbers => &mut categories.phone_numbers,
+            Category::Usernames => &mut categories.usernames,
+            Category::AttachmentLocators => &mut categories.attachment_locators,
+        };
+        *flag = false;
+    }
+    categories
 }
 
 fn main() -> ExitCode {
@@ -50,8 +93,24 @@ fn main() -> ExitCode {
         input,
         purpose,
         key_args,
+        pseudonymization_key,
+        keep,
+        load_mapping,
+        save_mapping,
     } = CliArgs::parse();
 
+    let mut scrambler = match pseudonymization_key {
+        Some(key) => Scrambler::new_keyed(key),
+        None => Scrambler::new(),
+    }
+    .with_categories(categories_to_scramble(&keep));
+    if let Some(path) = load_mapping {
+        let contents = std::fs::read(&path).expect("can read mapping file");
+        let table: MappingTable =
+            serde_json::from_slice(&contents).expect("mapping file is valid JSON");
+        scrambler.load_mapping_table(table);
+    }
+
     let source = input.filename().to_owned();
     let contents = FilenameOrContents::from(input);
     let mut factory = AsyncReaderFactory::from(&contents);
@@ -70,7 +129,6 @@ fn main() -> ExitCode {
         };
 
         let mut reader = VarintDelimitedReader::new(reader);
-        let mut scrambler = Scrambler::new();
         let mut exit_code = ExitCode::SUCCESS;
 
         let raw_backup_info = reader
@@ -180,6 +238,13 @@ fn main() -> ExitCode {
             }
         }
 
+        if let Some(path) = save_mapping {
+            let contents = serde_json::to_vec_pretty(&scrambler.mapping_table())
+                .expect("can serialize mapping");
+            std::fs::write(&path, contents).expect("can write mapping file");
+            log::info!("saved mapping to {path:?}");
+        }
+
         exit_code
     })
 }
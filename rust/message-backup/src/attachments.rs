@@ -0,0 +1,401 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Enumeration of the attachments referenced by a backup.
+//!
+//! Media-tier sync needs to know, for every attachment in a backup, where it can currently be found
+//! (its transit-tier CDN location) and where it should end up (its media ID on the media tier, and
+//! the key used to encrypt it there). [`AttachmentEnumerator`] streams through a backup's frames
+//! and produces an [`AttachmentReference`] for each
+//! [FilePointer](crate::proto::backup::FilePointer) it finds, with all media-tier identifiers
+//! already derived from the backup's media root key.
+//!
+//! Frames are not validated beyond what is needed to find their attachments; use
+//! [`BackupReader::validate_all`] for that.
+
+use futures::AsyncRead;
+use libsignal_account_keys::{BackupKey, MEDIA_ENCRYPTION_KEY_LEN, MEDIA_ID_LEN};
+use protobuf::Message as _;
+
+use crate::frame::VerifyHmac;
+use crate::migrate::Migrator;
+use crate::parse::VarintDelimitedReader;
+use crate::proto::backup as proto;
+use crate::proto::file_pointers::for_each_file_pointer_mut;
+use crate::unknown::PathPart;
+use crate::{BackupReader, Error};
+
+/// An attachment referenced somewhere in a backup.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttachmentReference {
+    /// The index of the frame containing the reference, where 0 is the BackupInfo.
+    pub frame_index: usize,
+    /// The location of the FilePointer within the frame.
+    pub path: Vec<PathPart>,
+    pub content_type: Option<String>,
+    /// The plaintext size of the attachment.
+    pub size: u32,
+    /// Present if the attachment was downloaded by the backup's creator.
+    pub plaintext_hash: Option<Vec<u8>>,
+    /// Present if the attachment can currently be fetched from the transit tier.
+    pub transit: Option<TransitTierLocation>,
+    /// Present if the attachment is eligible for the media tier, which requires it to have been
+    /// downloaded.
+    pub media_tier: Option<MediaTierLocation>,
+    /// Present for attachments that are expected to have a thumbnail on the media tier.
+    pub thumbnail: Option<MediaTierLocation>,
+}
+
+/// Where to fetch an attachment from the transit tier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransitTierLocation {
+    pub cdn_key: String,
+    pub cdn_number: u32,
+    pub upload_timestamp_ms: Option<u64>,
+}
+
+/// Identifiers and key material for an object on the media tier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaTierLocation {
+    pub media_name: String,
+    pub media_id: [u8; MEDIA_ID_LEN],
+    /// The HMAC key followed by the AES-CBC key.
+    pub encryption_key: [u8; MEDIA_ENCRYPTION_KEY_LEN],
+    /// The CDN the object was last uploaded to, if known.
+    pub cdn_number: Option<u32>,
+}
+
+/// Produces an [`AttachmentReference`] for every attachment in a backup.
+///
+/// Create one with [`BackupReader::into_attachments`].
+pub struct AttachmentEnumerator<R> {
+    reader: VarintDelimitedReader<R>,
+    media_root_key: BackupKey,
+    migrator: Migrator,
+    next_frame_index: usize,
+    pending: std::vec::IntoIter<AttachmentReference>,
+}
+
+impl<R: AsyncRead + Unpin + VerifyHmac> BackupReader<R> {
+    /// Reads the BackupInfo frame and prepares to enumerate the rest of the backup's attachments.
+    ///
+    /// Legacy attachment locators are upgraded as with [`BackupReader::with_migrations`] whether or
+    /// not that was requested.
+    pub async fn into_attachments(self) -> Result<AttachmentEnumerator<R>, Error> {
+        let Self {
+            purpose: _,
+            mut reader,
+            migrate: _,
+            visitor: _,
+        } = self;
+
+        let first = reader
+            .read_next()
+            .await
+            .map_err(Error::Parse)?
+            .ok_or(Error::NoFrames)?;
+        let backup_info = proto::BackupInfo::parse_from_bytes(&first)?;
+        let media_root_key = BackupKey(
+            backup_info
+                .mediaRootBackupKey
+                .as_slice()
+                .try_into()
+                .map_err(|_| {
+                    crate::backup::ValidationError::from(
+                        crate::backup::MetadataError::InvalidMediaRootBackupKey(
+                            backup_info.mediaRootBackupKey.len(),
+                        ),
+                    )
+                })?,
+        );
+
+        Ok(AttachmentEnumerator {
+            reader,
+            media_root_key,
+            migrator: Migrator::for_version(backup_info.version),
+            next_frame_index: 1,
+            pending: Vec::new().into_iter(),
+        })
+    }
+}
+
+impl<R: AsyncRead + Unpin + VerifyHmac> AttachmentEnumerator<R> {
+    /// Returns the next attachment, or `None` once the whole backup has been read.
+    ///
+    /// Reaching the end of the backup does not check its HMAC; call [`Self::finish`] for that
+    /// before relying on the results.
+    pub async fn next(&mut self) -> Result<Option<AttachmentReference>, Error> {
+        loop {
+            if let Some(next) = self.pending.next() {
+                return Ok(Some(next));
+            }
+
+            let Some(raw_frame) = self.reader.read_next().await.map_err(Error::Parse)? else {
+                return Ok(None);
+            };
+            let frame_index = self.next_frame_index;
+            self.next_frame_index += 1;
+
+            let mut frame = proto::Frame::parse_from_bytes(&raw_frame)?;
+            _ = self.migrator.migrate_frame(&mut frame);
+
+            let mut found = Vec::new();
+            for_each_file_pointer_mut(&mut frame, &mut |path, pointer| {
+                found.extend(reference_for_pointer(
+                    &self.media_root_key,
+                    frame_index,
+                    path,
+                    pointer,
+                ))
+            });
+            self.pending = found.into_iter();
+        }
+    }
+
+    /// Reads any remaining attachments and checks the backup's HMAC.
+    pub async fn collect_all(mut self) -> Result<Vec<AttachmentReference>, Error> {
+        let mut all = Vec::new();
+        while let Some(next) = self.next().await? {
+            all.push(next);
+        }
+        self.finish().await?;
+        Ok(all)
+    }
+
+    /// Checks that the backup read so far has a valid HMAC.
+    ///
+    /// Any frames that have not yet been read are skipped.
+    pub async fn finish(mut self) -> Result<(), Error> {
+        while self
+            .reader
+            .read_next()
+            .await
+            .map_err(Error::Parse)?
+            .is_some()
+        {}
+        self.reader.into_inner().verify_hmac().await?;
+        Ok(())
+    }
+}
+
+fn reference_for_pointer(
+    media_root_key: &BackupKey,
+    frame_index: usize,
+    path: &[PathPart],
+    pointer: &proto::FilePointer,
+) -> Option<AttachmentReference> {
+    let locator = pointer.locatorInfo.as_ref()?;
+    // The "invalid" locator is encoded as an empty message; there is nothing to fetch.
+    if *locator == proto::file_pointer::LocatorInfo::default() {
+        return None;
+    }
+
+    let proto::file_pointer::LocatorInfo {
+        key,
+        integrityCheck,
+        size,
+        transitCdnKey,
+        transitCdnNumber,
+        transitTierUploadTimestamp,
+        mediaTierCdnNumber,
+        localKey: _,
+        special_fields: _,
+    } = locator;
+
+    let plaintext_hash = match integrityCheck {
+        Some(proto::file_pointer::locator_info::IntegrityCheck::PlaintextHash(hash)) => {
+            Some(hash.clone())
+        }
+        Some(proto::file_pointer::locator_info::IntegrityCheck::EncryptedDigest(_)) | None => None,
+    };
+
+    let transit = match (transitCdnKey, transitCdnNumber) {
+        (Some(cdn_key), Some(cdn_number)) if !cdn_key.is_empty() => Some(TransitTierLocation {
+            cdn_key: cdn_key.clone(),
+            cdn_number: *cdn_number,
+            upload_timestamp_ms: *transitTierUploadTimestamp,
+        }),
+        _ => None,
+    };
+
+    let media_name = plaintext_hash
+        .as_ref()
+        .filter(|_| !key.is_empty())
+        .map(|hash| media_name(hash, key));
+
+    let media_tier = media_name.as_ref().map(|name| {
+        let media_id = media_root_key.derive_media_id(name);
+        MediaTierLocation {
+            media_name: name.clone(),
+            media_id,
+            encryption_key: media_root_key.derive_media_encryption_key_data(&media_id),
+            cdn_number: *mediaTierCdnNumber,
+        }
+    });
+
+    let has_thumbnail = pointer
+        .contentType
+        .as_deref()
+        .is_some_and(content_type_has_thumbnail);
+    let thumbnail = media_name.filter(|_| has_thumbnail).map(|name| {
+        let name = format!("{name}_thumbnail");
+        let media_id = media_root_key.derive_media_id(&name);
+        MediaTierLocation {
+            media_name: name,
+            media_id,
+            encryption_key: media_root_key.derive_thumbnail_transit_encryption_key_data(&media_id),
+            cdn_number: *mediaTierCdnNumber,
+        }
+    });
+
+    Some(AttachmentReference {
+        frame_index,
+        path: path.to_vec(),
+        content_type: pointer.contentType.clone(),
+        size: *size,
+        plaintext_hash,
+        transit,
+        media_tier,
+        thumbnail,
+    })
+}
+
+/// The media name shared by all clients: the hex-encoded plaintext hash followed by the key.
+pub fn media_name(plaintext_hash: &[u8], key: &[u8]) -> String {
+    let mut name = hex::encode(plaintext_hash);
+    name.push_str(&hex::encode(key));
+    name
+}
+
+/// Clients generate media-tier thumbnails for visual media only.
+fn content_type_has_thumbnail(content_type: &str) -> bool {
+    content_type.starts_with("image/") || content_type.starts_with("video/")
+}
+
+#[cfg(test)]
+mod test {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::backup::Purpose;
+
+    const MEDIA_ROOT_KEY: [u8; 32] = [0x99; 32];
+
+    fn write_length_delimited(out: &mut Vec<u8>, message: &impl protobuf::Message) {
+        message
+            .write_length_delimited_to_vec(out)
+            .expect("can serialize");
+    }
+
+    fn attachment_frame(pointer: proto::FilePointer) -> proto::Frame {
+        proto::Frame {
+            item: Some(proto::frame::Item::ChatItem(proto::ChatItem {
+                item: Some(proto::chat_item::Item::StandardMessage(
+                    proto::StandardMessage {
+                        attachments: vec![proto::MessageAttachment {
+                            pointer: Some(pointer).into(),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn enumerates_attachments() {
+        let backup_info = proto::BackupInfo {
+            version: 1,
+            mediaRootBackupKey: MEDIA_ROOT_KEY.to_vec(),
+            ..Default::default()
+        };
+        let downloaded = attachment_frame(proto::FilePointer {
+            contentType: Some("image/jpeg".into()),
+            locatorInfo: Some(proto::file_pointer::LocatorInfo {
+                key: vec![0xaa; 64],
+                integrityCheck: Some(
+                    proto::file_pointer::locator_info::IntegrityCheck::PlaintextHash(vec![
+                        0xbb;
+                        32
+                    ]),
+                ),
+                size: 100,
+                mediaTierCdnNumber: Some(3),
+                ..Default::default()
+            })
+            .into(),
+            ..Default::default()
+        });
+        let transit_only = attachment_frame(proto::FilePointer {
+            contentType: Some("application/pdf".into()),
+            locatorInfo: Some(proto::file_pointer::LocatorInfo {
+                key: vec![0xcc; 64],
+                integrityCheck: Some(
+                    proto::file_pointer::locator_info::IntegrityCheck::EncryptedDigest(vec![
+                        0xdd;
+                        32
+                    ]),
+                ),
+                size: 200,
+                transitCdnKey: Some("transit".into()),
+                transitCdnNumber: Some(2),
+                ..Default::default()
+            })
+            .into(),
+            ..Default::default()
+        });
+        let invalid = attachment_frame(proto::FilePointer {
+            locatorInfo: Some(proto::file_pointer::LocatorInfo::default()).into(),
+            ..Default::default()
+        });
+
+        let mut input = Vec::new();
+        write_length_delimited(&mut input, &backup_info);
+        for frame in [downloaded, transit_only, invalid] {
+            write_length_delimited(&mut input, &frame);
+        }
+        let reader = BackupReader::new_unencrypted(&input[..], Purpose::RemoteBackup);
+        let references = block_on(async { reader.into_attachments().await?.collect_all().await })
+            .expect("valid");
+
+        let media_root_key: BackupKey = BackupKey(MEDIA_ROOT_KEY);
+        let [first, second] = &references[..] else {
+            panic!("unexpected references: {references:#?}");
+        };
+
+        assert_eq!(first.frame_index, 1);
+        assert_eq!(first.plaintext_hash.as_deref(), Some(&[0xbb; 32][..]));
+        assert_eq!(first.transit, None);
+        let media_tier = first.media_tier.as_ref().expect("downloaded");
+        let expected_name = format!("{}{}", "bb".repeat(32), "aa".repeat(64));
+        assert_eq!(media_tier.media_name, expected_name);
+        assert_eq!(
+            media_tier.media_id,
+            media_root_key.derive_media_id(&expected_name)
+        );
+        assert_eq!(
+            media_tier.encryption_key,
+            media_root_key.derive_media_encryption_key_data(&media_tier.media_id)
+        );
+        assert_eq!(media_tier.cdn_number, Some(3));
+        let thumbnail = first.thumbnail.as_ref().expect("image");
+        assert_eq!(thumbnail.media_name, format!("{expected_name}_thumbnail"));
+
+        assert_eq!(second.frame_index, 2);
+        assert_eq!(second.media_tier, None);
+        assert_eq!(second.thumbnail, None);
+        assert_eq!(
+            second.transit,
+            Some(TransitTierLocation {
+                cdn_key: "transit".into(),
+                cdn_number: 2,
+                upload_timestamp_ms: None,
+            })
+        );
+    }
+}
@@ -177,7 +177,7 @@ pub enum Purpose {
     TakeoutExport = 2,
 }
 
-#[derive(Debug, displaydoc::Display, thiserror::Error)]
+#[derive(Debug, displaydoc::Display, thiserror::Error, strum::IntoStaticStr)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum CompletionError {
     /// no AccountData frames found
@@ -450,7 +450,7 @@ impl<M: Method + ReferencedTypes> CompletedBackup<M> {
     }
 }
 
-#[derive(Debug, displaydoc::Display, thiserror::Error)]
+#[derive(Debug, displaydoc::Display, thiserror::Error, strum::IntoStaticStr)]
 pub enum ValidationError {
     /// Frame.item is a oneof but has no value
     EmptyFrame,
@@ -0,0 +1,275 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Machine-readable validation output, one JSON object per line.
+//!
+//! Unlike the default text mode, validation continues past invalid frames so that every error in a
+//! backup is reported. Each record has a `type` field:
+//!
+//! - `error`: `frame_index`, `path` (a list of path parts), `kind`, `message`, and `fatal`. Fatal
+//!   errors are the ones that stopped processing (or happened once it was done); non-fatal errors
+//!   are for individual frames that were skipped.
+//! - `unknown_field`: `frame_index`, `path`, and `value`.
+//! - `migration`: `frame_index`, `path`, and `rewrite` (only with `--migrate`).
+//! - `summary`: always the last record, with counts of the above and whether the backup is valid.
+
+use std::io::Write;
+
+use futures::AsyncRead;
+use libsignal_message_backup::backup::method::ValidateOnly;
+use libsignal_message_backup::backup::{BackupMeta, CompletedBackup, PartialBackup, Purpose};
+use libsignal_message_backup::frame::VerifyHmac;
+use libsignal_message_backup::migrate::{MigrationRecord, Migrator};
+use libsignal_message_backup::parse::VarintDelimitedReader;
+use libsignal_message_backup::unknown::PathPart;
+use libsignal_message_backup::{Error, FoundUnknownField};
+use serde_json::json;
+
+pub(crate) struct JsonLinesReport<W> {
+    out: W,
+    frame_count: usize,
+    error_count: usize,
+    unknown_field_count: usize,
+    migration_count: usize,
+    fatal: bool,
+}
+
+impl<W: Write> JsonLinesReport<W> {
+    pub(crate) fn new(out: W) -> Self {
+        Self {
+            out,
+            frame_count: 0,
+            error_count: 0,
+            unknown_field_count: 0,
+            migration_count: 0,
+            fatal: false,
+        }
+    }
+
+    /// Validates every frame in `reader`, writing records as it goes.
+    ///
+    /// Returns whether the backup was valid.
+    pub(crate) async fn validate(
+        mut self,
+        reader: impl AsyncRead + Unpin + VerifyHmac,
+        purpose: Purpose,
+        migrate: bool,
+    ) -> bool {
+        self.validate_frames(VarintDelimitedReader::new(reader), purpose, migrate)
+            .await;
+        self.write_summary()
+    }
+
+    async fn validate_frames<R: AsyncRead + Unpin + VerifyHmac>(
+        &mut self,
+        mut reader: VarintDelimitedReader<R>,
+        purpose: Purpose,
+        migrate: bool,
+    ) {
+        let raw_backup_info = match reader.read_next().await {
+            Ok(Some(raw)) => raw,
+            Ok(None) => return self.write_error(0, &[], &Error::NoFrames, true),
+            Err(e) => return self.write_error(0, &[], &Error::Parse(e), true),
+        };
+        self.frame_count += 1;
+
+        let mut backup =
+            match PartialBackup::<ValidateOnly>::by_parsing(&raw_backup_info, purpose, |_| {}) {
+                Ok(backup) => backup,
+                Err(e) => return self.write_error(0, &[], &e, true),
+            };
+        let migrator = migrate.then(|| {
+            let meta: &BackupMeta = backup.as_ref();
+            Migrator::for_version(meta.version)
+        });
+
+        loop {
+            let raw_frame = match reader.read_next().await {
+                Ok(Some(raw)) => raw,
+                Ok(None) => break,
+                Err(e) => return self.write_error(self.frame_count, &[], &Error::Parse(e), true),
+            };
+            let frame_index = self.frame_count;
+            self.frame_count += 1;
+
+            // Remember which kind of frame this was so errors can point at it.
+            let mut item_name = None;
+            let result = match &migrator {
+                None => backup
+                    .parse_and_add_frame(&raw_frame, |frame| item_name = frame.item_field_name())
+                    .map(|unknown| (unknown, vec![])),
+                Some(migrator) => {
+                    backup.parse_migrate_and_add_frame(&raw_frame, migrator, |frame| {
+                        item_name = frame.item_field_name()
+                    })
+                }
+            };
+            match result {
+                Ok((unknown_fields, migrations)) => {
+                    for record in migrations
+                        .into_iter()
+                        .map(MigrationRecord::in_frame(frame_index))
+                    {
+                        self.write_migration(record);
+                    }
+                    for field in unknown_fields
+                        .into_iter()
+                        .map(FoundUnknownField::in_frame(frame_index))
+                    {
+                        self.write_unknown_field(field);
+                    }
+                }
+                Err(e) => {
+                    let path = item_name
+                        .map(|field_name| PathPart::Field {
+                            field_name: field_name.to_owned(),
+                        })
+                        .into_iter()
+                        .collect::<Vec<_>>();
+                    self.write_error(frame_index, &path, &e, false);
+                }
+            }
+        }
+
+        if let Err(e) = reader.into_inner().verify_hmac().await {
+            return self.write_error(self.frame_count, &[], &e.into(), true);
+        }
+
+        if let Err(e) = CompletedBackup::try_from(backup) {
+            self.write_error(self.frame_count, &[], &e.into(), true);
+        }
+    }
+
+    fn write_error(&mut self, frame_index: usize, path: &[PathPart], error: &Error, fatal: bool) {
+        self.error_count += 1;
+        self.fatal |= fatal;
+        self.write_record(json!({
+            "type": "error",
+            "frame_index": frame_index,
+            "path": path_to_json(path),
+            "kind": error.kind(),
+            "message": error.to_string(),
+            "fatal": fatal,
+        }));
+    }
+
+    fn write_unknown_field(&mut self, field: FoundUnknownField) {
+        self.unknown_field_count += 1;
+        let FoundUnknownField {
+            frame_index,
+            path,
+            value,
+        } = field;
+        self.write_record(json!({
+            "type": "unknown_field",
+            "frame_index": frame_index,
+            "path": path_to_json(&path),
+            "value": value.to_string(),
+        }));
+    }
+
+    fn write_migration(&mut self, record: MigrationRecord) {
+        self.migration_count += 1;
+        let MigrationRecord {
+            frame_index,
+            path,
+            rewrite,
+        } = record;
+        self.write_record(json!({
+            "type": "migration",
+            "frame_index": frame_index,
+            "path": path_to_json(&path),
+            "rewrite": rewrite.to_string(),
+        }));
+    }
+
+    fn write_summary(mut self) -> bool {
+        let valid = self.error_count == 0;
+        self.write_record(json!({
+            "type": "summary",
+            "frames": self.frame_count,
+            "errors": self.error_count,
+            "fatal": self.fatal,
+            "unknown_fields": self.unknown_field_count,
+            "migrations": self.migration_count,
+            "valid": valid,
+        }));
+        valid
+    }
+
+    fn write_record(&mut self, record: serde_json::Value) {
+        serde_json::to_writer(&mut self.out, &record).expect("can write output");
+        writeln!(self.out).expect("can write output");
+    }
+}
+
+fn path_to_json(path: &[PathPart]) -> Vec<String> {
+    path.iter().map(ToString::to_string).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use futures::io::Cursor;
+    use libsignal_message_backup::frame::UnvalidatedHmacReader;
+
+    use super::*;
+
+    const CANONICAL_BACKUP: &[u8] = include_bytes!("../../../tests/res/canonical-backup.binproto");
+
+    fn run(backup: &[u8]) -> (bool, Vec<String>) {
+        let mut out = Vec::new();
+        let valid = futures::executor::block_on(JsonLinesReport::new(&mut out).validate(
+            UnvalidatedHmacReader::new(Cursor::new(backup)),
+            Purpose::RemoteBackup,
+            false,
+        ));
+        let lines = String::from_utf8(out)
+            .expect("output is UTF-8")
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect();
+        (valid, lines)
+    }
+
+    #[test]
+    fn valid_backup() {
+        let (valid, lines) = run(CANONICAL_BACKUP);
+        assert!(valid);
+        assert_eq!(
+            lines,
+            [
+                r#"{"type":"summary","frames":6,"errors":0,"fatal":false,"unknown_fields":0,"migrations":0,"valid":true}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_frame_is_reported_and_skipped() {
+        // An empty frame (length 0) has no item.
+        let backup = [CANONICAL_BACKUP, &[0]].concat();
+        let (valid, lines) = run(&backup);
+        assert!(!valid);
+        assert_eq!(
+            lines,
+            [
+                r#"{"type":"error","frame_index":6,"path":[],"kind":"EmptyFrame","message":"Frame.item is a oneof but has no value","fatal":false}"#,
+                r#"{"type":"summary","frames":7,"errors":1,"fatal":false,"unknown_fields":0,"migrations":0,"valid":false}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_input_is_fatal() {
+        let (valid, lines) = run(&[]);
+        assert!(!valid);
+        assert_eq!(
+            lines,
+            [
+                r#"{"type":"error","frame_index":0,"path":[],"kind":"NoFrames","message":"no frames found","fatal":true}"#,
+                r#"{"type":"summary","frames":0,"errors":1,"fatal":true,"unknown_fields":0,"migrations":0,"valid":false}"#,
+            ]
+        );
+    }
+}
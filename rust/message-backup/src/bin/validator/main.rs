@@ -9,11 +9,13 @@ use libsignal_message_backup::backup::Purpose;
 use libsignal_message_backup::frame::{
     FramesReader, ReaderFactory as _, UnvalidatedHmacReader, VerifyHmac,
 };
+use libsignal_message_backup::migrate::MigrationRecord;
 use libsignal_message_backup::{BackupReader, Error, FoundUnknownField, ReadResult};
 
 use crate::args::ParseVerbosity;
 
 mod args;
+mod json_lines;
 
 #[path = "../support/mod.rs"]
 mod support;
@@ -43,10 +45,26 @@ struct Cli {
     #[arg(long, default_value_t=Purpose::RemoteBackup)]
     purpose: Purpose,
 
+    /// when set, legacy representations from older clients are upgraded before validation
+    #[arg(long)]
+    migrate: bool,
+
+    /// how validation results are reported
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
+
     #[command(flatten)]
     key_args: KeyArgs,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// human-readable output on stderr; stops at the first error
+    Text,
+    /// one JSON object per line on stdout, reporting every error found
+    JsonLines,
+}
+
 fn main() {
     futures::executor::block_on(async_main())
 }
@@ -58,6 +76,8 @@ async fn async_main() {
         purpose,
         print,
         verbose,
+        migrate,
+        output_format,
     } = Cli::parse();
     env_logger::init();
 
@@ -70,6 +90,23 @@ async fn async_main() {
     let contents = FilenameOrContents::from(file_or_stdin);
     let mut factory = AsyncReaderFactory::from(&contents);
 
+    if output_format == OutputFormat::JsonLines {
+        let report = json_lines::JsonLinesReport::new(std::io::stdout().lock());
+        let valid = if let Some(key) = key {
+            let reader = FramesReader::new(&key, factory)
+                .await
+                .unwrap_or_else(|e| panic!("invalid encrypted backup: {e:#}"));
+            report.validate(reader, purpose, migrate).await
+        } else {
+            let reader = UnvalidatedHmacReader::new(factory.make_reader().expect("failed to read"));
+            report.validate(reader, purpose, migrate).await
+        };
+        if !valid {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let reader = if let Some(key) = key {
         MaybeEncryptedBackupReader::EncryptedCompressed(Box::new(
             BackupReader::new_encrypted_compressed(&key, factory, purpose)
@@ -84,7 +121,7 @@ async fn async_main() {
     };
 
     reader
-        .execute(print, verbosity)
+        .execute(print, verbosity, MigrateLegacy(migrate))
         .await
         .unwrap_or_else(|e| panic!("backup error: {e:#}"));
 }
@@ -97,21 +134,34 @@ enum MaybeEncryptedBackupReader<R: AsyncRead + Unpin> {
 
 struct PrintOutput(bool);
 
+struct MigrateLegacy(bool);
+
 impl<R: AsyncRead + Unpin> MaybeEncryptedBackupReader<R> {
-    async fn execute(self, print: PrintOutput, verbosity: ParseVerbosity) -> Result<(), Error> {
+    async fn execute(
+        self,
+        print: PrintOutput,
+        verbosity: ParseVerbosity,
+        migrate: MigrateLegacy,
+    ) -> Result<(), Error> {
         async fn validate(
             mut backup_reader: BackupReader<impl AsyncRead + Unpin + VerifyHmac>,
             PrintOutput(print): PrintOutput,
             verbosity: ParseVerbosity,
+            MigrateLegacy(migrate): MigrateLegacy,
         ) -> Result<(), Error> {
             if let Some(visitor) = verbosity.into_visitor() {
                 backup_reader.visitor = visitor;
             }
+            if migrate {
+                backup_reader = backup_reader.with_migrations();
+            }
             let ReadResult {
                 found_unknown_fields,
+                migrations,
                 result,
             } = backup_reader.read_all().await;
 
+            print_migrations(migrations);
             print_unknown_fields(found_unknown_fields);
             let backup = result?;
 
@@ -122,12 +172,23 @@ impl<R: AsyncRead + Unpin> MaybeEncryptedBackupReader<R> {
         }
 
         match self {
-            Self::EncryptedCompressed(reader) => validate(*reader, print, verbosity).await,
-            Self::PlaintextBinproto(reader) => validate(reader, print, verbosity).await,
+            Self::EncryptedCompressed(reader) => validate(*reader, print, verbosity, migrate).await,
+            Self::PlaintextBinproto(reader) => validate(reader, print, verbosity, migrate).await,
         }
     }
 }
 
+fn print_migrations(migrations: Vec<MigrationRecord>) {
+    if migrations.is_empty() {
+        return;
+    }
+
+    eprintln!("some values were in a legacy format; applied the following migrations:");
+    for migration in migrations {
+        eprintln!("{migration}");
+    }
+}
+
 fn print_unknown_fields(found_unknown_fields: Vec<FoundUnknownField>) {
     if found_unknown_fields.is_empty() {
         return;
@@ -167,6 +228,8 @@ mod test {
             verbose: 0,
             print: false,
             purpose: Purpose::RemoteBackup,
+            migrate: false,
+            output_format: OutputFormat::Text,
             key_args: KeyArgs {
                 derive_key: DeriveKey { account_entropy: None, aci: None, forward_secrecy_token: None },
                 key_parts: KeyParts { hmac_key: None, aes_key: None }
@@ -193,6 +256,8 @@ mod test {
             verbose: 0,
             print: false,
             purpose: Purpose::RemoteBackup,
+            migrate: false,
+            output_format: OutputFormat::Text,
             key_args: KeyArgs {
                 derive_key,
                 key_parts: KeyParts { hmac_key: None, aes_key: None }
@@ -225,6 +290,8 @@ mod test {
             verbose: 0,
             print: false,
             purpose: Purpose::RemoteBackup,
+            migrate: false,
+            output_format: OutputFormat::Text,
             key_args: KeyArgs {
                 derive_key: DeriveKey { account_entropy: None, aci: None, forward_secrecy_token: None },
                 key_parts,
@@ -312,4 +379,12 @@ mod test {
         let cli = Cli::try_parse_from(input).expect("parse failed");
         assert_eq!(cli.purpose, expected_purpose);
     }
+
+    #[test_case("text", OutputFormat::Text; "text")]
+    #[test_case("json-lines", OutputFormat::JsonLines; "json lines")]
+    fn cli_parse_output_format(format_flag: &str, expected_format: OutputFormat) {
+        let input = [EXECUTABLE_NAME, "filename", "--output-format", format_flag];
+        let cli = Cli::try_parse_from(input).expect("parse failed");
+        assert_eq!(cli.output_format, expected_format);
+    }
 }
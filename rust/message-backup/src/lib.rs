@@ -21,13 +21,16 @@ use crate::frame::{
     HmacMismatchError, ReaderFactory, UnvalidatedHmacReader, VerifyHmac, VerifyHmacError,
 };
 use crate::key::MessageBackupKey;
+use crate::migrate::{MigrationRecord, Migrator};
 use crate::parse::VarintDelimitedReader;
 use crate::unknown::{FormatPath, PathPart, UnknownValue, VisitUnknownFieldsExt as _};
 
 pub mod args;
+pub mod attachments;
 pub mod backup;
 pub mod frame;
 pub mod key;
+pub mod migrate;
 pub mod parse;
 pub mod unknown;
 
@@ -49,6 +52,7 @@ pub mod scramble;
 pub struct BackupReader<R> {
     purpose: Purpose,
     reader: VarintDelimitedReader<R>,
+    migrate: bool,
     pub visitor: fn(&dyn std::fmt::Debug),
 }
 
@@ -68,10 +72,29 @@ pub enum Error {
     HmacMismatch(#[from] HmacMismatchError),
 }
 
+impl Error {
+    /// A short, stable name for the kind of error, suitable for aggregating across many backups.
+    ///
+    /// For validation and completion errors, this is the name of the specific error variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::BackupValidation(e) => e.into(),
+            Error::BackupCompletion(e) => e.into(),
+            Error::Parse(_) => "Parse",
+            Error::NoFrames => "NoFrames",
+            Error::InvalidProtobuf(_) => "InvalidProtobuf",
+            Error::HmacMismatch(_) => "HmacMismatch",
+        }
+    }
+}
+
 #[must_use]
 pub struct ReadResult<B> {
     pub result: Result<B, Error>,
     pub found_unknown_fields: Vec<FoundUnknownField>,
+    /// Rewrites of legacy representations, if the reader was created with
+    /// [`BackupReader::with_migrations`].
+    pub migrations: Vec<MigrationRecord>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -152,14 +175,28 @@ impl<R> ReadResult<R> {
         let Self {
             result,
             found_unknown_fields,
+            migrations,
         } = self;
         ReadResult {
             found_unknown_fields,
+            migrations,
             result: result.and_then(f),
         }
     }
 }
 
+impl<R> BackupReader<R> {
+    /// Upgrades frames written in legacy formats before validating them.
+    ///
+    /// Each rewrite is reported in [`ReadResult::migrations`]. See [`migrate`] for details.
+    pub fn with_migrations(self) -> Self {
+        Self {
+            migrate: true,
+            ..self
+        }
+    }
+}
+
 impl<R: AsyncRead + Unpin + VerifyHmac> BackupReader<R> {
     pub async fn read_all(self) -> ReadResult<backup::CompletedBackup<Store>> {
         self.collect_all()
@@ -184,12 +221,22 @@ impl<R: AsyncRead + Unpin + VerifyHmac> BackupReader<R> {
             reader,
             visitor,
             purpose,
+            migrate,
         } = self;
 
         let mut found_unknown_fields = Vec::new();
-        let result = read_all_frames(purpose, reader, visitor, &mut found_unknown_fields).await;
+        let mut migrations = Vec::new();
+        let result = read_all_frames(
+            purpose,
+            reader,
+            visitor,
+            &mut found_unknown_fields,
+            migrate.then_some(&mut migrations),
+        )
+        .await;
         ReadResult {
             found_unknown_fields,
+            migrations,
             result,
         }
     }
@@ -201,6 +248,7 @@ impl<R: AsyncRead + Unpin> BackupReader<UnvalidatedHmacReader<R>> {
         Self {
             reader,
             purpose,
+            migrate: false,
             visitor: |_| (),
         }
     }
@@ -216,6 +264,7 @@ impl<R: AsyncRead + AsyncSkip + Unpin> BackupReader<frame::FramesReader<R>> {
         Ok(Self {
             reader: VarintDelimitedReader::new(reader),
             purpose,
+            migrate: false,
             visitor: |_| (),
         })
     }
@@ -226,6 +275,7 @@ async fn read_all_frames<M: backup::method::Method + backup::ReferencedTypes>(
     mut reader: VarintDelimitedReader<impl AsyncRead + Unpin + VerifyHmac>,
     mut visitor: impl FnMut(&dyn std::fmt::Debug) + Send + 'static,
     unknown_fields: &mut Vec<FoundUnknownField>,
+    migrations: Option<&mut Vec<MigrationRecord>>,
 ) -> Result<backup::PartialBackup<M>, Error>
 where
     backup::PartialBackup<M>: Send,
@@ -248,6 +298,10 @@ where
     visitor(&backup_info);
     add_found_unknown(unknown_fields, backup_info.collect_unknown_fields(), 0);
 
+    let migrator = migrations
+        .is_some()
+        .then(|| Migrator::for_version(backup_info.version));
+
     let mut backup = backup::PartialBackup::new(backup_info, purpose)?;
 
     // From here on we split the work into two separate threads:
@@ -265,6 +319,7 @@ where
         .name("libsignal-backup-processing".to_owned())
         .spawn(move || {
             let mut unknown_fields = vec![];
+            let mut migrations = vec![];
             let mut frame_index = 1;
 
             // Continue until all frames have been read from the stream...
@@ -274,7 +329,7 @@ where
                         Ok(frame) => break frame,
                         Err(std::sync::mpsc::TryRecvError::Disconnected) => {
                             // ...as signalled by the sender being dropped.
-                            return Ok::<_, Error>((backup, unknown_fields));
+                            return Ok::<_, Error>((backup, unknown_fields, migrations));
                         }
                         Err(std::sync::mpsc::TryRecvError::Empty) => {
                             // Rather than doing a blocking read, just sleep quickly to let the
@@ -287,8 +342,21 @@ where
                     }
                 };
 
-                let these_unknown_fields =
-                    backup.parse_and_add_frame(&frame, |frame| visitor(frame))?;
+                let these_unknown_fields = match &migrator {
+                    None => backup.parse_and_add_frame(&frame, |frame| visitor(frame))?,
+                    Some(migrator) => {
+                        let (these_unknown_fields, these_migrations) = backup
+                            .parse_migrate_and_add_frame(&frame, migrator, |frame| {
+                                visitor(frame)
+                            })?;
+                        migrations.extend(
+                            these_migrations
+                                .into_iter()
+                                .map(MigrationRecord::in_frame(frame_index)),
+                        );
+                        these_unknown_fields
+                    }
+                };
                 add_found_unknown(&mut unknown_fields, these_unknown_fields, frame_index);
                 frame_index += 1;
             }
@@ -317,12 +385,15 @@ where
     // Let the frame-processing thread know there's nothing more to read.
     drop(frame_tx);
 
-    let (backup, inner_unknown_fields) = match frame_processing_thread.join() {
+    let (backup, inner_unknown_fields, inner_migrations) = match frame_processing_thread.join() {
         Ok(Ok(success)) => success,
         Ok(Err(validation_error)) => return Err(validation_error),
         Err(panic) => std::panic::resume_unwind(panic),
     };
     unknown_fields.extend(inner_unknown_fields);
+    if let Some(migrations) = migrations {
+        migrations.extend(inner_migrations);
+    }
 
     // Before reporting success, check that the HMAC still matches. This
     // prevents TOC/TOU issues.
@@ -369,6 +440,32 @@ impl<M: backup::method::Method + backup::ReferencedTypes> backup::PartialBackup<
         self.add_frame(frame_proto)?;
         Ok(unknown_fields)
     }
+
+    /// Like [`Self::parse_and_add_frame`], but first rewrites any legacy representations using
+    /// `migrator`.
+    ///
+    /// The visitor sees the migrated frame. Returns the unknown fields remaining after migration,
+    /// along with the rewrites that were performed.
+    pub fn parse_migrate_and_add_frame(
+        &mut self,
+        raw_frame: &[u8],
+        migrator: &Migrator,
+        mut visitor: impl FnMut(&proto::backup::Frame) + Send,
+    ) -> Result<
+        (
+            Vec<(Vec<PathPart>, UnknownValue)>,
+            Vec<(Vec<PathPart>, migrate::Rewrite)>,
+        ),
+        crate::Error,
+    > {
+        let mut frame_proto = proto::backup::Frame::new();
+        frame_proto.merge_from_bytes(raw_frame)?;
+        let rewrites = migrator.migrate_frame(&mut frame_proto);
+        visitor(&frame_proto);
+        let unknown_fields = frame_proto.collect_unknown_fields();
+        self.add_frame(frame_proto)?;
+        Ok((unknown_fields, rewrites))
+    }
 }
 
 impl From<VerifyHmacError> for Error {
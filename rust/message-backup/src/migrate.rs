@@ -0,0 +1,445 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Upgrades of deprecated representations found in backups from older clients.
+//!
+//! Earlier client releases wrote some data in shapes that have since been removed from
+//! `backup.proto`. When parsed with the current schema, those fields show up as unknown fields, and
+//! the data they carried appears to be missing, which can make an otherwise-fine backup fail
+//! validation. A [`Migrator`] recognizes these shapes and rewrites them into the current form
+//! before validation, reporting each rewrite so the caller can tell what happened.
+
+use protobuf::{Message as _, UnknownValueRef};
+
+use crate::proto::file_pointers::for_each_file_pointer_mut;
+use crate::proto::{backup as proto, legacy};
+use crate::unknown::{FormatPath, PathPart};
+
+/// A single rewrite performed by a [`Migrator`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MigrationRecord {
+    pub frame_index: usize,
+    pub path: Vec<PathPart>,
+    pub rewrite: Rewrite,
+}
+
+impl MigrationRecord {
+    /// Convenience method for mapping over an iterator of rewrites from the same frame.
+    ///
+    /// Note that this *returns* a function that you then pass to `map`.
+    pub fn in_frame(frame_index: usize) -> impl Fn((Vec<PathPart>, Rewrite)) -> Self {
+        move |(path, rewrite)| Self {
+            frame_index,
+            path,
+            rewrite,
+        }
+    }
+}
+
+impl std::fmt::Display for MigrationRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            frame_index,
+            path,
+            rewrite,
+        } = self;
+        write!(
+            f,
+            "in frame {frame_index}, {}: {rewrite}",
+            FormatPath(path.as_slice())
+        )
+    }
+}
+
+/// The kinds of rewrites a [`Migrator`] can perform.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, displaydoc::Display)]
+pub enum Rewrite {
+    /// converted legacy backupLocator to locatorInfo
+    LegacyBackupLocator,
+    /// converted legacy attachmentLocator to locatorInfo
+    LegacyAttachmentLocator,
+    /// converted legacy invalidAttachmentLocator to an empty locatorInfo
+    LegacyInvalidAttachmentLocator,
+    /// converted legacy localLocator to locatorInfo
+    LegacyLocalLocator,
+    /// dropped legacy backupsSubscriberData (no IAP subscription ID)
+    DroppedLegacyBackupsSubscriberData,
+}
+
+/// One upgrade step, applied to each frame of a backup.
+trait FrameMigration: Sync {
+    /// Whether backups with the given version may contain the shape this migration upgrades.
+    fn applies_to_version(&self, version: u64) -> bool;
+
+    /// Rewrites `frame` in place, recording each change in `rewrites`.
+    fn migrate(&self, frame: &mut proto::Frame, rewrites: &mut Vec<(Vec<PathPart>, Rewrite)>);
+}
+
+const ALL_MIGRATIONS: &[&dyn FrameMigration] =
+    &[&FilePointerLegacyLocator, &AccountDataLegacySubscriberData];
+
+/// Applies every migration relevant to a particular backup version.
+pub struct Migrator {
+    migrations: Vec<&'static dyn FrameMigration>,
+}
+
+impl Migrator {
+    /// Selects the migrations that apply to backups with the given [`proto::BackupInfo`] version.
+    pub fn for_version(version: u64) -> Self {
+        Self {
+            migrations: ALL_MIGRATIONS
+                .iter()
+                .copied()
+                .filter(|m| m.applies_to_version(version))
+                .collect(),
+        }
+    }
+
+    /// Rewrites `frame` into the current format, returning what was changed.
+    pub fn migrate_frame(&self, frame: &mut proto::Frame) -> Vec<(Vec<PathPart>, Rewrite)> {
+        let mut rewrites = Vec::new();
+        for migration in &self.migrations {
+            migration.migrate(frame, &mut rewrites);
+        }
+        rewrites
+    }
+}
+
+/// All backups written so far use version 1, including those from clients that used the legacy
+/// shapes, so every migration currently applies to it.
+const LAST_VERSION_WITH_LEGACY_SHAPES: u64 = 1;
+
+/// `FilePointer.locator` was a oneof of several locator messages before it was replaced by
+/// `FilePointer.locatorInfo`.
+struct FilePointerLegacyLocator;
+
+impl FilePointerLegacyLocator {
+    const BACKUP_LOCATOR_TAG: u32 = 1;
+    const ATTACHMENT_LOCATOR_TAG: u32 = 2;
+    const INVALID_ATTACHMENT_LOCATOR_TAG: u32 = 3;
+    const LOCAL_LOCATOR_TAG: u32 = 12;
+
+    fn convert(pointer: &mut proto::FilePointer) -> Option<Rewrite> {
+        let unknown = pointer.special_fields.unknown_fields();
+        let (tag, rewrite, locator_info) = [
+            Self::BACKUP_LOCATOR_TAG,
+            Self::ATTACHMENT_LOCATOR_TAG,
+            Self::INVALID_ATTACHMENT_LOCATOR_TAG,
+            Self::LOCAL_LOCATOR_TAG,
+        ]
+        .into_iter()
+        .find_map(|tag| {
+            let UnknownValueRef::LengthDelimited(bytes) = unknown.get(tag)? else {
+                return None;
+            };
+            let (rewrite, locator_info) = match tag {
+                Self::BACKUP_LOCATOR_TAG => (
+                    Rewrite::LegacyBackupLocator,
+                    legacy::file_pointer::BackupLocator::parse_from_bytes(bytes)
+                        .ok()?
+                        .into(),
+                ),
+                Self::ATTACHMENT_LOCATOR_TAG => (
+                    Rewrite::LegacyAttachmentLocator,
+                    legacy::file_pointer::AttachmentLocator::parse_from_bytes(bytes)
+                        .ok()?
+                        .into(),
+                ),
+                Self::INVALID_ATTACHMENT_LOCATOR_TAG => (
+                    Rewrite::LegacyInvalidAttachmentLocator,
+                    proto::file_pointer::LocatorInfo::default(),
+                ),
+                Self::LOCAL_LOCATOR_TAG => (
+                    Rewrite::LegacyLocalLocator,
+                    legacy::file_pointer::LocalLocator::parse_from_bytes(bytes)
+                        .ok()?
+                        .into(),
+                ),
+                _ => unreachable!("only iterating over known tags"),
+            };
+            Some((tag, rewrite, locator_info))
+        })?;
+
+        pointer.special_fields.mut_unknown_fields().remove(tag);
+        pointer.locatorInfo = Some(locator_info).into();
+        Some(rewrite)
+    }
+}
+
+impl FrameMigration for FilePointerLegacyLocator {
+    fn applies_to_version(&self, version: u64) -> bool {
+        version <= LAST_VERSION_WITH_LEGACY_SHAPES
+    }
+
+    fn migrate(&self, frame: &mut proto::Frame, rewrites: &mut Vec<(Vec<PathPart>, Rewrite)>) {
+        for_each_file_pointer_mut(frame, &mut |path, pointer| {
+            // A pointer that already has a locatorInfo was written by a current client; any
+            // lingering legacy fields are left to be reported as unknown.
+            if pointer.locatorInfo.is_some() {
+                return;
+            }
+            if let Some(rewrite) = Self::convert(pointer) {
+                rewrites.push((path.to_vec(), rewrite));
+            }
+        });
+    }
+}
+
+impl From<legacy::file_pointer::BackupLocator> for proto::file_pointer::LocatorInfo {
+    fn from(value: legacy::file_pointer::BackupLocator) -> Self {
+        let legacy::file_pointer::BackupLocator {
+            mediaName: _,
+            cdnNumber,
+            key,
+            digest,
+            size,
+            transitCdnKey,
+            transitCdnNumber,
+            special_fields: _,
+        } = value;
+        Self {
+            key,
+            integrityCheck: Some(
+                proto::file_pointer::locator_info::IntegrityCheck::EncryptedDigest(digest),
+            ),
+            size,
+            transitCdnKey,
+            transitCdnNumber,
+            mediaTierCdnNumber: cdnNumber,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<legacy::file_pointer::AttachmentLocator> for proto::file_pointer::LocatorInfo {
+    fn from(value: legacy::file_pointer::AttachmentLocator) -> Self {
+        let legacy::file_pointer::AttachmentLocator {
+            cdnKey,
+            cdnNumber,
+            uploadTimestamp,
+            key,
+            digest,
+            size,
+            special_fields: _,
+        } = value;
+        Self {
+            key,
+            integrityCheck: Some(
+                proto::file_pointer::locator_info::IntegrityCheck::EncryptedDigest(digest),
+            ),
+            size,
+            transitCdnKey: Some(cdnKey),
+            transitCdnNumber: Some(cdnNumber),
+            transitTierUploadTimestamp: uploadTimestamp,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<legacy::file_pointer::LocalLocator> for proto::file_pointer::LocatorInfo {
+    fn from(value: legacy::file_pointer::LocalLocator) -> Self {
+        let legacy::file_pointer::LocalLocator {
+            mediaName: _,
+            localKey,
+            remoteKey,
+            remoteDigest,
+            size,
+            backupCdnNumber,
+            transitCdnKey,
+            transitCdnNumber,
+            special_fields: _,
+        } = value;
+        Self {
+            key: remoteKey,
+            integrityCheck: Some(
+                proto::file_pointer::locator_info::IntegrityCheck::EncryptedDigest(remoteDigest),
+            ),
+            size,
+            transitCdnKey,
+            transitCdnNumber,
+            mediaTierCdnNumber: backupCdnNumber,
+            localKey: Some(localKey),
+            ..Default::default()
+        }
+    }
+}
+
+/// `AccountData.backupsSubscriberData` used to be a plain `SubscriberData` at tag 8. That format
+/// can't identify the IAP subscription, so current importers would ignore it anyway.
+struct AccountDataLegacySubscriberData;
+
+impl AccountDataLegacySubscriberData {
+    const LEGACY_TAG: u32 = 8;
+}
+
+impl FrameMigration for AccountDataLegacySubscriberData {
+    fn applies_to_version(&self, version: u64) -> bool {
+        version <= LAST_VERSION_WITH_LEGACY_SHAPES
+    }
+
+    fn migrate(&self, frame: &mut proto::Frame, rewrites: &mut Vec<(Vec<PathPart>, Rewrite)>) {
+        let Some(proto::frame::Item::Account(account)) = frame.item.as_mut() else {
+            return;
+        };
+        let unknown = account.special_fields.mut_unknown_fields();
+        if unknown.get(Self::LEGACY_TAG).is_none() {
+            return;
+        }
+        unknown.remove(Self::LEGACY_TAG);
+        rewrites.push((
+            vec![PathPart::Field {
+                field_name: "account".to_owned(),
+            }],
+            Rewrite::DroppedLegacyBackupsSubscriberData,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use protobuf::UnknownValue;
+
+    use super::*;
+
+    fn frame_with_attachment(pointer: proto::FilePointer) -> proto::Frame {
+        proto::Frame {
+            item: Some(proto::frame::Item::ChatItem(proto::ChatItem {
+                item: Some(proto::chat_item::Item::StandardMessage(
+                    proto::StandardMessage {
+                        attachments: vec![proto::MessageAttachment {
+                            pointer: Some(pointer).into(),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn pointer_with_legacy_field(tag: u32, message: &impl protobuf::Message) -> proto::FilePointer {
+        let mut pointer = proto::FilePointer {
+            contentType: Some("image/jpeg".into()),
+            ..Default::default()
+        };
+        pointer.special_fields.mut_unknown_fields().add_value(
+            tag,
+            UnknownValue::LengthDelimited(message.write_to_bytes().expect("can serialize")),
+        );
+        pointer
+    }
+
+    fn migrated_pointer(frame: &mut proto::Frame) -> proto::FilePointer {
+        let mut found = None;
+        for_each_file_pointer_mut(frame, &mut |_, pointer| found = Some(pointer.clone()));
+        found.expect("has a pointer")
+    }
+
+    #[test]
+    fn attachment_locator() {
+        let mut frame = frame_with_attachment(pointer_with_legacy_field(
+            FilePointerLegacyLocator::ATTACHMENT_LOCATOR_TAG,
+            &legacy::file_pointer::AttachmentLocator {
+                cdnKey: "ABCDEF".into(),
+                cdnNumber: 3,
+                uploadTimestamp: Some(1000),
+                key: vec![0x11; 64],
+                digest: vec![0x22; 32],
+                size: 123,
+                ..Default::default()
+            },
+        ));
+
+        let rewrites = Migrator::for_version(1).migrate_frame(&mut frame);
+        assert_matches!(&rewrites[..], [(path, Rewrite::LegacyAttachmentLocator)] => {
+            assert_eq!(
+                FormatPath(path.as_slice()).to_string(),
+                "chatItem.standardMessage.attachments[0].pointer"
+            );
+        });
+
+        let pointer = migrated_pointer(&mut frame);
+        assert_eq!(pointer.special_fields.unknown_fields().iter().count(), 0);
+        let locator_info = pointer.locatorInfo.as_ref().expect("migrated");
+        assert_eq!(locator_info.key, vec![0x11; 64]);
+        assert_eq!(locator_info.size, 123);
+        assert_eq!(locator_info.transitCdnKey.as_deref(), Some("ABCDEF"));
+        assert_eq!(locator_info.transitCdnNumber, Some(3));
+        assert_eq!(locator_info.transitTierUploadTimestamp, Some(1000));
+        assert_matches!(
+            &locator_info.integrityCheck,
+            Some(proto::file_pointer::locator_info::IntegrityCheck::EncryptedDigest(digest))
+                if digest == &vec![0x22; 32]
+        );
+    }
+
+    #[test]
+    fn invalid_attachment_locator() {
+        let mut frame = frame_with_attachment(pointer_with_legacy_field(
+            FilePointerLegacyLocator::INVALID_ATTACHMENT_LOCATOR_TAG,
+            &legacy::file_pointer::InvalidAttachmentLocator::default(),
+        ));
+
+        let rewrites = Migrator::for_version(1).migrate_frame(&mut frame);
+        assert_matches!(
+            &rewrites[..],
+            [(_, Rewrite::LegacyInvalidAttachmentLocator)]
+        );
+        assert_eq!(
+            migrated_pointer(&mut frame).locatorInfo.as_ref(),
+            Some(&proto::file_pointer::LocatorInfo::default())
+        );
+    }
+
+    #[test]
+    fn current_pointers_are_left_alone() {
+        let mut frame = frame_with_attachment(proto::FilePointer {
+            locatorInfo: Some(proto::file_pointer::LocatorInfo::default()).into(),
+            ..Default::default()
+        });
+        let original = frame.clone();
+
+        let rewrites = Migrator::for_version(1).migrate_frame(&mut frame);
+        assert_eq!(rewrites, vec![]);
+        assert_eq!(frame, original);
+    }
+
+    #[test]
+    fn newer_versions_are_not_migrated() {
+        let mut frame = frame_with_attachment(pointer_with_legacy_field(
+            FilePointerLegacyLocator::INVALID_ATTACHMENT_LOCATOR_TAG,
+            &legacy::file_pointer::InvalidAttachmentLocator::default(),
+        ));
+
+        let rewrites =
+            Migrator::for_version(LAST_VERSION_WITH_LEGACY_SHAPES + 1).migrate_frame(&mut frame);
+        assert_eq!(rewrites, vec![]);
+    }
+
+    #[test]
+    fn legacy_subscriber_data() {
+        let mut account = proto::AccountData::default();
+        account.special_fields.mut_unknown_fields().add_value(
+            AccountDataLegacySubscriberData::LEGACY_TAG,
+            UnknownValue::LengthDelimited(vec![]),
+        );
+        let mut frame = proto::Frame {
+            item: Some(proto::frame::Item::Account(account)),
+            ..Default::default()
+        };
+
+        let rewrites = Migrator::for_version(1).migrate_frame(&mut frame);
+        assert_matches!(
+            &rewrites[..],
+            [(_, Rewrite::DroppedLegacyBackupsSubscriberData)]
+        );
+        assert_matches!(frame.item, Some(proto::frame::Item::Account(account)) => {
+            assert_eq!(account.special_fields.unknown_fields().iter().count(), 0);
+        });
+    }
+}
@@ -11,6 +11,8 @@
 
 include!(concat!(env!("OUT_DIR"), "/protos/mod.rs"));
 
+pub(crate) mod file_pointers;
+
 /// Implement [`From`] to allow construction of a `oneof` enum from a contained
 /// message type.
 macro_rules! impl_from_oneof {
@@ -25,6 +27,22 @@ macro_rules! impl_from_oneof {
 
 use self::backup::*;
 
+impl Frame {
+    /// The name of the field set in [`Frame::item`], for use in reports.
+    pub fn item_field_name(&self) -> Option<&'static str> {
+        Some(match self.item.as_ref()? {
+            frame::Item::Account(_) => "account",
+            frame::Item::Recipient(_) => "recipient",
+            frame::Item::Chat(_) => "chat",
+            frame::Item::ChatItem(_) => "chatItem",
+            frame::Item::StickerPack(_) => "stickerPack",
+            frame::Item::AdHocCall(_) => "adHocCall",
+            frame::Item::NotificationProfile(_) => "notificationProfile",
+            frame::Item::ChatFolder(_) => "chatFolder",
+        })
+    }
+}
+
 impl_from_oneof!(
     chat_item::DirectionalDetails,
     chat_item::IncomingMessageDetails,
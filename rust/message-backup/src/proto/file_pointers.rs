@@ -0,0 +1,189 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Traversal of every [`FilePointer`] in a [`Frame`].
+//!
+//! FilePointers can show up in a number of places in a backup, and several consumers (migrations,
+//! media enumeration) care about all of them equally. Keeping the list of places in one spot makes
+//! it less likely for one of those consumers to miss a new location.
+
+use super::backup::*;
+use crate::unknown::PathPart;
+
+/// Calls `visitor` with every [`FilePointer`] reachable from `frame`, along with its path.
+///
+/// The path uses the same field names as unknown field reports.
+pub(crate) fn for_each_file_pointer_mut(
+    frame: &mut Frame,
+    visitor: &mut dyn FnMut(&[PathPart], &mut FilePointer),
+) {
+    let mut walker = Walker {
+        path: Vec::new(),
+        visitor,
+    };
+
+    let Some(item) = frame.item.as_mut() else {
+        return;
+    };
+    match item {
+        frame::Item::Account(account) => walker.field("account", |w| {
+            if let Some(settings) = account.accountSettings.as_mut() {
+                w.field("accountSettings", |w| {
+                    if let Some(style) = settings.defaultChatStyle.as_mut() {
+                        w.field("defaultChatStyle", |w| w.chat_style(style));
+                    }
+                });
+            }
+        }),
+        frame::Item::Chat(chat) => walker.field("chat", |w| {
+            if let Some(style) = chat.style.as_mut() {
+                w.field("style", |w| w.chat_style(style));
+            }
+        }),
+        frame::Item::ChatItem(chat_item) => walker.field("chatItem", |w| w.chat_item(chat_item)),
+        frame::Item::Recipient(_)
+        | frame::Item::StickerPack(_)
+        | frame::Item::AdHocCall(_)
+        | frame::Item::NotificationProfile(_)
+        | frame::Item::ChatFolder(_) => {}
+    }
+}
+
+struct Walker<'a> {
+    path: Vec<PathPart>,
+    visitor: &'a mut dyn FnMut(&[PathPart], &mut FilePointer),
+}
+
+impl Walker<'_> {
+    fn field(&mut self, field_name: &str, body: impl FnOnce(&mut Self)) {
+        self.path.push(PathPart::Field {
+            field_name: field_name.to_owned(),
+        });
+        body(self);
+        self.path.pop();
+    }
+
+    fn repeated<T>(
+        &mut self,
+        field_name: &str,
+        items: &mut [T],
+        mut body: impl FnMut(&mut Self, &mut T),
+    ) {
+        for (index, item) in items.iter_mut().enumerate() {
+            self.path.push(PathPart::Repeated {
+                field_name: field_name.to_owned(),
+                index,
+            });
+            body(self, item);
+            self.path.pop();
+        }
+    }
+
+    fn file_pointer(&mut self, field_name: &str, pointer: &mut FilePointer) {
+        self.field(field_name, |w| (w.visitor)(&w.path, pointer));
+    }
+
+    fn optional_file_pointer(
+        &mut self,
+        field_name: &str,
+        pointer: &mut protobuf::MessageField<FilePointer>,
+    ) {
+        if let Some(pointer) = pointer.as_mut() {
+            self.file_pointer(field_name, pointer);
+        }
+    }
+
+    fn message_attachment(&mut self, attachment: &mut MessageAttachment) {
+        self.optional_file_pointer("pointer", &mut attachment.pointer);
+    }
+
+    fn chat_style(&mut self, style: &mut ChatStyle) {
+        match style.wallpaper.as_mut() {
+            Some(chat_style::Wallpaper::WallpaperPhoto(photo)) => {
+                self.file_pointer("wallpaperPhoto", photo)
+            }
+            Some(chat_style::Wallpaper::WallpaperPreset(_)) | None => {}
+        }
+    }
+
+    fn chat_item(&mut self, chat_item: &mut ChatItem) {
+        self.repeated("revisions", &mut chat_item.revisions, |w, revision| {
+            w.chat_item(revision)
+        });
+
+        let Some(item) = chat_item.item.as_mut() else {
+            return;
+        };
+        match item {
+            chat_item::Item::StandardMessage(message) => {
+                self.field("standardMessage", |w| w.standard_message(message))
+            }
+            chat_item::Item::ContactMessage(message) => self.field("contactMessage", |w| {
+                if let Some(contact) = message.contact.as_mut() {
+                    w.field("contact", |w| {
+                        w.optional_file_pointer("avatar", &mut contact.avatar)
+                    });
+                }
+            }),
+            chat_item::Item::StickerMessage(message) => self.field("stickerMessage", |w| {
+                if let Some(sticker) = message.sticker.as_mut() {
+                    w.field("sticker", |w| {
+                        w.optional_file_pointer("data", &mut sticker.data)
+                    });
+                }
+            }),
+            chat_item::Item::ViewOnceMessage(message) => self.field("viewOnceMessage", |w| {
+                if let Some(attachment) = message.attachment.as_mut() {
+                    w.field("attachment", |w| w.message_attachment(attachment));
+                }
+            }),
+            chat_item::Item::DirectStoryReplyMessage(message) => {
+                self.field("directStoryReplyMessage", |w| {
+                    match message.reply.as_mut() {
+                        Some(direct_story_reply_message::Reply::TextReply(reply)) => w
+                            .field("textReply", |w| {
+                                w.optional_file_pointer("longText", &mut reply.longText)
+                            }),
+                        Some(direct_story_reply_message::Reply::Emoji(_)) | None => {}
+                    }
+                })
+            }
+            chat_item::Item::RemoteDeletedMessage(_)
+            | chat_item::Item::UpdateMessage(_)
+            | chat_item::Item::PaymentNotification(_)
+            | chat_item::Item::GiftBadge(_)
+            | chat_item::Item::Poll(_) => {}
+        }
+    }
+
+    fn standard_message(&mut self, message: &mut StandardMessage) {
+        let StandardMessage {
+            quote,
+            text: _,
+            attachments,
+            linkPreview,
+            longText,
+            reactions: _,
+            special_fields: _,
+        } = message;
+
+        if let Some(quote) = quote.as_mut() {
+            self.field("quote", |w| {
+                w.repeated("attachments", &mut quote.attachments, |w, attachment| {
+                    if let Some(thumbnail) = attachment.thumbnail.as_mut() {
+                        w.field("thumbnail", |w| w.message_attachment(thumbnail));
+                    }
+                })
+            });
+        }
+        self.repeated("attachments", attachments, |w, attachment| {
+            w.message_attachment(attachment)
+        });
+        self.repeated("linkPreview", linkPreview, |w, preview| {
+            w.optional_file_pointer("image", &mut preview.image)
+        });
+        self.optional_file_pointer("longText", longText);
+    }
+}
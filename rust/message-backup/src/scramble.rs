@@ -9,9 +9,12 @@
 //!
 //! Located in the library proper so that matches over `oneof`s can be exhaustive.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use hmac::{Hmac, Mac as _};
 use rand::SeedableRng as _;
+use serde_with::serde_as;
+use sha2::Sha256;
 use zkgroup::receipts::ReceiptCredentialPresentation;
 
 use crate::backup::MY_STORY_UUID;
@@ -22,9 +25,73 @@ use randomize::*;
 
 pub struct Scrambler {
     rng: rand::rngs::StdRng,
+    key: Option<PseudonymizationKey>,
+    categories: ScrambleCategories,
     e164s: intmap::IntMap<u64, u64>,
+    /// The values of `e164s`, so that a new replacement can be checked for collisions.
+    used_e164s: HashSet<u64>,
     uuids: HashMap<Box<[u8]>, Box<[u8]>>,
-    usernames: u64,
+    usernames: HashMap<String, String>,
+    /// The values of `usernames`, so that a new replacement can be checked for collisions.
+    used_usernames: HashSet<String>,
+    username_count: u64,
+}
+
+/// A secret used to derive replacement identifiers deterministically.
+///
+/// Two [`Scrambler`]s created with the same key will map a given ACI, PNI, phone number, or
+/// username to the same replacement, even when they are run over different backups. This makes it
+/// possible to correlate participants across several backups attached to the same bug report
+/// without revealing who they are.
+pub type PseudonymizationKey = [u8; 32];
+
+/// Selects which kinds of potentially-identifying data a [`Scrambler`] replaces.
+///
+/// Service IDs, keys, and other secrets are always replaced; the categories here are the ones that
+/// can be useful to leave intact when reproducing a problem.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScrambleCategories {
+    /// Message body text.
+    pub text_bodies: bool,
+    /// Profile, system, and contact-card names.
+    pub names: bool,
+    /// E164s and contact-card phone numbers.
+    pub phone_numbers: bool,
+    /// Usernames for the account and for contacts.
+    pub usernames: bool,
+    /// Attachment keys, digests, and CDN locators.
+    pub attachment_locators: bool,
+}
+
+impl ScrambleCategories {
+    pub const ALL: Self = Self {
+        text_bodies: true,
+        names: true,
+        phone_numbers: true,
+        usernames: true,
+        attachment_locators: true,
+    };
+}
+
+impl Default for ScrambleCategories {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// The replacements chosen by a [`Scrambler`] for identifiers it has seen.
+///
+/// This can be saved after scrambling one backup and loaded into the [`Scrambler`] for the next
+/// one (see [`Scrambler::load_mapping_table`]), so that identifiers stay consistent even without a
+/// [`PseudonymizationKey`]. It can also be used by whoever holds it to map scrambled identifiers
+/// back to the originals, so it should be treated as being as sensitive as the original backup.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MappingTable {
+    pub e164s: BTreeMap<u64, u64>,
+    #[serde_as(as = "BTreeMap<serde_with::hex::Hex, serde_with::hex::Hex>")]
+    pub service_ids: BTreeMap<Vec<u8>, Vec<u8>>,
+    pub usernames: BTreeMap<String, String>,
 }
 
 impl Scrambler {
@@ -32,9 +99,68 @@ impl Scrambler {
         Self {
             // Use a constant seed for consistent results given the same input.
             rng: rand::rngs::StdRng::seed_from_u64(0),
+            key: None,
+            categories: ScrambleCategories::ALL,
             e164s: Default::default(),
+            used_e164s: Default::default(),
             uuids: Default::default(),
-            usernames: 0,
+            usernames: Default::default(),
+            used_usernames: Default::default(),
+            username_count: 0,
+        }
+    }
+
+    /// Creates a scrambler that derives replacement identifiers from `key`.
+    ///
+    /// See [`PseudonymizationKey`].
+    pub fn new_keyed(key: PseudonymizationKey) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::new()
+        }
+    }
+
+    /// Limits scrambling to the given categories; everything else is left as is.
+    pub fn with_categories(self, categories: ScrambleCategories) -> Self {
+        Self { categories, ..self }
+    }
+
+    /// Seeds the scrambler with replacements chosen previously.
+    ///
+    /// Entries already known to this scrambler are overwritten.
+    pub fn load_mapping_table(&mut self, table: MappingTable) {
+        let MappingTable {
+            e164s,
+            service_ids,
+            usernames,
+        } = table;
+        for (original, replacement) in e164s {
+            self.e164s.insert(original, replacement);
+            self.used_e164s.insert(replacement);
+        }
+        self.uuids.extend(
+            service_ids
+                .into_iter()
+                .map(|(k, v)| (k.into_boxed_slice(), v.into_boxed_slice())),
+        );
+        self.used_usernames.extend(usernames.values().cloned());
+        self.usernames.extend(usernames);
+    }
+
+    /// Returns all the replacements chosen so far (including any that were loaded).
+    pub fn mapping_table(&self) -> MappingTable {
+        MappingTable {
+            e164s: self.e164s.iter().map(|(k, v)| (k, *v)).collect(),
+            service_ids: self
+                .uuids
+                .iter()
+                .map(|(k, v)| (k.to_vec(), v.to_vec()))
+                .collect(),
+            usernames: self
+                .usernames
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
         }
     }
 
@@ -51,13 +177,39 @@ impl Scrambler {
         // Start with numbers in the range +1-555-555-01xx, generate further plausible numbers after that.
         #[expect(clippy::inconsistent_digit_grouping)]
         const E164_START: u64 = 1_555_555_0100;
+        // Keyed replacements are spread over +1-555-xxx-xxxx instead.
+        #[expect(clippy::inconsistent_digit_grouping)]
+        const KEYED_E164_START: u64 = 1_555_000_0000;
+        const KEYED_E164_RANGE: u64 = 10_000_000;
+
+        if !self.categories.phone_numbers {
+            return;
+        }
 
         let original = *field;
+        if let intmap::Entry::Occupied(replacement) = self.e164s.entry(original) {
+            *field = *replacement.get();
+            return;
+        }
+
         let count_of_e164s_so_far: u64 = self.e164s.len().try_into().expect("u64 can hold usize");
-        *field = match self.e164s.entry(original) {
-            intmap::Entry::Occupied(replacement) => *replacement.get(),
-            intmap::Entry::Vacant(entry) => *entry.insert(count_of_e164s_so_far + E164_START),
-        };
+        let replacement = (0u64..)
+            .map(|attempt| match &self.key {
+                None => E164_START + count_of_e164s_so_far + attempt,
+                Some(key) => {
+                    let input = [original.to_be_bytes(), attempt.to_be_bytes()].concat();
+                    let digest = prf(key, b"e164", &input);
+                    let value = u64::from_be_bytes(digest[..8].try_into().expect("correct size"));
+                    KEYED_E164_START + value % KEYED_E164_RANGE
+                }
+            })
+            // Collisions are rare, but we can't let two numbers map to the same replacement. (Loaded
+            // mapping tables can also collide with the unkeyed sequence.)
+            .find(|candidate| !self.used_e164s.contains(candidate))
+            .expect("range is large enough");
+        self.e164s.insert(original, replacement);
+        self.used_e164s.insert(replacement);
+        *field = replacement;
     }
 
     /// Consistently replaces a serialized ServiceId (or other UUID) across a backup.
@@ -75,7 +227,18 @@ impl Scrambler {
             .uuids
             .entry(original.into_boxed_slice())
             .or_insert_with_key(|original| {
-                let mut replacement = random_uuid(&mut self.rng);
+                let mut replacement = match &self.key {
+                    None => random_uuid(&mut self.rng),
+                    Some(key) => {
+                        let digest = prf(key, b"uuid", original);
+                        uuid::Builder::from_random_bytes(
+                            digest[..16].try_into().expect("correct size"),
+                        )
+                        .into_uuid()
+                        .into_bytes()
+                        .to_vec()
+                    }
+                };
                 if original.len() == replacement.len() + 1 {
                     // Assume original is a non-ACI ServiceId; preserve the type.
                     replacement.insert(0, original[0]);
@@ -90,13 +253,63 @@ impl Scrambler {
             .to_vec()
     }
 
-    /// Generates a username
-    fn next_username(&mut self) -> String {
-        self.usernames += 1;
-        format!("user.{:02}", self.usernames)
+    /// Consistently replaces a username across a backup.
+    fn replace_username(&mut self, field: &mut String) {
+        if !self.categories.usernames {
+            return;
+        }
+
+        let original = std::mem::take(field);
+        *field = match self.usernames.get(&original) {
+            Some(replacement) => replacement.clone(),
+            None => {
+                let replacement = (0u64..)
+                    .map(|attempt| match &self.key {
+                        None => {
+                            self.username_count += 1;
+                            format!("user.{:02}", self.username_count)
+                        }
+                        Some(key) => {
+                            // The first attempt uses the bare username so that existing
+                            // pseudonyms stay the same.
+                            let digest = if attempt == 0 {
+                                prf(key, b"username", original.as_bytes())
+                            } else {
+                                let input = [original.as_bytes(), &attempt.to_be_bytes()].concat();
+                                prf(key, b"username", &input)
+                            };
+                            // Discriminators run from 01 to 99.
+                            let discriminator = digest[4] % 99 + 1;
+                            format!("user_{}.{discriminator:02}", hex::encode(&digest[..4]))
+                        }
+                    })
+                    // As with e164s, two usernames must not map to the same replacement.
+                    .find(|candidate| !self.used_usernames.contains(candidate))
+                    .expect("enough usernames");
+                self.usernames.insert(original, replacement.clone());
+                self.used_usernames.insert(replacement.clone());
+                replacement
+            }
+        };
+    }
+
+    /// Replaces a name field, if names are being scrambled.
+    fn scramble_name(&mut self, field: &mut impl Randomize) {
+        if self.categories.names {
+            field.randomize(&mut self.rng);
+        }
     }
 }
 
+/// Computes HMAC-SHA256 over a domain separator and some input.
+fn prf(key: &PseudonymizationKey, domain: &[u8], input: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(domain);
+    mac.update(&[0]);
+    mac.update(input);
+    mac.finalize().into_bytes().into()
+}
+
 impl Default for Scrambler {
     fn default() -> Self {
         Self::new()
@@ -234,11 +447,11 @@ impl Visit<Scrambler> for proto::AccountData {
 
         profileKey.randomize(&mut visitor.rng);
         if let Some(username) = username {
-            *username = visitor.next_username();
+            visitor.replace_username(username);
         }
         usernameLink.accept(visitor);
-        givenName.randomize(&mut visitor.rng);
-        familyName.randomize(&mut visitor.rng);
+        visitor.scramble_name(givenName);
+        visitor.scramble_name(familyName);
         if !avatarUrlPath.is_empty() {
             *avatarUrlPath = "https://cdn.signal.org/avatarUrlPath".into();
         }
@@ -387,6 +600,10 @@ impl Visit<Scrambler> for proto::file_pointer::LocatorInfo {
             special_fields: _,
         } = self;
 
+        if !visitor.categories.attachment_locators {
+            return;
+        }
+
         localKey.randomize(&mut visitor.rng);
         key.randomize(&mut visitor.rng);
 
@@ -498,14 +715,14 @@ impl Visit<Scrambler> for proto::Contact {
             visitor.replace_service_id(pni);
         }
         if let Some(username) = username {
-            *username = visitor.next_username();
+            visitor.replace_username(username);
         };
         if let Some(e164) = e164 {
             visitor.replace_e164(e164);
         }
         profileKey.randomize(&mut visitor.rng);
-        profileGivenName.randomize(&mut visitor.rng);
-        profileFamilyName.randomize(&mut visitor.rng);
+        visitor.scramble_name(profileGivenName);
+        visitor.scramble_name(profileFamilyName);
         if let Some(identity_key) = identityKey {
             if libsignal_protocol::PublicKey::deserialize(identity_key).is_ok() {
                 *identity_key = libsignal_protocol::KeyPair::generate(&mut visitor.rng)
@@ -526,9 +743,9 @@ impl Visit<Scrambler> for proto::Contact {
         }
 
         nickname.accept(visitor);
-        systemGivenName.randomize(&mut visitor.rng);
-        systemFamilyName.randomize(&mut visitor.rng);
-        systemNickname.randomize(&mut visitor.rng);
+        visitor.scramble_name(systemGivenName);
+        visitor.scramble_name(systemFamilyName);
+        visitor.scramble_name(systemNickname);
         note.randomize(&mut visitor.rng);
     }
 }
@@ -555,8 +772,8 @@ impl Visit<Scrambler> for proto::contact::Name {
             family,
             special_fields: _,
         } = self;
-        given.randomize(&mut visitor.rng);
-        family.randomize(&mut visitor.rng);
+        visitor.scramble_name(given);
+        visitor.scramble_name(family);
     }
 }
 
@@ -979,6 +1196,11 @@ impl Visit<Scrambler> for proto::Text {
             special_fields: _,
         } = self;
 
+        if !visitor.categories.text_bodies {
+            bodyRanges.accept(visitor);
+            return;
+        }
+
         // Use constant text input for better compression later.
         // But make sure we're at least as long as the original body.
         let mut new_body = if body.len() < REPLACEMENT_BODY_TEXT.len() {
@@ -1132,12 +1354,12 @@ impl Visit<Scrambler> for proto::contact_attachment::Name {
             nickname,
             special_fields: _,
         } = self;
-        givenName.randomize(&mut visitor.rng);
-        familyName.randomize(&mut visitor.rng);
-        prefix.randomize(&mut visitor.rng);
-        suffix.randomize(&mut visitor.rng);
-        middleName.randomize(&mut visitor.rng);
-        nickname.randomize(&mut visitor.rng);
+        visitor.scramble_name(givenName);
+        visitor.scramble_name(familyName);
+        visitor.scramble_name(prefix);
+        visitor.scramble_name(suffix);
+        visitor.scramble_name(middleName);
+        visitor.scramble_name(nickname);
     }
 }
 
@@ -1151,7 +1373,9 @@ impl Visit<Scrambler> for proto::contact_attachment::Phone {
         } = self;
 
         // We could try harder to make this a valid number, but clients can't trust it anyway.
-        value.randomize(&mut visitor.rng);
+        if visitor.categories.phone_numbers {
+            value.randomize(&mut visitor.rng);
+        }
         label.randomize(&mut visitor.rng);
     }
 }
@@ -1842,7 +2066,7 @@ impl Visit<Scrambler> for proto::LearnedProfileChatUpdate {
             use proto::learned_profile_chat_update::PreviousName;
             match name {
                 PreviousName::E164(e164) => visitor.replace_e164(e164),
-                PreviousName::Username(username) => *username = visitor.next_username(),
+                PreviousName::Username(username) => visitor.replace_username(username),
             }
         }
     }
@@ -2152,3 +2376,110 @@ impl Visit<Scrambler> for proto::chat_item::PinDetails {
         } = self;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEY: PseudonymizationKey = [0x42; 32];
+
+    fn contact() -> proto::Contact {
+        proto::Contact {
+            aci: Some(vec![0x11; 16]),
+            pni: Some(vec![0x22; 16]),
+            username: Some("boba_fett.66".into()),
+            e164: Some(16505550101),
+            profileGivenName: Some("Boba".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keyed_scrambling_is_consistent_across_scramblers() {
+        let first = Scrambler::new_keyed(KEY).scramble(&contact());
+        // Scramble something else first, so the replacements can't depend on order.
+        let mut second_scrambler = Scrambler::new_keyed(KEY);
+        _ = second_scrambler.scramble(&proto::Contact {
+            aci: Some(vec![0x33; 16]),
+            e164: Some(16505550102),
+            username: Some("jango_fett.01".into()),
+            ..Default::default()
+        });
+        let second = second_scrambler.scramble(&contact());
+
+        assert_eq!(first.aci, second.aci);
+        assert_eq!(first.pni, second.pni);
+        assert_eq!(first.e164, second.e164);
+        assert_eq!(first.username, second.username);
+        assert_ne!(first.aci, contact().aci);
+        assert_ne!(first.e164, contact().e164);
+
+        let other_key = Scrambler::new_keyed([0x43; 32]).scramble(&contact());
+        assert_ne!(first.aci, other_key.aci);
+    }
+
+    #[test]
+    fn categories_can_be_kept() {
+        let scrambled = Scrambler::new()
+            .with_categories(ScrambleCategories {
+                names: false,
+                phone_numbers: false,
+                usernames: false,
+                ..ScrambleCategories::ALL
+            })
+            .scramble(&contact());
+
+        let original = contact();
+        assert_eq!(scrambled.profileGivenName, original.profileGivenName);
+        assert_eq!(scrambled.e164, original.e164);
+        assert_eq!(scrambled.username, original.username);
+        assert_ne!(scrambled.aci, original.aci);
+    }
+
+    #[test]
+    fn mapping_table_round_trip() {
+        let mut scrambler = Scrambler::new();
+        let first = scrambler.scramble(&contact());
+        let table = scrambler.mapping_table();
+        assert_eq!(table.e164s.len(), 1);
+        assert_eq!(table.service_ids.len(), 2);
+        assert_eq!(table.usernames.len(), 1);
+
+        let mut reloaded = Scrambler::new();
+        // Use up some randomness so that any fresh replacements would differ.
+        _ = reloaded.scramble(&proto::Contact {
+            aci: Some(vec![0x33; 16]),
+            ..Default::default()
+        });
+        reloaded.load_mapping_table(table);
+        let second = reloaded.scramble(&contact());
+
+        assert_eq!(first.aci, second.aci);
+        assert_eq!(first.pni, second.pni);
+        assert_eq!(first.e164, second.e164);
+        assert_eq!(first.username, second.username);
+    }
+
+    #[test]
+    fn keyed_replacements_do_not_collide() {
+        let first = Scrambler::new_keyed(KEY).scramble(&contact());
+
+        // Pretend some other identifiers already took the replacements this contact would get.
+        let mut scrambler = Scrambler::new_keyed(KEY);
+        scrambler.load_mapping_table(MappingTable {
+            e164s: BTreeMap::from([(16505550199, first.e164.unwrap())]),
+            service_ids: BTreeMap::new(),
+            usernames: BTreeMap::from([(
+                "someone_else.01".into(),
+                first.username.clone().unwrap(),
+            )]),
+        });
+        let second = scrambler.scramble(&contact());
+
+        assert_ne!(first.e164, second.e164);
+        assert_ne!(first.username, second.username);
+        let table = scrambler.mapping_table();
+        assert_eq!(table.e164s.len(), 2);
+        assert_eq!(table.usernames.len(), 2);
+    }
+}
@@ -360,6 +360,7 @@ fn invalid_jsonproto(input: Fixture<PathBuf>) {
     let ReadResult {
         result,
         found_unknown_fields: _,
+        migrations: _,
     } = futures::executor::block_on(reader.read_all());
 
     let text = result.expect_err("unexpectedly valid").to_string();
@@ -401,8 +402,10 @@ fn validate(mut reader: BackupReader<impl AsyncRead + Unpin + VerifyHmac>) {
     let ReadResult {
         result,
         found_unknown_fields,
+        migrations,
     } = futures::executor::block_on(reader.read_all());
     assert_eq!(found_unknown_fields, Vec::new());
+    assert_eq!(migrations, Vec::new());
 
     let backup = result.expect("invalid backup");
     println!("got backup:\n{backup:#?}");
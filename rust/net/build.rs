@@ -6,11 +6,13 @@
 fn main() {
     let protos = [
         "src/proto/cds2.proto",
+        "src/proto/chat_envelope.proto",
         "src/proto/chat_provisioning.proto",
         "src/proto/chat_websocket.proto",
     ];
     prost_build::Config::new()
         .bytes([
+            ".signal.proto.chat_envelope",
             ".signal.proto.chat_provisioning",
             ".signal.proto.chat_websocket",
         ])
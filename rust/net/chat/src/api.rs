@@ -107,8 +107,7 @@ impl<E> From<DisconnectedError> for RequestError<E> {
     }
 }
 
-#[derive(Debug, thiserror::Error, displaydoc::Display)]
-#[cfg_attr(test, derive(Clone))]
+#[derive(Clone, Debug, thiserror::Error, displaydoc::Display)]
 /// retry after completing a rate limit challenge {options:?}
 pub struct RateLimitChallenge {
     pub token: String,
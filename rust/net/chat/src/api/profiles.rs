@@ -5,6 +5,8 @@
 
 use async_trait::async_trait;
 use libsignal_core::Aci;
+use libsignal_core::curve::PublicKey;
+use zkgroup::profiles::{MobileCoinAddress, ProfileName};
 
 use super::{RequestError, UserBasedAuthorization};
 
@@ -16,8 +18,40 @@ pub enum ProfileKeyCredentialRequestError {
     VersionNotFound,
 }
 
+#[derive(Debug, displaydoc::Display)]
+pub enum GetProfileError {
+    /// authorization failed
+    AuthFailed,
+    /// profile version not found
+    VersionNotFound,
+}
+
+/// Another user's profile, with the fields encrypted under their profile key already decrypted.
+///
+/// Encrypted fields that the user hasn't set are `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub identity_key: PublicKey,
+    pub name: Option<ProfileName>,
+    pub about: Option<String>,
+    pub about_emoji: Option<String>,
+    /// The CDN path of the encrypted avatar, to be decrypted with
+    /// [`ProfileCipher::decrypt_avatar`](zkgroup::profiles::ProfileCipher::decrypt_avatar).
+    pub avatar_path: Option<String>,
+    /// Already checked against `identity_key`.
+    pub payment_address: Option<MobileCoinAddress>,
+    pub unrestricted_unidentified_access: bool,
+}
+
 #[async_trait]
 pub trait UnauthenticatedChatApi {
+    async fn get_profile(
+        &self,
+        peer_aci: Aci,
+        profile_key: zkgroup::profiles::ProfileKey,
+        auth: UserBasedAuthorization,
+    ) -> Result<Profile, RequestError<GetProfileError>>;
+
     async fn get_profile_key_credential(
         &self,
         peer_aci: Aci,
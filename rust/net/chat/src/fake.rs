@@ -0,0 +1,1357 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A scriptable fake chat server for tests.
+//!
+//! [`FakeChatServer`] routes [`chat::Request`]s to handlers by method and path, each of which gets
+//! access to a shared in-memory [`FakeServerModel`]. It implements [`WsConnection`] directly, so it
+//! can stand in for a real connection anywhere the [`crate::api`] traits are used (wrapped in
+//! [`Unauth`](crate::api::Unauth) as appropriate). It can also answer requests from a real
+//! [`ChatConnection`](libsignal_net::chat::ChatConnection) through a [`FakeChatRemote`], and
+//! deliver queued envelopes to it.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use base64::Engine as _;
+use base64::prelude::{BASE64_STANDARD, BASE64_STANDARD_NO_PAD, BASE64_URL_SAFE_NO_PAD};
+use bytes::Bytes;
+use libsignal_core::curve::PublicKey;
+use libsignal_core::{Aci, DeviceId, Pni, ServiceId};
+use libsignal_net::chat::envelope::Envelope;
+use libsignal_net::chat::fake::{Disconnected, FakeChatRemote, ReceiveRequestError};
+use libsignal_net::chat::{self, RequestProto, ResponseProto};
+use libsignal_net::env::TIMESTAMP_HEADER_NAME;
+use libsignal_net::proto::chat_envelope::envelope::Type as EnvelopeType;
+use libsignal_protocol::{
+    GenericSignedPreKey as _, KyberPreKeyRecord, PreKeyRecord, SealedSenderV2SentMessage,
+    SignedPreKeyRecord,
+};
+use prost::Message as _;
+use rand::Rng as _;
+use zkgroup::profiles::{ProfileKeyCommitment, ProfileKeyCredentialRequest};
+
+use crate::ws::WsConnection;
+
+/// The device ID given to the first device on an account.
+pub const PRIMARY_DEVICE_ID: DeviceId = match DeviceId::new(1) {
+    Ok(id) => id,
+    Err(_) => unreachable!(),
+};
+
+/// Produces a response for a request that matched a route.
+pub type Handler = Box<dyn FnMut(&FakeRequest, &mut FakeServerModel) -> chat::Response + Send>;
+
+/// A fake chat server with pluggable request handlers.
+///
+/// Cloning produces another handle to the same server.
+#[derive(Clone, Default)]
+pub struct FakeChatServer {
+    state: Arc<Mutex<ServerState>>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    routes: Vec<Route>,
+    model: FakeServerModel,
+    next_request_id: u64,
+}
+
+struct Route {
+    method: http::Method,
+    pattern: Vec<PatternSegment>,
+    handler: Handler,
+}
+
+enum PatternSegment {
+    Literal(String),
+    Param,
+}
+
+/// A request as seen by a [`Handler`].
+#[derive(Debug)]
+pub struct FakeRequest {
+    pub method: http::Method,
+    /// The request path, without the query string.
+    pub path: String,
+    pub query: Option<String>,
+    /// The path segments that matched each `{}` in the route's pattern, in order.
+    pub params: Vec<String>,
+    pub headers: http::HeaderMap,
+    pub body: Option<Bytes>,
+}
+
+/// In-memory server state, available to every handler.
+#[derive(Default)]
+pub struct FakeServerModel {
+    pub accounts: Vec<FakeAccount>,
+    /// Used to issue profile key credentials; without it, those requests fail with a 500.
+    pub zkgroup_params: Option<zkgroup::ServerSecretParams>,
+    pub key_transparency: FakeKeyTransparency,
+    /// Requests that didn't match any route, in the order they were received.
+    pub unhandled_requests: Vec<chat::Request>,
+}
+
+#[derive(Debug)]
+pub struct FakeAccount {
+    pub aci: Aci,
+    pub pni: Pni,
+    /// The ACI identity key; the account's profile and pre-keys are only served once it is set.
+    pub identity_key: Option<PublicKey>,
+    pub profile: Option<FakeProfile>,
+    pub username_hash: Option<Vec<u8>>,
+    /// The username link handle, along with the encrypted username it refers to.
+    pub username_link: Option<(uuid::Uuid, Vec<u8>)>,
+    pub devices: BTreeMap<DeviceId, FakeDevice>,
+}
+
+/// A versioned profile, with fields encrypted by the client.
+pub struct FakeProfile {
+    /// The profile key version the profile was uploaded under.
+    pub version: String,
+    pub commitment: ProfileKeyCommitment,
+    pub name: Option<Vec<u8>>,
+    pub about: Option<Vec<u8>>,
+    pub about_emoji: Option<Vec<u8>>,
+    pub avatar: Option<String>,
+    pub payment_address: Option<Vec<u8>>,
+    pub unrestricted_unidentified_access: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct FakeDevice {
+    pub registration_id: u16,
+    /// Serialized signed pre-key record, as uploaded by the client.
+    pub signed_pre_key: Option<Bytes>,
+    /// Serialized last-resort Kyber pre-key record, as uploaded by the client.
+    pub kyber_pre_key: Option<Bytes>,
+    /// Serialized one-time pre-key records, handed out in order.
+    pub one_time_pre_keys: VecDeque<Bytes>,
+    /// Envelopes waiting to be delivered to this device.
+    pub queue: VecDeque<Envelope>,
+}
+
+/// Canned key transparency responses.
+///
+/// The fake server can't produce proofs of its own, so tests supply serialized responses (usually
+/// recorded from a real server) for the requests they expect.
+#[derive(Debug, Default)]
+pub struct FakeKeyTransparency {
+    /// Serialized `ChatDistinguishedResponse`.
+    pub distinguished: Option<Vec<u8>>,
+    /// Serialized `ChatSearchResponse`s, by the ACI being searched for.
+    pub search: HashMap<Aci, Vec<u8>>,
+    /// Serialized `ChatMonitorResponse`s, by the ACI being monitored.
+    pub monitor: HashMap<Aci, Vec<u8>>,
+}
+
+impl FakeChatServer {
+    /// Creates a server with no routes; every request will get a 404 response.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a server with routes for the requests made by [`crate::api`] traits, backed by the
+    /// [`FakeServerModel`].
+    ///
+    /// That covers username lookups, profiles and profile key credentials, key transparency,
+    /// multi-recipient message sends, and fetching pre-keys.
+    pub fn with_default_routes() -> Self {
+        let server = Self::new();
+        server.route(http::Method::GET, "/v1/profile/{}/{}", get_profile);
+        server.route(
+            http::Method::GET,
+            "/v1/profile/{}/{}/{}",
+            get_profile_key_credential,
+        );
+        server.route(
+            http::Method::GET,
+            "/v1/key-transparency/distinguished",
+            |_request, model| {
+                key_transparency_response(model.key_transparency.distinguished.as_deref())
+            },
+        );
+        server.route(
+            http::Method::POST,
+            "/v1/key-transparency/search",
+            key_transparency_search,
+        );
+        server.route(
+            http::Method::POST,
+            "/v1/key-transparency/monitor",
+            key_transparency_monitor,
+        );
+        server.route(http::Method::GET, "/v2/keys/{}/{}", get_pre_keys);
+        server.route(
+            http::Method::GET,
+            "/v1/accounts/username_hash/{}",
+            look_up_username_hash,
+        );
+        server.route(
+            http::Method::GET,
+            "/v1/accounts/username_link/{}",
+            look_up_username_link,
+        );
+        server.route(
+            http::Method::PUT,
+            "/v1/messages/multi_recipient",
+            send_multi_recipient_message,
+        );
+        server
+    }
+
+    /// Adds a route for `method` and `pattern`.
+    ///
+    /// `pattern` is matched against the request path (not including the query string) one
+    /// `/`-separated segment at a time; a segment of `{}` matches any single segment and is
+    /// available in [`FakeRequest::params`]. Routes added later take precedence, so tests can
+    /// override the defaults.
+    pub fn route(
+        &self,
+        method: http::Method,
+        pattern: &str,
+        handler: impl FnMut(&FakeRequest, &mut FakeServerModel) -> chat::Response + Send + 'static,
+    ) {
+        let pattern = pattern
+            .split('/')
+            .map(|segment| match segment {
+                "{}" => PatternSegment::Param,
+                literal => PatternSegment::Literal(literal.to_owned()),
+            })
+            .collect();
+        self.state.lock().expect("not poisoned").routes.push(Route {
+            method,
+            pattern,
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Runs `f` with exclusive access to the server's model.
+    pub fn with_model<T>(&self, f: impl FnOnce(&mut FakeServerModel) -> T) -> T {
+        f(&mut self.state.lock().expect("not poisoned").model)
+    }
+
+    /// Dispatches `request` to the most recently added matching route.
+    ///
+    /// Requests that don't match any route are recorded in
+    /// [`FakeServerModel::unhandled_requests`] and get a 404 response.
+    pub fn handle(&self, request: chat::Request) -> chat::Response {
+        let mut guard = self.state.lock().expect("not poisoned");
+        let ServerState { routes, model, .. } = &mut *guard;
+
+        let path = request.path.path();
+        let matched = routes.iter_mut().rev().find_map(|route| {
+            let params = route.matches(&request.method, path)?;
+            Some((route, params))
+        });
+        let Some((route, params)) = matched else {
+            log::info!("fake chat server: no route for {} {}", request.method, path);
+            model.unhandled_requests.push(request);
+            return empty(404);
+        };
+
+        let fake_request = FakeRequest {
+            method: request.method.clone(),
+            path: path.to_owned(),
+            query: request.path.query().map(ToOwned::to_owned),
+            params,
+            headers: request.headers,
+            body: request.body,
+        };
+        (route.handler)(&fake_request, model)
+    }
+
+    /// Answers requests from the client end of `remote` until it disconnects.
+    ///
+    /// Responses to requests sent by the server (such as message acknowledgements) are skipped.
+    pub async fn serve(&self, remote: &FakeChatRemote) -> Result<(), ServeError> {
+        loop {
+            let request = match remote.receive_request().await {
+                Ok(Some(request)) => request,
+                Ok(None) => return Ok(()),
+                Err(ReceiveRequestError::GotResponse) => continue,
+                Err(e) => return Err(ServeError::InvalidRequest(e)),
+            };
+            let id = request.id;
+            let response = match request_from_proto(request) {
+                Some(request) => self.handle(request),
+                None => empty(400),
+            };
+            remote
+                .send_response(response_to_proto(id, response))
+                .map_err(|Disconnected| ServeError::Disconnected)?;
+        }
+    }
+
+    /// Sends every envelope queued for `service_id`'s `device_id` to the client end of `remote`,
+    /// followed by a "queue empty" notification.
+    ///
+    /// Returns the number of envelopes delivered.
+    pub fn deliver_queue(
+        &self,
+        remote: &FakeChatRemote,
+        service_id: ServiceId,
+        device_id: DeviceId,
+    ) -> Result<usize, Disconnected> {
+        let mut guard = self.state.lock().expect("not poisoned");
+        let ServerState {
+            model,
+            next_request_id,
+            ..
+        } = &mut *guard;
+
+        let queue = model
+            .account_mut(service_id)
+            .and_then(|account| account.devices.get_mut(&device_id))
+            .map(|device| std::mem::take(&mut device.queue))
+            .unwrap_or_default();
+        let count = queue.len();
+
+        for envelope in queue {
+            let timestamp = envelope.server_timestamp.unwrap_or_default();
+            remote.send_request(RequestProto {
+                verb: Some(http::Method::PUT.to_string()),
+                path: Some("/api/v1/message".to_owned()),
+                body: Some(envelope.encode_to_vec().into()),
+                headers: vec![format!("{TIMESTAMP_HEADER_NAME}: {timestamp}")],
+                id: Some(take_request_id(next_request_id)),
+            })?;
+        }
+        remote.send_request(RequestProto {
+            verb: Some(http::Method::PUT.to_string()),
+            path: Some("/api/v1/queue/empty".to_owned()),
+            body: None,
+            headers: vec![],
+            id: Some(take_request_id(next_request_id)),
+        })?;
+        Ok(count)
+    }
+}
+
+impl WsConnection for FakeChatServer {
+    fn send(
+        &self,
+        _log_tag: &'static str,
+        _log_safe_path: &str,
+        request: chat::Request,
+    ) -> impl Future<Output = Result<chat::Response, chat::SendError>> + Send {
+        std::future::ready(Ok(self.handle(request)))
+    }
+}
+
+#[derive(Debug, displaydoc::Display)]
+pub enum ServeError {
+    /// client sent an invalid request: {0:?}
+    InvalidRequest(ReceiveRequestError),
+    /// client disconnected
+    Disconnected,
+}
+
+impl Route {
+    fn matches(&self, method: &http::Method, path: &str) -> Option<Vec<String>> {
+        if self.method != *method {
+            return None;
+        }
+        let mut segments = path.split('/');
+        let mut params = vec![];
+        for pattern in &self.pattern {
+            let segment = segments.next()?;
+            match pattern {
+                PatternSegment::Literal(literal) if literal == segment => {}
+                PatternSegment::Literal(_) => return None,
+                PatternSegment::Param => params.push(segment.to_owned()),
+            }
+        }
+        segments.next().is_none().then_some(params)
+    }
+}
+
+impl FakeRequest {
+    /// Looks up a parameter in the query string. Values are not percent-decoded.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.as_deref()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then_some(value)
+        })
+    }
+}
+
+impl FakeServerModel {
+    /// Adds an account with a single primary device.
+    pub fn add_account(&mut self, aci: Aci, pni: Pni) -> &mut FakeAccount {
+        self.accounts.push(FakeAccount {
+            aci,
+            pni,
+            identity_key: None,
+            profile: None,
+            username_hash: None,
+            username_link: None,
+            devices: BTreeMap::from([(PRIMARY_DEVICE_ID, FakeDevice::default())]),
+        });
+        self.accounts.last_mut().expect("just added")
+    }
+
+    /// Finds the account with `service_id` as either its ACI or PNI.
+    pub fn account(&self, service_id: ServiceId) -> Option<&FakeAccount> {
+        self.accounts
+            .iter()
+            .find(|account| account.has_service_id(service_id))
+    }
+
+    /// Finds the account with `service_id` as either its ACI or PNI.
+    pub fn account_mut(&mut self, service_id: ServiceId) -> Option<&mut FakeAccount> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.has_service_id(service_id))
+    }
+}
+
+impl std::fmt::Debug for FakeServerModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            accounts,
+            zkgroup_params,
+            key_transparency,
+            unhandled_requests,
+        } = self;
+        f.debug_struct("FakeServerModel")
+            .field("accounts", accounts)
+            .field("zkgroup_params", &zkgroup_params.as_ref().map(|_| "_"))
+            .field("key_transparency", key_transparency)
+            .field("unhandled_requests", unhandled_requests)
+            .finish()
+    }
+}
+
+impl FakeAccount {
+    fn has_service_id(&self, service_id: ServiceId) -> bool {
+        service_id == self.aci.into() || service_id == self.pni.into()
+    }
+}
+
+impl FakeProfile {
+    /// Creates an empty profile for `aci` under `profile_key`.
+    pub fn new(aci: Aci, profile_key: zkgroup::profiles::ProfileKey) -> Self {
+        Self {
+            version: profile_key.get_profile_key_version(aci).as_ref().to_owned(),
+            commitment: profile_key.get_commitment(aci),
+            name: None,
+            about: None,
+            about_emoji: None,
+            avatar: None,
+            payment_address: None,
+            unrestricted_unidentified_access: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for FakeProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeProfile")
+            .field("version", &self.version)
+            .field("name", &self.name)
+            .field("about", &self.about)
+            .field("about_emoji", &self.about_emoji)
+            .field("avatar", &self.avatar)
+            .field("payment_address", &self.payment_address)
+            .field(
+                "unrestricted_unidentified_access",
+                &self.unrestricted_unidentified_access,
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl FakeDevice {
+    /// Removes and returns the next one-time pre-key, as the server does when handing out a
+    /// pre-key bundle.
+    pub fn take_pre_key(&mut self) -> Option<Bytes> {
+        self.one_time_pre_keys.pop_front()
+    }
+}
+
+/// Creates a response with status `status` and a JSON body.
+pub fn json(status: u16, body: &serde_json::Value) -> chat::Response {
+    chat::Response {
+        status: http::StatusCode::from_u16(status).expect("valid"),
+        message: None,
+        headers: http::HeaderMap::from_iter([(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/json"),
+        )]),
+        body: Some(serde_json::to_vec(body).expect("can serialize").into()),
+    }
+}
+
+/// Creates a response with status `status` and no body.
+pub fn empty(status: u16) -> chat::Response {
+    chat::Response {
+        status: http::StatusCode::from_u16(status).expect("valid"),
+        message: None,
+        headers: http::HeaderMap::new(),
+        body: None,
+    }
+}
+
+fn look_up_username_hash(request: &FakeRequest, model: &mut FakeServerModel) -> chat::Response {
+    let Ok(hash) = BASE64_URL_SAFE_NO_PAD.decode(&request.params[0]) else {
+        return empty(400);
+    };
+    match model
+        .accounts
+        .iter()
+        .find(|account| account.username_hash.as_ref() == Some(&hash))
+    {
+        Some(account) => json(
+            200,
+            &serde_json::json!({ "uuid": account.aci.service_id_string() }),
+        ),
+        None => empty(404),
+    }
+}
+
+fn look_up_username_link(request: &FakeRequest, model: &mut FakeServerModel) -> chat::Response {
+    let Ok(handle) = request.params[0].parse::<uuid::Uuid>() else {
+        return empty(400);
+    };
+    match model.accounts.iter().find_map(|account| {
+        let (link_handle, encrypted_username) = account.username_link.as_ref()?;
+        (*link_handle == handle).then_some(encrypted_username)
+    }) {
+        Some(encrypted_username) => json(
+            200,
+            &serde_json::json!({
+                "usernameLinkEncryptedValue": BASE64_URL_SAFE_NO_PAD.encode(encrypted_username),
+            }),
+        ),
+        None => empty(404),
+    }
+}
+
+fn get_profile(request: &FakeRequest, model: &mut FakeServerModel) -> chat::Response {
+    match find_profile(request, model) {
+        Ok((identity_key, profile)) => json(200, &profile_json(identity_key, profile)),
+        Err(response) => response,
+    }
+}
+
+fn get_profile_key_credential(
+    request: &FakeRequest,
+    model: &mut FakeServerModel,
+) -> chat::Response {
+    if request.query_param("credentialType") != Some("expiringProfileKey") {
+        return empty(400);
+    }
+    let Some(credential_request) = hex::decode(&request.params[2])
+        .ok()
+        .and_then(|bytes| zkgroup::deserialize::<ProfileKeyCredentialRequest>(&bytes).ok())
+    else {
+        return empty(400);
+    };
+    let (identity_key, profile) = match find_profile(request, model) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    let Some(zkgroup_params) = &model.zkgroup_params else {
+        log::warn!("fake chat server: no zkgroup params to issue credentials with");
+        return empty(500);
+    };
+
+    let aci = Aci::parse_from_service_id_string(&request.params[0]).expect("found above");
+    let now = zkgroup::Timestamp::from(SystemTime::now());
+    let expiration = now
+        .sub_seconds(now.epoch_seconds() % zkgroup::SECONDS_PER_DAY)
+        .add_seconds(7 * zkgroup::SECONDS_PER_DAY);
+    let Ok(credential) = zkgroup_params.issue_expiring_profile_key_credential(
+        rand::rng().random(),
+        &credential_request,
+        aci,
+        profile.commitment,
+        expiration,
+    ) else {
+        return empty(400);
+    };
+
+    let mut body = profile_json(identity_key, profile);
+    body["credential"] = BASE64_STANDARD
+        .encode(zkgroup::serialize(&credential))
+        .into();
+    json(200, &body)
+}
+
+/// Finds the profile for a request whose first two parameters are an ACI and a profile key
+/// version, or produces the response the server gives when it's not available.
+fn find_profile<'a>(
+    request: &FakeRequest,
+    model: &'a FakeServerModel,
+) -> Result<(&'a PublicKey, &'a FakeProfile), chat::Response> {
+    let aci = Aci::parse_from_service_id_string(&request.params[0]).ok_or_else(|| empty(400))?;
+    let account = model.account(aci.into()).ok_or_else(|| empty(404))?;
+    match (&account.identity_key, &account.profile) {
+        (Some(identity_key), Some(profile)) if profile.version == request.params[1] => {
+            Ok((identity_key, profile))
+        }
+        _ => Err(empty(404)),
+    }
+}
+
+fn profile_json(identity_key: &PublicKey, profile: &FakeProfile) -> serde_json::Value {
+    let FakeProfile {
+        version: _,
+        commitment: _,
+        name,
+        about,
+        about_emoji,
+        avatar,
+        payment_address,
+        unrestricted_unidentified_access,
+    } = profile;
+    let encode =
+        |field: &Option<Vec<u8>>| field.as_ref().map(|bytes| BASE64_STANDARD.encode(bytes));
+    serde_json::json!({
+        "identityKey": BASE64_STANDARD.encode(identity_key.serialize()),
+        "name": encode(name),
+        "about": encode(about),
+        "aboutEmoji": encode(about_emoji),
+        "avatar": avatar,
+        "paymentAddress": encode(payment_address),
+        "unrestrictedUnidentifiedAccess": unrestricted_unidentified_access,
+    })
+}
+
+fn key_transparency_response(serialized: Option<&[u8]>) -> chat::Response {
+    match serialized {
+        Some(serialized) => json(
+            200,
+            &serde_json::json!({
+                "serializedResponse": BASE64_STANDARD_NO_PAD.encode(serialized),
+            }),
+        ),
+        None => empty(404),
+    }
+}
+
+fn key_transparency_search(request: &FakeRequest, model: &mut FakeServerModel) -> chat::Response {
+    #[derive(serde::Deserialize)]
+    struct SearchRequest {
+        aci: String,
+    }
+    let Some(SearchRequest { aci }) = parse_json_body(request) else {
+        return empty(400);
+    };
+    let Some(aci) = Aci::parse_from_service_id_string(&aci) else {
+        return empty(400);
+    };
+    key_transparency_response(model.key_transparency.search.get(&aci).map(Vec::as_slice))
+}
+
+fn key_transparency_monitor(request: &FakeRequest, model: &mut FakeServerModel) -> chat::Response {
+    #[derive(serde::Deserialize)]
+    struct MonitorRequest {
+        aci: ValueMonitor,
+    }
+    #[derive(serde::Deserialize)]
+    struct ValueMonitor {
+        value: String,
+    }
+    let Some(MonitorRequest { aci }) = parse_json_body(request) else {
+        return empty(400);
+    };
+    let Some(aci) = Aci::parse_from_service_id_string(&aci.value) else {
+        return empty(400);
+    };
+    key_transparency_response(model.key_transparency.monitor.get(&aci).map(Vec::as_slice))
+}
+
+fn get_pre_keys(request: &FakeRequest, model: &mut FakeServerModel) -> chat::Response {
+    let Some(service_id) = ServiceId::parse_from_service_id_string(&request.params[0]) else {
+        return empty(400);
+    };
+    let requested_device = match request.params[1].as_str() {
+        "*" => None,
+        device_id => match device_id.parse::<u8>().ok().map(DeviceId::new) {
+            Some(Ok(device_id)) => Some(device_id),
+            _ => return empty(400),
+        },
+    };
+    let Some(account) = model.account_mut(service_id) else {
+        return empty(404);
+    };
+    let Some(identity_key) = account.identity_key else {
+        return empty(404);
+    };
+
+    let mut devices = vec![];
+    for (device_id, device) in &mut account.devices {
+        if requested_device.is_some_and(|requested| requested != *device_id) {
+            continue;
+        }
+        let (Some(signed_pre_key), Some(kyber_pre_key)) =
+            (&device.signed_pre_key, &device.kyber_pre_key)
+        else {
+            continue;
+        };
+        let signed_pre_key =
+            SignedPreKeyRecord::deserialize(signed_pre_key).expect("valid signed pre-key record");
+        let kyber_pre_key =
+            KyberPreKeyRecord::deserialize(kyber_pre_key).expect("valid Kyber pre-key record");
+        let pre_key = device.take_pre_key().map(|record| {
+            let record = PreKeyRecord::deserialize(&record).expect("valid pre-key record");
+            serde_json::json!({
+                "keyId": u32::from(record.id().expect("valid")),
+                "publicKey": BASE64_STANDARD.encode(record.public_key().expect("valid").serialize()),
+            })
+        });
+        devices.push(serde_json::json!({
+            "deviceId": u8::from(*device_id),
+            "registrationId": device.registration_id,
+            "signedPreKey": {
+                "keyId": u32::from(signed_pre_key.id().expect("valid")),
+                "publicKey": BASE64_STANDARD.encode(signed_pre_key.public_key().expect("valid").serialize()),
+                "signature": BASE64_STANDARD.encode(signed_pre_key.signature().expect("valid")),
+            },
+            "preKey": pre_key,
+            "pqPreKey": {
+                "keyId": u32::from(kyber_pre_key.id().expect("valid")),
+                "publicKey": BASE64_STANDARD.encode(kyber_pre_key.public_key().expect("valid").serialize()),
+                "signature": BASE64_STANDARD.encode(kyber_pre_key.signature().expect("valid")),
+            },
+        }));
+    }
+    if devices.is_empty() {
+        return empty(404);
+    }
+
+    json(
+        200,
+        &serde_json::json!({
+            "identityKey": BASE64_STANDARD.encode(identity_key.serialize()),
+            "devices": devices,
+        }),
+    )
+}
+
+fn send_multi_recipient_message(
+    request: &FakeRequest,
+    model: &mut FakeServerModel,
+) -> chat::Response {
+    let Some(message) = request
+        .body
+        .as_deref()
+        .and_then(|body| SealedSenderV2SentMessage::parse(body).ok())
+    else {
+        return empty(400);
+    };
+    let timestamp = request
+        .query_param("ts")
+        .and_then(|ts| ts.parse::<u64>().ok())
+        .unwrap_or_default();
+    let urgent = request.query_param("urgent") != Some("false");
+    let story = request.query_param("story") == Some("true");
+
+    // Like the real server, reject the whole message if any recipient's device list doesn't match
+    // (409), or else if any registration IDs are out of date (410).
+    let mut mismatched = vec![];
+    let mut stale = vec![];
+    for (service_id, recipient) in &message.recipients {
+        let Some(account) = model.account(*service_id) else {
+            continue;
+        };
+        let sent_devices = BTreeMap::from_iter(recipient.devices.iter().copied());
+        let missing_devices = account
+            .devices
+            .keys()
+            .filter(|device_id| !sent_devices.contains_key(*device_id))
+            .map(|&device_id| u8::from(device_id))
+            .collect::<Vec<_>>();
+        let extra_devices = sent_devices
+            .keys()
+            .filter(|device_id| !account.devices.contains_key(*device_id))
+            .map(|&device_id| u8::from(device_id))
+            .collect::<Vec<_>>();
+        if !missing_devices.is_empty() || !extra_devices.is_empty() {
+            mismatched.push(serde_json::json!({
+                "uuid": service_id.service_id_string(),
+                "devices": {
+                    "missingDevices": missing_devices,
+                    "extraDevices": extra_devices,
+                },
+            }));
+            continue;
+        }
+        let stale_devices = sent_devices
+            .iter()
+            .filter(|(device_id, registration_id)| {
+                account.devices[*device_id].registration_id != **registration_id
+            })
+            .map(|(&device_id, _)| u8::from(device_id))
+            .collect::<Vec<_>>();
+        if !stale_devices.is_empty() {
+            stale.push(serde_json::json!({
+                "uuid": service_id.service_id_string(),
+                "devices": { "staleDevices": stale_devices },
+            }));
+        }
+    }
+    if !mismatched.is_empty() {
+        return json(409, &mismatched.into());
+    }
+    if !stale.is_empty() {
+        return json(410, &stale.into());
+    }
+
+    let mut uuids404 = vec![];
+    for (service_id, recipient) in &message.recipients {
+        let Some(account) = model.account_mut(*service_id) else {
+            uuids404.push(service_id.service_id_string());
+            continue;
+        };
+        let content = message
+            .received_message_parts_for_recipient(recipient)
+            .as_ref()
+            .concat();
+        for (device_id, _registration_id) in &recipient.devices {
+            let device = account.devices.get_mut(device_id).expect("checked above");
+            device.queue.push_back(Envelope {
+                r#type: Some(EnvelopeType::UnidentifiedSender.into()),
+                destination_service_id_binary: Some(service_id.service_id_binary().into()),
+                timestamp: Some(timestamp),
+                server_timestamp: Some(timestamp),
+                content: Some(content.clone().into()),
+                urgent: Some(urgent),
+                story: Some(story),
+                ..Default::default()
+            });
+        }
+    }
+
+    json(200, &serde_json::json!({ "uuids404": uuids404 }))
+}
+
+fn parse_json_body<T: serde::de::DeserializeOwned>(request: &FakeRequest) -> Option<T> {
+    serde_json::from_slice(request.body.as_deref()?).ok()
+}
+
+fn take_request_id(next_request_id: &mut u64) -> u64 {
+    let id = *next_request_id;
+    *next_request_id += 1;
+    id
+}
+
+fn request_from_proto(proto: RequestProto) -> Option<chat::Request> {
+    let RequestProto {
+        verb,
+        path,
+        body,
+        headers,
+        id: _,
+    } = proto;
+    let headers = headers
+        .iter()
+        .map(|header| {
+            let (name, value) = header.split_once(':')?;
+            Some((
+                http::HeaderName::try_from(name).ok()?,
+                http::HeaderValue::from_str(value.trim()).ok()?,
+            ))
+        })
+        .collect::<Option<_>>()?;
+    Some(chat::Request {
+        method: verb?.parse().ok()?,
+        path: path?.parse().ok()?,
+        headers,
+        body,
+    })
+}
+
+fn response_to_proto(id: Option<u64>, response: chat::Response) -> ResponseProto {
+    let chat::Response {
+        status,
+        message,
+        headers,
+        body,
+    } = response;
+    ResponseProto {
+        id,
+        status: Some(status.as_u16().into()),
+        message: message.or_else(|| status.canonical_reason().map(ToOwned::to_owned)),
+        headers: headers
+            .iter()
+            .filter_map(|(name, value)| Some(format!("{name}: {}", value.to_str().ok()?)))
+            .collect(),
+        body,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use futures_util::FutureExt as _;
+    use libsignal_net::chat::ChatConnection;
+    use libsignal_net::chat::server_requests::ServerEvent;
+    use libsignal_net::chat::ws::ListenerEvent;
+    use tokio::sync::mpsc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::api::keytrans::LowLevelChatApi as _;
+    use crate::api::messages::{
+        MismatchedDeviceError, MultiRecipientSendAuthorization, MultiRecipientSendFailure,
+        UnauthenticatedChatApi as _,
+    };
+    use crate::api::profiles::{GetProfileError, Profile, UnauthenticatedChatApi as _};
+    use crate::api::usernames::UnauthenticatedChatApi as _;
+    use crate::api::{RequestError, Unauth, UserBasedAuthorization};
+
+    const ACI: Aci = Aci::from_uuid_bytes([0xaa; 16]);
+    const PNI: Pni = Pni::from_uuid_bytes([0xbb; 16]);
+    const OTHER_ACI: Aci = Aci::from_uuid_bytes([0xcc; 16]);
+    const ACCESS_KEY: UserBasedAuthorization =
+        UserBasedAuthorization::AccessKey([0; zkgroup::ACCESS_KEY_LEN]);
+
+    fn device_id(id: u8) -> DeviceId {
+        DeviceId::new(id).expect("valid")
+    }
+
+    /// Builds a sealed sender v2 message addressed to `recipients`' devices, with placeholder
+    /// ciphertext.
+    fn multi_recipient_message(recipients: &[(Aci, &[(u8, u16)])]) -> Bytes {
+        let mut message = vec![0x22, recipients.len().try_into().expect("small")];
+        for (aci, devices) in recipients {
+            message.extend_from_slice(Uuid::from(*aci).as_bytes());
+            for (i, (device_id, registration_id)) in devices.iter().enumerate() {
+                let has_more = if i + 1 < devices.len() { 0x8000 } else { 0 };
+                message.push(*device_id);
+                message.extend_from_slice(&(registration_id | has_more).to_be_bytes());
+            }
+            message.extend_from_slice(&[0; 48]);
+        }
+        message.extend_from_slice(&[0x5a; 64]);
+        message.into()
+    }
+
+    fn send_multi_recipient_message(
+        server: &FakeChatServer,
+        message: Bytes,
+    ) -> Result<
+        crate::api::messages::MultiRecipientMessageResponse,
+        RequestError<MultiRecipientSendFailure>,
+    > {
+        Unauth(server.clone())
+            .send_multi_recipient_message(
+                message,
+                libsignal_protocol::Timestamp::from_epoch_millis(1234),
+                MultiRecipientSendAuthorization::Story,
+                false,
+                true,
+            )
+            .now_or_never()
+            .expect("sync")
+    }
+
+    #[test]
+    fn username_hash_lookup_uses_model() {
+        let server = FakeChatServer::with_default_routes();
+        let hash = [0x00, 0xff, 0xff, 0xff];
+        server.with_model(|model| {
+            model.add_account(ACI, PNI).username_hash = Some(hash.to_vec());
+        });
+
+        let found = Unauth(server.clone())
+            .look_up_username_hash(&hash)
+            .now_or_never()
+            .expect("sync")
+            .expect("success");
+        assert_eq!(found, Some(ACI));
+
+        let not_found = Unauth(server)
+            .look_up_username_hash(&[1, 2, 3])
+            .now_or_never()
+            .expect("sync")
+            .expect("success");
+        assert_eq!(not_found, None);
+    }
+
+    #[test]
+    fn get_profile_uses_model() {
+        let mut rng = rand::rng();
+        let profile_key = zkgroup::profiles::ProfileKey::create(zkgroup::TEST_ARRAY_32_1);
+        let identity_key = libsignal_protocol::KeyPair::generate(&mut rng).public_key;
+        let cipher = zkgroup::profiles::ProfileCipher::new(profile_key);
+        let name = zkgroup::profiles::ProfileName {
+            given_name: "Alice".to_owned(),
+            family_name: None,
+        };
+
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            let account = model.add_account(ACI, PNI);
+            account.identity_key = Some(identity_key);
+            account.profile = Some(FakeProfile {
+                name: Some(
+                    cipher
+                        .encrypt_name(zkgroup::TEST_ARRAY_32_2, &name)
+                        .expect("short enough"),
+                ),
+                avatar: Some("profiles/abcdef".to_owned()),
+                ..FakeProfile::new(ACI, profile_key)
+            });
+        });
+
+        let profile = Unauth(server.clone())
+            .get_profile(ACI, profile_key, ACCESS_KEY)
+            .now_or_never()
+            .expect("sync")
+            .expect("success");
+        assert_eq!(
+            profile,
+            Profile {
+                identity_key,
+                name: Some(name),
+                about: None,
+                about_emoji: None,
+                avatar_path: Some("profiles/abcdef".to_owned()),
+                payment_address: None,
+                unrestricted_unidentified_access: false,
+            }
+        );
+
+        let other_profile_key = zkgroup::profiles::ProfileKey::create(zkgroup::TEST_ARRAY_32_3);
+        let wrong_version = Unauth(server)
+            .get_profile(ACI, other_profile_key, ACCESS_KEY)
+            .now_or_never()
+            .expect("sync");
+        assert_matches!(
+            wrong_version,
+            Err(RequestError::Other(GetProfileError::VersionNotFound))
+        );
+    }
+
+    #[test]
+    fn issues_profile_key_credentials() {
+        let zkgroup_params = zkgroup::ServerSecretParams::generate(zkgroup::TEST_ARRAY_32);
+        let public_params = zkgroup_params.get_public_params();
+        let profile_key = zkgroup::profiles::ProfileKey::create(zkgroup::TEST_ARRAY_32_1);
+
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            model.zkgroup_params = Some(zkgroup_params);
+            let account = model.add_account(ACI, PNI);
+            account.identity_key =
+                Some(libsignal_protocol::KeyPair::generate(&mut rand::rng()).public_key);
+            account.profile = Some(FakeProfile::new(ACI, profile_key));
+        });
+
+        let context = public_params.create_profile_key_credential_request_context(
+            zkgroup::TEST_ARRAY_32_2,
+            ACI,
+            profile_key,
+        );
+        let response = Unauth(server)
+            .get_profile_key_credential(ACI, profile_key, context.get_request(), ACCESS_KEY)
+            .now_or_never()
+            .expect("sync")
+            .expect("success");
+        public_params
+            .receive_expiring_profile_key_credential(&context, &response, SystemTime::now().into())
+            .expect("valid credential");
+    }
+
+    #[test]
+    fn key_transparency_returns_canned_responses() {
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            model.key_transparency.distinguished = Some(b"distinguished".to_vec());
+            model
+                .key_transparency
+                .search
+                .insert(ACI, b"search".to_vec());
+        });
+
+        let distinguished = Unauth(server.clone())
+            .distinguished(None)
+            .now_or_never()
+            .expect("sync")
+            .expect("success");
+        assert_eq!(distinguished, b"distinguished");
+
+        let search = |aci: Aci| {
+            server.handle(chat::Request {
+                method: http::Method::POST,
+                path: http::uri::PathAndQuery::from_static("/v1/key-transparency/search"),
+                headers: http::HeaderMap::new(),
+                body: Some(
+                    serde_json::to_vec(&serde_json::json!({
+                        "aci": aci.service_id_string(),
+                        "aciIdentityKey": "",
+                        "distinguishedTreeHeadSize": 1,
+                    }))
+                    .expect("can serialize")
+                    .into(),
+                ),
+            })
+        };
+        let found = search(ACI);
+        assert_eq!(found.status, http::StatusCode::OK);
+        let body: serde_json::Value =
+            serde_json::from_slice(found.body.as_deref().expect("has body")).expect("JSON");
+        assert_eq!(
+            body,
+            serde_json::json!({ "serializedResponse": BASE64_STANDARD_NO_PAD.encode(b"search") })
+        );
+        assert_eq!(search(OTHER_ACI).status, http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn serves_pre_keys_from_model() {
+        let mut rng = rand::rng();
+        let identity_key = libsignal_protocol::IdentityKeyPair::generate(&mut rng);
+        let signed_key_pair = libsignal_protocol::KeyPair::generate(&mut rng);
+        let signed_pre_key = SignedPreKeyRecord::new(
+            7u32.into(),
+            libsignal_protocol::Timestamp::from_epoch_millis(1234),
+            &signed_key_pair,
+            &identity_key
+                .private_key()
+                .calculate_signature(&signed_key_pair.public_key.serialize(), &mut rng)
+                .expect("can sign"),
+        );
+        let kyber_pre_key = KyberPreKeyRecord::generate(
+            libsignal_protocol::kem::KeyType::Kyber1024,
+            8u32.into(),
+            identity_key.private_key(),
+        )
+        .expect("can generate");
+        let one_time_pre_key = PreKeyRecord::new(
+            9u32.into(),
+            &libsignal_protocol::KeyPair::generate(&mut rng),
+        );
+
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            let account = model.add_account(ACI, PNI);
+            account.identity_key = Some(*identity_key.public_key());
+            let device = account
+                .devices
+                .get_mut(&PRIMARY_DEVICE_ID)
+                .expect("has primary");
+            device.registration_id = 1234;
+            device.signed_pre_key = Some(signed_pre_key.serialize().expect("valid").into());
+            device.kyber_pre_key = Some(kyber_pre_key.serialize().expect("valid").into());
+            device
+                .one_time_pre_keys
+                .push_back(one_time_pre_key.serialize().expect("valid").into());
+        });
+
+        let get_pre_keys = || {
+            let response = server.handle(chat::Request {
+                method: http::Method::GET,
+                path: format!("/v2/keys/{}/1", ACI.service_id_string())
+                    .parse()
+                    .expect("valid"),
+                headers: http::HeaderMap::new(),
+                body: None,
+            });
+            assert_eq!(response.status, http::StatusCode::OK);
+            serde_json::from_slice::<serde_json::Value>(response.body.as_deref().expect("has body"))
+                .expect("JSON")
+        };
+
+        let first = get_pre_keys();
+        assert_eq!(
+            first["identityKey"],
+            BASE64_STANDARD.encode(identity_key.public_key().serialize())
+        );
+        let device = &first["devices"][0];
+        assert_eq!(device["deviceId"], 1);
+        assert_eq!(device["registrationId"], 1234);
+        assert_eq!(device["signedPreKey"]["keyId"], 7);
+        assert_eq!(device["pqPreKey"]["keyId"], 8);
+        assert_eq!(device["preKey"]["keyId"], 9);
+        assert_eq!(
+            device["preKey"]["publicKey"],
+            BASE64_STANDARD.encode(one_time_pre_key.public_key().expect("valid").serialize())
+        );
+
+        // The one-time pre-key has been used up.
+        let second = get_pre_keys();
+        assert_eq!(second["devices"][0]["preKey"], serde_json::Value::Null);
+        assert_eq!(second["devices"][0]["signedPreKey"]["keyId"], 7);
+    }
+
+    #[test]
+    fn multi_recipient_message_is_queued_for_each_device() {
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            let account = model.add_account(ACI, PNI);
+            account.devices.insert(device_id(2), FakeDevice::default());
+        });
+
+        let response = send_multi_recipient_message(
+            &server,
+            multi_recipient_message(&[(ACI, &[(1, 0), (2, 0)]), (OTHER_ACI, &[(1, 0)])]),
+        )
+        .expect("success");
+        assert_eq!(response.unregistered_ids, [ServiceId::from(OTHER_ACI)]);
+
+        server.with_model(|model| {
+            for device in model.accounts[0].devices.values() {
+                assert_eq!(device.queue.len(), 1);
+            }
+        });
+    }
+
+    #[test]
+    fn multi_recipient_message_rejects_mismatched_devices() {
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            let account = model.add_account(ACI, PNI);
+            account.devices.insert(device_id(2), FakeDevice::default());
+        });
+
+        let result = send_multi_recipient_message(
+            &server,
+            multi_recipient_message(&[(ACI, &[(1, 0), (3, 0)])]),
+        );
+        let errors = assert_matches!(
+            result,
+            Err(RequestError::Other(MultiRecipientSendFailure::MismatchedDevices(errors))) => errors
+        );
+        assert_eq!(
+            errors,
+            [MismatchedDeviceError {
+                account: ACI.into(),
+                missing_devices: vec![device_id(2)],
+                extra_devices: vec![device_id(3)],
+                stale_devices: vec![],
+            }]
+        );
+
+        // Nothing was delivered.
+        server.with_model(|model| {
+            for device in model.accounts[0].devices.values() {
+                assert!(device.queue.is_empty());
+            }
+        });
+    }
+
+    #[test]
+    fn multi_recipient_message_rejects_stale_devices() {
+        let server = FakeChatServer::with_default_routes();
+        server.with_model(|model| {
+            let account = model.add_account(ACI, PNI);
+            account
+                .devices
+                .get_mut(&PRIMARY_DEVICE_ID)
+                .expect("has primary")
+                .registration_id = 1234;
+        });
+
+        let result =
+            send_multi_recipient_message(&server, multi_recipient_message(&[(ACI, &[(1, 4321)])]));
+        let errors = assert_matches!(
+            result,
+            Err(RequestError::Other(MultiRecipientSendFailure::MismatchedDevices(errors))) => errors
+        );
+        assert_eq!(
+            errors,
+            [MismatchedDeviceError {
+                account: ACI.into(),
+                missing_devices: vec![],
+                extra_devices: vec![],
+                stale_devices: vec![PRIMARY_DEVICE_ID],
+            }]
+        );
+    }
+
+    #[test]
+    fn later_routes_take_precedence() {
+        let server = FakeChatServer::with_default_routes();
+        server.route(
+            http::Method::GET,
+            "/v1/accounts/username_hash/{}",
+            |request, _model| {
+                assert_eq!(request.params, ["AQID"]);
+                empty(500)
+            },
+        );
+
+        let response = server.handle(chat::Request {
+            method: http::Method::GET,
+            path: http::uri::PathAndQuery::from_static("/v1/accounts/username_hash/AQID"),
+            headers: http::HeaderMap::new(),
+            body: None,
+        });
+        assert_eq!(response.status, http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn unmatched_requests_are_recorded() {
+        let server = FakeChatServer::new();
+        let request = chat::Request {
+            method: http::Method::GET,
+            path: http::uri::PathAndQuery::from_static("/v1/nonexistent?x=1"),
+            headers: http::HeaderMap::new(),
+            body: None,
+        };
+        let response = server.handle(request.clone());
+        assert_eq!(response.status, http::StatusCode::NOT_FOUND);
+        server.with_model(|model| assert_eq!(model.unhandled_requests, [request]));
+    }
+
+    #[tokio::test]
+    async fn delivers_queued_envelopes_then_queue_empty() {
+        let server = FakeChatServer::new();
+        server.with_model(|model| {
+            let account = model.add_account(ACI, PNI);
+            let device = account
+                .devices
+                .get_mut(&PRIMARY_DEVICE_ID)
+                .expect("has primary");
+            device.queue.push_back(Envelope {
+                r#type: Some(EnvelopeType::Ciphertext.into()),
+                server_timestamp: Some(1234),
+                content: Some(Bytes::from_static(b"ciphertext")),
+                ..Default::default()
+            });
+        });
+
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let (_chat, remote) = ChatConnection::new_fake(
+            tokio::runtime::Handle::current(),
+            Box::new(move |event: ListenerEvent| {
+                if let Ok(event) = ServerEvent::try_from(event) {
+                    let _ignore_closed = events_tx.send(event);
+                }
+            }),
+            [],
+        );
+
+        let delivered = server
+            .deliver_queue(&remote, ACI.into(), PRIMARY_DEVICE_ID)
+            .expect("connected");
+        assert_eq!(delivered, 1);
+
+        let (envelope, server_delivery_timestamp) = assert_matches!(
+            events_rx.recv().await,
+            Some(ServerEvent::IncomingMessage { envelope, server_delivery_timestamp, .. }) =>
+                (envelope, server_delivery_timestamp)
+        );
+        assert_eq!(server_delivery_timestamp.epoch_millis(), 1234);
+        let envelope = Envelope::decode(envelope).expect("valid");
+        assert_eq!(envelope.content.as_deref(), Some(&b"ciphertext"[..]));
+
+        assert_matches!(events_rx.recv().await, Some(ServerEvent::QueueEmpty));
+        server.with_model(|model| {
+            assert!(
+                model.accounts[0].devices[&PRIMARY_DEVICE_ID]
+                    .queue
+                    .is_empty()
+            )
+        });
+    }
+}
@@ -7,8 +7,12 @@
 
 pub mod api;
 mod logging;
+pub mod outbox;
 pub mod registration;
 pub mod ws;
 
+#[cfg(feature = "test-util")]
+pub mod fake;
+
 #[cfg(feature = "grpc")]
 pub mod grpc;
@@ -0,0 +1,687 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A persistent queue of outgoing requests, sent in order with retries.
+//!
+//! [`WsConnection::send`] is one-shot: if the server says to retry later, or asks for a rate limit
+//! challenge, or the connection drops, the caller has to decide what to do. [`Outbox`] makes those
+//! decisions in one place:
+//!
+//! - Requests are persisted through an [`OutboxStore`] as soon as they're enqueued, and only
+//!   removed once the server has given a final answer.
+//! - `429 Too Many Requests` with a `Retry-After` header waits that long before trying again; other
+//!   server-side errors and timeouts back off exponentially, up to [`RetryConfig::max_attempts`].
+//! - `428 Precondition Required` with a challenge pauses the queue until the app reports the
+//!   challenge as completed via [`Outbox::challenge_completed`].
+//! - Disconnects stop the current drain without losing anything; calling [`Outbox::drain`] again
+//!   with a new connection picks up where it left off.
+//!
+//! Each request is assigned a timestamp when it's enqueued, which is persisted along with it and
+//! reused for every attempt. Newly assigned timestamps are always later than any still-pending
+//! one, even across restarts, so a resent message can never collide with a newer one.
+//!
+//! Delivery is at-least-once: if the app stops after the server accepted a request but before the
+//! store recorded that, the request will be sent again. Message recipients deduplicate by
+//! timestamp.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::time::{Duration, SystemTime};
+
+use libsignal_net::chat;
+use libsignal_protocol::Timestamp;
+
+use crate::api::{DisconnectedError, RateLimitChallenge, RequestError};
+use crate::ws::{CustomError, ResponseError, WsConnection};
+
+/// Identifies a request in an [`Outbox`].
+///
+/// IDs are never reused, even after the request they identified has completed.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct OutboxId(pub u64);
+
+/// A request that has not yet received a final response, in a form suitable for persisting.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PendingSend {
+    pub id: OutboxId,
+    pub timestamp: Timestamp,
+    /// A version of the request path that's safe to log.
+    pub log_safe_path: String,
+    pub method: String,
+    pub path: String,
+    /// Header names and values. Values are kept as bytes, since they aren't required to be UTF-8.
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Option<Vec<u8>>,
+    /// The number of attempts that have failed with a retryable error.
+    pub failed_attempts: u32,
+}
+
+/// Persistent storage for an [`Outbox`].
+pub trait OutboxStore: Send {
+    /// Returns every pending send that has been stored, in the order they were enqueued.
+    fn load(&mut self) -> Vec<PendingSend>;
+    /// Inserts or replaces the pending send with the same [`PendingSend::id`].
+    fn put(&mut self, send: &PendingSend);
+    /// Removes a pending send, once it no longer needs to be sent.
+    fn remove(&mut self, id: OutboxId);
+    /// Returns the ID most recently saved with [`Self::put_next_id`], or the default ID if there
+    /// hasn't been one.
+    fn load_next_id(&mut self) -> OutboxId;
+    /// Saves the ID to be assigned to the next enqueued send, so that IDs aren't reused once
+    /// every pending send has been removed.
+    fn put_next_id(&mut self, id: OutboxId);
+}
+
+/// An [`OutboxStore`] that keeps everything in memory.
+///
+/// Mostly useful for tests; it doesn't survive the process exiting.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryOutboxStore {
+    pub sends: Vec<PendingSend>,
+    pub next_id: OutboxId,
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    /// The delay before the first retry of a failed request that didn't specify one.
+    pub initial_backoff: Duration,
+    /// The longest delay between attempts that didn't specify one.
+    pub max_backoff: Duration,
+    /// The number of retryable failures after which a request is given up on.
+    pub max_attempts: u32,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_attempts: 8,
+        }
+    }
+}
+
+/// The final result of a request in an [`Outbox`].
+#[derive(Debug)]
+pub enum Outcome {
+    /// The server produced a response that shouldn't be retried.
+    ///
+    /// This isn't necessarily a success; it's up to the caller to interpret the status.
+    Responded(chat::Response),
+    /// The request failed too many times in a row.
+    GaveUp(RequestError<Infallible>),
+}
+
+/// A request that has been removed from an [`Outbox`].
+#[derive(Debug)]
+pub struct Completed {
+    pub id: OutboxId,
+    pub timestamp: Timestamp,
+    pub outcome: Outcome,
+}
+
+/// Why [`Outbox::drain`] stopped.
+#[derive(Debug)]
+pub enum DrainStopped {
+    /// Every request has been completed.
+    Empty,
+    /// The server requires a challenge to be completed before sending anything more.
+    ///
+    /// Call [`Outbox::challenge_completed`] once it's done, then drain again.
+    Challenge(RateLimitChallenge),
+    /// The connection was lost. Drain again once a new connection is available.
+    Disconnected(DisconnectedError),
+}
+
+/// A persistent queue of outgoing requests. See the [module-level documentation](self).
+pub struct Outbox<S> {
+    store: S,
+    config: RetryConfig,
+    pending: VecDeque<PendingSend>,
+    next_id: u64,
+    last_timestamp: Option<Timestamp>,
+    challenge: Option<RateLimitChallenge>,
+}
+
+impl<S: OutboxStore> Outbox<S> {
+    /// Creates an outbox, resuming any sends that were pending in `store`.
+    pub fn new(mut store: S, config: RetryConfig) -> Self {
+        let pending = VecDeque::from(store.load());
+        let next_id = pending
+            .iter()
+            .map(|send| send.id.0 + 1)
+            .chain([store.load_next_id().0])
+            .max()
+            .expect("not empty");
+        let last_timestamp = pending.iter().map(|send| send.timestamp).max();
+        Self {
+            store,
+            config,
+            pending,
+            next_id,
+            last_timestamp,
+            challenge: None,
+        }
+    }
+
+    /// The sends that have not yet completed, in order.
+    pub fn pending(&self) -> impl ExactSizeIterator<Item = &PendingSend> {
+        self.pending.iter()
+    }
+
+    /// The outstanding rate limit challenge, if the outbox is paused.
+    pub fn challenge(&self) -> Option<&RateLimitChallenge> {
+        self.challenge.as_ref()
+    }
+
+    /// Adds a request to the end of the queue and persists it.
+    ///
+    /// `make_request` is given the timestamp assigned to this request, which should be used for
+    /// any message timestamps within it.
+    pub fn enqueue(
+        &mut self,
+        log_safe_path: impl Into<String>,
+        make_request: impl FnOnce(Timestamp) -> chat::Request,
+    ) -> (OutboxId, Timestamp) {
+        let id = OutboxId(self.next_id);
+        self.next_id += 1;
+        self.store.put_next_id(OutboxId(self.next_id));
+        let timestamp = self.next_timestamp();
+
+        let chat::Request {
+            method,
+            path,
+            headers,
+            body,
+        } = make_request(timestamp);
+        let send = PendingSend {
+            id,
+            timestamp,
+            log_safe_path: log_safe_path.into(),
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+                .collect(),
+            body: body.map(Vec::from),
+            failed_attempts: 0,
+        };
+        self.store.put(&send);
+        self.pending.push_back(send);
+        (id, timestamp)
+    }
+
+    /// Reports that the rate limit challenge from [`DrainStopped::Challenge`] has been completed.
+    pub fn challenge_completed(&mut self) {
+        self.challenge = None;
+    }
+
+    /// Sends pending requests over `connection`, in order, until there are none left or sending
+    /// can't continue.
+    ///
+    /// `on_complete` is called for each request as it's removed from the queue.
+    pub async fn drain(
+        &mut self,
+        connection: &impl WsConnection,
+        mut on_complete: impl FnMut(Completed),
+    ) -> DrainStopped {
+        loop {
+            if let Some(challenge) = &self.challenge {
+                return DrainStopped::Challenge(challenge.clone());
+            }
+            let Some(send) = self.pending.front() else {
+                return DrainStopped::Empty;
+            };
+
+            let result = match send.to_request() {
+                Some(request) => {
+                    connection
+                        .send("outbox", &send.log_safe_path, request)
+                        .await
+                }
+                // Not something we can ever send; treat it as the server rejecting it.
+                None => Ok(chat::Response {
+                    status: http::StatusCode::BAD_REQUEST,
+                    message: Some("invalid request in outbox".to_owned()),
+                    headers: http::HeaderMap::new(),
+                    body: None,
+                }),
+            };
+
+            let retry_error = match result {
+                Ok(response) => match classify_response(response) {
+                    Ok(response) => {
+                        self.complete(Outcome::Responded(response), &mut on_complete);
+                        continue;
+                    }
+                    Err(e) => e,
+                },
+                Err(e) => RequestError::from(e),
+            };
+
+            match retry_error {
+                RequestError::Disconnected(e) => return DrainStopped::Disconnected(e),
+                RequestError::Challenge(challenge) => {
+                    log::info!("outbox paused for a rate limit challenge");
+                    self.challenge = Some(challenge.clone());
+                    return DrainStopped::Challenge(challenge);
+                }
+                RequestError::RetryLater(retry_later) => {
+                    let delay = Duration::from_secs(retry_later.retry_after_seconds.into());
+                    log::info!("outbox waiting {delay:?} before retrying, as requested");
+                    tokio::time::sleep(delay).await;
+                }
+                e @ (RequestError::Timeout
+                | RequestError::ServerSideError
+                | RequestError::Unexpected { .. }) => {
+                    let send = self.pending.front_mut().expect("still pending");
+                    send.failed_attempts += 1;
+                    if send.failed_attempts >= self.config.max_attempts {
+                        log::warn!(
+                            "outbox giving up after {} attempts: {}",
+                            send.failed_attempts,
+                            &e as &dyn libsignal_net::infra::errors::LogSafeDisplay
+                        );
+                        self.complete(Outcome::GaveUp(e), &mut on_complete);
+                        continue;
+                    }
+                    self.store.put(send);
+                    let failed_attempts = send.failed_attempts;
+                    let delay = self.backoff(failed_attempts);
+                    log::info!("outbox retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                }
+                RequestError::Other(infallible) => match infallible {},
+            }
+        }
+    }
+
+    fn complete(&mut self, outcome: Outcome, on_complete: &mut impl FnMut(Completed)) {
+        let send = self.pending.pop_front().expect("still pending");
+        self.store.remove(send.id);
+        on_complete(Completed {
+            id: send.id,
+            timestamp: send.timestamp,
+            outcome,
+        });
+    }
+
+    /// Picks a timestamp later than any that's still pending, preferring the current time.
+    fn next_timestamp(&mut self) -> Timestamp {
+        let now = Timestamp::from_epoch_millis(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("after the epoch")
+                .as_millis()
+                .try_into()
+                .expect("fits in u64"),
+        );
+        let timestamp = match self.last_timestamp {
+            Some(last) if last >= now => last.add_millis(1),
+            _ => now,
+        };
+        self.last_timestamp = Some(timestamp);
+        timestamp
+    }
+
+    fn backoff(&self, failed_attempts: u32) -> Duration {
+        let exponent = failed_attempts.saturating_sub(1).min(16);
+        self.config
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.config.max_backoff)
+    }
+}
+
+impl PendingSend {
+    fn to_request(&self) -> Option<chat::Request> {
+        Some(chat::Request {
+            method: self.method.parse().ok()?,
+            path: self.path.parse().ok()?,
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    Some((
+                        http::HeaderName::try_from(name).ok()?,
+                        http::HeaderValue::from_bytes(value).ok()?,
+                    ))
+                })
+                .collect::<Option<_>>()?,
+            body: self.body.clone().map(Into::into),
+        })
+    }
+}
+
+impl OutboxStore for InMemoryOutboxStore {
+    fn load(&mut self) -> Vec<PendingSend> {
+        self.sends.clone()
+    }
+
+    fn put(&mut self, send: &PendingSend) {
+        match self
+            .sends
+            .iter_mut()
+            .find(|existing| existing.id == send.id)
+        {
+            Some(existing) => *existing = send.clone(),
+            None => self.sends.push(send.clone()),
+        }
+    }
+
+    fn remove(&mut self, id: OutboxId) {
+        self.sends.retain(|send| send.id != id);
+    }
+
+    fn load_next_id(&mut self) -> OutboxId {
+        self.next_id
+    }
+
+    fn put_next_id(&mut self, id: OutboxId) {
+        self.next_id = id;
+    }
+}
+
+impl<T: OutboxStore + ?Sized> OutboxStore for &mut T {
+    fn load(&mut self) -> Vec<PendingSend> {
+        (**self).load()
+    }
+
+    fn put(&mut self, send: &PendingSend) {
+        (**self).put(send)
+    }
+
+    fn remove(&mut self, id: OutboxId) {
+        (**self).remove(id)
+    }
+
+    fn load_next_id(&mut self) -> OutboxId {
+        (**self).load_next_id()
+    }
+
+    fn put_next_id(&mut self, id: OutboxId) {
+        (**self).put_next_id(id)
+    }
+}
+
+/// Separates final responses from ones that should be retried.
+///
+/// Errors other than the ones [`Outbox::drain`] retries are considered final.
+fn classify_response(response: chat::Response) -> Result<chat::Response, RequestError<Infallible>> {
+    if response.status.is_success() {
+        return Ok(response);
+    }
+    let error = ResponseError::UnrecognizedStatus {
+        status: response.status,
+        response: response.clone(),
+    }
+    .into_request_error(|_| CustomError::<Infallible>::NoCustomHandling);
+    match error {
+        RequestError::RetryLater(_)
+        | RequestError::Challenge(_)
+        | RequestError::ServerSideError
+        | RequestError::Timeout
+        | RequestError::Disconnected(_) => Err(error),
+        RequestError::Unexpected { .. } => Ok(response),
+        RequestError::Other(infallible) => match infallible {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use assert_matches::assert_matches;
+    use libsignal_net::infra::AsStaticHttpHeader as _;
+    use libsignal_net::infra::errors::RetryLater;
+
+    use super::*;
+    use crate::fake::{FakeChatServer, empty, json};
+
+    fn put_request(path: &'static str) -> impl FnOnce(Timestamp) -> chat::Request {
+        move |timestamp| chat::Request {
+            method: http::Method::PUT,
+            path: format!("{path}?ts={}", timestamp.epoch_millis())
+                .parse()
+                .expect("valid"),
+            headers: http::HeaderMap::new(),
+            body: Some(b"body"[..].into()),
+        }
+    }
+
+    /// A route that produces each of `responses` in turn, then 200 OK forever.
+    fn scripted(server: &FakeChatServer, responses: Vec<chat::Response>) -> Arc<AtomicUsize> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut responses = VecDeque::from(responses);
+        server.route(http::Method::PUT, "/v1/test", {
+            let count = count.clone();
+            move |_request, _model| {
+                count.fetch_add(1, Ordering::SeqCst);
+                responses.pop_front().unwrap_or_else(|| empty(200))
+            }
+        });
+        count
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_in_order_and_removes_from_store() {
+        let server = FakeChatServer::new();
+        let attempts = scripted(&server, vec![]);
+        let mut store = InMemoryOutboxStore::default();
+        let mut outbox = Outbox::new(&mut store, RetryConfig::default());
+
+        let (first, first_timestamp) = outbox.enqueue("/v1/test", put_request("/v1/test"));
+        let (second, second_timestamp) = outbox.enqueue("/v1/test", put_request("/v1/test"));
+        assert!(first_timestamp < second_timestamp);
+
+        let mut completed = vec![];
+        let stopped = outbox
+            .drain(&server, |c| completed.push((c.id, c.outcome)))
+            .await;
+        assert_matches!(stopped, DrainStopped::Empty);
+        assert_matches!(
+            &completed[..],
+            [
+                (id1, Outcome::Responded(r1)),
+                (id2, Outcome::Responded(r2)),
+            ] if *id1 == first && *id2 == second && r1.status == 200 && r2.status == 200
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        drop(outbox);
+        assert!(store.sends.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ids_are_not_reused_after_store_empties() {
+        let server = FakeChatServer::new();
+        scripted(&server, vec![]);
+        let mut store = InMemoryOutboxStore::default();
+
+        let first = {
+            let mut outbox = Outbox::new(&mut store, RetryConfig::default());
+            let (id, _) = outbox.enqueue("/v1/test", put_request("/v1/test"));
+            assert_matches!(outbox.drain(&server, |_| {}).await, DrainStopped::Empty);
+            id
+        };
+        assert!(store.sends.is_empty());
+
+        let mut outbox = Outbox::new(&mut store, RetryConfig::default());
+        let (second, _) = outbox.enqueue("/v1/test", put_request("/v1/test"));
+        assert!(second > first);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn preserves_non_utf8_header_values() {
+        const HEADER_VALUE: &[u8] = b"caf\xe9";
+        let server = FakeChatServer::new();
+        server.route(http::Method::PUT, "/v1/test", |request, _model| {
+            assert_eq!(request.headers["x-test"].as_bytes(), HEADER_VALUE);
+            empty(204)
+        });
+
+        let mut store = InMemoryOutboxStore::default();
+        let mut outbox = Outbox::new(&mut store, RetryConfig::default());
+        outbox.enqueue("/v1/test", |timestamp| {
+            let mut request = put_request("/v1/test")(timestamp);
+            request.headers.insert(
+                http::HeaderName::from_static("x-test"),
+                http::HeaderValue::from_bytes(HEADER_VALUE).expect("valid"),
+            );
+            request
+        });
+        assert_eq!(
+            outbox.pending().next().expect("enqueued").headers,
+            [("x-test".to_owned(), HEADER_VALUE.to_vec())]
+        );
+
+        let mut completed = vec![];
+        outbox.drain(&server, |c| completed.push(c.outcome)).await;
+        assert_matches!(&completed[..], [Outcome::Responded(r)] if r.status == 204);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn honors_retry_later() {
+        let server = FakeChatServer::new();
+        let mut retry_later = empty(429);
+        retry_later.headers.insert(
+            RetryLater::HEADER_NAME,
+            http::HeaderValue::from_static("30"),
+        );
+        let attempts = scripted(&server, vec![retry_later]);
+
+        let mut outbox = Outbox::new(InMemoryOutboxStore::default(), RetryConfig::default());
+        outbox.enqueue("/v1/test", put_request("/v1/test"));
+
+        let start = tokio::time::Instant::now();
+        let stopped = outbox.drain(&server, |_| {}).await;
+        assert_matches!(stopped, DrainStopped::Empty);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_secs(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pauses_for_challenge() {
+        let server = FakeChatServer::new();
+        let attempts = scripted(
+            &server,
+            vec![json(
+                428,
+                &serde_json::json!({"token": "abc", "options": ["captcha"]}),
+            )],
+        );
+
+        let mut outbox = Outbox::new(InMemoryOutboxStore::default(), RetryConfig::default());
+        let (id, _) = outbox.enqueue("/v1/test", put_request("/v1/test"));
+
+        let stopped = outbox.drain(&server, |_| unreachable!()).await;
+        let challenge = assert_matches!(stopped, DrainStopped::Challenge(c) => c);
+        assert_eq!(challenge.token, "abc");
+
+        // Still paused until the challenge is reported as done.
+        assert_matches!(
+            outbox.drain(&server, |_| unreachable!()).await,
+            DrainStopped::Challenge(_)
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        outbox.challenge_completed();
+        let mut completed = vec![];
+        assert_matches!(
+            outbox.drain(&server, |c| completed.push(c.id)).await,
+            DrainStopped::Empty
+        );
+        assert_eq!(completed, [id]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let server = FakeChatServer::new();
+        let attempts = scripted(&server, vec![empty(500), empty(503), empty(500)]);
+
+        let mut outbox = Outbox::new(
+            InMemoryOutboxStore::default(),
+            RetryConfig {
+                max_attempts: 3,
+                ..Default::default()
+            },
+        );
+        outbox.enqueue("/v1/test", put_request("/v1/test"));
+
+        let mut completed = vec![];
+        outbox.drain(&server, |c| completed.push(c.outcome)).await;
+        assert_matches!(
+            &completed[..],
+            [Outcome::GaveUp(RequestError::ServerSideError)]
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    struct AlwaysDisconnected;
+
+    impl WsConnection for AlwaysDisconnected {
+        fn send(
+            &self,
+            _log_tag: &'static str,
+            _log_safe_path: &str,
+            _request: chat::Request,
+        ) -> impl std::future::Future<Output = Result<chat::Response, chat::SendError>> + Send
+        {
+            std::future::ready(Err(chat::SendError::Disconnected))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resumes_after_disconnect_with_same_timestamp() {
+        let mut store = InMemoryOutboxStore::default();
+        let (id, timestamp) = {
+            let mut outbox = Outbox::new(&mut store, RetryConfig::default());
+            let enqueued = outbox.enqueue("/v1/test", put_request("/v1/test"));
+            assert_matches!(
+                outbox.drain(&AlwaysDisconnected, |_| unreachable!()).await,
+                DrainStopped::Disconnected(DisconnectedError::Closed)
+            );
+            enqueued
+        };
+        assert_eq!(store.sends.len(), 1);
+
+        // Simulate the app restarting with a new connection.
+        let server = FakeChatServer::new();
+        let expected_path = format!("/v1/test?ts={}", timestamp.epoch_millis());
+        server.route(http::Method::PUT, "/v1/test", move |request, _model| {
+            assert_eq!(
+                format!(
+                    "{}?{}",
+                    request.path,
+                    request.query.as_deref().unwrap_or_default()
+                ),
+                expected_path
+            );
+            empty(204)
+        });
+        let mut outbox = Outbox::new(&mut store, RetryConfig::default());
+        let (_, new_timestamp) = outbox.enqueue("/v1/test", put_request("/v1/test"));
+        assert!(new_timestamp > timestamp);
+
+        let mut completed = vec![];
+        outbox
+            .drain(&server, |c| completed.push((c.id, c.timestamp)))
+            .await;
+        assert_eq!(completed[0], (id, timestamp));
+    }
+}
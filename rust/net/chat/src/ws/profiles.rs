@@ -5,12 +5,14 @@
 
 use async_trait::async_trait;
 use libsignal_core::Aci;
+use libsignal_core::curve::PublicKey;
 use libsignal_net::chat::Request;
 use libsignal_net::infra::AsHttpHeader as _;
 use serde_with::serde_as;
+use zkgroup::profiles::{ProfileCipher, ProfileCipherError};
 
 use super::{CustomError, TryIntoResponse as _, WsConnection};
-use crate::api::profiles::ProfileKeyCredentialRequestError;
+use crate::api::profiles::{GetProfileError, Profile, ProfileKeyCredentialRequestError};
 use crate::api::{RequestError, Unauth, UserBasedAuthorization};
 use crate::logging::{Redact, RedactHex};
 
@@ -19,6 +21,99 @@ type Base64Padded =
 
 #[async_trait]
 impl<T: WsConnection> crate::api::profiles::UnauthenticatedChatApi for Unauth<T> {
+    async fn get_profile(
+        &self,
+        peer_aci: Aci,
+        profile_key: zkgroup::profiles::ProfileKey,
+        auth: UserBasedAuthorization,
+    ) -> Result<Profile, RequestError<GetProfileError>> {
+        let profile_key_version = profile_key.get_profile_key_version(peer_aci);
+        let response = self
+            .send(
+                "unauth",
+                &format!(
+                    "/v1/profile/{}/{}",
+                    Redact(&peer_aci),
+                    RedactHex(profile_key_version.as_ref()),
+                ),
+                Request {
+                    method: http::Method::GET,
+                    path: format!(
+                        "/v1/profile/{}/{}",
+                        peer_aci.service_id_string(),
+                        profile_key_version.as_ref(),
+                    )
+                    .parse()
+                    .expect("valid"),
+                    headers: http::HeaderMap::from_iter([auth.as_header()]),
+                    body: None,
+                },
+            )
+            .await?;
+
+        #[serde_as]
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct GetProfileResponse {
+            #[serde_as(as = "Base64Padded")]
+            identity_key: Vec<u8>,
+            #[serde_as(as = "Option<Base64Padded>")]
+            #[serde(default)]
+            name: Option<Vec<u8>>,
+            #[serde_as(as = "Option<Base64Padded>")]
+            #[serde(default)]
+            about: Option<Vec<u8>>,
+            #[serde_as(as = "Option<Base64Padded>")]
+            #[serde(default)]
+            about_emoji: Option<Vec<u8>>,
+            #[serde(default)]
+            avatar: Option<String>,
+            #[serde_as(as = "Option<Base64Padded>")]
+            #[serde(default)]
+            payment_address: Option<Vec<u8>>,
+            #[serde(default)]
+            unrestricted_unidentified_access: bool,
+        }
+
+        let GetProfileResponse {
+            identity_key,
+            name,
+            about,
+            about_emoji,
+            avatar,
+            payment_address,
+            unrestricted_unidentified_access,
+        } = response.try_into_response().map_err(|e| {
+            e.into_request_error(|response| {
+                CustomError::Err(match response.status.as_u16() {
+                    401 => GetProfileError::AuthFailed,
+                    404 => GetProfileError::VersionNotFound,
+                    _ => return CustomError::NoCustomHandling,
+                })
+            })
+        })?;
+
+        let identity_key =
+            PublicKey::deserialize(&identity_key).map_err(|e| RequestError::Unexpected {
+                log_safe: format!("invalid identity key: {e}"),
+            })?;
+
+        let cipher = ProfileCipher::new(profile_key);
+        Ok(Profile {
+            name: decrypt_profile_field("name", name, |e| cipher.decrypt_name(e))?,
+            about: decrypt_profile_field("about", about, |e| cipher.decrypt_about(e))?,
+            about_emoji: decrypt_profile_field("about emoji", about_emoji, |e| {
+                cipher.decrypt_about_emoji(e)
+            })?,
+            avatar_path: avatar.filter(|path| !path.is_empty()),
+            payment_address: decrypt_profile_field("payment address", payment_address, |e| {
+                cipher.decrypt_payment_address(e, &identity_key)
+            })?,
+            identity_key,
+            unrestricted_unidentified_access,
+        })
+    }
+
     async fn get_profile_key_credential(
         &self,
         peer_aci: Aci,
@@ -81,6 +176,22 @@ impl<T: WsConnection> crate::api::profiles::UnauthenticatedChatApi for Unauth<T>
     }
 }
 
+/// Decrypts an optional profile field, treating an empty value (as left by clients that clear a
+/// field rather than omitting it) the same as an absent one.
+fn decrypt_profile_field<V>(
+    field_name: &str,
+    encrypted: Option<Vec<u8>>,
+    decrypt: impl FnOnce(&[u8]) -> Result<V, ProfileCipherError>,
+) -> Result<Option<V>, RequestError<GetProfileError>> {
+    encrypted
+        .filter(|encrypted| !encrypted.is_empty())
+        .map(|encrypted| decrypt(&encrypted))
+        .transpose()
+        .map_err(|e| RequestError::Unexpected {
+            log_safe: format!("invalid profile {field_name}: {e}"),
+        })
+}
+
 #[cfg(test)]
 mod test {
     use base64::Engine as _;
@@ -96,6 +207,113 @@ mod test {
 
     const ACI_UUID: &str = "9d0652a3-dcc3-4d11-975f-74d61598733f";
 
+    #[tokio::test]
+    async fn test_get_profile() {
+        let mut rng = rand::rng();
+        let aci = Aci::parse_from_service_id_string(ACI_UUID).expect("valid");
+        let profile_key = zkgroup::profiles::ProfileKey::create(zkgroup::TEST_ARRAY_32_1);
+        let identity_key = libsignal_protocol::KeyPair::generate(&mut rng);
+
+        let cipher = ProfileCipher::new(profile_key);
+        let name = zkgroup::profiles::ProfileName {
+            given_name: "Alice".to_owned(),
+            family_name: Some("Liddell".to_owned()),
+        };
+        let payment_address = zkgroup::profiles::MobileCoinAddress::sign(
+            vec![0x5a; 100],
+            &identity_key.private_key,
+            &mut rng,
+        );
+        let encrypted_name = cipher
+            .encrypt_name(zkgroup::TEST_ARRAY_32_2, &name)
+            .expect("short enough");
+        let encrypted_about_emoji = cipher
+            .encrypt_about_emoji(zkgroup::TEST_ARRAY_32_3, "🐇")
+            .expect("short enough");
+        let encrypted_payment_address = cipher
+            .encrypt_payment_address(zkgroup::TEST_ARRAY_32_4, &payment_address)
+            .expect("short enough");
+
+        let validator = RequestValidator {
+            expected: Request {
+                method: http::Method::GET,
+                path: http::uri::PathAndQuery::from_static(concat!(
+                    "/v1/profile/9d0652a3-dcc3-4d11-975f-74d61598733f",
+                    "/f74078448aa501a163593a4c0b2ec4644b27a2a747639bb1a5e2af71ff355d9c",
+                )),
+                headers: http::HeaderMap::from_iter([(
+                    ACCESS_KEY_HEADER_NAME,
+                    http::HeaderValue::from_static("AAAAAAAAAAAAAAAAAAAAAA=="),
+                )]),
+                body: None,
+            },
+            response: json(
+                200,
+                serde_json::json!({
+                    "identityKey": BASE64_STANDARD.encode(identity_key.public_key.serialize()),
+                    "name": BASE64_STANDARD.encode(&encrypted_name),
+                    "about": "",
+                    "aboutEmoji": BASE64_STANDARD.encode(&encrypted_about_emoji),
+                    "avatar": "profiles/abcdef",
+                    "paymentAddress": BASE64_STANDARD.encode(&encrypted_payment_address),
+                    "unidentifiedAccess": "AAAAAAAAAAAAAAAAAAAAAA==",
+                    "unrestrictedUnidentifiedAccess": false,
+                    "capabilities": {},
+                    "badges": [],
+                })
+                .to_string(),
+            ),
+        };
+
+        let profile = Unauth(validator)
+            .get_profile(
+                aci,
+                profile_key,
+                UserBasedAuthorization::AccessKey([0; zkgroup::ACCESS_KEY_LEN]),
+            )
+            .now_or_never()
+            .expect("sync")
+            .expect("success");
+
+        assert_eq!(
+            profile,
+            Profile {
+                identity_key: identity_key.public_key,
+                name: Some(name),
+                about: None,
+                about_emoji: Some("🐇".to_owned()),
+                avatar_path: Some("profiles/abcdef".to_owned()),
+                payment_address: Some(payment_address),
+                unrestricted_unidentified_access: false,
+            }
+        );
+    }
+
+    #[test_case(empty(401) => matches RequestError::Other(GetProfileError::AuthFailed))]
+    #[test_case(empty(404) => matches RequestError::Other(GetProfileError::VersionNotFound))]
+    #[test_case(json(200, r#"{"identityKey": "AA=="}"#) => matches RequestError::Unexpected { .. })]
+    #[test_case(json(
+        200,
+        r#"{"identityKey": "BdU7n+od1NVw2+OBgHZ8I2RWymYz8QPxqgY357YT0lJ0", "name": "AAAA"}"#
+    ) => matches RequestError::Unexpected { .. })]
+    #[test_case(empty(500) => matches RequestError::ServerSideError)]
+    #[tokio::test]
+    async fn test_get_profile_failures(response: chat::Response) -> RequestError<GetProfileError> {
+        let aci = Aci::parse_from_service_id_string(ACI_UUID).expect("valid");
+        let profile_key = zkgroup::profiles::ProfileKey::create(zkgroup::TEST_ARRAY_32_1);
+
+        Unauth(ProduceResponse(response))
+            .get_profile(
+                aci,
+                profile_key,
+                UserBasedAuthorization::AccessKey([0; zkgroup::ACCESS_KEY_LEN]),
+            )
+            .now_or_never()
+            .expect("sync")
+            .map(|_| ())
+            .expect_err("should have failed")
+    }
+
     #[tokio::test]
     async fn test_successful_request() {
         let randomness = zkgroup::TEST_ARRAY_32;
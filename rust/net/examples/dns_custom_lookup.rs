@@ -14,6 +14,7 @@ use libsignal_net::infra::dns::dns_lookup::{DnsLookup, DnsLookupRequest};
 use libsignal_net::infra::host::Host;
 use libsignal_net_infra::dns::dns_transport_doh::DohTransportConnectorFactory;
 use libsignal_net_infra::dns::dns_transport_udp::UdpTransportConnectorFactory;
+use libsignal_net_infra::dns::dnssec::TrustAnchor;
 use libsignal_net_infra::route::{
     HttpRouteFragment, HttpVersion, HttpsTlsRoute, TcpRoute, TlsRoute, TlsRouteFragment, UdpRoute,
 };
@@ -39,6 +40,9 @@ struct Args {
     /// domain name to resolve
     #[arg(long)]
     domain: String,
+    /// validate answers with DNSSEC (UDP only)
+    #[arg(long)]
+    dnssec: bool,
 }
 
 #[tokio::main]
@@ -57,9 +61,14 @@ async fn main() {
                 address: HOST_IP,
                 port: nonzero!(53u16),
             };
+            let connector_factory = if args.dnssec {
+                UdpTransportConnectorFactory::validating(TrustAnchor::iana_root())
+            } else {
+                UdpTransportConnectorFactory::default()
+            };
             Either::Left(CustomDnsResolver::new(
                 vec![ns_address],
-                UdpTransportConnectorFactory,
+                connector_factory,
                 &no_network_change_events(),
                 DNS_LATER_RESPONSE_GRACE_PERIOD,
             ))
@@ -79,6 +88,7 @@ async fn main() {
                         sni: Host::Domain(host.clone()),
                         alpn: Some(Alpn::Http2),
                         min_protocol_version: None,
+                        ech_config_list: None,
                     },
                     inner: TcpRoute {
                         address: HOST_IP,
@@ -99,6 +109,7 @@ async fn main() {
     let lookup_request = DnsLookupRequest {
         hostname: Arc::from(args.domain.as_str()),
         ipv6_enabled: true,
+        service_bindings: false,
     };
 
     // first time making a DNS query
@@ -58,6 +58,7 @@ async fn main() {
                 sni: Host::Domain(host),
                 alpn: Some(Alpn::Http2),
                 min_protocol_version: None,
+                ech_config_list: None,
             },
             inner: TcpRoute {
                 address,
@@ -83,6 +84,7 @@ async fn main() {
     let request = DnsLookupRequest {
         hostname: Arc::from(args.domain),
         ipv6_enabled: !args.no_ipv6,
+        service_bindings: false,
     };
     log::info!("sending DNS request: {request:?}");
     let mut stream = doh_transport.send_queries(request).await.unwrap();
@@ -60,6 +60,7 @@ async fn main() {
     let request = DnsLookupRequest {
         hostname: Arc::from(args.domain.as_str()),
         ipv6_enabled: !args.no_ipv6,
+        service_bindings: false,
     };
     log::info!("sending DNS request: {request:?}");
     let stream = udp_transport.send_queries(request).await.unwrap();
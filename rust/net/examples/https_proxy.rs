@@ -98,6 +98,7 @@ async fn main() {
                 sni: proxy_host.clone(),
                 alpn: Some(Alpn::Http1_1),
                 min_protocol_version: None,
+                ech_config_list: None,
             },
         }),
         scheme => panic!("unsupported protocol {scheme}"),
@@ -36,11 +36,12 @@ use crate::{Alpn, OverrideNagleAlgorithm, utils};
 pub mod custom_resolver;
 mod dns_errors;
 pub mod dns_lookup;
-mod dns_message;
+pub(crate) mod dns_message;
 pub mod dns_transport_doh;
 pub mod dns_transport_udp;
 mod dns_types;
 pub(crate) mod dns_utils;
+pub mod dnssec;
 pub mod lookup_result;
 
 pub type DnsError = Error;
@@ -59,6 +60,8 @@ fn has_dns64_prefix(addr: &Ipv6Addr) -> bool {
 struct DnsResolverState {
     /// Controls if lookup results will contain IPv6 entries.
     ipv6_enabled: bool,
+    /// Controls if lookups will also ask for HTTPS (service binding) records.
+    service_bindings_enabled: bool,
     in_flight_lookups: HashMap<String, Receiver<Result<LookupResult>>>,
 }
 
@@ -66,6 +69,7 @@ impl std::fmt::Debug for DnsResolverState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DnsResolverState")
             .field("ipv6_enabled", &self.ipv6_enabled)
+            .field("service_bindings_enabled", &self.service_bindings_enabled)
             .field("in_flight_lookups", &self.in_flight_lookups.keys())
             .finish()
     }
@@ -75,6 +79,7 @@ impl Default for DnsResolverState {
     fn default() -> Self {
         Self {
             ipv6_enabled: true,
+            service_bindings_enabled: false,
             in_flight_lookups: Default::default(),
         }
     }
@@ -115,6 +120,7 @@ pub fn build_custom_resolver_cloudflare_doh(
                     root_certs: RootCertificates::Native,
                     alpn: Some(Alpn::Http2),
                     min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_2),
+                    ech_config_list: None,
                 },
                 inner: TcpRoute {
                     address: ip_addr,
@@ -216,6 +222,14 @@ impl DnsResolver {
         }
     }
 
+    pub fn set_service_bindings_enabled(&self, service_bindings_enabled: bool) {
+        let mut guard = self.state.lock().expect("not poisoned");
+        if guard.service_bindings_enabled != service_bindings_enabled {
+            guard.service_bindings_enabled = service_bindings_enabled;
+            guard.in_flight_lookups.clear();
+        }
+    }
+
     pub fn on_network_change(&self, now: Instant) {
         for option in &self.lookup_options[..] {
             option.lookup.on_network_change(now);
@@ -233,7 +247,7 @@ impl DnsResolver {
                 std::net::IpAddr::V4(ip) => (vec![ip], vec![]),
                 std::net::IpAddr::V6(ip) => (vec![], vec![ip]),
             };
-            return Ok(LookupResult { ipv4, ipv6 });
+            return Ok(LookupResult::new(ipv4, ipv6));
         }
         match self.start_or_join_lookup(hostname).val().await {
             Ok(r) => r,
@@ -247,12 +261,13 @@ impl DnsResolver {
     fn start_or_join_lookup(&self, hostname: &str) -> Receiver<Result<LookupResult>> {
         let mut guard = self.state.lock().expect("not poisoned");
         let ipv6_enabled = guard.ipv6_enabled;
+        let service_bindings = guard.service_bindings_enabled;
         guard
             .in_flight_lookups
             .entry(hostname.to_string())
             .or_insert_with(|| {
                 let (tx, rx) = oneshot_broadcast::channel();
-                self.spawn_lookup(hostname.to_string(), tx, ipv6_enabled);
+                self.spawn_lookup(hostname.to_string(), tx, ipv6_enabled, service_bindings);
                 rx
             })
             .clone()
@@ -263,6 +278,7 @@ impl DnsResolver {
         hostname: String,
         result_sender: Sender<Result<LookupResult>>,
         ipv6_enabled: bool,
+        service_bindings: bool,
     ) {
         let Self {
             lookup_options,
@@ -273,6 +289,7 @@ impl DnsResolver {
             let request = DnsLookupRequest {
                 hostname: Arc::from(hostname.as_str()),
                 ipv6_enabled,
+                service_bindings,
             };
 
             let successful_lookups = futures_util::stream::iter(lookup_options.iter())
@@ -367,17 +384,21 @@ mod test {
     use std::collections::HashMap;
     use std::future;
     use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::num::NonZeroU16;
     use std::sync::Arc;
     use std::time::Duration;
 
     use assert_matches::assert_matches;
     use async_trait::async_trait;
     use const_str::ip_addr;
+    use test_case::test_case;
 
     use super::*;
     use crate::dns::dns_lookup::DnsLookupRequest;
+    use crate::dns::dns_transport_udp::UdpTransportConnectorFactory;
     use crate::dns::{DnsLookup, DnsResolver, Error, LookupResult, StaticDnsMap};
-    use crate::utils::{sleep_and_catch_up, timed};
+    use crate::route::UdpRoute;
+    use crate::utils::{no_network_change_events, sleep_and_catch_up, timed};
 
     const IPV4: Ipv4Addr = ip_addr!(v4, "192.0.2.1");
     const IPV6: Ipv6Addr = ip_addr!(v6, "3fff::1");
@@ -852,4 +873,52 @@ mod test {
         // making sure that the `test_lookup` have only seen one request
         assert_matches!(test_lookup.logged_requests().as_slice(), [_, _]);
     }
+
+    #[test_case(false, &[ResourceType::AAAA, ResourceType::A])]
+    #[test_case(true, &[ResourceType::AAAA, ResourceType::A, ResourceType::HTTPS])]
+    #[tokio::test]
+    async fn sends_https_query_only_when_service_bindings_are_enabled(
+        enabled: bool,
+        expected: &[ResourceType],
+    ) {
+        // Stands in for a DNS server; it never answers, only records what was asked.
+        let server = tokio::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("can bind");
+        let port = server.local_addr().expect("bound").port();
+        let custom_resolver = CustomDnsResolver::new(
+            vec![UdpRoute {
+                address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                port: NonZeroU16::new(port).expect("bound to a real port"),
+            }],
+            UdpTransportConnectorFactory::default(),
+            &no_network_change_events(),
+            DNS_LATER_RESPONSE_GRACE_PERIOD,
+        );
+        let dns_resolver =
+            DnsResolver::new_custom(vec![(Box::new(custom_resolver), ATTEMPT_TIMEOUT)]);
+        dns_resolver.set_service_bindings_enabled(enabled);
+
+        let lookup = tokio::spawn(async move { dns_resolver.lookup_ip(CUSTOM_DOMAIN).await });
+
+        let mut query_types = vec![];
+        let mut buf = [0; 512];
+        for _ in expected {
+            let len = server.recv(&mut buf).await.expect("query");
+            let mut question = &buf[12..len];
+            while question[0] != 0 {
+                question = &question[usize::from(question[0]) + 1..];
+            }
+            query_types.push(u16::from_be_bytes([question[1], question[2]]));
+        }
+        // Give any unexpected extra query a chance to show up.
+        let extra = tokio::time::timeout(Duration::from_millis(100), server.recv(&mut buf)).await;
+        lookup.abort();
+
+        assert_eq!(
+            query_types,
+            expected.iter().map(|t| *t as u16).collect::<Vec<_>>()
+        );
+        assert_matches!(extra, Err(_), "unexpected extra query");
+    }
 }
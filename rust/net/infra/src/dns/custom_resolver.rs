@@ -12,7 +12,6 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-use either::Either;
 use futures_util::{FutureExt as _, Stream, StreamExt as _};
 use tokio::sync::oneshot;
 use tokio::time::Instant;
@@ -21,7 +20,7 @@ use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::DnsLookupRequest;
 use crate::dns::dns_types::Expiring;
 use crate::dns::dns_utils::log_safe_domain;
-use crate::dns::lookup_result::LookupResult;
+use crate::dns::lookup_result::{LookupResult, ServiceBinding};
 use crate::route::{
     ConnectionOutcomeParams, ConnectionOutcomes, ConnectorFactory, InterfaceMonitor, ResolvedRoute,
 };
@@ -35,7 +34,16 @@ use crate::{DnsSource, dns};
 
 pub type DnsIpv4Result = Expiring<Vec<Ipv4Addr>>;
 pub type DnsIpv6Result = Expiring<Vec<Ipv6Addr>>;
-pub type DnsQueryResult = Either<DnsIpv4Result, DnsIpv6Result>;
+pub type DnsServiceBindingResult = Expiring<Vec<ServiceBinding>>;
+
+/// The result of a single query sent by a [`DnsTransport`].
+#[derive(Clone, Debug)]
+pub enum DnsQueryResult {
+    Ipv4(DnsIpv4Result),
+    Ipv6(DnsIpv6Result),
+    /// The contents of the HTTPS records for the name, see [`DnsLookupRequest::service_bindings`].
+    ServiceBindings(DnsServiceBindingResult),
+}
 
 /// Artificially limit DNS lookup results, so we don't get stuck on stale info with a bad TTL field.
 const MAX_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
@@ -49,11 +57,11 @@ pub trait DnsTransport: Debug + Sized + Send {
     /// Sends DNS queries and returns an async stream of the results
     /// that the caller can handle according to the resolution logic.
     ///
-    /// The returned stream of results is not guaranteed to produce exactly two elements.
-    /// Depending on the context and restrictions, implementations may choose to return
-    /// streams with fewer elements.
+    /// The returned stream of results is not guaranteed to produce exactly two elements
+    /// (or three, if service bindings were requested). Depending on the context and
+    /// restrictions, implementations may choose to return streams with fewer elements.
     ///
-    /// Each result is a list of either IPv4 or IPv6 records
+    /// Each result is a list of either IPv4 records, IPv6 records, or service bindings,
     /// with the order of results not specified.
     fn send_queries(
         self,
@@ -103,7 +111,9 @@ pub struct CustomDnsResolver<R, T> {
     routes: Vec<R>,
     network_change_event: NetworkChangeEvent,
     attempts_record: Arc<tokio::sync::RwLock<ConnectionOutcomes<R>>>,
-    cache: Arc<std::sync::Mutex<SharedCacheWithGenerations<String, Expiring<LookupResult>>>>,
+    /// Keyed by hostname and whether service bindings were requested.
+    cache:
+        Arc<std::sync::Mutex<SharedCacheWithGenerations<(String, bool), Expiring<LookupResult>>>>,
     /// How long to wait for a second response after the first one is received.
     second_response_grace_period: Duration,
 }
@@ -140,7 +150,7 @@ where
     }
 
     pub async fn resolve(&self, request: DnsLookupRequest) -> dns::Result<LookupResult> {
-        match self.cache_get(&request.hostname) {
+        match self.cache_get(&request) {
             Some(res) => {
                 log::info!(
                     "DNS record for {} found in cache",
@@ -158,11 +168,12 @@ where
         }
     }
 
-    fn cache_get(&self, hostname: &str) -> Option<LookupResult> {
+    fn cache_get(&self, request: &DnsLookupRequest) -> Option<LookupResult> {
+        let key = (request.hostname.to_string(), request.service_bindings);
         let mut guard = self.cache.lock().expect("not poisoned");
-        match guard.map.get(hostname) {
+        match guard.map.get(&key) {
             Some(expiring) if expiring.expiration < Instant::now() => {
-                guard.map.remove(hostname);
+                guard.map.remove(&key);
                 None
             }
             Some(expiring) => Some(expiring.data.clone()),
@@ -201,7 +212,8 @@ where
         );
         let transport = result.map_err(|_| dns::DnsError::TransportFailure)?;
 
-        let (ipv4_res_rx, ipv6_res_rx) = self.send_dns_queries(transport, request);
+        let wants_service_bindings = request.service_bindings;
+        let (ipv4_res_rx, ipv6_res_rx, svcb_res_rx) = self.send_dns_queries(transport, request);
         let (maybe_ipv4, maybe_ipv6) = results_within_interval(
             ipv4_res_rx.map(Result::ok),
             ipv6_res_rx.map(Result::ok),
@@ -210,14 +222,27 @@ where
         .await;
         let ipv4s = maybe_ipv4.map_or(vec![], |r| r.data);
         let ipv6s = maybe_ipv6.map_or(vec![], |r| r.data);
-        match LookupResult::new(ipv4s, ipv6s) {
-            lookup_result if !lookup_result.is_empty() => Ok(lookup_result),
-            _ => Err(Error::LookupFailed),
+        let lookup_result = LookupResult::new(ipv4s, ipv6s);
+        if lookup_result.is_empty() {
+            return Err(Error::LookupFailed);
+        }
+        if !wants_service_bindings {
+            return Ok(lookup_result);
         }
+
+        // Service bindings are only an optimization, so don't hold up the
+        // connection for them longer than we'd wait for a second address family.
+        let service_bindings = tokio::time::timeout(self.second_response_grace_period, svcb_res_rx)
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .map_or(vec![], |r| r.data);
+        Ok(lookup_result.with_service_bindings(service_bindings))
     }
 
     /// This method connects to the DNS server using the transport `T`,
-    /// sends DNS queries for both IPv4 and IPv6 records, and then processes
+    /// sends DNS queries for both IPv4 and IPv6 records (and HTTPS records, if
+    /// requested), and then processes
     /// the responses. It will also take care of caching the results when they are received.
     ///
     /// The method has its own timeout value to wait for the results to arrive.
@@ -229,12 +254,14 @@ where
     ) -> (
         oneshot::Receiver<DnsIpv4Result>,
         oneshot::Receiver<DnsIpv6Result>,
+        oneshot::Receiver<DnsServiceBindingResult>,
     ) {
         let (ipv4_res_tx, ipv4_res_rx) = oneshot::channel::<DnsIpv4Result>();
         let (ipv6_res_tx, ipv6_res_rx) = oneshot::channel::<DnsIpv6Result>();
+        let (svcb_res_tx, svcb_res_rx) = oneshot::channel::<DnsServiceBindingResult>();
         let cache = self.cache.clone();
         let generation_before_lookup = cache.lock().expect("not poisoned").generation;
-        let hostname = request.hostname.clone();
+        let cache_key = (request.hostname.to_string(), request.service_bindings);
         // We're starting this operation on a separate thread because we want to let it run
         // beyond an individual attempt timeout so that even if a result arrived late
         // we could still cache it for the next time.
@@ -243,7 +270,7 @@ where
         tokio::spawn(do_lookup_task_body(
             transport,
             request,
-            (ipv4_res_tx, ipv6_res_tx),
+            (ipv4_res_tx, ipv6_res_tx, svcb_res_tx),
             move |expiring_entry| {
                 let mut guard = cache.lock().expect("not poisoned");
                 // There are two ways the generation could be out of date:
@@ -254,12 +281,12 @@ where
                 // distinguish them is tricky. Not caching just means we might do another lookup
                 // sooner than necessary.
                 if guard.generation == generation_before_lookup {
-                    guard.map.insert(hostname.to_string(), expiring_entry);
+                    guard.map.insert(cache_key, expiring_entry);
                 }
             },
         ));
 
-        (ipv4_res_rx, ipv6_res_rx)
+        (ipv4_res_rx, ipv6_res_rx, svcb_res_rx)
     }
 }
 
@@ -275,9 +302,10 @@ impl<R: Debug, T> std::fmt::Debug for CustomDnsResolver<R, T> {
 async fn do_lookup_task_body<T: DnsTransport>(
     transport: T,
     request: DnsLookupRequest,
-    (ipv4_res_tx, ipv6_res_tx): (
+    (ipv4_res_tx, ipv6_res_tx, svcb_res_tx): (
         oneshot::Sender<DnsIpv4Result>,
         oneshot::Sender<DnsIpv6Result>,
+        oneshot::Sender<DnsServiceBindingResult>,
     ),
     try_cache_result: impl FnOnce(Expiring<LookupResult>),
 ) {
@@ -298,20 +326,23 @@ async fn do_lookup_task_body<T: DnsTransport>(
     };
     let mut stream = std::pin::pin!(stream);
 
-    // We're expecting two responses from the DNS server,
-    // but they can arrive in any order.
+    // We're expecting two responses from the DNS server (three if service
+    // bindings were requested), but they can arrive in any order.
     let mut ipv4_res_tx_opt = Some(ipv4_res_tx);
     let mut ipv6_res_tx_opt = Some(ipv6_res_tx);
+    let mut svcb_res_tx_opt = Some(svcb_res_tx);
 
     let mut maybe_ipv4_res = None;
     let mut maybe_ipv6_res = None;
+    let mut maybe_svcb_res = None;
 
-    for _ in 0..2 {
+    let expected_responses = 2 + usize::from(request.service_bindings);
+    for _ in 0..expected_responses {
         match tokio::select! {
             _ = tokio::time::sleep_until(timeout_at) => None,
             res = stream.next() => res,
         } {
-            Some(Ok(DnsQueryResult::Left(res))) => {
+            Some(Ok(DnsQueryResult::Ipv4(res))) => {
                 maybe_ipv4_res = Some(res.clone());
                 if let Some(p) = ipv4_res_tx_opt.take() {
                     // it is possible that the receiver is dropped,
@@ -324,7 +355,7 @@ async fn do_lookup_task_body<T: DnsTransport>(
                     started_at.elapsed()
                 );
             }
-            Some(Ok(DnsQueryResult::Right(res))) => {
+            Some(Ok(DnsQueryResult::Ipv6(res))) => {
                 maybe_ipv6_res = Some(res.clone());
                 if let Some(p) = ipv6_res_tx_opt.take() {
                     // it is possible that the receiver is dropped,
@@ -337,6 +368,20 @@ async fn do_lookup_task_body<T: DnsTransport>(
                     started_at.elapsed()
                 );
             }
+            Some(Ok(DnsQueryResult::ServiceBindings(mut res))) => {
+                res.data.sort_by_key(|binding| binding.priority);
+                maybe_svcb_res = Some(res.clone());
+                if let Some(p) = svcb_res_tx_opt.take() {
+                    // it is possible that the receiver is dropped,
+                    // so we're not treating this as an error
+                    let _ = p.send(res);
+                }
+                log::info!(
+                    "Received result of the HTTPS DNS query for [{}] after {:?}",
+                    log_safe_domain(&request.hostname),
+                    started_at.elapsed()
+                );
+            }
             Some(Err(error)) => {
                 log::warn!(
                     "One of DNS queries for [{}] failed with an error after {:?}: {}",
@@ -364,11 +409,18 @@ async fn do_lookup_task_body<T: DnsTransport>(
         return;
     };
 
+    // Most names don't publish HTTPS records at all, so a missing result is
+    // cached the same as an empty one.
+    let (service_bindings, expiration) = match maybe_svcb_res {
+        Some(svcb) => (svcb.data, min(expiration, svcb.expiration)),
+        None => (vec![], expiration),
+    };
+
     // update cache
     let v4 = maybe_ipv4_res.map_or(vec![], |e| e.data);
     let v6 = maybe_ipv6_res.map_or(vec![], |e| e.data);
     let expiring_entry = Expiring {
-        data: LookupResult::new(v4, v6),
+        data: LookupResult::new(v4, v6).with_service_bindings(service_bindings),
         // Clamp cached TTLs.
         expiration: min(expiration, started_at + MAX_CACHE_TTL),
     };
@@ -391,8 +443,8 @@ pub(crate) mod test {
     use test_case::test_case;
 
     use super::*;
-    use crate::route::Connector;
     use crate::route::testutils::ConnectFn;
+    use crate::route::{Connector, EchConfigList};
     use crate::timeouts::DNS_LATER_RESPONSE_GRACE_PERIOD;
     use crate::utils::{no_network_change_events, sleep_and_catch_up, sleep_until_and_catch_up};
 
@@ -580,14 +632,14 @@ pub(crate) mod test {
     }
 
     fn ok_query_result_ipv4(ttl: Duration, data: &[Ipv4Addr]) -> dns::Result<DnsQueryResult> {
-        Ok(DnsQueryResult::Left(Expiring {
+        Ok(DnsQueryResult::Ipv4(Expiring {
             data: data.to_vec(),
             expiration: Instant::now() + ttl,
         }))
     }
 
     fn ok_query_result_ipv6(ttl: Duration, data: &[Ipv6Addr]) -> dns::Result<DnsQueryResult> {
-        Ok(DnsQueryResult::Right(Expiring {
+        Ok(DnsQueryResult::Ipv6(Expiring {
             data: data.to_vec(),
             expiration: Instant::now() + ttl,
         }))
@@ -622,6 +674,7 @@ pub(crate) mod test {
         DnsLookupRequest {
             hostname: Arc::from("chat.signal.org"),
             ipv6_enabled: true,
+            service_bindings: false,
         }
     }
 
@@ -700,6 +753,92 @@ pub(crate) mod test {
         assert_lookup_result_content_equal(&result.unwrap(), IP_V4_LIST_1, IP_V6_LIST_1);
     }
 
+    fn service_binding(priority: u16, ech: &[u8]) -> ServiceBinding {
+        ServiceBinding {
+            priority,
+            target_name: None,
+            alpn: vec![b"h2".to_vec()],
+            no_default_alpn: false,
+            port: None,
+            ipv4_hint: vec![],
+            ipv6_hint: vec![],
+            ech_config_list: Some(ech.into()),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn includes_service_bindings_if_requested() {
+        let (transport, resolver) =
+            TestDnsTransportWithThreeResponses::transport_and_custom_dns_resolver(
+                |request, _, txs| {
+                    assert!(request.service_bindings);
+                    let [tx_1, tx_2, tx_3] = txs;
+                    let res_3 = Ok(DnsQueryResult::ServiceBindings(Expiring {
+                        data: vec![service_binding(2, b"second"), service_binding(1, b"first")],
+                        expiration: Instant::now() + NORMAL_TTL,
+                    }));
+                    let timeout = DNS_LATER_RESPONSE_GRACE_PERIOD / 4;
+                    respond_after_timeout(
+                        timeout,
+                        tx_1,
+                        ok_query_result_ipv4(NORMAL_TTL, IP_V4_LIST_1),
+                    );
+                    respond_after_timeout(
+                        timeout,
+                        tx_2,
+                        ok_query_result_ipv6(NORMAL_TTL, IP_V6_LIST_1),
+                    );
+                    respond_after_timeout(timeout * 2, tx_3, res_3);
+                },
+            );
+        let request = DnsLookupRequest {
+            service_bindings: true,
+            ..test_request()
+        };
+
+        let result = resolver.resolve(request.clone()).await.expect("success");
+        assert_lookup_result_content_equal(&result, IP_V4_LIST_1, IP_V6_LIST_1);
+        assert_eq!(
+            result
+                .service_bindings()
+                .iter()
+                .map(|b| b.priority)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(
+            result.ech_config_list(),
+            Some(&EchConfigList::from(&b"first"[..]))
+        );
+
+        // The second lookup should come from the cache, bindings included.
+        let cached = resolver.resolve(request).await.expect("success");
+        assert_eq!(cached.service_bindings(), result.service_bindings());
+        assert_eq!(1, transport.queries_count());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_serve_cached_result_without_service_bindings() {
+        let (transport, resolver) =
+            TestDnsTransportWithTwoResponses::transport_and_custom_dns_resolver(|_, _, txs| {
+                let [tx_1, tx_2] = txs;
+                tx_1.send(ok_query_result_ipv4(NORMAL_TTL, IP_V4_LIST_1))
+                    .unwrap();
+                tx_2.send(ok_query_result_ipv6(NORMAL_TTL, IP_V6_LIST_1))
+                    .unwrap();
+            });
+        let result = resolver.resolve(test_request()).await.expect("success");
+        assert!(result.service_bindings().is_empty());
+
+        let _ = resolver
+            .resolve(DnsLookupRequest {
+                service_bindings: true,
+                ..test_request()
+            })
+            .await;
+        assert_eq!(2, transport.queries_count());
+    }
+
     #[tokio::test(start_paused = true)]
     async fn returns_second_result_if_first_result_fails() {
         let resolver = TestDnsTransportWithTwoResponses::custom_dns_resolver(|_, _, txs| {
@@ -941,9 +1080,7 @@ pub(crate) mod test {
         });
 
         let result_1 = resolver.resolve(test_request()).await.expect("success");
-        let cached_result = resolver
-            .cache_get(&test_request().hostname)
-            .expect("cached");
+        let cached_result = resolver.cache_get(&test_request()).expect("cached");
         assert_eq!(Vec::from_iter(result_1), Vec::from_iter(cached_result));
 
         tokio::task::spawn_blocking({
@@ -952,7 +1089,7 @@ pub(crate) mod test {
         })
         .await
         .expect("no panics");
-        assert_matches!(resolver.cache_get(&test_request().hostname), None);
+        assert_matches!(resolver.cache_get(&test_request()), None);
     }
 
     #[tokio::test(start_paused = true)]
@@ -993,6 +1130,6 @@ pub(crate) mod test {
 
         sleep_and_catch_up(timeout).await;
         lookup.await.expect("success");
-        assert_matches!(resolver.cache_get(&test_request().hostname), None);
+        assert_matches!(resolver.cache_get(&test_request()), None);
     }
 }
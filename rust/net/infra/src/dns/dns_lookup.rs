@@ -24,6 +24,11 @@ use crate::route::{ConnectorFactory, ResolvedRoute};
 pub struct DnsLookupRequest {
     pub hostname: Arc<str>,
     pub ipv6_enabled: bool,
+    /// Whether to also query for HTTPS records (RFC 9460), which can carry ALPN
+    /// hints and ECH configurations for the host.
+    ///
+    /// Only respected by [`CustomDnsResolver`]; other lookups ignore it.
+    pub service_bindings: bool,
 }
 
 #[async_trait]
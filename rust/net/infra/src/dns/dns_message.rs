@@ -7,6 +7,7 @@ use std::cmp::{max, min};
 use std::io;
 use std::io::Cursor;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use std::num::NonZeroU16;
 use std::time::Duration;
 
 use bitstream_io::{
@@ -16,6 +17,7 @@ use tokio::time::Instant;
 
 use crate::dns::ResourceType;
 use crate::dns::dns_types::Expiring;
+use crate::dns::lookup_result::ServiceBinding;
 
 pub(crate) const QCLASS_IN: u16 = 1;
 const POINTER_MASK: u8 = 0xC0;
@@ -25,6 +27,17 @@ const POINTER_MASK: u8 = 0xC0;
 pub(crate) const MAX_DNS_LABEL_LEN: usize = 63;
 pub(crate) const MAX_DNS_NAME_LEN: usize = 255;
 pub(crate) const MAX_DNS_UDP_MESSAGE_LEN: usize = 512;
+/// The UDP payload size we advertise with EDNS(0), as recommended by
+/// [DNS Flag Day 2020](https://www.dnsflagday.net/2020/).
+pub(crate) const MAX_DNS_EDNS_UDP_MESSAGE_LEN: usize = 1232;
+
+pub(crate) const RECORD_TYPE_CNAME: u16 = 5;
+const RECORD_TYPE_OPT: u16 = 41;
+pub(crate) const RECORD_TYPE_RRSIG: u16 = 46;
+
+// the information hardcoded in this section is that the message is a request
+// and that the request is recursive
+const RECURSIVE_REQUEST_WITH_ONE_QUESTION: [u8; 10] = [1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
 
 #[derive(displaydoc::Display, Debug, thiserror::Error, Clone)]
 pub enum Error {
@@ -42,6 +55,8 @@ pub enum Error {
     ProtocolErrorInvalidNameCharacters,
     /// Failed to parse resourse record
     ProtocolErrorFailedToParseResourceRecord,
+    /// The response was truncated
+    ProtocolErrorTruncated,
     /// Data for the given name is not available
     NoData,
     /// DNS request resulted in a non-zero error code: {0}
@@ -54,7 +69,7 @@ impl From<io::Error> for Error {
     }
 }
 
-type Result<T> = std::result::Result<T, Error>;
+pub(crate) type Result<T> = std::result::Result<T, Error>;
 
 /// Creates a DNS request for the given request id, domain name, and resource type.
 ///
@@ -70,10 +85,6 @@ pub fn create_request_with_id(
     domain: &str,
     resource_type: ResourceType,
 ) -> Result<Vec<u8>> {
-    // the information hardcoded in this section is that the message is a request
-    // and that the request is recursive
-    const RECURSIVE_REQUEST_WITH_ONE_QUESTION: [u8; 10] = [1, 0, 0, 1, 0, 0, 0, 0, 0, 0];
-    //
     const MESSAGE_BOILERPLATE_SIZE: usize = 18;
 
     let mut writer = BitWriter::endian(
@@ -99,6 +110,46 @@ pub fn create_request_with_id(
     Ok(writer.into_writer())
 }
 
+/// Like [`create_request_with_id`], but with an EDNS(0) OPT record that sets the "DNSSEC OK" bit,
+/// so that the response includes RRSIG records.
+///
+/// Unlike [`create_request_with_id`], this accepts the root name (`""`), since DNSSEC
+/// validation needs the root zone's keys.
+///
+/// [EDNS(0)](https://datatracker.ietf.org/doc/html/rfc6891#section-6.1.2)
+/// [DO bit](https://datatracker.ietf.org/doc/html/rfc3225#section-3)
+pub(crate) fn create_dnssec_request_with_id(
+    request_id: u16,
+    domain: &str,
+    resource_type: ResourceType,
+) -> Result<Vec<u8>> {
+    const ADDITIONAL_COUNT_OFFSET: usize = 10;
+    const DNSSEC_OK: u16 = 0x8000;
+
+    let mut request = if domain.is_empty() {
+        let mut request = request_id.to_be_bytes().to_vec();
+        request.extend_from_slice(&RECURSIVE_REQUEST_WITH_ONE_QUESTION);
+        request.push(0);
+        request.extend_from_slice(&(resource_type as u16).to_be_bytes());
+        request.extend_from_slice(&QCLASS_IN.to_be_bytes());
+        request
+    } else {
+        create_request_with_id(request_id, domain, resource_type)?
+    };
+    request[ADDITIONAL_COUNT_OFFSET..][..2].copy_from_slice(&1u16.to_be_bytes());
+
+    // OPT pseudo-record: root name, type, payload size in place of the class,
+    // extended RCODE and version (both 0) and flags in place of the TTL, no options.
+    let payload_size = u16::try_from(MAX_DNS_EDNS_UDP_MESSAGE_LEN).expect("fits");
+    request.push(0);
+    request.extend_from_slice(&RECORD_TYPE_OPT.to_be_bytes());
+    request.extend_from_slice(&payload_size.to_be_bytes());
+    request.extend_from_slice(&[0, 0]);
+    request.extend_from_slice(&DNSSEC_OK.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    Ok(request)
+}
+
 pub fn get_id(message: &[u8]) -> Result<u16> {
     match message {
         [a, b, ..] => Ok(((*a as u16) << 8) | *b as u16),
@@ -106,6 +157,18 @@ pub fn get_id(message: &[u8]) -> Result<u16> {
     }
 }
 
+/// Returns whether the TC flag is set, meaning the answer didn't fit and should be requested again
+/// over TCP.
+///
+/// [TCP usage](https://datatracker.ietf.org/doc/html/rfc7766#section-5)
+pub(crate) fn is_truncated(message: &[u8]) -> Result<bool> {
+    const FLAG_TRUNCATED: u8 = 0x02;
+    match message {
+        [_, _, flags, ..] => Ok(flags & FLAG_TRUNCATED != 0),
+        _ => Err(Error::ProtocolErrorInvalidMessage),
+    }
+}
+
 pub fn parse_a_record(bytes_vec: &[u8]) -> Result<Ipv4Addr> {
     let octets: [u8; 4] = bytes_vec
         .try_into()
@@ -120,6 +183,82 @@ pub fn parse_aaaa_record(bytes_vec: &[u8]) -> Result<Ipv6Addr> {
     Ok(Ipv6Addr::from(octets))
 }
 
+/// Parses the RDATA of an SVCB or HTTPS record.
+///
+/// Parameters other than the ones in [`ServiceBinding`] are skipped, including
+/// `mandatory`: since we only use the parameters as hints, it's okay to not
+/// understand some of them.
+///
+/// [RDATA wire format](https://datatracker.ietf.org/doc/html/rfc9460#section-2.2)
+pub fn parse_svcb_record(bytes_vec: &[u8]) -> Result<ServiceBinding> {
+    const KEY_ALPN: u16 = 1;
+    const KEY_NO_DEFAULT_ALPN: u16 = 2;
+    const KEY_PORT: u16 = 3;
+    const KEY_IPV4_HINT: u16 = 4;
+    const KEY_ECH: u16 = 5;
+    const KEY_IPV6_HINT: u16 = 6;
+
+    let mut rest = bytes_vec;
+    let priority = take_u16(&mut rest)?;
+    let target_name = read_uncompressed_name(&mut rest)?;
+
+    let mut binding = ServiceBinding {
+        priority,
+        target_name,
+        alpn: vec![],
+        no_default_alpn: false,
+        port: None,
+        ipv4_hint: vec![],
+        ipv6_hint: vec![],
+        ech_config_list: None,
+    };
+
+    let mut previous_key = None;
+    while !rest.is_empty() {
+        let key = take_u16(&mut rest)?;
+        // Keys must appear in strictly increasing order.
+        if previous_key.is_some_and(|previous| previous >= key) {
+            return Err(Error::ProtocolErrorFailedToParseResourceRecord);
+        }
+        previous_key = Some(key);
+        let value_len = take_u16(&mut rest)?;
+        let mut value = take(&mut rest, value_len.into())?;
+
+        match key {
+            KEY_ALPN => {
+                while !value.is_empty() {
+                    let [id_len] = *take(&mut value, 1)? else {
+                        unreachable!("took one byte")
+                    };
+                    binding.alpn.push(take(&mut value, id_len.into())?.to_vec());
+                }
+            }
+            KEY_NO_DEFAULT_ALPN => binding.no_default_alpn = true,
+            KEY_PORT => {
+                binding.port = NonZeroU16::new(take_u16(&mut value)?);
+            }
+            KEY_IPV4_HINT => {
+                binding.ipv4_hint = value.chunks(4).map(parse_a_record).collect::<Result<_>>()?;
+            }
+            KEY_ECH => binding.ech_config_list = Some(value.into()),
+            KEY_IPV6_HINT => {
+                binding.ipv6_hint = value
+                    .chunks(16)
+                    .map(parse_aaaa_record)
+                    .collect::<Result<_>>()?;
+            }
+            _ => {}
+        }
+    }
+
+    // The alpn parameter is required when no-default-alpn is present.
+    if binding.no_default_alpn && binding.alpn.is_empty() {
+        return Err(Error::ProtocolErrorFailedToParseResourceRecord);
+    }
+
+    Ok(binding)
+}
+
 pub fn parse_response<T>(
     message: &[u8],
     expected_type: ResourceType,
@@ -181,6 +320,96 @@ pub fn parse_response<T>(
     })
 }
 
+/// A resource record from the answer section of a response, kept in wire format for
+/// DNSSEC validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RawRecord {
+    /// The owner name, lowercased and without the trailing dot. The root is `""`.
+    pub name: String,
+    pub record_type: u16,
+    pub class: u16,
+    pub ttl: u32,
+    /// The record data. For CNAME records the target name is decompressed and lowercased,
+    /// which makes this the canonical form used for signing.
+    pub rdata: Vec<u8>,
+}
+
+/// Parses all records in the answer section of a response, without filtering by type.
+///
+/// Truncated responses are rejected, since they can't be validated.
+pub(crate) fn parse_raw_answers(message: &[u8]) -> Result<Vec<RawRecord>> {
+    let mut reader = BitReader::endian(Cursor::new(message), BigEndian);
+
+    let _id = reader.read_to::<u16>()?;
+    let _flags = reader.read::<u8>(6)?;
+    let flag_truncated = reader.read_bit()?;
+    let _flags = reader.read::<u8>(5)?;
+    let response_code = reader.read::<u8>(4)?;
+    let _questions_count = reader.read_to::<u16>()?;
+    let answers_count = reader.read_to::<u16>()?;
+    let _authority_record_count = reader.read_to::<u16>()?;
+    let _additional_record_count = reader.read_to::<u16>()?;
+
+    if flag_truncated {
+        return Err(Error::ProtocolErrorTruncated);
+    }
+    if response_code != 0 {
+        return Err(Error::RequestFailedWithErrorCode(response_code));
+    }
+    if answers_count == 0 {
+        return Err(Error::NoData);
+    }
+
+    // question section repeats here
+    let _name = read_name(&mut reader, message)?;
+    let _data_type = reader.read_to::<u16>()?;
+    let _data_class = reader.read_to::<u16>()?;
+
+    (0..answers_count)
+        .map(|_| {
+            let name = read_name(&mut reader, message)?.to_ascii_lowercase();
+            let record_type = reader.read_to::<u16>()?;
+            let class = reader.read_to::<u16>()?;
+            let ttl = reader.read_to::<u32>()?;
+            let data_length = reader.read_to::<u16>()?;
+
+            let rdata = if record_type == RECORD_TYPE_CNAME {
+                let start = reader.position_in_bits()?;
+                let target = read_name(&mut reader, message)?;
+                if reader.position_in_bits()? - start != u64::from(data_length) * 8 {
+                    return Err(Error::ProtocolErrorFailedToParseResourceRecord);
+                }
+                canonical_name_wire(&target)
+            } else {
+                reader.read_to_vec(data_length.into())?
+            };
+            Ok(RawRecord {
+                name,
+                record_type,
+                class,
+                ttl,
+                rdata,
+            })
+        })
+        .collect()
+}
+
+/// Encodes a name in the canonical wire format: lowercased and uncompressed.
+///
+/// [Canonical form](https://datatracker.ietf.org/doc/html/rfc4034#section-6.2)
+pub(crate) fn canonical_name_wire(name: &str) -> Vec<u8> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let mut wire = Vec::with_capacity(name.len() + 2);
+    if !name.is_empty() {
+        for label in name.split('.') {
+            wire.push(u8::try_from(label.len()).unwrap_or(u8::MAX));
+            wire.extend(label.bytes().map(|b| b.to_ascii_lowercase()));
+        }
+    }
+    wire.push(0);
+    wire
+}
+
 fn write_name<W: io::Write>(writer: &mut ByteWriter<W, BigEndian>, name: &str) -> Result<()> {
     let no_trailing_dot = name.strip_suffix('.').unwrap_or(name);
 
@@ -202,6 +431,53 @@ fn write_name<W: io::Write>(writer: &mut ByteWriter<W, BigEndian>, name: &str) -
     Ok(())
 }
 
+pub(crate) fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if bytes.len() < len {
+        return Err(Error::ProtocolErrorFailedToParseResourceRecord);
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+pub(crate) fn take_u16(bytes: &mut &[u8]) -> Result<u16> {
+    let taken = take(bytes, 2)?;
+    Ok(u16::from_be_bytes(
+        taken.try_into().expect("correct length"),
+    ))
+}
+
+/// Reads a name that isn't allowed to use compression, as in SVCB records.
+///
+/// Returns `None` for the root name (`.`).
+pub(crate) fn read_uncompressed_name(bytes: &mut &[u8]) -> Result<Option<String>> {
+    let mut dst = vec![];
+    loop {
+        let [label_len] = *take(bytes, 1)? else {
+            unreachable!("took one byte")
+        };
+        if label_len == 0 {
+            break;
+        }
+        if label_len & POINTER_MASK != 0 {
+            return Err(Error::ProtocolErrorFailedToParseResourceRecord);
+        }
+        if !dst.is_empty() {
+            dst.push(b'.');
+        }
+        dst.extend_from_slice(take(bytes, label_len.into())?);
+        if dst.len() > MAX_DNS_NAME_LEN {
+            return Err(Error::ProtocolErrorNameTooLong);
+        }
+    }
+    if dst.is_empty() {
+        return Ok(None);
+    }
+    String::from_utf8(dst)
+        .map(Some)
+        .map_err(|_| Error::ProtocolErrorInvalidNameCharacters)
+}
+
 fn read_name<R: io::Read + io::Seek>(
     reader: &mut BitReader<R, BigEndian>,
     src: &[u8],
@@ -553,6 +829,154 @@ mod test {
         assert_eq!(&[EXPECTED_IP], response.data.as_slice());
     }
 
+    #[test]
+    fn svcb_record_parsed_correctly() {
+        let rdata = concat_bytes!(
+            [0, 1],                                     // priority
+            0,                                          // target name: the owner name
+            [0, 1, 0, 6, 2, b'h', b'2', 2, b'h', b'3'], // alpn: "h2", "h3"
+            [0, 3, 0, 2, 0x20, 0xFB],                   // port: 8443
+            [0, 4, 0, 4, 192, 0, 2, 1],                 // ipv4hint
+            [0, 5, 0, 3, 1, 2, 3],                      // ech
+            [0, 6, 0, 16],                              // ipv6hint
+            [0x3f, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            [0, 100, 0, 1, 0xAA], // unknown key, skipped
+        );
+        let binding = parse_svcb_record(rdata).expect("valid record");
+        assert_eq!(
+            binding,
+            ServiceBinding {
+                priority: 1,
+                target_name: None,
+                alpn: vec![b"h2".to_vec(), b"h3".to_vec()],
+                no_default_alpn: false,
+                port: NonZeroU16::new(8443),
+                ipv4_hint: vec![ip_addr!(v4, "192.0.2.1")],
+                ipv6_hint: vec![ip_addr!(v6, "3fff::1")],
+                ech_config_list: Some([1, 2, 3].as_slice().into()),
+            }
+        );
+        assert!(!binding.is_alias());
+    }
+
+    #[test]
+    fn svcb_alias_record_parsed_correctly() {
+        let rdata = concat_bytes!(0, 0, 6, b"signal", 3, b"org", 0);
+        let binding = parse_svcb_record(rdata).expect("valid record");
+        assert!(binding.is_alias());
+        assert_eq!(binding.target_name.as_deref(), Some("signal.org"));
+    }
+
+    #[test]
+    fn invalid_svcb_records() {
+        for rdata in [
+            // keys out of order
+            concat_bytes!(0, 1, 0, 0, 3, 0, 2, 1, 187, 0, 1, 0, 3, 2, b"h2").as_slice(),
+            // repeated key
+            concat_bytes!(0, 1, 0, 0, 5, 0, 1, 1, 0, 5, 0, 1, 1).as_slice(),
+            // no-default-alpn without alpn
+            concat_bytes!(0, 1, 0, 0, 2, 0, 0).as_slice(),
+            // value longer than the record
+            concat_bytes!(0, 1, 0, 0, 5, 0, 4, 1).as_slice(),
+            // compressed target name
+            concat_bytes!(0, 1, POINTER_MASK, 12).as_slice(),
+            // truncated ipv4hint
+            concat_bytes!(0, 1, 0, 0, 4, 0, 3, 192, 0, 2).as_slice(),
+        ] {
+            assert_matches!(
+                parse_svcb_record(rdata),
+                Err(Error::ProtocolErrorFailedToParseResourceRecord),
+                "{rdata:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn dnssec_request_for_root_zone() {
+        let request = create_dnssec_request_with_id(REQUEST_ID, "", ResourceType::DNSKEY)
+            .expect("valid request");
+        let expected = concat_bytes!(
+            [0xAB, 0xCD],                   // request ID
+            [1, 0, 0, 1, 0, 0, 0, 0, 0, 1], // flags and counts, with one additional record
+            [0, 0, 48, 0, 1],               // root name, DNSKEY, IN
+            [0, 0, 41, 0x04, 0xD0],         // OPT, 1232-byte payload
+            [0, 0, 0x80, 0, 0, 0],          // DO bit set, no options
+        );
+        assert_eq!(request.as_slice(), expected);
+    }
+
+    #[test]
+    fn dnssec_request_extends_plain_request() {
+        let plain = create_request_with_id(REQUEST_ID, VALID_DOMAIN, ResourceType::A)
+            .expect("valid request");
+        let dnssec = create_dnssec_request_with_id(REQUEST_ID, VALID_DOMAIN, ResourceType::A)
+            .expect("valid request");
+        assert_eq!(dnssec[..10], plain[..10]);
+        assert_eq!(dnssec[10..12], [0, 1]);
+        assert_eq!(dnssec[12..plain.len()], plain[12..]);
+        assert_eq!(dnssec.len(), plain.len() + 11);
+    }
+
+    #[test]
+    fn raw_answers_have_canonical_names() {
+        let name = Name::from_str("Chat.Signal.org").expect("valid name");
+        let ip = ip_addr!(v4, "192.0.2.1");
+        let response_message = response_bytes(RecordType::A, |message| {
+            let cname = Name::from_str("CDN.signal.org").unwrap();
+            let mut rr = hickory_proto::rr::Record::<RData>::new();
+            rr.set_name(name.clone())
+                .set_record_type(RecordType::CNAME)
+                .set_ttl(100)
+                .set_data(Some(RData::CNAME(CNAME(cname.clone()))));
+            message.add_answer(rr);
+
+            let mut rr = hickory_proto::rr::Record::<RData>::new();
+            rr.set_name(cname)
+                .set_record_type(RecordType::A)
+                .set_ttl(200)
+                .set_data(Some(RData::A(A::from(ip))));
+            message.add_answer(rr);
+        });
+
+        let answers = parse_raw_answers(&response_message).expect("parsed");
+        assert_eq!(
+            answers,
+            [
+                RawRecord {
+                    name: VALID_DOMAIN.to_owned(),
+                    record_type: RECORD_TYPE_CNAME,
+                    class: QCLASS_IN,
+                    ttl: 100,
+                    rdata: concat_bytes!(3, b"cdn", 6, b"signal", 3, b"org", 0).to_vec(),
+                },
+                RawRecord {
+                    name: "cdn.signal.org".to_owned(),
+                    record_type: ResourceType::A as u16,
+                    class: QCLASS_IN,
+                    ttl: 200,
+                    rdata: ip.octets().to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_raw_answers_are_rejected() {
+        let mut response_message = response_bytes(RecordType::A, |message| {
+            message.set_truncated(true);
+        });
+        assert_matches!(is_truncated(&response_message), Ok(true));
+        assert_matches!(
+            parse_raw_answers(&response_message),
+            Err(Error::ProtocolErrorTruncated)
+        );
+
+        // Without the flag, the (empty) answer is fine.
+        response_message[2] &= !0x02;
+        assert_matches!(is_truncated(&response_message), Ok(false));
+        assert_matches!(parse_raw_answers(&response_message), Err(Error::NoData));
+    }
+
     fn response_bytes<F>(record_type: RecordType, builder: F) -> Vec<u8>
     where
         F: FnOnce(&mut hickory_proto::op::message::Message),
@@ -16,7 +16,7 @@ use crate::dns::custom_resolver::{DnsQueryResult, DnsTransport};
 use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::DnsLookupRequest;
 use crate::dns::dns_message;
-use crate::dns::dns_message::{parse_a_record, parse_aaaa_record};
+use crate::dns::dns_message::{parse_a_record, parse_aaaa_record, parse_svcb_record};
 use crate::dns::dns_types::ResourceType;
 use crate::errors::{LogSafeDisplay, TransportConnectError};
 use crate::http_client::{AggregatingHttp2Client, Http2Connector, HttpConnectError};
@@ -113,6 +113,10 @@ impl DnsTransport for DohTransport {
                     .send_request(request.clone(), ResourceType::AAAA)
             })
             .into_iter()
+            .chain(request.service_bindings.then(|| {
+                self.clone()
+                    .send_request(request.clone(), ResourceType::HTTPS)
+            }))
             .chain([self.send_request(request, ResourceType::A)]);
         Ok(FuturesUnordered::from_iter(futures))
     }
@@ -151,16 +155,22 @@ impl DohTransport {
             return Err(Error::DohRequestBadStatus(response_parts.status.as_u16()));
         }
         let result = match resource_type {
-            ResourceType::A => DnsQueryResult::Left(dns_message::parse_response(
+            ResourceType::A => DnsQueryResult::Ipv4(dns_message::parse_response(
                 &response_body,
                 ResourceType::A,
                 parse_a_record,
             )?),
-            ResourceType::AAAA => DnsQueryResult::Right(dns_message::parse_response(
+            ResourceType::AAAA => DnsQueryResult::Ipv6(dns_message::parse_response(
                 &response_body,
                 ResourceType::AAAA,
                 parse_aaaa_record,
             )?),
+            ResourceType::SVCB | ResourceType::HTTPS => DnsQueryResult::ServiceBindings(
+                dns_message::parse_response(&response_body, resource_type, parse_svcb_record)?,
+            ),
+            ResourceType::DS | ResourceType::DNSKEY => {
+                unreachable!("DNSSEC records are never requested over DoH")
+            }
         };
         Ok(result)
     }
@@ -3,39 +3,84 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::collections::VecDeque;
 use std::net::IpAddr;
-use std::sync::Arc;
+use std::num::NonZeroU16;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use futures_util::{Stream, StreamExt, stream};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
 use tokio::net::UdpSocket;
+use tokio::time::Instant;
 
 use crate::dns::custom_resolver::{DnsQueryResult, DnsTransport};
 use crate::dns::dns_errors::Error;
 use crate::dns::dns_lookup::DnsLookupRequest;
 use crate::dns::dns_message;
-use crate::dns::dns_message::{MAX_DNS_UDP_MESSAGE_LEN, parse_a_record, parse_aaaa_record};
-use crate::dns::dns_types::ResourceType;
+use crate::dns::dns_message::{
+    MAX_DNS_EDNS_UDP_MESSAGE_LEN, MAX_DNS_UDP_MESSAGE_LEN, RawRecord, parse_a_record,
+    parse_aaaa_record, parse_svcb_record,
+};
+use crate::dns::dns_types::{Expiring, ResourceType};
+use crate::dns::dnssec::{RecordFetcher, TrustAnchor, ValidatedKeys, Validator};
 use crate::route::{
-    Connector, ConnectorExt as _, ConnectorFactory, StatelessUdpConnector, UdpRoute,
+    Connector, ConnectorExt as _, ConnectorFactory, StatelessUdpConnector, TcpRoute, UdpRoute,
 };
-use crate::{DnsSource, dns};
+use crate::tcp_ssl::StatelessTcp;
+use crate::{DnsSource, OverrideNagleAlgorithm, dns};
 
 const A_REQUEST_ID: u16 = 0;
 const AAAA_REQUEST_ID: u16 = 1;
+const HTTPS_REQUEST_ID: u16 = 2;
+/// Queries for DNSSEC records needed to validate the answers get random IDs from here on up, so
+/// their responses can't be confused with the answers to the original queries.
+const FIRST_DNSSEC_REQUEST_ID: u16 = 0x100;
 
-pub struct UdpTransportConnectorFactory;
+/// Makes the transports for a [`CustomDnsResolver`], which keeps the factory for its lifetime.
+///
+/// When validating, the DNSSEC keys for each zone are cached here rather than in the individual
+/// transports, so that every lookup the resolver makes can reuse them until their TTL runs out.
+///
+/// [`CustomDnsResolver`]: crate::dns::custom_resolver::CustomDnsResolver
+#[derive(Default)]
+pub struct UdpTransportConnectorFactory {
+    trust_anchor: Option<Arc<TrustAnchor>>,
+    validated_keys: Arc<ValidatedKeys>,
+}
+
+impl UdpTransportConnectorFactory {
+    /// Creates a factory for transports that validate every answer with DNSSEC, starting from
+    /// `trust_anchor`.
+    ///
+    /// Answers that fail validation are dropped, which protects against spoofed responses but
+    /// also means names in unsigned zones can't be resolved.
+    pub fn validating(trust_anchor: TrustAnchor) -> Self {
+        Self {
+            trust_anchor: Some(Arc::new(trust_anchor)),
+            validated_keys: Default::default(),
+        }
+    }
+}
 
 impl ConnectorFactory<UdpRoute<IpAddr>> for UdpTransportConnectorFactory {
     type Connector = UdpTransportConnector;
     type Connection = UdpTransport;
 
     fn make(&self) -> Self::Connector {
-        Default::default()
+        UdpTransportConnector {
+            trust_anchor: self.trust_anchor.clone(),
+            validated_keys: self.validated_keys.clone(),
+        }
     }
 }
 
 #[derive(Default)]
-pub struct UdpTransportConnector;
+pub struct UdpTransportConnector {
+    trust_anchor: Option<Arc<TrustAnchor>>,
+    validated_keys: Arc<ValidatedKeys>,
+}
 
 impl Connector<UdpRoute<IpAddr>, ()> for UdpTransportConnector {
     type Connection = UdpTransport;
@@ -59,6 +104,15 @@ impl Connector<UdpRoute<IpAddr>, ()> for UdpTransportConnector {
             })?;
         Ok(UdpTransport {
             socket: socket.into(),
+            log_tag: log_tag.into(),
+            dnssec: self.trust_anchor.clone().map(|trust_anchor| {
+                Arc::new(DnssecState {
+                    trust_anchor,
+                    validated_keys: self.validated_keys.clone(),
+                    last_request_id: AtomicU16::default(),
+                    unclaimed_responses: Default::default(),
+                })
+            }),
         })
     }
 }
@@ -67,6 +121,20 @@ impl Connector<UdpRoute<IpAddr>, ()> for UdpTransportConnector {
 #[derive(Clone, Debug)]
 pub struct UdpTransport {
     socket: Arc<UdpSocket>,
+    log_tag: Arc<str>,
+    /// Present if answers should be validated with DNSSEC.
+    dnssec: Option<Arc<DnssecState>>,
+}
+
+#[derive(Debug)]
+struct DnssecState {
+    trust_anchor: Arc<TrustAnchor>,
+    /// Shared with the factory that made this transport.
+    validated_keys: Arc<ValidatedKeys>,
+    /// The ID of the most recent DNSSEC query, or 0 if none has been sent.
+    last_request_id: AtomicU16,
+    /// Responses to the original queries that arrived while waiting for DNSSEC records.
+    unclaimed_responses: Mutex<VecDeque<Vec<u8>>>,
 }
 
 impl DnsTransport for UdpTransport {
@@ -83,45 +151,132 @@ impl DnsTransport for UdpTransport {
         if request.ipv6_enabled {
             arc.send_request(&request.hostname, AAAA_REQUEST_ID, ResourceType::AAAA)
                 .await?;
-            futures.push(arc.clone().next_dns_query_result1());
+            futures.push(arc.clone().next_dns_query_result1(request.hostname.clone()));
         }
 
         // always sending A request
         arc.send_request(&request.hostname, A_REQUEST_ID, ResourceType::A)
             .await?;
-        futures.push(arc.clone().next_dns_query_result1());
+        futures.push(arc.clone().next_dns_query_result1(request.hostname.clone()));
+
+        // HTTPS records are only requested when the caller asks for them
+        if request.service_bindings {
+            arc.send_request(&request.hostname, HTTPS_REQUEST_ID, ResourceType::HTTPS)
+                .await?;
+            futures.push(arc.clone().next_dns_query_result1(request.hostname.clone()));
+        }
         Ok(stream::iter(futures).then(|task| task))
     }
 }
 
 impl UdpTransport {
-    async fn next_dns_query_result1(self: Arc<Self>) -> dns::Result<DnsQueryResult> {
-        let mut buf = [0; MAX_DNS_UDP_MESSAGE_LEN];
-        let bytes_received = self.socket.recv(&mut buf).await?;
-        let message = &buf[..bytes_received];
+    async fn next_dns_query_result1(
+        self: Arc<Self>,
+        hostname: Arc<str>,
+    ) -> dns::Result<DnsQueryResult> {
+        let message = loop {
+            let unclaimed = self.dnssec.as_ref().and_then(|dnssec| {
+                dnssec
+                    .unclaimed_responses
+                    .lock()
+                    .expect("not poisoned")
+                    .pop_front()
+            });
+            let message = match unclaimed {
+                Some(message) => message,
+                None => self.recv().await?,
+            };
+            // A DNSSEC query that already got its answer may see a late duplicate.
+            if self.dnssec.is_some() && dns_message::get_id(&message)? >= FIRST_DNSSEC_REQUEST_ID {
+                log::debug!("dropping stale response to a DNSSEC query");
+                continue;
+            }
+            break message;
+        };
+        let message = message.as_slice();
         let result = match dns_message::get_id(message)? {
-            A_REQUEST_ID => DnsQueryResult::Left(dns_message::parse_response(
-                message,
-                ResourceType::A,
-                parse_a_record,
-            )?),
-            AAAA_REQUEST_ID => DnsQueryResult::Right(dns_message::parse_response(
-                message,
-                ResourceType::AAAA,
-                parse_aaaa_record,
-            )?),
+            A_REQUEST_ID => DnsQueryResult::Ipv4(
+                self.parse_response(&hostname, message, ResourceType::A, parse_a_record)
+                    .await?,
+            ),
+            AAAA_REQUEST_ID => DnsQueryResult::Ipv6(
+                self.parse_response(&hostname, message, ResourceType::AAAA, parse_aaaa_record)
+                    .await?,
+            ),
+            HTTPS_REQUEST_ID => DnsQueryResult::ServiceBindings(
+                self.parse_response(&hostname, message, ResourceType::HTTPS, parse_svcb_record)
+                    .await?,
+            ),
             _ => Err(Error::UnexpectedMessageId)?,
         };
         Ok(result)
     }
 
+    async fn recv(&self) -> dns::Result<Vec<u8>> {
+        let mut buf = [0; MAX_DNS_EDNS_UDP_MESSAGE_LEN];
+        let bytes_received = self.socket.recv(&mut buf).await?;
+        Ok(buf[..bytes_received].to_vec())
+    }
+
+    /// Parses the records in a response, validating them first if DNSSEC is enabled.
+    async fn parse_response<T>(
+        &self,
+        hostname: &str,
+        message: &[u8],
+        resource_type: ResourceType,
+        parser: fn(&[u8]) -> dns_message::Result<T>,
+    ) -> dns::Result<Expiring<Vec<T>>> {
+        let Some(dnssec) = &self.dnssec else {
+            return Ok(dns_message::parse_response(message, resource_type, parser)?);
+        };
+
+        // Signed answers can easily outgrow a UDP response; if so, ask again over TCP.
+        let tcp_response;
+        let message = if dns_message::is_truncated(message)? {
+            let request_id = dns_message::get_id(message)?;
+            tcp_response = self
+                .query_over_tcp(hostname, request_id, resource_type)
+                .await?;
+            tcp_response.as_slice()
+        } else {
+            message
+        };
+
+        let answers = dns_message::parse_raw_answers(message)?;
+        let validator = Validator::new(
+            &dnssec.trust_anchor,
+            self,
+            &dnssec.validated_keys,
+            SystemTime::now(),
+        )
+        .map_err(Error::DnssecValidationFailed)?;
+        let records = validator
+            .validate_answer(hostname, resource_type, answers)
+            .await
+            .map_err(Error::DnssecValidationFailed)?;
+
+        let min_ttl = records.iter().map(|r| r.ttl).min().ok_or(Error::NoData)?;
+        let data = records
+            .iter()
+            .filter_map(|record| {
+                parser(&record.rdata)
+                    .inspect_err(|error| log::warn!("error parsing DNS response: {error}"))
+                    .ok()
+            })
+            .collect();
+        Ok(Expiring {
+            data,
+            expiration: Instant::now() + Duration::from_secs(min_ttl.into()),
+        })
+    }
+
     async fn send_request(
         &self,
         hostname: &str,
         request_id: u16,
         resource_type: ResourceType,
     ) -> dns::Result<()> {
-        let request = dns_message::create_request_with_id(request_id, hostname, resource_type)?;
+        let request = self.create_request(hostname, request_id, resource_type)?;
         let udp_message = match request {
             data if data.len() > MAX_DNS_UDP_MESSAGE_LEN => Err(Error::MessageTooLong),
             data => Ok(data),
@@ -129,4 +284,223 @@ impl UdpTransport {
         let _bytes_sent = self.socket.send(udp_message.as_slice()).await?;
         Ok(())
     }
+
+    fn create_request(
+        &self,
+        hostname: &str,
+        request_id: u16,
+        resource_type: ResourceType,
+    ) -> dns::Result<Vec<u8>> {
+        Ok(if self.dnssec.is_some() {
+            dns_message::create_dnssec_request_with_id(request_id, hostname, resource_type)?
+        } else {
+            dns_message::create_request_with_id(request_id, hostname, resource_type)?
+        })
+    }
+
+    /// Repeats a query over TCP to the same server, for an answer that was truncated over UDP.
+    ///
+    /// [TCP usage](https://datatracker.ietf.org/doc/html/rfc1035#section-4.2.2)
+    async fn query_over_tcp(
+        &self,
+        hostname: &str,
+        request_id: u16,
+        resource_type: ResourceType,
+    ) -> dns::Result<Vec<u8>> {
+        let server = self.socket.peer_addr()?;
+        let route = TcpRoute {
+            address: server.ip(),
+            port: NonZeroU16::new(server.port()).ok_or(Error::TransportFailure)?,
+            override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+        };
+        let mut stream = StatelessTcp
+            .connect(route, &self.log_tag)
+            .await
+            .map_err(|_| Error::TransportFailure)?;
+
+        // Over TCP, each message is preceded by its length.
+        let request = self.create_request(hostname, request_id, resource_type)?;
+        let request_len = u16::try_from(request.len()).map_err(|_| Error::MessageTooLong)?;
+        stream.write_all(&request_len.to_be_bytes()).await?;
+        stream.write_all(&request).await?;
+
+        let response_len = stream.read_u16().await?;
+        let mut response = vec![0; response_len.into()];
+        stream.read_exact(&mut response).await?;
+        if dns_message::get_id(&response)? != request_id {
+            return Err(Error::UnexpectedMessageId);
+        }
+        Ok(response)
+    }
+}
+
+impl RecordFetcher for UdpTransport {
+    async fn fetch(&self, name: &str, record_type: ResourceType) -> dns::Result<Vec<RawRecord>> {
+        let dnssec = self.dnssec.as_ref().expect("only used when validating");
+        // Random IDs make responses harder to forge. Skipping the previous ID means a late answer
+        // to the last query can't be mistaken for an answer to this one.
+        let request_id = loop {
+            let id = rand::random_range(FIRST_DNSSEC_REQUEST_ID..=u16::MAX);
+            if dnssec.last_request_id.swap(id, Ordering::Relaxed) != id {
+                break id;
+            }
+        };
+        self.send_request(name, request_id, record_type).await?;
+
+        // Responses to the original queries may still be arriving; set them aside
+        // for `next_dns_query_result1` to pick up later. Anything else is a stale or
+        // duplicated answer to an earlier DNSSEC query and is dropped.
+        loop {
+            let message = self.recv().await?;
+            let id = dns_message::get_id(&message)?;
+            if id == request_id {
+                let message = if dns_message::is_truncated(&message)? {
+                    self.query_over_tcp(name, request_id, record_type).await?
+                } else {
+                    message
+                };
+                return Ok(dns_message::parse_raw_answers(&message)?);
+            }
+            if id >= FIRST_DNSSEC_REQUEST_ID {
+                log::debug!("dropping stale response to a DNSSEC query");
+                continue;
+            }
+            let mut unclaimed = dnssec.unclaimed_responses.lock().expect("not poisoned");
+            let is_duplicate = unclaimed
+                .iter()
+                .any(|pending| dns_message::get_id(pending).is_ok_and(|pending| pending == id));
+            if is_duplicate {
+                log::debug!("dropping duplicate response");
+            } else {
+                unclaimed.push_back(message);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// A response with a single A record, answering the query with `id`.
+    fn response(id: u16) -> Vec<u8> {
+        let mut message = id.to_be_bytes().to_vec();
+        message.extend([0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]); // flags and counts
+        message.extend([0, 0, 1, 0, 1]); // question: the root, A, IN
+        message.extend([0, 0, 1, 0, 1, 0, 0, 1, 44, 0, 4, 192, 0, 2, 1]);
+        message
+    }
+
+    async fn validating_transport(server: &UdpSocket) -> UdpTransport {
+        let port = server.local_addr().expect("bound").port();
+        UdpTransportConnectorFactory::validating(TrustAnchor::new(vec![]))
+            .make()
+            .connect_over(
+                (),
+                UdpRoute {
+                    address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                    port: NonZeroU16::new(port).expect("bound to a real port"),
+                },
+                "test",
+            )
+            .await
+            .expect("can connect")
+    }
+
+    #[tokio::test]
+    async fn drops_stale_and_duplicate_responses_while_fetching() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("can bind");
+        let transport = validating_transport(&server).await;
+
+        let serve = async {
+            let mut buf = [0; MAX_DNS_EDNS_UDP_MESSAGE_LEN];
+            let (_, client) = server.recv_from(&mut buf).await.expect("first query");
+            let first_id = dns_message::get_id(&buf).expect("valid");
+            server
+                .send_to(&response(first_id), client)
+                .await
+                .expect("can send");
+
+            let (_, client) = server.recv_from(&mut buf).await.expect("second query");
+            let second_id = dns_message::get_id(&buf).expect("valid");
+            // A repeat of the first answer, and a duplicated answer to an original query, arrive
+            // before the answer that's being waited for.
+            for id in [first_id, A_REQUEST_ID, A_REQUEST_ID, second_id] {
+                server
+                    .send_to(&response(id), client)
+                    .await
+                    .expect("can send");
+            }
+        };
+        let fetch = async {
+            transport
+                .fetch("", ResourceType::DNSKEY)
+                .await
+                .expect("first answer");
+            transport
+                .fetch("", ResourceType::DNSKEY)
+                .await
+                .expect("second answer");
+        };
+        tokio::join!(serve, fetch);
+
+        let dnssec = transport.dnssec.as_ref().expect("validating");
+        let unclaimed = dnssec.unclaimed_responses.lock().expect("not poisoned");
+        assert_eq!(
+            unclaimed
+                .iter()
+                .map(|message| dns_message::get_id(message).expect("valid"))
+                .collect::<Vec<_>>(),
+            [A_REQUEST_ID]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_truncated_responses_over_tcp() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("can bind");
+        let tcp_server = TcpListener::bind(server.local_addr().expect("bound"))
+            .await
+            .expect("can bind");
+        let transport = validating_transport(&server).await;
+
+        let serve = async {
+            let mut buf = [0; MAX_DNS_EDNS_UDP_MESSAGE_LEN];
+            let (len, client) = server.recv_from(&mut buf).await.expect("query");
+            let udp_query = buf[..len].to_vec();
+            let id = dns_message::get_id(&udp_query).expect("valid");
+            let mut truncated = response(id);
+            truncated[2] |= 0x02;
+            server.send_to(&truncated, client).await.expect("can send");
+
+            let (mut stream, _) = tcp_server.accept().await.expect("can accept");
+            let len = stream.read_u16().await.expect("length");
+            let mut tcp_query = vec![0; len.into()];
+            stream.read_exact(&mut tcp_query).await.expect("query");
+            assert_eq!(tcp_query, udp_query);
+
+            let full = response(id);
+            let full_len = u16::try_from(full.len()).expect("short");
+            stream
+                .write_all(&full_len.to_be_bytes())
+                .await
+                .expect("can send");
+            stream.write_all(&full).await.expect("can send");
+        };
+        let fetch = async {
+            transport
+                .fetch("", ResourceType::DNSKEY)
+                .await
+                .expect("answer over TCP")
+        };
+        let ((), records) = tokio::join!(serve, fetch);
+        assert_eq!(records.len(), 1);
+    }
 }
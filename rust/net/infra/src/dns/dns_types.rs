@@ -15,10 +15,12 @@ pub struct Expiring<T> {
 /// Only lists the ones required/supported for our purposes.
 ///
 /// Values for the variants are assigned based on the Resource Record type values
-/// from [RFC1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.2)
-/// and [RFC3596](https://datatracker.ietf.org/doc/html/rfc3596#section-2.1)
+/// from [RFC1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.2),
+/// [RFC3596](https://datatracker.ietf.org/doc/html/rfc3596#section-2.1),
+/// [RFC4034](https://datatracker.ietf.org/doc/html/rfc4034#section-7),
+/// and [RFC9460](https://datatracker.ietf.org/doc/html/rfc9460#section-14.1)
 #[repr(u16)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[expect(clippy::upper_case_acronyms)]
 pub enum ResourceType {
     /// An IPv4 host address type
@@ -29,4 +31,20 @@ pub enum ResourceType {
     ///
     /// https://datatracker.ietf.org/doc/html/rfc3596#section-2.1
     AAAA = 28,
+    /// A delegation signer, linking a child zone's keys to its parent
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc4034#section-5
+    DS = 43,
+    /// A DNSSEC public key for a zone
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc4034#section-2
+    DNSKEY = 48,
+    /// A general-purpose service binding type
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc9460#section-2
+    SVCB = 64,
+    /// A service binding type for HTTPS origins
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc9460#section-9
+    HTTPS = 65,
 }
@@ -0,0 +1,1090 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! DNSSEC validation of answers received over a plaintext transport.
+//!
+//! Answers are checked by following the chain of RRSIG, DNSKEY, and DS records from the zone that
+//! signed them up to a configured [`TrustAnchor`], as described in
+//! [RFC 4035](https://datatracker.ietf.org/doc/html/rfc4035#section-5).
+//!
+//! Only the pieces needed to validate positive answers are implemented:
+//! - Only RSA/SHA-256 (algorithm 8) and ECDSA P-256/SHA-256 (algorithm 13) signatures, and
+//!   SHA-256 DS digests, are supported. Together these cover the root zone and the vast majority
+//!   of signed zones.
+//! - Negative answers aren't authenticated, since NSEC and NSEC3 proofs aren't checked. This is
+//!   fine for our purposes: a spoofed "no such name" can't send us to a hijacked address.
+//! - Unsigned zones fail validation rather than being treated as insecure.
+//! - Answers synthesized from a wildcard are rejected. Accepting them safely requires an NSEC or
+//!   NSEC3 proof that the queried name doesn't exist, which isn't checked; without it, a signed
+//!   wildcard answer can be replayed for names it doesn't actually cover.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use boring_signal::bn::BigNum;
+use boring_signal::ec::{EcGroup, EcKey};
+use boring_signal::ecdsa::EcdsaSig;
+use boring_signal::hash::MessageDigest;
+use boring_signal::nid::Nid;
+use boring_signal::pkey::PKey;
+use boring_signal::rsa::Rsa;
+use boring_signal::sign::Verifier;
+use const_str::hex;
+use itertools::Itertools as _;
+use tokio::time::Instant;
+
+use crate::dns;
+use crate::dns::dns_message::{
+    QCLASS_IN, RECORD_TYPE_CNAME, RECORD_TYPE_RRSIG, RawRecord, canonical_name_wire,
+    read_uncompressed_name, take, take_u16,
+};
+use crate::dns::dns_types::{Expiring, ResourceType};
+use crate::dns::dns_utils::log_safe_domain;
+
+const ALGORITHM_RSASHA256: u8 = 8;
+const ALGORITHM_ECDSAP256SHA256: u8 = 13;
+const DIGEST_TYPE_SHA256: u8 = 2;
+const DNSKEY_FLAG_ZONE_KEY: u16 = 0x0100;
+const DNSKEY_PROTOCOL: u8 = 3;
+
+/// Limits how many zones (and how many CNAMEs) we're willing to follow for a single answer.
+const MAX_CHAIN_LENGTH: usize = 16;
+
+#[derive(displaydoc::Display, Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// records are not covered by any signature
+    MissingSignature,
+    /// no signature over the records could be verified
+    BadSignature,
+    /// signature is not valid at the current time
+    SignatureExpired,
+    /// records were synthesized from a wildcard, which can't be authenticated
+    WildcardExpansion,
+    /// unsupported DNSSEC algorithm {0}
+    UnsupportedAlgorithm(u8),
+    /// zone keys don't match any trusted delegation signer
+    UntrustedKeys,
+    /// signer is not a parent of the signed records
+    SignerNotAncestor,
+    /// chain of trust or CNAME chain is too long
+    ChainTooLong,
+    /// malformed DNSSEC record
+    MalformedRecord,
+    /// failed to fetch DNSSEC records
+    FetchFailed,
+    /// the current time is before the Unix epoch
+    InvalidTime,
+}
+
+/// A DS record for a zone whose keys are trusted without further validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelegationSigner {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: Vec<u8>,
+}
+
+/// The starting point of the DNSSEC chain of trust: DS records for the root zone's keys.
+#[derive(Clone, Debug)]
+pub struct TrustAnchor {
+    root_ds: Vec<DelegationSigner>,
+}
+
+impl TrustAnchor {
+    pub fn new(root_ds: Vec<DelegationSigner>) -> Self {
+        Self { root_ds }
+    }
+
+    /// The root zone trust anchors published by IANA.
+    ///
+    /// Includes both KSK-2017 and KSK-2024, so that validation keeps working across the rollover.
+    ///
+    /// <https://data.iana.org/root-anchors/root-anchors.xml>
+    pub fn iana_root() -> Self {
+        Self::new(vec![
+            DelegationSigner {
+                key_tag: 20326,
+                algorithm: ALGORITHM_RSASHA256,
+                digest_type: DIGEST_TYPE_SHA256,
+                digest: hex!("E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D")
+                    .to_vec(),
+            },
+            DelegationSigner {
+                key_tag: 38696,
+                algorithm: ALGORITHM_RSASHA256,
+                digest_type: DIGEST_TYPE_SHA256,
+                digest: hex!("683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16")
+                    .to_vec(),
+            },
+        ])
+    }
+}
+
+/// Source of the DNSKEY and DS records needed to build a chain of trust.
+pub(crate) trait RecordFetcher {
+    /// Returns the answer records for the query, including the covering RRSIGs.
+    fn fetch(
+        &self,
+        name: &str,
+        record_type: ResourceType,
+    ) -> impl Future<Output = dns::Result<Vec<RawRecord>>> + Send;
+}
+
+/// Zone keys that have already been validated, keyed by zone name.
+///
+/// Each entry expires with the shortest TTL of the DNSKEY and DS records that established it, so
+/// key rollovers are picked up.
+#[derive(Debug, Default)]
+pub(crate) struct ValidatedKeys {
+    by_zone: Mutex<HashMap<String, Expiring<Arc<[Dnskey]>>>>,
+}
+
+impl ValidatedKeys {
+    fn get(&self, zone: &str) -> Option<Arc<[Dnskey]>> {
+        let mut by_zone = self.by_zone.lock().expect("not poisoned");
+        if by_zone.get(zone)?.expiration <= Instant::now() {
+            by_zone.remove(zone);
+            return None;
+        }
+        by_zone.get(zone).map(|entry| entry.data.clone())
+    }
+
+    fn insert(&self, zone: String, keys: Arc<[Dnskey]>, ttl: u32) {
+        let entry = Expiring {
+            data: keys,
+            expiration: Instant::now() + Duration::from_secs(ttl.into()),
+        };
+        self.by_zone
+            .lock()
+            .expect("not poisoned")
+            .insert(zone, entry);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Dnskey {
+    flags: u16,
+    algorithm: u8,
+    key_tag: u16,
+    public_key: Vec<u8>,
+    rdata: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+struct Rrsig {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    expiration: u32,
+    inception: u32,
+    key_tag: u16,
+    signer: String,
+    signature: Vec<u8>,
+}
+
+pub(crate) struct Validator<'a, F> {
+    trust_anchor: &'a TrustAnchor,
+    fetcher: &'a F,
+    validated_keys: &'a ValidatedKeys,
+    /// Seconds since the epoch, in the 32-bit serial number space used by RRSIG timestamps.
+    now: u32,
+}
+
+impl<'a, F: RecordFetcher + Sync> Validator<'a, F> {
+    pub(crate) fn new(
+        trust_anchor: &'a TrustAnchor,
+        fetcher: &'a F,
+        validated_keys: &'a ValidatedKeys,
+        now: SystemTime,
+    ) -> Result<Self, ValidationError> {
+        let since_epoch = now
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| ValidationError::InvalidTime)?;
+        // RRSIG timestamps wrap around, so only the low 32 bits matter.
+        #[expect(clippy::cast_possible_truncation)]
+        let now = since_epoch.as_secs() as u32;
+        Ok(Self {
+            trust_anchor,
+            fetcher,
+            validated_keys,
+            now,
+        })
+    }
+
+    /// Validates an answer to a query for `record_type` records of `name`.
+    ///
+    /// Returns the validated records of the requested type, following any CNAMEs in the answer.
+    /// Every RRset along the way must carry a valid signature.
+    pub(crate) async fn validate_answer(
+        &self,
+        name: &str,
+        record_type: ResourceType,
+        answers: Vec<RawRecord>,
+    ) -> Result<Vec<RawRecord>, ValidationError> {
+        let record_type = record_type as u16;
+        let mut name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            let (rrset, sigs) = split_rrset(&answers, &name, record_type)?;
+            if !rrset.is_empty() {
+                self.validate_rrset(&name, record_type, &rrset, &sigs)
+                    .await?;
+                return Ok(rrset);
+            }
+
+            let (cname, sigs) = split_rrset(&answers, &name, RECORD_TYPE_CNAME)?;
+            let [cname] = cname.as_slice() else {
+                // Neither the requested records nor an alias: nothing to return.
+                return Ok(vec![]);
+            };
+            self.validate_rrset(&name, RECORD_TYPE_CNAME, std::slice::from_ref(cname), &sigs)
+                .await?;
+            name = read_uncompressed_name(&mut cname.rdata.as_slice())
+                .map_err(|_| ValidationError::MalformedRecord)?
+                .unwrap_or_default();
+        }
+        Err(ValidationError::ChainTooLong)
+    }
+
+    async fn validate_rrset(
+        &self,
+        owner: &str,
+        record_type: u16,
+        rrset: &[RawRecord],
+        sigs: &[Rrsig],
+    ) -> Result<(), ValidationError> {
+        let signer = sigs
+            .first()
+            .map(|sig| sig.signer.as_str())
+            .ok_or(ValidationError::MissingSignature)?;
+        if !is_ancestor_or_self(signer, owner) {
+            return Err(ValidationError::SignerNotAncestor);
+        }
+        // Signatures from other signers can't be checked with these keys.
+        let sigs = sigs
+            .iter()
+            .filter(|sig| sig.signer == signer)
+            .cloned()
+            .collect_vec();
+        let keys = self.zone_keys(signer).await?;
+        verify_rrset(owner, record_type, rrset, &sigs, &keys, self.now)
+    }
+
+    /// Returns the validated keys for `zone`, building the chain of trust to it if necessary.
+    async fn zone_keys(&self, zone: &str) -> Result<Arc<[Dnskey]>, ValidationError> {
+        if let Some(keys) = self.cached_keys(zone) {
+            return Ok(keys);
+        }
+
+        struct Link {
+            zone: String,
+            dnskeys: Vec<RawRecord>,
+            ds: Option<Vec<RawRecord>>,
+        }
+
+        // Walk up from the zone, following the signers of each DS record set, until reaching a
+        // zone whose keys are already trusted (or the root).
+        let mut links = vec![];
+        let mut zone = zone.to_owned();
+        let mut parent_keys = loop {
+            if links.len() >= MAX_CHAIN_LENGTH {
+                return Err(ValidationError::ChainTooLong);
+            }
+            let dnskeys = self.fetch(&zone, ResourceType::DNSKEY).await?;
+            if zone.is_empty() {
+                links.push(Link {
+                    zone,
+                    dnskeys,
+                    ds: None,
+                });
+                break None;
+            }
+
+            let ds = self.fetch(&zone, ResourceType::DS).await?;
+            let (_, ds_sigs) = split_rrset(&ds, &zone, ResourceType::DS as u16)?;
+            let parent = ds_sigs
+                .first()
+                .map(|sig| sig.signer.clone())
+                .ok_or(ValidationError::MissingSignature)?;
+            // Requiring a strict ancestor guarantees the walk terminates at the root.
+            if parent == zone || !is_ancestor_or_self(&parent, &zone) {
+                return Err(ValidationError::SignerNotAncestor);
+            }
+            links.push(Link {
+                zone,
+                dnskeys,
+                ds: Some(ds),
+            });
+            if let Some(keys) = self.cached_keys(&parent) {
+                break Some(keys);
+            }
+            zone = parent;
+        };
+
+        // Then validate back down, each zone's DS records vouching for the next zone's keys.
+        for Link { zone, dnskeys, ds } in links.into_iter().rev() {
+            let (trusted_ds, ds_ttl) = match (ds, parent_keys) {
+                (None, _) => (self.trust_anchor.root_ds.clone(), None),
+                (Some(ds), Some(parent_keys)) => {
+                    let (rrset, sigs) = split_rrset(&ds, &zone, ResourceType::DS as u16)?;
+                    verify_rrset(
+                        &zone,
+                        ResourceType::DS as u16,
+                        &rrset,
+                        &sigs,
+                        &parent_keys,
+                        self.now,
+                    )?;
+                    let trusted_ds = rrset
+                        .iter()
+                        .map(|record| parse_ds(&record.rdata))
+                        .try_collect()?;
+                    (trusted_ds, rrset.iter().map(|record| record.ttl).min())
+                }
+                (Some(_), None) => unreachable!("the top link is either the root or cached"),
+            };
+
+            let (rrset, sigs) = split_rrset(&dnskeys, &zone, ResourceType::DNSKEY as u16)?;
+            let keys: Arc<[Dnskey]> = rrset
+                .iter()
+                .map(|record| parse_dnskey(&record.rdata))
+                .try_collect::<_, Vec<_>, _>()?
+                .into();
+            let entry_keys = keys
+                .iter()
+                .filter(|key| trusted_ds.iter().any(|ds| ds_matches_key(ds, &zone, key)))
+                .cloned()
+                .collect_vec();
+            if entry_keys.is_empty() {
+                return Err(ValidationError::UntrustedKeys);
+            }
+            verify_rrset(
+                &zone,
+                ResourceType::DNSKEY as u16,
+                &rrset,
+                &sigs,
+                &entry_keys,
+                self.now,
+            )?;
+
+            let ttl = rrset
+                .iter()
+                .map(|record| record.ttl)
+                .chain(ds_ttl)
+                .min()
+                .expect("checked for keys above");
+            self.validated_keys.insert(zone, keys.clone(), ttl);
+            parent_keys = Some(keys);
+        }
+
+        Ok(parent_keys.expect("at least one link"))
+    }
+
+    fn cached_keys(&self, zone: &str) -> Option<Arc<[Dnskey]>> {
+        self.validated_keys.get(zone)
+    }
+
+    async fn fetch(
+        &self,
+        zone: &str,
+        record_type: ResourceType,
+    ) -> Result<Vec<RawRecord>, ValidationError> {
+        self.fetcher.fetch(zone, record_type).await.map_err(|e| {
+            log::warn!(
+                "failed to fetch {record_type:?} records for [{}]: {e}",
+                log_safe_domain(zone)
+            );
+            ValidationError::FetchFailed
+        })
+    }
+}
+
+/// Separates the records of `record_type` owned by `owner` from the signatures covering them.
+fn split_rrset(
+    records: &[RawRecord],
+    owner: &str,
+    record_type: u16,
+) -> Result<(Vec<RawRecord>, Vec<Rrsig>), ValidationError> {
+    let mut rrset = vec![];
+    let mut sigs = vec![];
+    for record in records
+        .iter()
+        .filter(|r| r.name == owner && r.class == QCLASS_IN)
+    {
+        if record.record_type == record_type {
+            rrset.push(record.clone());
+        } else if record.record_type == RECORD_TYPE_RRSIG {
+            let sig = parse_rrsig(&record.rdata)?;
+            if sig.type_covered == record_type {
+                sigs.push(sig);
+            }
+        }
+    }
+    Ok((rrset, sigs))
+}
+
+/// Checks that at least one of `sigs` is a currently valid signature over `rrset` by one of
+/// `keys`.
+fn verify_rrset(
+    owner: &str,
+    record_type: u16,
+    rrset: &[RawRecord],
+    sigs: &[Rrsig],
+    keys: &[Dnskey],
+    now: u32,
+) -> Result<(), ValidationError> {
+    if sigs.is_empty() {
+        return Err(ValidationError::MissingSignature);
+    }
+
+    let mut error = ValidationError::BadSignature;
+    for sig in sigs {
+        // An RRSIG with fewer labels than the owner name was synthesized from a wildcard.
+        // https://datatracker.ietf.org/doc/html/rfc4035#section-5.3.2
+        if usize::from(sig.labels) < label_count(owner) {
+            error = ValidationError::WildcardExpansion;
+            continue;
+        }
+
+        // RRSIG timestamps use serial number arithmetic, so compare via wrapping differences.
+        // https://datatracker.ietf.org/doc/html/rfc4034#section-3.1.5
+        let started = (now.wrapping_sub(sig.inception) as i32) >= 0;
+        let not_expired = (sig.expiration.wrapping_sub(now) as i32) >= 0;
+        if !started || !not_expired {
+            error = ValidationError::SignatureExpired;
+            continue;
+        }
+
+        let data = signed_data(sig, owner, record_type, rrset)?;
+        for key in keys.iter().filter(|key| {
+            key.key_tag == sig.key_tag
+                && key.algorithm == sig.algorithm
+                && key.flags & DNSKEY_FLAG_ZONE_KEY != 0
+        }) {
+            match verify_signature(key, &data, &sig.signature) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => error = e,
+            }
+        }
+    }
+    Err(error)
+}
+
+/// Builds the data covered by an RRSIG.
+///
+/// [Signature calculation](https://datatracker.ietf.org/doc/html/rfc4034#section-3.1.8.1)
+fn signed_data(
+    sig: &Rrsig,
+    owner: &str,
+    record_type: u16,
+    rrset: &[RawRecord],
+) -> Result<Vec<u8>, ValidationError> {
+    let mut data = vec![];
+    data.extend_from_slice(&sig.type_covered.to_be_bytes());
+    data.push(sig.algorithm);
+    data.push(sig.labels);
+    data.extend_from_slice(&sig.original_ttl.to_be_bytes());
+    data.extend_from_slice(&sig.expiration.to_be_bytes());
+    data.extend_from_slice(&sig.inception.to_be_bytes());
+    data.extend_from_slice(&sig.key_tag.to_be_bytes());
+    data.extend_from_slice(&canonical_name_wire(&sig.signer));
+
+    // Wildcard expansions are rejected before getting here, so the labels must match exactly.
+    if usize::from(sig.labels) != label_count(owner) {
+        return Err(ValidationError::MalformedRecord);
+    }
+    let owner_wire = canonical_name_wire(owner);
+
+    let rdatas = rrset
+        .iter()
+        .map(|record| record.rdata.as_slice())
+        .sorted()
+        .dedup();
+    for rdata in rdatas {
+        let rdata_len = u16::try_from(rdata.len()).map_err(|_| ValidationError::MalformedRecord)?;
+        data.extend_from_slice(&owner_wire);
+        data.extend_from_slice(&record_type.to_be_bytes());
+        data.extend_from_slice(&QCLASS_IN.to_be_bytes());
+        data.extend_from_slice(&sig.original_ttl.to_be_bytes());
+        data.extend_from_slice(&rdata_len.to_be_bytes());
+        data.extend_from_slice(rdata);
+    }
+    Ok(data)
+}
+
+fn verify_signature(key: &Dnskey, data: &[u8], signature: &[u8]) -> Result<bool, ValidationError> {
+    match key.algorithm {
+        ALGORITHM_RSASHA256 => {
+            // https://datatracker.ietf.org/doc/html/rfc3110#section-2
+            let mut public_key = key.public_key.as_slice();
+            let exponent_len = match take(&mut public_key, 1) {
+                Ok([0]) => take_u16(&mut public_key).map(usize::from),
+                Ok([len]) => Ok(usize::from(*len)),
+                _ => return Err(ValidationError::MalformedRecord),
+            }
+            .map_err(|_| ValidationError::MalformedRecord)?;
+            let exponent = take(&mut public_key, exponent_len)
+                .map_err(|_| ValidationError::MalformedRecord)?;
+            let modulus = public_key;
+
+            let verify = || {
+                let rsa = Rsa::from_public_components(
+                    BigNum::from_slice(modulus)?,
+                    BigNum::from_slice(exponent)?,
+                )?;
+                let pkey = PKey::from_rsa(rsa)?;
+                let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)?;
+                verifier.update(data)?;
+                verifier.verify(signature)
+            };
+            verify().map_err(|_| ValidationError::MalformedRecord)
+        }
+        ALGORITHM_ECDSAP256SHA256 => {
+            // https://datatracker.ietf.org/doc/html/rfc6605#section-4
+            let (Some((x, y)), Some((r, s))) = (
+                key.public_key
+                    .split_at_checked(32)
+                    .filter(|(_, y)| y.len() == 32),
+                signature
+                    .split_at_checked(32)
+                    .filter(|(_, s)| s.len() == 32),
+            ) else {
+                return Err(ValidationError::MalformedRecord);
+            };
+
+            let verify = || {
+                let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+                let ec_key = EcKey::from_public_key_affine_coordinates(
+                    &group,
+                    &BigNum::from_slice(x)?,
+                    &BigNum::from_slice(y)?,
+                )?;
+                let sig = EcdsaSig::from_private_components(
+                    BigNum::from_slice(r)?,
+                    BigNum::from_slice(s)?,
+                )?;
+                sig.verify(&boring_signal::sha::sha256(data), &ec_key)
+            };
+            verify().map_err(|_| ValidationError::MalformedRecord)
+        }
+        algorithm => Err(ValidationError::UnsupportedAlgorithm(algorithm)),
+    }
+}
+
+fn ds_matches_key(ds: &DelegationSigner, zone: &str, key: &Dnskey) -> bool {
+    if ds.key_tag != key.key_tag
+        || ds.algorithm != key.algorithm
+        || ds.digest_type != DIGEST_TYPE_SHA256
+    {
+        return false;
+    }
+    // https://datatracker.ietf.org/doc/html/rfc4034#section-5.1.4
+    let mut digest_input = canonical_name_wire(zone);
+    digest_input.extend_from_slice(&key.rdata);
+    boring_signal::sha::sha256(&digest_input).as_slice() == ds.digest.as_slice()
+}
+
+/// [DNSKEY RDATA](https://datatracker.ietf.org/doc/html/rfc4034#section-2.1)
+fn parse_dnskey(rdata: &[u8]) -> Result<Dnskey, ValidationError> {
+    let mut rest = rdata;
+    let flags = take_u16(&mut rest).map_err(|_| ValidationError::MalformedRecord)?;
+    let [protocol, algorithm] =
+        *take(&mut rest, 2).map_err(|_| ValidationError::MalformedRecord)?
+    else {
+        unreachable!("took two bytes")
+    };
+    if protocol != DNSKEY_PROTOCOL {
+        return Err(ValidationError::MalformedRecord);
+    }
+    Ok(Dnskey {
+        flags,
+        algorithm,
+        key_tag: key_tag(rdata),
+        public_key: rest.to_vec(),
+        rdata: rdata.to_vec(),
+    })
+}
+
+/// [DS RDATA](https://datatracker.ietf.org/doc/html/rfc4034#section-5.1)
+fn parse_ds(rdata: &[u8]) -> Result<DelegationSigner, ValidationError> {
+    let mut rest = rdata;
+    let key_tag = take_u16(&mut rest).map_err(|_| ValidationError::MalformedRecord)?;
+    let [algorithm, digest_type] =
+        *take(&mut rest, 2).map_err(|_| ValidationError::MalformedRecord)?
+    else {
+        unreachable!("took two bytes")
+    };
+    Ok(DelegationSigner {
+        key_tag,
+        algorithm,
+        digest_type,
+        digest: rest.to_vec(),
+    })
+}
+
+/// [RRSIG RDATA](https://datatracker.ietf.org/doc/html/rfc4034#section-3.1)
+fn parse_rrsig(rdata: &[u8]) -> Result<Rrsig, ValidationError> {
+    let parse = || {
+        let mut rest = rdata;
+        let type_covered = take_u16(&mut rest)?;
+        let [algorithm, labels] = *take(&mut rest, 2)? else {
+            unreachable!("took two bytes")
+        };
+        let original_ttl = u32::from_be_bytes(take(&mut rest, 4)?.try_into().expect("4 bytes"));
+        let expiration = u32::from_be_bytes(take(&mut rest, 4)?.try_into().expect("4 bytes"));
+        let inception = u32::from_be_bytes(take(&mut rest, 4)?.try_into().expect("4 bytes"));
+        let key_tag = take_u16(&mut rest)?;
+        let signer = read_uncompressed_name(&mut rest)?
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Ok::<_, dns::dns_message::Error>(Rrsig {
+            type_covered,
+            algorithm,
+            labels,
+            original_ttl,
+            expiration,
+            inception,
+            key_tag,
+            signer,
+            signature: rest.to_vec(),
+        })
+    };
+    parse().map_err(|_| ValidationError::MalformedRecord)
+}
+
+/// [Key tag calculation](https://datatracker.ietf.org/doc/html/rfc4034#appendix-B)
+fn key_tag(rdata: &[u8]) -> u16 {
+    let mut accumulator: u32 = rdata
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            if i % 2 == 0 {
+                u32::from(*byte) << 8
+            } else {
+                u32::from(*byte)
+            }
+        })
+        .sum();
+    accumulator += (accumulator >> 16) & 0xFFFF;
+    (accumulator & 0xFFFF) as u16
+}
+
+fn label_count(name: &str) -> usize {
+    match name {
+        "" => 0,
+        name => name.split('.').count(),
+    }
+}
+
+fn is_ancestor_or_self(ancestor: &str, name: &str) -> bool {
+    ancestor.is_empty()
+        || name == ancestor
+        || name
+            .strip_suffix(ancestor)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use base64::prelude::{BASE64_STANDARD, Engine as _};
+    use boring_signal::bn::BigNumContext;
+    use boring_signal::ec::PointConversionForm;
+    use boring_signal::pkey::Private;
+    use const_str::ip_addr;
+
+    use super::*;
+
+    const NOW: u32 = 1_700_000_000;
+    const TTL: u32 = 300;
+    const HOUR: u32 = 3600;
+
+    fn record(name: &str, record_type: u16, rdata: Vec<u8>) -> RawRecord {
+        RawRecord {
+            name: name.to_owned(),
+            record_type,
+            class: QCLASS_IN,
+            ttl: TTL,
+            rdata,
+        }
+    }
+
+    fn a_record(name: &str, ip: std::net::Ipv4Addr) -> RawRecord {
+        record(name, ResourceType::A as u16, ip.octets().to_vec())
+    }
+
+    struct TestZone {
+        name: &'static str,
+        key: EcKey<Private>,
+        dnskey: RawRecord,
+    }
+
+    impl TestZone {
+        fn new(name: &'static str) -> Self {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+            let key = EcKey::generate(&group).unwrap();
+            let point = key
+                .public_key()
+                .to_bytes(
+                    &group,
+                    PointConversionForm::UNCOMPRESSED,
+                    &mut BigNumContext::new().unwrap(),
+                )
+                .unwrap();
+            // Zone key and secure entry point flags.
+            let mut rdata = vec![0x01, 0x01, DNSKEY_PROTOCOL, ALGORITHM_ECDSAP256SHA256];
+            rdata.extend_from_slice(&point[1..]);
+            let dnskey = record(name, ResourceType::DNSKEY as u16, rdata);
+            Self { name, key, dnskey }
+        }
+
+        fn ds(&self) -> RawRecord {
+            let mut digest_input = canonical_name_wire(self.name);
+            digest_input.extend_from_slice(&self.dnskey.rdata);
+            let mut rdata = key_tag(&self.dnskey.rdata).to_be_bytes().to_vec();
+            rdata.extend([ALGORITHM_ECDSAP256SHA256, DIGEST_TYPE_SHA256]);
+            rdata.extend(boring_signal::sha::sha256(&digest_input));
+            record(self.name, ResourceType::DS as u16, rdata)
+        }
+
+        fn sign_with_validity(
+            &self,
+            rrset: &[RawRecord],
+            inception: u32,
+            expiration: u32,
+        ) -> RawRecord {
+            let owner = &rrset[0].name;
+            let mut sig = Rrsig {
+                type_covered: rrset[0].record_type,
+                algorithm: ALGORITHM_ECDSAP256SHA256,
+                labels: label_count(owner).try_into().unwrap(),
+                original_ttl: TTL,
+                expiration,
+                inception,
+                key_tag: key_tag(&self.dnskey.rdata),
+                signer: self.name.to_owned(),
+                signature: vec![],
+            };
+            let data = signed_data(&sig, owner, sig.type_covered, rrset).unwrap();
+            let ecdsa = EcdsaSig::sign(&boring_signal::sha::sha256(&data), &self.key).unwrap();
+            sig.signature = ecdsa.r().to_vec_padded(32).unwrap();
+            sig.signature.extend(ecdsa.s().to_vec_padded(32).unwrap());
+
+            let mut rdata = sig.type_covered.to_be_bytes().to_vec();
+            rdata.extend([sig.algorithm, sig.labels]);
+            rdata.extend(sig.original_ttl.to_be_bytes());
+            rdata.extend(sig.expiration.to_be_bytes());
+            rdata.extend(sig.inception.to_be_bytes());
+            rdata.extend(sig.key_tag.to_be_bytes());
+            rdata.extend(canonical_name_wire(&sig.signer));
+            rdata.extend(sig.signature);
+            record(owner, RECORD_TYPE_RRSIG, rdata)
+        }
+
+        fn signed(&self, rrset: Vec<RawRecord>) -> Vec<RawRecord> {
+            let sig = self.sign_with_validity(&rrset, NOW - HOUR, NOW + HOUR);
+            rrset.into_iter().chain([sig]).collect()
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeFetcher(HashMap<(String, u16), Vec<RawRecord>>);
+
+    impl FakeFetcher {
+        fn insert(&mut self, name: &str, record_type: ResourceType, records: Vec<RawRecord>) {
+            self.0
+                .insert((name.to_owned(), record_type as u16), records);
+        }
+    }
+
+    impl RecordFetcher for FakeFetcher {
+        fn fetch(
+            &self,
+            name: &str,
+            record_type: ResourceType,
+        ) -> impl Future<Output = dns::Result<Vec<RawRecord>>> + Send {
+            std::future::ready(
+                self.0
+                    .get(&(name.to_owned(), record_type as u16))
+                    .cloned()
+                    .ok_or(dns::DnsError::NoData),
+            )
+        }
+    }
+
+    /// Sets up a signed root, `org`, and `signal.org`, returning the last one.
+    fn signed_hierarchy() -> (TrustAnchor, FakeFetcher, TestZone) {
+        let root = TestZone::new("");
+        let org = TestZone::new("org");
+        let signal = TestZone::new("signal.org");
+
+        let mut fetcher = FakeFetcher::default();
+        fetcher.insert(
+            "",
+            ResourceType::DNSKEY,
+            root.signed(vec![root.dnskey.clone()]),
+        );
+        fetcher.insert("org", ResourceType::DS, root.signed(vec![org.ds()]));
+        fetcher.insert(
+            "org",
+            ResourceType::DNSKEY,
+            org.signed(vec![org.dnskey.clone()]),
+        );
+        fetcher.insert(
+            "signal.org",
+            ResourceType::DS,
+            org.signed(vec![signal.ds()]),
+        );
+        fetcher.insert(
+            "signal.org",
+            ResourceType::DNSKEY,
+            signal.signed(vec![signal.dnskey.clone()]),
+        );
+
+        let trust_anchor = TrustAnchor::new(vec![parse_ds(&root.ds().rdata).unwrap()]);
+        (trust_anchor, fetcher, signal)
+    }
+
+    async fn validate(
+        trust_anchor: &TrustAnchor,
+        fetcher: &FakeFetcher,
+        name: &str,
+        answers: Vec<RawRecord>,
+    ) -> Result<Vec<RawRecord>, ValidationError> {
+        let validated_keys = ValidatedKeys::default();
+        Validator::new(
+            trust_anchor,
+            fetcher,
+            &validated_keys,
+            UNIX_EPOCH + Duration::from_secs(NOW.into()),
+        )
+        .expect("valid time")
+        .validate_answer(name, ResourceType::A, answers)
+        .await
+    }
+
+    #[tokio::test]
+    async fn validates_signed_answer() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let rrset = vec![
+            a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1")),
+            a_record("chat.signal.org", ip_addr!(v4, "192.0.2.2")),
+        ];
+
+        let validated = validate(
+            &trust_anchor,
+            &fetcher,
+            "Chat.Signal.org.",
+            zone.signed(rrset.clone()),
+        )
+        .await;
+        assert_eq!(validated, Ok(rrset));
+    }
+
+    #[tokio::test]
+    async fn follows_signed_cname() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let cname = record(
+            "www.signal.org",
+            RECORD_TYPE_CNAME,
+            canonical_name_wire("chat.signal.org"),
+        );
+        let rrset = vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))];
+        let answers = [zone.signed(vec![cname]), zone.signed(rrset.clone())].concat();
+
+        let validated = validate(&trust_anchor, &fetcher, "www.signal.org", answers).await;
+        assert_eq!(validated, Ok(rrset));
+    }
+
+    #[tokio::test]
+    async fn rejects_modified_answer() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let mut answers = zone.signed(vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))]);
+        answers[0].rdata = ip_addr!(v4, "198.51.100.1").octets().to_vec();
+
+        let validated = validate(&trust_anchor, &fetcher, "chat.signal.org", answers).await;
+        assert_eq!(validated, Err(ValidationError::BadSignature));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsigned_answer() {
+        let (trust_anchor, fetcher, _zone) = signed_hierarchy();
+        let answers = vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))];
+
+        let validated = validate(&trust_anchor, &fetcher, "chat.signal.org", answers).await;
+        assert_eq!(validated, Err(ValidationError::MissingSignature));
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_signature() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let rrset = vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))];
+        let sig = zone.sign_with_validity(&rrset, NOW - 2 * HOUR, NOW - HOUR);
+        let answers = rrset.into_iter().chain([sig]).collect();
+
+        let validated = validate(&trust_anchor, &fetcher, "chat.signal.org", answers).await;
+        assert_eq!(validated, Err(ValidationError::SignatureExpired));
+    }
+
+    #[tokio::test]
+    async fn rejects_keys_not_vouched_for_by_parent() {
+        let (trust_anchor, mut fetcher, _zone) = signed_hierarchy();
+        // An attacker's zone keys, which the real DS records for signal.org don't match.
+        let impostor = TestZone::new("signal.org");
+        fetcher.insert(
+            "signal.org",
+            ResourceType::DNSKEY,
+            impostor.signed(vec![impostor.dnskey.clone()]),
+        );
+        let answers = impostor.signed(vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))]);
+
+        let validated = validate(&trust_anchor, &fetcher, "chat.signal.org", answers).await;
+        assert_eq!(validated, Err(ValidationError::UntrustedKeys));
+    }
+
+    #[tokio::test]
+    async fn rejects_signer_outside_the_name() {
+        let (trust_anchor, fetcher, _zone) = signed_hierarchy();
+        let other = TestZone::new("example.org");
+        let answers = other.signed(vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))]);
+
+        let validated = validate(&trust_anchor, &fetcher, "chat.signal.org", answers).await;
+        assert_eq!(validated, Err(ValidationError::SignerNotAncestor));
+    }
+
+    #[tokio::test]
+    async fn rejects_wildcard_expansion() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let mut answers = zone.signed(vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))]);
+        // Claim the signature was made over *.signal.org.
+        let [_, sig] = answers.as_mut_slice() else {
+            unreachable!("one record and its signature");
+        };
+        sig.rdata[3] = 2;
+
+        let validated = validate(&trust_anchor, &fetcher, "chat.signal.org", answers).await;
+        assert_eq!(validated, Err(ValidationError::WildcardExpansion));
+    }
+
+    #[test]
+    fn rejects_time_before_epoch() {
+        let (trust_anchor, fetcher, _zone) = signed_hierarchy();
+        assert!(matches!(
+            Validator::new(
+                &trust_anchor,
+                &fetcher,
+                &ValidatedKeys::default(),
+                UNIX_EPOCH - Duration::from_secs(1),
+            ),
+            Err(ValidationError::InvalidTime)
+        ));
+    }
+
+    #[tokio::test]
+    async fn reuses_validated_keys() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let validated_keys = ValidatedKeys::default();
+        let answers = zone.signed(vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))]);
+        let now = UNIX_EPOCH + Duration::from_secs(NOW.into());
+
+        Validator::new(&trust_anchor, &fetcher, &validated_keys, now)
+            .expect("valid time")
+            .validate_answer("chat.signal.org", ResourceType::A, answers.clone())
+            .await
+            .expect("valid");
+        assert_eq!(
+            validated_keys
+                .by_zone
+                .lock()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .sorted()
+                .collect_vec(),
+            ["", "org", "signal.org"]
+        );
+
+        // With the keys already validated, nothing else needs to be fetched.
+        Validator::new(&trust_anchor, &FakeFetcher::default(), &validated_keys, now)
+            .expect("valid time")
+            .validate_answer("chat.signal.org", ResourceType::A, answers)
+            .await
+            .expect("valid");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn validated_keys_expire_with_their_ttl() {
+        let (trust_anchor, fetcher, zone) = signed_hierarchy();
+        let validated_keys = ValidatedKeys::default();
+        let answers = zone.signed(vec![a_record("chat.signal.org", ip_addr!(v4, "192.0.2.1"))]);
+        let now = UNIX_EPOCH + Duration::from_secs(NOW.into());
+
+        Validator::new(&trust_anchor, &fetcher, &validated_keys, now)
+            .expect("valid time")
+            .validate_answer("chat.signal.org", ResourceType::A, answers.clone())
+            .await
+            .expect("valid");
+
+        tokio::time::advance(Duration::from_secs(TTL.into())).await;
+        // Once the records have expired, the chain has to be fetched again.
+        assert_eq!(
+            Validator::new(&trust_anchor, &FakeFetcher::default(), &validated_keys, now)
+                .expect("valid time")
+                .validate_answer("chat.signal.org", ResourceType::A, answers)
+                .await,
+            Err(ValidationError::FetchFailed)
+        );
+    }
+
+    #[test]
+    fn iana_root_key_tag() {
+        // The KSK-2017 root key, as published in the root zone.
+        let dnskey = parse_dnskey(&concat_rdata(
+            257,
+            ALGORITHM_RSASHA256,
+            &BASE64_STANDARD
+                .decode(
+                    "AwEAAaz/tAm8yTn4Mfeh5eyI96WSVexTBAvkMgJzkKTOiW1vkIbzxeF3\
+                    +/4RgWOq7HrxRixHlFlExOLAJr5emLvN7SWXgnLh4+B5xQlNVz8Og8kv\
+                    ArMtNROxVQuCaSnIDdD5LKyWbRd2n9WGe2R8PzgCmr3EgVLrjyBxWezF\
+                    0jLHwVN8efS3rCj/EWgvIWgb9tarpVUDK/b58Da+sqqls3eNbuv7pr+e\
+                    oZG+SrDK6nWeL3c6H5Apxz7LjVc1uTIdsIXxuOLYA4/ilBmSVIzuDWfd\
+                    RUfhHdY6+cn8HFRm+2hM8AnXGXws9555KrUB5qihylGa8subX2Nn6UwN\
+                    R1AkUTV74bU=",
+                )
+                .unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(dnskey.key_tag, 20326);
+        assert!(
+            TrustAnchor::iana_root()
+                .root_ds
+                .iter()
+                .any(|ds| ds_matches_key(ds, "", &dnskey))
+        );
+    }
+
+    fn concat_rdata(flags: u16, algorithm: u8, public_key: &[u8]) -> Vec<u8> {
+        let mut rdata = flags.to_be_bytes().to_vec();
+        rdata.extend([DNSKEY_PROTOCOL, algorithm]);
+        rdata.extend_from_slice(public_key);
+        rdata
+    }
+
+    #[test]
+    fn ancestry() {
+        assert!(is_ancestor_or_self("", "signal.org"));
+        assert!(is_ancestor_or_self("org", "signal.org"));
+        assert!(is_ancestor_or_self("signal.org", "signal.org"));
+        assert!(!is_ancestor_or_self("al.org", "signal.org"));
+        assert!(!is_ancestor_or_self("chat.signal.org", "signal.org"));
+    }
+}
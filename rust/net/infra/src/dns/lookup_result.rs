@@ -5,13 +5,45 @@
 
 use std::iter::Map;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::num::NonZeroU16;
 use std::slice::Iter;
 use std::vec::IntoIter;
 
+use crate::route::EchConfigList;
+
 #[derive(Debug, Clone)]
 pub struct LookupResult {
     pub(crate) ipv4: Vec<Ipv4Addr>,
     pub(crate) ipv6: Vec<Ipv6Addr>,
+    /// Parsed HTTPS records, if they were requested and the name has any.
+    ///
+    /// These don't contribute to iteration; they only describe how to connect to the addresses.
+    pub(crate) service_bindings: Vec<ServiceBinding>,
+}
+
+/// A single HTTPS or SVCB record.
+///
+/// Only the parameters we have a use for are kept; any others are skipped when parsing.
+///
+/// [RFC 9460](https://datatracker.ietf.org/doc/html/rfc9460#section-2.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceBinding {
+    /// The priority of this binding relative to others for the same name; lower is preferred.
+    ///
+    /// Priority 0 marks an alias record, which only has a target name.
+    pub priority: u16,
+    /// The name to connect to, or `None` if it's the same as the name that was queried.
+    pub target_name: Option<String>,
+    /// ALPN protocol identifiers supported by the target.
+    pub alpn: Vec<Vec<u8>>,
+    /// If set, the target doesn't support the protocol's default ALPN (`http/1.1` for HTTPS).
+    pub no_default_alpn: bool,
+    /// The port to connect to, if not the default for the protocol.
+    pub port: Option<NonZeroU16>,
+    pub ipv4_hint: Vec<Ipv4Addr>,
+    pub ipv6_hint: Vec<Ipv6Addr>,
+    /// ECH configs to use when connecting to the target.
+    pub ech_config_list: Option<EchConfigList>,
 }
 
 impl IntoIterator for LookupResult {
@@ -52,7 +84,34 @@ impl<'a> IntoIterator for &'a LookupResult {
 
 impl LookupResult {
     pub fn new(ipv4: Vec<Ipv4Addr>, ipv6: Vec<Ipv6Addr>) -> Self {
-        Self { ipv4, ipv6 }
+        Self {
+            ipv4,
+            ipv6,
+            service_bindings: vec![],
+        }
+    }
+
+    pub fn with_service_bindings(self, service_bindings: Vec<ServiceBinding>) -> Self {
+        Self {
+            service_bindings,
+            ..self
+        }
+    }
+
+    /// HTTPS records for the name, sorted by priority.
+    pub fn service_bindings(&self) -> &[ServiceBinding] {
+        &self.service_bindings
+    }
+
+    /// The ECH configs from the most preferred HTTPS record that has any.
+    ///
+    /// Records that point to a different target are skipped, since their configs are for a
+    /// different server.
+    pub fn ech_config_list(&self) -> Option<&EchConfigList> {
+        self.service_bindings
+            .iter()
+            .filter(|binding| !binding.is_alias() && binding.target_name.is_none())
+            .find_map(|binding| binding.ech_config_list.as_ref())
     }
 
     pub fn iter(&self) -> <&Self as IntoIterator>::IntoIter {
@@ -64,6 +123,13 @@ impl LookupResult {
     }
 }
 
+impl ServiceBinding {
+    /// Whether this is an alias record, which only redirects to another name.
+    pub fn is_alias(&self) -> bool {
+        self.priority == 0
+    }
+}
+
 #[cfg(any(test, feature = "test-util"))]
 impl LookupResult {
     pub fn localhost() -> Self {
@@ -9,6 +9,7 @@ use std::time::Duration;
 use http::{HeaderName, HeaderValue};
 use tokio_boring_signal::HandshakeError;
 
+use crate::route::EchConfigList;
 use crate::{AsStaticHttpHeader, certs};
 
 pub trait LogSafeDisplay: Display {}
@@ -36,6 +37,11 @@ pub enum TransportConnectError {
     CertError,
     /// Failed to establish SSL connection: {0}
     SslFailedHandshake(FailedHandshakeReason),
+    /// Server rejected Encrypted Client Hello
+    EchRejected {
+        /// The configs the server will accept instead, or `None` if it has disabled ECH.
+        retry_configs: Option<EchConfigList>,
+    },
     /// Proxy handshake failed
     ProxyProtocol,
     /// Abort due to local error
@@ -173,6 +179,7 @@ impl From<TransportConnectError> for std::io::Error {
             TransportConnectError::InvalidConfiguration => ErrorKind::InvalidInput,
             TransportConnectError::TcpConnectionFailed => ErrorKind::ConnectionRefused,
             TransportConnectError::SslFailedHandshake(_)
+            | TransportConnectError::EchRejected { .. }
             | TransportConnectError::SslError(_)
             | TransportConnectError::CertError
             | TransportConnectError::ProxyProtocol => ErrorKind::InvalidData,
@@ -460,6 +460,7 @@ mod test {
                         )),
                         alpn: Some(crate::Alpn::Http2),
                         min_protocol_version: None,
+                        ech_config_list: None,
                     },
                     inner: TcpRoute {
                         address: Ipv6Addr::LOCALHOST.into(),
@@ -539,6 +540,7 @@ mod test {
                         )),
                         alpn: None,
                         min_protocol_version: None,
+                        ech_config_list: None,
                     },
                     inner: TcpRoute {
                         address: Ipv6Addr::LOCALHOST.into(),
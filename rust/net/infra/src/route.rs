@@ -98,12 +98,18 @@ pub use connect::*;
 mod describe;
 pub use describe::*;
 
+mod fingerprint;
+pub use fingerprint::*;
+
 mod http;
 pub use http::*;
 
 pub mod provider;
 pub use crate::route::provider::RouteProviderExt;
 
+mod pluggable;
+pub use pluggable::*;
+
 mod proxy;
 pub use proxy::*;
 
@@ -709,7 +715,7 @@ mod test {
     use std::future::Future;
     use std::net::{IpAddr, Ipv6Addr};
     use std::num::NonZeroU16;
-    use std::sync::LazyLock;
+    use std::sync::{Arc, LazyLock};
 
     use ::http::HeaderMap;
     use ::http::uri::PathAndQuery;
@@ -725,10 +731,12 @@ mod test {
     use super::*;
     use crate::certs::RootCertificates;
     use crate::dns::lookup_result::LookupResult;
+    use crate::errors::TransportConnectError;
     use crate::host::Host;
     use crate::route::resolve::testutils::FakeResolver;
     use crate::route::testutils::{FakeConnectError, FakeContext, FakeRoute};
     use crate::route::{SocksProxy, TlsProxy};
+    use crate::tcp_ssl::proxy::pluggable::PluggableStream;
     use crate::tcp_ssl::proxy::socks;
     use crate::{Alpn, OverrideNagleAlgorithm};
 
@@ -766,6 +774,7 @@ mod test {
                     sni: Host::Domain("sni-name".into()),
                     certs: ROOT_CERTS.clone(),
                     min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_3),
+                    ech_config_list: None,
                     inner: DirectTcpRouteProvider {
                         dns_hostname: "target-host".into(),
                         port: TARGET_PORT,
@@ -797,6 +806,7 @@ mod test {
                             sni: Host::Domain("sni-name".into()),
                             alpn: Some(Alpn::Http1_1),
                             min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_3),
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("target-host".into()),
@@ -825,6 +835,7 @@ mod test {
                             sni: Host::Domain("front-sni1".into()),
                             alpn: Some(Alpn::Http2),
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni1".into()),
@@ -853,6 +864,7 @@ mod test {
                             sni: Host::Domain("front-sni2".into()),
                             alpn: Some(Alpn::Http2),
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni2".into()),
@@ -877,6 +889,7 @@ mod test {
             sni: Host::Domain("direct-sni".into()),
             certs: ROOT_CERTS.clone(),
             min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+            ech_config_list: None,
             inner: DirectTcpRouteProvider {
                 dns_hostname: "direct-target".into(),
                 port: TARGET_PORT,
@@ -906,6 +919,7 @@ mod test {
                     sni: Host::Domain("direct-sni".into()),
                     alpn: None,
                     min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+                    ech_config_list: None,
                 },
                 inner: DirectOrProxyRoute::Proxy(ConnectionProxyRoute::Tls {
                     proxy: TlsRoute {
@@ -919,6 +933,7 @@ mod test {
                             sni: Host::Domain("tls-proxy".into()),
                             alpn: None,
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                     },
                 }),
@@ -926,6 +941,145 @@ mod test {
         );
     }
 
+    #[test]
+    fn pluggable_transport_proxy_route() {
+        const TARGET_PORT: NonZeroU16 = nonzero!(7898u16);
+        const PROXY_PORT: NonZeroU16 = nonzero!(13u16);
+
+        #[derive(Debug)]
+        struct FakeTransport(&'static [u8]);
+
+        #[async_trait::async_trait]
+        impl PluggableTransport for FakeTransport {
+            fn name(&self) -> &'static str {
+                "fake"
+            }
+            fn configuration(&self) -> &[u8] {
+                self.0
+            }
+            async fn wrap(
+                &self,
+                _stream: PluggableStream,
+                _log_tag: &str,
+            ) -> Result<PluggableStream, TransportConnectError> {
+                unreachable!("not connecting")
+            }
+        }
+
+        let direct_provider = TlsRouteProvider {
+            sni: Host::Domain("direct-sni".into()),
+            certs: ROOT_CERTS.clone(),
+            min_protocol_version: None,
+            ech_config_list: None,
+            inner: DirectTcpRouteProvider {
+                dns_hostname: "direct-target".into(),
+                port: TARGET_PORT,
+                override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+            },
+        };
+
+        let provider = DirectOrProxyProvider {
+            mode: DirectOrProxyMode::ProxyOnly(
+                PluggableTransportProxy {
+                    proxy_host: Host::Domain("relay".into()),
+                    proxy_port: PROXY_PORT,
+                    transport: Arc::new(FakeTransport(b"key")),
+                }
+                .into(),
+            ),
+            inner: direct_provider,
+        };
+
+        let routes = provider.routes(&FakeContext::new()).collect_vec();
+
+        // Transports are compared by configuration, not identity.
+        assert_eq!(
+            routes,
+            vec![TlsRoute {
+                fragment: TlsRouteFragment {
+                    root_certs: ROOT_CERTS.clone(),
+                    sni: Host::Domain("direct-sni".into()),
+                    alpn: None,
+                    min_protocol_version: None,
+                    ech_config_list: None,
+                },
+                inner: DirectOrProxyRoute::Proxy(ConnectionProxyRoute::Pluggable(
+                    PluggableTransportRoute {
+                        fragment: PluggableTransportFragment(Arc::new(FakeTransport(b"key"))),
+                        inner: TcpRoute {
+                            address: Host::Domain(UnresolvedHost("relay".into())),
+                            port: PROXY_PORT,
+                            override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+                        },
+                    }
+                )),
+            }]
+        );
+        assert_ne!(
+            PluggableTransportFragment(Arc::new(FakeTransport(b"key"))),
+            PluggableTransportFragment(Arc::new(FakeTransport(b"other key")))
+        );
+    }
+
+    #[test]
+    fn domain_fronted_pluggable_transport_route() {
+        const TARGET_PORT: NonZeroU16 = nonzero!(7898u16);
+        const PROXY_PORT: NonZeroU16 = nonzero!(443u16);
+
+        let provider = DirectOrProxyProvider {
+            mode: DirectOrProxyMode::ProxyOnly(
+                PluggableTransportProxy {
+                    proxy_host: Host::Domain("cdn-edge".into()),
+                    proxy_port: PROXY_PORT,
+                    transport: Arc::new(DomainFrontTransport::new(DomainFront {
+                        sni: "front-sni".into(),
+                        http_host: Some("front-host".into()),
+                        root_certs: Some(PROXY_ROOT_CERTS),
+                    })),
+                }
+                .into(),
+            ),
+            inner: HttpsProvider {
+                direct_host_header: "http-host".into(),
+                direct_http_version: HttpVersion::Http1_1,
+                domain_front: DomainFrontRouteProvider {
+                    fronts: vec![],
+                    http_version: HttpVersion::Http1_1,
+                    override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+                },
+                inner: TlsRouteProvider {
+                    sni: Host::Domain("direct-sni".into()),
+                    certs: ROOT_CERTS.clone(),
+                    min_protocol_version: None,
+                    ech_config_list: None,
+                    inner: DirectTcpRouteProvider {
+                        dns_hostname: "direct-target".into(),
+                        port: TARGET_PORT,
+                        override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+                    },
+                },
+            },
+        };
+
+        let routes = provider.routes(&FakeContext::new()).collect_vec();
+
+        let [route] = <[_; 1]>::try_from(routes).expect("one route");
+        assert_eq!(&*route.fragment.host_header, "front-host");
+        assert_eq!(route.inner.fragment.sni, Host::Domain("front-sni".into()));
+        assert_eq!(route.inner.fragment.root_certs, PROXY_ROOT_CERTS);
+        assert_matches!(
+            route.inner.inner,
+            DirectOrProxyRoute::Proxy(ConnectionProxyRoute::Pluggable(PluggableTransportRoute {
+                fragment: _,
+                inner: TcpRoute {
+                    address: Host::Domain(UnresolvedHost(host)),
+                    port,
+                    override_nagle_algorithm: _,
+                },
+            })) if &*host == "cdn-edge" && port == PROXY_PORT
+        );
+    }
+
     #[test]
     fn socks_proxy_route() {
         const TARGET_PORT: NonZeroU16 = nonzero!(7898u16);
@@ -938,6 +1092,7 @@ mod test {
             sni: Host::Domain("direct-sni".into()),
             certs: ROOT_CERTS.clone(),
             min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+            ech_config_list: None,
             inner: DirectTcpRouteProvider {
                 dns_hostname: "direct-target".into(),
                 port: TARGET_PORT,
@@ -967,6 +1122,7 @@ mod test {
                     sni: Host::Domain("direct-sni".into()),
                     alpn: None,
                     min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+                    ech_config_list: None,
                 },
                 inner: DirectOrProxyRoute::Proxy(ConnectionProxyRoute::Socks(SocksRoute {
                     proxy: TcpRoute {
@@ -987,6 +1143,7 @@ mod test {
                     sni: Host::Domain("direct-sni".into()),
                     alpn: None,
                     min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+                    ech_config_list: None,
                 },
                 inner: DirectOrProxyRoute::Direct(TcpRoute {
                     address: UnresolvedHost("direct-target".into()),
@@ -1295,15 +1452,11 @@ mod test {
         let (connector, connection_responders) = FakeConnector::new();
 
         let outcomes = NoDelay;
-        let resolver = HashMap::from_iter(HOSTNAMES.iter().map(|(name, ip)| {
-            (
-                *name,
-                LookupResult {
-                    ipv4: vec![],
-                    ipv6: vec![*ip],
-                },
-            )
-        }));
+        let resolver = HashMap::from_iter(
+            HOSTNAMES
+                .iter()
+                .map(|(name, ip)| (*name, LookupResult::new(vec![], vec![*ip]))),
+        );
 
         let connect_task = tokio::spawn(async move {
             let route_resolver = RouteResolver::default();
@@ -1348,15 +1501,11 @@ mod test {
         let (connector, mut connection_responders) = FakeConnector::new();
 
         let outcomes = NoDelay;
-        let resolver = HashMap::from_iter(HOSTNAMES.iter().map(|(name, ip)| {
-            (
-                *name,
-                LookupResult {
-                    ipv4: vec![],
-                    ipv6: vec![*ip],
-                },
-            )
-        }));
+        let resolver = HashMap::from_iter(
+            HOSTNAMES
+                .iter()
+                .map(|(name, ip)| (*name, LookupResult::new(vec![], vec![*ip]))),
+        );
 
         let start = Instant::now();
         let connect_task = tokio::spawn(async move {
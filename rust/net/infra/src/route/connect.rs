@@ -10,9 +10,9 @@ use static_assertions::assert_impl_all;
 
 use crate::errors::TransportConnectError;
 use crate::route::{
-    ConnectionProxyRoute, DirectOrProxyRoute, HttpRouteFragment, HttpsTlsRoute, TcpRoute, TlsRoute,
-    TlsRouteFragment, TransportRoute, WebSocketRoute, WebSocketRouteFragment,
-    WebSocketServiceRoute,
+    ConnectionProxyRoute, DirectOrProxyRoute, HttpRouteFragment, HttpsTlsRoute,
+    PluggableTransportFragment, PluggableTransportRoute, TcpRoute, TlsRoute, TlsRouteFragment,
+    TransportRoute, WebSocketRoute, WebSocketRouteFragment, WebSocketServiceRoute,
 };
 
 mod composed;
@@ -21,6 +21,9 @@ pub use composed::*;
 mod direct_or_proxy;
 pub use direct_or_proxy::*;
 
+mod ech_retry;
+pub use ech_retry::*;
+
 mod interface_monitor;
 pub use interface_monitor::*;
 
@@ -193,6 +196,31 @@ where
     }
 }
 
+/// Establishes a pluggable transport connection over a transport stream.
+impl<A, B, Inner, T> Connector<PluggableTransportRoute<T>, Inner> for ComposedConnector<A, B>
+where
+    A: Connector<PluggableTransportFragment, B::Connection> + Sync,
+    B: Connector<T, Inner, Error: Into<A::Error>> + Sync,
+    Inner: Send,
+    T: Send,
+{
+    type Connection = A::Connection;
+    type Error = A::Error;
+
+    fn connect_over(
+        &self,
+        over: Inner,
+        route: PluggableTransportRoute<T>,
+        log_tag: &str,
+    ) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send {
+        let PluggableTransportRoute {
+            fragment: transport_fragment,
+            inner: tcp_route,
+        } = route;
+        self.connect_inner_then_outer(over, tcp_route, transport_fragment, log_tag)
+    }
+}
+
 /// Same as above but with a variable timeout
 impl<A, B, Inner, T, Error> Connector<TlsRoute<T>, Inner>
     for VariableTlsTimeoutConnector<A, B, Error>
@@ -0,0 +1,299 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::errors::TransportConnectError;
+use crate::host::Host;
+use crate::route::{Connector, EchConfigList, TlsRoute};
+
+/// A [`Connector`] for [`TlsRoute`]s that retries once if the server rejects
+/// Encrypted Client Hello.
+///
+/// When a server can't decrypt the client hello, it completes the handshake
+/// using the public name from the ECH config and sends back the configs it
+/// does accept. Those "retry configs" are only usable on a fresh connection, so
+/// unlike the TLS connector itself, this needs to wrap the entire TCP+TLS
+/// connection process. If the server didn't provide any retry configs, it has
+/// disabled ECH, and the retry is made without it.
+///
+/// The outcome is remembered in an [`EchRetryConfigs`], so later connections
+/// offering the same rejected configs go straight to the replacement.
+#[derive(Debug, Default)]
+pub struct EchRetryConnector<C> {
+    inner: C,
+    retry_configs: Arc<EchRetryConfigs>,
+}
+
+/// Replacements for ECH configs that servers have rejected, keyed by SNI.
+///
+/// Published configs can lag behind a server's key rotation for as long as
+/// they're cached in DNS. Sharing this between connectors avoids paying for a
+/// rejected handshake on every connection in the meantime.
+#[derive(Debug, Default)]
+pub struct EchRetryConfigs {
+    by_host: Mutex<HashMap<Arc<str>, RetryEntry>>,
+}
+
+#[derive(Debug)]
+struct RetryEntry {
+    rejected: EchConfigList,
+    replacement: Option<EchConfigList>,
+}
+
+impl<C> EchRetryConnector<C> {
+    pub fn new(inner: C) -> Self {
+        Self::with_retry_configs(inner, Default::default())
+    }
+
+    pub fn with_retry_configs(inner: C, retry_configs: Arc<EchRetryConfigs>) -> Self {
+        Self {
+            inner,
+            retry_configs,
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl EchRetryConfigs {
+    /// Returns the configs to offer to `host` in place of `offered`.
+    ///
+    /// If `offered` was rejected before, this is whatever the server sent back
+    /// then (possibly nothing); otherwise `offered` is returned unchanged.
+    fn replacement_for(&self, host: &str, offered: Option<EchConfigList>) -> Option<EchConfigList> {
+        let offered = offered?;
+        match self.by_host.lock().expect("not poisoned").get(host) {
+            Some(RetryEntry {
+                rejected,
+                replacement,
+            }) if *rejected == offered => replacement.clone(),
+            Some(_) | None => Some(offered),
+        }
+    }
+
+    fn record_rejection(
+        &self,
+        host: Arc<str>,
+        rejected: EchConfigList,
+        replacement: Option<EchConfigList>,
+    ) {
+        self.by_host.lock().expect("not poisoned").insert(
+            host,
+            RetryEntry {
+                rejected,
+                replacement,
+            },
+        );
+    }
+}
+
+impl<C, T> Connector<TlsRoute<T>, ()> for EchRetryConnector<C>
+where
+    C: Connector<TlsRoute<T>, (), Error = TransportConnectError> + Sync,
+    T: Clone + Send,
+{
+    type Connection = C::Connection;
+
+    type Error = TransportConnectError;
+
+    async fn connect_over(
+        &self,
+        (): (),
+        mut route: TlsRoute<T>,
+        log_tag: &str,
+    ) -> Result<Self::Connection, Self::Error> {
+        // The configs as they came from the route, which is what a previous
+        // rejection would have been recorded under.
+        let original_configs = route.fragment.ech_config_list.clone();
+        let host = match &route.fragment.sni {
+            Host::Domain(host) => Some(Arc::clone(host)),
+            Host::Ip(_) => None,
+        };
+        if let Some(host) = &host {
+            route.fragment.ech_config_list = self
+                .retry_configs
+                .replacement_for(host, route.fragment.ech_config_list.take());
+        }
+
+        // Only keep a copy around if a retry is possible.
+        let retry_route = route
+            .fragment
+            .ech_config_list
+            .is_some()
+            .then(|| route.clone());
+
+        let error = match self.inner.connect_over((), route, log_tag).await {
+            Err(error @ TransportConnectError::EchRejected { .. }) => error,
+            result => return result,
+        };
+        let (Some(mut retry_route), TransportConnectError::EchRejected { retry_configs }) =
+            (retry_route, &error)
+        else {
+            // The inner connector reported a rejection for a route without ECH. That shouldn't
+            // happen, but there's nothing to retry with.
+            return Err(error);
+        };
+
+        log::info!(
+            "[{log_tag}] server rejected ECH; retrying {}",
+            if retry_configs.is_some() {
+                "with updated configs"
+            } else {
+                "without ECH"
+            }
+        );
+        if let (Some(host), Some(original_configs)) = (host, original_configs) {
+            self.retry_configs
+                .record_rejection(host, original_configs, retry_configs.clone());
+        }
+        retry_route.fragment.ech_config_list = retry_configs.clone();
+        self.inner.connect_over((), retry_route, log_tag).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::certs::RootCertificates;
+    use crate::route::TlsRouteFragment;
+    use crate::route::testutils::ConnectFn;
+
+    fn route(ech_config_list: Option<&[u8]>) -> TlsRoute<()> {
+        TlsRoute {
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::Native,
+                sni: Host::Domain("chat.example".into()),
+                alpn: None,
+                min_protocol_version: None,
+                ech_config_list: ech_config_list.map(EchConfigList::from),
+            },
+            inner: (),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_with_updated_configs() {
+        let attempts = Mutex::new(vec![]);
+        let connector = EchRetryConnector::new(ConnectFn(|(), route: TlsRoute<()>| {
+            let mut attempts = attempts.lock().expect("not poisoned");
+            attempts.push(route.fragment.ech_config_list.clone());
+            let result = if attempts.len() == 1 {
+                Err(TransportConnectError::EchRejected {
+                    retry_configs: Some(b"new".as_slice().into()),
+                })
+            } else {
+                Ok(())
+            };
+            std::future::ready(result)
+        }));
+
+        connector
+            .connect_over((), route(Some(b"old")), "test")
+            .await
+            .expect("success on retry");
+        assert_eq!(
+            *attempts.lock().expect("not poisoned"),
+            [
+                Some(b"old".as_slice().into()),
+                Some(b"new".as_slice().into())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_without_ech_only_once() {
+        let attempts = Mutex::new(vec![]);
+        let connector = EchRetryConnector::new(ConnectFn(|(), route: TlsRoute<()>| {
+            attempts
+                .lock()
+                .expect("not poisoned")
+                .push(route.fragment.ech_config_list.clone());
+            std::future::ready(Err::<(), _>(TransportConnectError::EchRejected {
+                retry_configs: None,
+            }))
+        }));
+
+        assert_matches!(
+            connector
+                .connect_over((), route(Some(b"old")), "test")
+                .await,
+            Err(TransportConnectError::EchRejected {
+                retry_configs: None
+            })
+        );
+        assert_eq!(
+            *attempts.lock().expect("not poisoned"),
+            [Some(b"old".as_slice().into()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let attempts = Mutex::new(0);
+        let connector = EchRetryConnector::new(ConnectFn(|(), _route: TlsRoute<()>| {
+            *attempts.lock().expect("not poisoned") += 1;
+            std::future::ready(Err::<(), _>(TransportConnectError::TcpConnectionFailed))
+        }));
+
+        assert_matches!(
+            connector
+                .connect_over((), route(Some(b"old")), "test")
+                .await,
+            Err(TransportConnectError::TcpConnectionFailed)
+        );
+        assert_eq!(*attempts.lock().expect("not poisoned"), 1);
+    }
+
+    #[tokio::test]
+    async fn remembers_retry_configs_across_connectors() {
+        let retry_configs = Arc::new(EchRetryConfigs::default());
+        let attempts = Mutex::new(vec![]);
+        let connect = ConnectFn(|(), route: TlsRoute<()>| {
+            let offered = route.fragment.ech_config_list.clone();
+            attempts.lock().expect("not poisoned").push(offered.clone());
+            let result = if offered == Some(b"old".as_slice().into()) {
+                Err(TransportConnectError::EchRejected {
+                    retry_configs: Some(b"new".as_slice().into()),
+                })
+            } else {
+                Ok(())
+            };
+            std::future::ready(result)
+        });
+
+        for _ in 0..2 {
+            EchRetryConnector::with_retry_configs(&connect, Arc::clone(&retry_configs))
+                .connect_over((), route(Some(b"old")), "test")
+                .await
+                .expect("success");
+        }
+        // Only the first connection had to be retried.
+        assert_eq!(
+            *attempts.lock().expect("not poisoned"),
+            [
+                Some(b"old".as_slice().into()),
+                Some(b"new".as_slice().into()),
+                Some(b"new".as_slice().into()),
+            ]
+        );
+
+        // Configs other than the rejected ones are offered as-is.
+        attempts.lock().expect("not poisoned").clear();
+        EchRetryConnector::with_retry_configs(&connect, Arc::clone(&retry_configs))
+            .connect_over((), route(Some(b"rotated")), "test")
+            .await
+            .expect("success");
+        assert_eq!(
+            *attempts.lock().expect("not poisoned"),
+            [Some(b"rotated".as_slice().into())]
+        );
+    }
+}
@@ -12,6 +12,7 @@ use futures_util::TryFutureExt as _;
 use nonzero_ext::nonzero;
 
 use crate::dns::dns_utils::log_safe_domain;
+use crate::dns::lookup_result::LookupResult;
 use crate::errors::LogSafeDisplay;
 use crate::host::Host;
 use crate::route::{
@@ -101,6 +102,10 @@ impl<R: ResolveHostnames + DescribeForLog> ResolveHostnames for ResolveWithSaved
             description,
         }
     }
+
+    fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+        self.0.apply_service_bindings(lookups)
+    }
 }
 
 impl<R: ResolvedRoute, D> ResolvedRoute for WithLoggableDescription<R, D> {
@@ -186,7 +191,7 @@ impl<Transport: UsesTransport<UnresolvedTransportRoute>> DescribeForLog
                 (Host::Domain(address.clone().into()), *port)
             }
             DirectOrProxyRoute::Proxy(proxy) => match proxy {
-                ConnectionProxyRoute::Tls { proxy: _ } => {
+                ConnectionProxyRoute::Tls { proxy: _ } | ConnectionProxyRoute::Pluggable(_) => {
                     // The host is implicit; the proxy will look for the TLS SNI and resolve that.
                     (tls_fragment.sni.clone(), DEFAULT_HTTPS_PORT)
                 }
@@ -0,0 +1,376 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::net::IpAddr;
+use std::num::NonZeroU16;
+use std::sync::Arc;
+
+use either::Either;
+
+use crate::certs::RootCertificates;
+use crate::host::Host;
+use crate::route::{
+    ConnectionProxyRoute, DirectOrProxyRoute, HttpProxyAuth, HttpProxyRouteFragment,
+    PluggableTransportFragment, ProxyTarget, SimpleRoute, SocksRoute, TcpRoute, TlsRouteFragment,
+    UdpRoute,
+};
+use crate::tcp_ssl::proxy::socks;
+use crate::{Alpn, OverrideNagleAlgorithm};
+
+/// A route that can be written out in a fixed byte format, so that it can be recognized across
+/// process restarts.
+///
+/// Every enum variant and optional value is tagged, and every variable-length value is
+/// length-prefixed, so that different routes can't produce the same bytes. Changing what any
+/// implementation writes makes previously saved fingerprints stop matching; bump
+/// [`RouteFingerprint::ENCODING_VERSION`] when doing so.
+pub trait EncodeForFingerprint {
+    /// Appends the encoding of `self` to `out`.
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>);
+}
+
+/// Identifies a route across process restarts.
+///
+/// This is a truncated SHA-256 digest of the route's [`EncodeForFingerprint`] bytes, prefixed
+/// with [`Self::ENCODING_VERSION`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct RouteFingerprint(pub(crate) u64);
+
+impl RouteFingerprint {
+    pub(crate) const ENCODING_VERSION: u8 = 1;
+
+    pub(crate) fn of<R: EncodeForFingerprint + ?Sized>(route: &R) -> Self {
+        let mut bytes = vec![Self::ENCODING_VERSION];
+        route.encode_for_fingerprint(&mut bytes);
+        let digest = boring_signal::sha::sha256(&bytes);
+        Self(u64::from_be_bytes(
+            digest[..8].try_into().expect("correct length"),
+        ))
+    }
+}
+
+fn encode_tag(out: &mut Vec<u8>, tag: u8) {
+    out.push(tag);
+}
+
+fn encode_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn encode_port(out: &mut Vec<u8>, port: NonZeroU16) {
+    out.extend_from_slice(&port.get().to_be_bytes());
+}
+
+impl<T: EncodeForFingerprint + ?Sized> EncodeForFingerprint for &T {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        (**self).encode_for_fingerprint(out)
+    }
+}
+
+impl<T: EncodeForFingerprint + ?Sized> EncodeForFingerprint for Arc<T> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        (**self).encode_for_fingerprint(out)
+    }
+}
+
+impl EncodeForFingerprint for str {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        encode_bytes(out, self.as_bytes())
+    }
+}
+
+impl<T: EncodeForFingerprint> EncodeForFingerprint for Option<T> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            None => encode_tag(out, 0),
+            Some(value) => {
+                encode_tag(out, 1);
+                value.encode_for_fingerprint(out)
+            }
+        }
+    }
+}
+
+impl<L: EncodeForFingerprint, R: EncodeForFingerprint> EncodeForFingerprint for Either<L, R> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            Either::Left(left) => {
+                encode_tag(out, 0);
+                left.encode_for_fingerprint(out)
+            }
+            Either::Right(right) => {
+                encode_tag(out, 1);
+                right.encode_for_fingerprint(out)
+            }
+        }
+    }
+}
+
+impl EncodeForFingerprint for IpAddr {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            IpAddr::V4(ip) => {
+                encode_tag(out, 4);
+                out.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                encode_tag(out, 6);
+                out.extend_from_slice(&ip.octets());
+            }
+        }
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for Host<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            Host::Ip(ip) => {
+                encode_tag(out, 0);
+                ip.encode_for_fingerprint(out)
+            }
+            Host::Domain(domain) => {
+                encode_tag(out, 1);
+                domain.encode_for_fingerprint(out)
+            }
+        }
+    }
+}
+
+impl<F: EncodeForFingerprint, I: EncodeForFingerprint> EncodeForFingerprint for SimpleRoute<F, I> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self { fragment, inner } = self;
+        fragment.encode_for_fingerprint(out);
+        inner.encode_for_fingerprint(out);
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for TcpRoute<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self {
+            address,
+            port,
+            override_nagle_algorithm,
+        } = self;
+        address.encode_for_fingerprint(out);
+        encode_port(out, *port);
+        encode_tag(
+            out,
+            match override_nagle_algorithm {
+                OverrideNagleAlgorithm::UseSystemDefault => 0,
+                OverrideNagleAlgorithm::OverrideToOff => 1,
+            },
+        );
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for UdpRoute<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self { address, port } = self;
+        address.encode_for_fingerprint(out);
+        encode_port(out, *port);
+    }
+}
+
+impl EncodeForFingerprint for TlsRouteFragment {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self {
+            root_certs,
+            sni,
+            alpn,
+            // SslVersion is opaque, and the ECH configs come from DNS and change independently of
+            // the route. Neither affects where the connection goes.
+            min_protocol_version: _,
+            ech_config_list: _,
+        } = self;
+        match root_certs {
+            RootCertificates::Native => encode_tag(out, 0),
+            RootCertificates::FromStaticDers(ders) => {
+                encode_tag(out, 1);
+                out.extend_from_slice(&(ders.len() as u64).to_be_bytes());
+                for der in *ders {
+                    encode_bytes(out, der);
+                }
+            }
+            RootCertificates::FromDer(der) => {
+                encode_tag(out, 2);
+                encode_bytes(out, der);
+            }
+        }
+        sni.encode_for_fingerprint(out);
+        alpn.encode_for_fingerprint(out);
+    }
+}
+
+impl EncodeForFingerprint for Alpn {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        encode_bytes(out, self.encoded())
+    }
+}
+
+impl<D: EncodeForFingerprint, P: EncodeForFingerprint> EncodeForFingerprint
+    for DirectOrProxyRoute<D, P>
+{
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            DirectOrProxyRoute::Direct(direct) => {
+                encode_tag(out, 0);
+                direct.encode_for_fingerprint(out)
+            }
+            DirectOrProxyRoute::Proxy(proxy) => {
+                encode_tag(out, 1);
+                proxy.encode_for_fingerprint(out)
+            }
+        }
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for ConnectionProxyRoute<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            ConnectionProxyRoute::Tls { proxy } => {
+                encode_tag(out, 0);
+                proxy.encode_for_fingerprint(out)
+            }
+            #[cfg(feature = "dev-util")]
+            ConnectionProxyRoute::Tcp { proxy } => {
+                encode_tag(out, 1);
+                proxy.encode_for_fingerprint(out)
+            }
+            ConnectionProxyRoute::Socks(socks) => {
+                encode_tag(out, 2);
+                socks.encode_for_fingerprint(out)
+            }
+            ConnectionProxyRoute::Https(https) => {
+                encode_tag(out, 3);
+                https.encode_for_fingerprint(out)
+            }
+            ConnectionProxyRoute::Pluggable(pluggable) => {
+                encode_tag(out, 4);
+                pluggable.encode_for_fingerprint(out)
+            }
+        }
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for ProxyTarget<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        match self {
+            ProxyTarget::ResolvedLocally(addr) => {
+                encode_tag(out, 0);
+                addr.encode_for_fingerprint(out)
+            }
+            ProxyTarget::ResolvedRemotely { name } => {
+                encode_tag(out, 1);
+                name.encode_for_fingerprint(out)
+            }
+        }
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for SocksRoute<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self {
+            proxy,
+            target_addr,
+            target_port,
+            protocol,
+        } = self;
+        proxy.encode_for_fingerprint(out);
+        target_addr.encode_for_fingerprint(out);
+        encode_port(out, *target_port);
+        match protocol {
+            socks::Protocol::Socks4 { user_id } => {
+                encode_tag(out, 4);
+                user_id.as_deref().encode_for_fingerprint(out);
+            }
+            socks::Protocol::Socks5 { username_password } => {
+                encode_tag(out, 5);
+                match username_password {
+                    None => encode_tag(out, 0),
+                    Some((username, password)) => {
+                        encode_tag(out, 1);
+                        encode_bytes(out, username.as_bytes());
+                        encode_bytes(out, password.as_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<A: EncodeForFingerprint> EncodeForFingerprint for HttpProxyRouteFragment<A> {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self {
+            target_host,
+            target_port,
+            authorization,
+        } = self;
+        target_host.encode_for_fingerprint(out);
+        encode_port(out, *target_port);
+        authorization.encode_for_fingerprint(out);
+    }
+}
+
+impl EncodeForFingerprint for HttpProxyAuth {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self { username, password } = self;
+        encode_bytes(out, username.as_bytes());
+        encode_bytes(out, password.as_bytes());
+    }
+}
+
+impl EncodeForFingerprint for PluggableTransportFragment {
+    fn encode_for_fingerprint(&self, out: &mut Vec<u8>) {
+        let Self(transport) = self;
+        encode_bytes(out, transport.name().as_bytes());
+        encode_bytes(out, transport.configuration());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use const_str::{hex, ip_addr};
+    use nonzero_ext::nonzero;
+
+    use super::*;
+    use crate::route::{TlsRoute, TransportRoute};
+
+    #[test]
+    fn fixed_vector() {
+        let route: TransportRoute = TlsRoute {
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::FromDer(Cow::Borrowed(b"cert")),
+                sni: Host::Domain("chat.signal.org".into()),
+                alpn: Some(Alpn::Http1_1),
+                min_protocol_version: None,
+                ech_config_list: None,
+            },
+            inner: DirectOrProxyRoute::Direct(TcpRoute {
+                address: ip_addr!("192.0.2.1"),
+                port: nonzero!(443u16),
+                override_nagle_algorithm: OverrideNagleAlgorithm::OverrideToOff,
+            }),
+        };
+
+        let mut encoded = vec![];
+        route.encode_for_fingerprint(&mut encoded);
+        assert_eq!(
+            encoded,
+            hex!([
+                "02 0000000000000004 63657274",
+                "01 000000000000000f 636861742e7369676e616c2e6f7267",
+                "01 0000000000000008 687474702f312e31",
+                "00 04 c0000201 01bb 01",
+            ])
+        );
+        assert_eq!(
+            RouteFingerprint::of(&route),
+            RouteFingerprint(0x65be432e20e023d7)
+        );
+    }
+}
@@ -154,6 +154,7 @@ impl RouteProvider for DomainFrontRouteProvider {
                             sni: Host::Domain(Arc::clone(sni)),
                             alpn: Some((*http_version).into()),
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                     },
                     fragment: HttpRouteFragment {
@@ -266,6 +267,7 @@ mod test {
                 sni: Host::Domain("direct-host".into()),
                 certs: RootCertificates::Native,
                 min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+                ech_config_list: None,
                 inner: DirectTcpRouteProvider {
                     dns_hostname: "direct-tcp-host".into(),
                     port: DIRECT_TCP_PORT,
@@ -292,6 +294,7 @@ mod test {
                             sni: Host::Domain("direct-host".into()),
                             alpn: Some(Alpn::Http2),
                             min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_1),
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("direct-tcp-host".into()),
@@ -313,6 +316,7 @@ mod test {
                             sni: Host::Domain("front-sni-1a".into()),
                             alpn: Some(Alpn::Http1_1),
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni-1a".into()),
@@ -334,6 +338,7 @@ mod test {
                             sni: Host::Domain("front-sni-1b".into()),
                             alpn: Some(Alpn::Http1_1),
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni-1b".into()),
@@ -355,6 +360,7 @@ mod test {
                             sni: Host::Domain("front-sni-2a".into()),
                             alpn: Some(Alpn::Http1_1),
                             min_protocol_version: None,
+                            ech_config_list: None,
                         },
                         inner: TcpRoute {
                             address: UnresolvedHost("front-sni-2a".into()),
@@ -0,0 +1,192 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroU16;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::certs::RootCertificates;
+use crate::errors::TransportConnectError;
+use crate::host::Host;
+use crate::route::{HttpsTlsRoute, SimpleRoute, TcpRoute, TlsRoute, WebSocketRoute};
+use crate::tcp_ssl::proxy::pluggable::PluggableStream;
+
+/// A circumvention technique that disguises the bytes sent over a connection.
+///
+/// Pluggable transports connect to a relay that removes the disguise and forwards the connection
+/// on to its real destination, much like a [TLS proxy](crate::route::TlsProxy). The transport is
+/// only responsible for transforming the stream; the TCP connection to the relay underneath and
+/// the TLS session to the destination on top are established the same way as for any other route.
+///
+/// This lets new techniques, like obfuscation with a stream cipher, be added without new route or
+/// connector types.
+#[async_trait]
+pub trait PluggableTransport: Debug + Send + Sync {
+    /// A short, log-safe name for the technique.
+    fn name(&self) -> &'static str;
+
+    /// Distinguishes this configuration from others with the same [`name`](Self::name).
+    ///
+    /// Routes are compared by name and configuration, so this should include anything that affects
+    /// whether a connection can succeed, such as keys or parameters. It is never logged.
+    fn configuration(&self) -> &[u8];
+
+    /// Names to present to the destination in place of its own, for domain fronting.
+    ///
+    /// These are applied to routes as they're produced, so the TLS session and HTTP requests
+    /// carried over the transport use them. The default is not to front.
+    fn domain_front(&self) -> Option<&DomainFront> {
+        None
+    }
+
+    /// Disguises a newly-established connection to the relay.
+    ///
+    /// This may perform a handshake with the relay before returning.
+    async fn wrap(
+        &self,
+        stream: PluggableStream,
+        log_tag: &str,
+    ) -> Result<PluggableStream, TransportConnectError>;
+}
+
+/// Replacement TLS SNI and HTTP Host for routes over a [`PluggableTransport`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DomainFront {
+    /// Sent as the TLS SNI, and used to verify the server's certificate.
+    pub sni: Arc<str>,
+    /// Sent as the HTTP Host header, if different from the route's.
+    pub http_host: Option<Arc<str>>,
+    /// Used to verify the server's certificate, if different from the route's.
+    pub root_certs: Option<RootCertificates>,
+}
+
+/// A [`PluggableTransport`] that doesn't change the bytes sent, only the names the destination is
+/// addressed by.
+///
+/// The relay is typically a CDN edge that routes by HTTP Host rather than by TLS SNI.
+#[derive(Debug)]
+pub struct DomainFrontTransport {
+    front: DomainFront,
+    configuration: Box<[u8]>,
+}
+
+/// Route fragment that applies a [`PluggableTransport`] to the connection beneath it.
+#[derive(Clone, Debug)]
+pub struct PluggableTransportFragment(pub Arc<dyn PluggableTransport>);
+
+/// Route for connecting to a relay using a [`PluggableTransport`].
+pub type PluggableTransportRoute<T> = SimpleRoute<PluggableTransportFragment, T>;
+
+/// Configuration for connecting through a relay with a [`PluggableTransport`].
+#[derive(Clone, Debug)]
+pub struct PluggableTransportProxy {
+    pub proxy_host: Host<Arc<str>>,
+    pub proxy_port: NonZeroU16,
+    pub transport: Arc<dyn PluggableTransport>,
+}
+
+impl DomainFrontTransport {
+    pub fn new(front: DomainFront) -> Self {
+        let DomainFront {
+            sni,
+            http_host,
+            root_certs: _,
+        } = &front;
+        // Domain names can't contain NUL, so this is unambiguous.
+        let configuration = [
+            sni.as_bytes(),
+            b"\0",
+            http_host.as_deref().unwrap_or("").as_bytes(),
+        ]
+        .concat()
+        .into();
+        Self {
+            front,
+            configuration,
+        }
+    }
+}
+
+#[async_trait]
+impl PluggableTransport for DomainFrontTransport {
+    fn name(&self) -> &'static str {
+        "domain-front"
+    }
+
+    fn configuration(&self) -> &[u8] {
+        &self.configuration
+    }
+
+    fn domain_front(&self) -> Option<&DomainFront> {
+        Some(&self.front)
+    }
+
+    async fn wrap(
+        &self,
+        stream: PluggableStream,
+        _log_tag: &str,
+    ) -> Result<PluggableStream, TransportConnectError> {
+        Ok(stream)
+    }
+}
+
+/// Applies a [`DomainFront`] to the outermost TLS and HTTP layers of a route.
+pub(crate) trait SetDomainFront {
+    fn set_domain_front(&mut self, front: &DomainFront);
+}
+
+impl<A> SetDomainFront for TcpRoute<A> {
+    fn set_domain_front(&mut self, _front: &DomainFront) {}
+}
+
+impl<T> SetDomainFront for TlsRoute<T> {
+    fn set_domain_front(&mut self, front: &DomainFront) {
+        let DomainFront {
+            sni,
+            http_host: _,
+            root_certs,
+        } = front;
+        self.fragment.sni = Host::Domain(Arc::clone(sni));
+        if let Some(root_certs) = root_certs {
+            self.fragment.root_certs = root_certs.clone();
+        }
+        // Any ECH configs were published for the original name, not the front.
+        self.fragment.ech_config_list = None;
+    }
+}
+
+impl<T: SetDomainFront> SetDomainFront for HttpsTlsRoute<T> {
+    fn set_domain_front(&mut self, front: &DomainFront) {
+        if let Some(http_host) = &front.http_host {
+            self.fragment.host_header = Arc::clone(http_host);
+        }
+        self.inner.set_domain_front(front);
+    }
+}
+
+impl<T: SetDomainFront> SetDomainFront for WebSocketRoute<T> {
+    fn set_domain_front(&mut self, front: &DomainFront) {
+        self.inner.set_domain_front(front);
+    }
+}
+
+impl PartialEq for PluggableTransportFragment {
+    fn eq(&self, other: &Self) -> bool {
+        let (this, other) = (&self.0, &other.0);
+        this.name() == other.name() && this.configuration() == other.configuration()
+    }
+}
+
+impl Eq for PluggableTransportFragment {}
+
+impl Hash for PluggableTransportFragment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.name().hash(state);
+        self.0.configuration().hash(state);
+    }
+}
@@ -14,7 +14,8 @@ use crate::certs::RootCertificates;
 use crate::errors::LogSafeDisplay;
 use crate::host::Host;
 use crate::route::{
-    ReplaceFragment, RouteProvider, RouteProviderContext, SimpleRoute, TcpRoute, TlsRoute,
+    PluggableTransportFragment, PluggableTransportProxy, PluggableTransportRoute, ReplaceFragment,
+    RouteProvider, RouteProviderContext, SetDomainFront, SimpleRoute, TcpRoute, TlsRoute,
     TlsRouteFragment, UnresolvedHost,
 };
 use crate::tcp_ssl::proxy::socks;
@@ -66,6 +67,7 @@ pub enum ConnectionProxyRoute<Addr> {
     },
     Socks(SocksRoute<Addr>),
     Https(HttpsProxyRoute<Addr>),
+    Pluggable(PluggableTransportRoute<TcpRoute<Addr>>),
 }
 
 /// Target address for proxy protocols that support remote resolution.
@@ -144,6 +146,7 @@ pub enum ConnectionProxyConfig {
     Tcp(TcpProxy),
     Socks(SocksProxy),
     Http(HttpProxy),
+    Pluggable(PluggableTransportProxy),
 }
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -259,7 +262,7 @@ impl ConnectionProxyConfig {
 
     pub fn is_signal_transparent_proxy(&self) -> bool {
         match self {
-            Self::Tls(_) => true,
+            Self::Tls(_) | Self::Pluggable(_) => true,
             #[cfg(feature = "dev-util")]
             Self::Tcp(_) => true,
             Self::Socks(_) | Self::Http(_) => false,
@@ -298,6 +301,7 @@ where
             Replacement<DirectOrProxyReplacement> = R,
         > + Clone,
     >,
+    D::Route: SetDomainFront,
     <D::Route as ReplaceFragment<TcpRoute<UnresolvedHost>>>::Replacement<
         ConnectionProxyRoute<Host<UnresolvedHost>>,
     >: ReplaceFragment<
@@ -343,18 +347,21 @@ where
 }
 
 trait AsReplacer {
-    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
         &self,
     ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>>;
 }
 
 impl AsReplacer for ConnectionProxyConfig {
-    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
         &self,
     ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
         let replacer = match self {
             ConnectionProxyConfig::Tls(tls_proxy) => {
-                Either::Left(Either::Left(tls_proxy.as_replacer()))
+                Either::Left(Either::Left(Either::Left(tls_proxy.as_replacer())))
+            }
+            ConnectionProxyConfig::Pluggable(pluggable_proxy) => {
+                Either::Left(Either::Left(Either::Right(pluggable_proxy.as_replacer())))
             }
             #[cfg(feature = "dev-util")]
             ConnectionProxyConfig::Tcp(tcp_proxy) => {
@@ -371,7 +378,8 @@ impl AsReplacer for ConnectionProxyConfig {
             }
         };
         move |route| match &replacer {
-            Either::Left(Either::Left(f)) => f(route),
+            Either::Left(Either::Left(Either::Left(f))) => f(route),
+            Either::Left(Either::Left(Either::Right(f))) => f(route),
             Either::Left(Either::Right(f)) => f(route),
             Either::Right(f) => match f {
                 #[cfg(feature = "dev-util")]
@@ -387,7 +395,7 @@ impl AsReplacer for ConnectionProxyConfig {
 
 #[cfg(feature = "dev-util")]
 impl AsReplacer for TcpProxy {
-    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
         &self,
     ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
         let Self {
@@ -415,7 +423,7 @@ impl AsReplacer for TcpProxy {
 }
 
 impl AsReplacer for TlsProxy {
-    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
         &self,
     ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
         let Self {
@@ -428,6 +436,7 @@ impl AsReplacer for TlsProxy {
             sni: proxy_host.clone(),
             alpn: None,
             min_protocol_version: None,
+            ech_config_list: None,
         };
 
         let tcp = TcpRoute {
@@ -453,8 +462,40 @@ impl AsReplacer for TlsProxy {
     }
 }
 
+impl AsReplacer for PluggableTransportProxy {
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
+        &self,
+    ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
+        let Self {
+            proxy_host,
+            proxy_port,
+            transport,
+        } = self;
+
+        let pluggable_route = PluggableTransportRoute {
+            fragment: PluggableTransportFragment(Arc::clone(transport)),
+            inner: TcpRoute {
+                address: proxy_host.clone().map_domain(UnresolvedHost::from),
+                port: *proxy_port,
+                override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+            },
+        };
+        let front = transport.domain_front().cloned();
+        move |mut route: R| {
+            if let Some(front) = &front {
+                route.set_domain_front(front);
+            }
+            route.replace(|tcp_route: TcpRoute<UnresolvedHost>| {
+                let mut pluggable_route = pluggable_route.clone();
+                pluggable_route.inner.override_nagle_algorithm = tcp_route.override_nagle_algorithm;
+                ConnectionProxyRoute::Pluggable(pluggable_route)
+            })
+        }
+    }
+}
+
 impl AsReplacer for SocksProxy {
-    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
         &self,
     ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
         let Self {
@@ -497,7 +538,7 @@ impl AsReplacer for SocksProxy {
 }
 
 impl AsReplacer for HttpProxy {
-    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>>>(
+    fn as_replacer<R: ReplaceFragment<TcpRoute<UnresolvedHost>> + SetDomainFront>(
         &self,
     ) -> impl Fn(R) -> R::Replacement<ConnectionProxyRoute<Host<UnresolvedHost>>> {
         let Self {
@@ -520,6 +561,7 @@ impl AsReplacer for HttpProxy {
                     sni: proxy_host.clone(),
                     alpn: Some(Alpn::Http1_1),
                     min_protocol_version: None,
+                    ech_config_list: None,
                 },
             }),
             None => Either::Right(proxy_tcp_route),
@@ -39,6 +39,14 @@ pub trait ResolveHostnames {
     /// The provided `lookup` callback must be able to resolve every hostname
     /// that is yielded by `self.hostnames()`.
     fn resolve(self, lookup: impl FnMut(&str) -> IpAddr) -> Self::Resolved;
+
+    /// Applies what was learned from service binding (HTTPS) records for the
+    /// looked-up names.
+    ///
+    /// This is called once, before [`Self::resolve`], with the results for
+    /// every name yielded by `self.hostnames()`. The default implementation
+    /// does nothing; wrapper routes should forward to their inner routes.
+    fn apply_service_bindings(&mut self, _lookups: &[(Arc<str>, LookupResult)]) {}
 }
 
 /// A route that has had all its hostnames resolved to IP addresses.
@@ -87,7 +95,7 @@ type ResolveRouteIter<R> = std::iter::Chain<
 /// attempted.
 pub async fn resolve_route<R: ResolveHostnames + Clone + 'static>(
     dns: &impl Resolver,
-    route: R,
+    mut route: R,
 ) -> Result<ResolveRouteIter<R::Resolved>, (Arc<str>, DnsError)> {
     let to_resolve = route.hostnames().map(|UnresolvedHost(hostname)| {
         dns.lookup_ip(hostname).map(|result| match result {
@@ -97,6 +105,7 @@ pub async fn resolve_route<R: ResolveHostnames + Clone + 'static>(
     });
 
     let resolved = futures_util::future::try_join_all(to_resolve).await?;
+    route.apply_service_bindings(&resolved);
 
     let resolutions = resolved
         .into_iter()
@@ -201,6 +210,10 @@ macro_rules! impl_resolve_hostnames {
                     $($other_fields)*
                 }
             }
+
+            fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+                self.$delegate_field.apply_service_bindings(lookups)
+            }
         }
     };
     ($typ:ident, $delegate_field:ident) => {
@@ -209,11 +222,45 @@ macro_rules! impl_resolve_hostnames {
 }
 
 impl_resolve_hostnames!(TcpRoute, address, port, override_nagle_algorithm);
-impl_resolve_hostnames!(TlsRoute, inner, fragment);
+impl_resolve_hostnames!(PluggableTransportRoute, inner, fragment);
 impl_resolve_hostnames!(HttpsTlsRoute, inner, fragment);
 impl_resolve_hostnames!(WebSocketRoute, inner, fragment);
 impl_resolve_hostnames!(UsePreconnect, inner, should);
 
+impl<A: ResolveHostnames> ResolveHostnames for TlsRoute<A> {
+    type Resolved = TlsRoute<A::Resolved>;
+
+    fn hostnames(&self) -> impl Iterator<Item = &UnresolvedHost> {
+        self.inner.hostnames()
+    }
+
+    fn resolve(self, lookup: impl FnMut(&str) -> IpAddr) -> Self::Resolved {
+        let Self { inner, fragment } = self;
+        Self::Resolved {
+            inner: inner.resolve(lookup),
+            fragment,
+        }
+    }
+
+    /// Uses the ECH configs published for the SNI, if it was looked up.
+    ///
+    /// Configs from DNS take precedence over any configured ahead of time,
+    /// since they reflect what the server currently accepts.
+    fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+        let published = match &self.fragment.sni {
+            Host::Domain(sni) => lookups
+                .iter()
+                .find(|(hostname, _)| hostname == sni)
+                .and_then(|(_, result)| result.ech_config_list()),
+            Host::Ip(_) => None,
+        };
+        if let Some(ech_config_list) = published {
+            self.fragment.ech_config_list = Some(ech_config_list.clone());
+        }
+        self.inner.apply_service_bindings(lookups)
+    }
+}
+
 impl<D: ResolveHostnames, P: ResolveHostnames> ResolveHostnames for DirectOrProxyRoute<D, P> {
     type Resolved = DirectOrProxyRoute<D::Resolved, P::Resolved>;
 
@@ -230,6 +277,13 @@ impl<D: ResolveHostnames, P: ResolveHostnames> ResolveHostnames for DirectOrProx
             DirectOrProxyRoute::Proxy(p) => DirectOrProxyRoute::Proxy(p.resolve(lookup)),
         }
     }
+
+    fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+        match self {
+            DirectOrProxyRoute::Direct(d) => d.apply_service_bindings(lookups),
+            DirectOrProxyRoute::Proxy(p) => p.apply_service_bindings(lookups),
+        }
+    }
 }
 
 impl<A: ResolveHostnames> ResolveHostnames for ConnectionProxyRoute<A> {
@@ -245,8 +299,11 @@ impl<A: ResolveHostnames> ResolveHostnames for ConnectionProxyRoute<A> {
             }
             #[cfg(feature = "dev-util")]
             Self::Tcp { proxy } => Either::Left(Either::Right(proxy.hostnames())),
-            Self::Socks(socks) => Either::Right(Either::Right(socks.hostnames())),
+            Self::Socks(socks) => Either::Right(Either::Right(Either::Left(socks.hostnames()))),
             Self::Https(http) => Either::Right(Either::Left(http.hostnames())),
+            Self::Pluggable(pluggable) => {
+                Either::Right(Either::Right(Either::Right(pluggable.hostnames())))
+            }
         }
     }
 
@@ -263,6 +320,20 @@ impl<A: ResolveHostnames> ResolveHostnames for ConnectionProxyRoute<A> {
                 ConnectionProxyRoute::Socks(socks.resolve(lookup))
             }
             ConnectionProxyRoute::Https(http) => ConnectionProxyRoute::Https(http.resolve(lookup)),
+            ConnectionProxyRoute::Pluggable(pluggable) => {
+                ConnectionProxyRoute::Pluggable(pluggable.resolve(lookup))
+            }
+        }
+    }
+
+    fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+        match self {
+            ConnectionProxyRoute::Tls { proxy } => proxy.apply_service_bindings(lookups),
+            #[cfg(feature = "dev-util")]
+            ConnectionProxyRoute::Tcp { proxy } => proxy.apply_service_bindings(lookups),
+            ConnectionProxyRoute::Socks(socks) => socks.apply_service_bindings(lookups),
+            ConnectionProxyRoute::Https(http) => http.apply_service_bindings(lookups),
+            ConnectionProxyRoute::Pluggable(pluggable) => pluggable.apply_service_bindings(lookups),
         }
     }
 }
@@ -309,6 +380,10 @@ impl<A: ResolveHostnames> ResolveHostnames for HttpsProxyRoute<A> {
             fragment,
         }
     }
+
+    fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+        either::for_both!(&mut self.inner, inner => inner.apply_service_bindings(lookups))
+    }
 }
 
 impl<A: ResolveHostnames> ResolveHostnames for SocksRoute<A> {
@@ -341,6 +416,10 @@ impl<A: ResolveHostnames> ResolveHostnames for SocksRoute<A> {
             protocol,
         }
     }
+
+    fn apply_service_bindings(&mut self, lookups: &[(Arc<str>, LookupResult)]) {
+        self.proxy.apply_service_bindings(lookups)
+    }
 }
 
 impl<A: ResolveHostnames> ProxyTarget<A> {
@@ -384,6 +463,7 @@ impl ResolvedRoute for IpAddr {
 
 impl_resolved_route!(TcpRoute, address);
 impl_resolved_route!(TlsRoute, inner);
+impl_resolved_route!(PluggableTransportRoute, inner);
 impl_resolved_route!(HttpsTlsRoute, inner);
 impl_resolved_route!(HttpsProxyRoute, inner);
 impl_resolved_route!(WebSocketRoute, inner);
@@ -407,6 +487,7 @@ impl<A: ResolvedRoute> ResolvedRoute for ConnectionProxyRoute<A> {
             ConnectionProxyRoute::Tcp { proxy } => proxy.immediate_target(),
             ConnectionProxyRoute::Socks(proxy) => proxy.immediate_target(),
             ConnectionProxyRoute::Https(proxy) => proxy.immediate_target(),
+            ConnectionProxyRoute::Pluggable(proxy) => proxy.immediate_target(),
         }
     }
 }
@@ -436,6 +517,8 @@ impl<L: ResolvedRoute, R: ResolvedRoute> ResolvedRoute for Either<L, R> {
 
 #[cfg(any(test, feature = "test-util"))]
 pub mod testutils {
+    use std::collections::HashMap;
+
     use futures_util::Stream;
     use tokio::sync::{mpsc, oneshot};
     use tokio_stream::wrappers::UnboundedReceiverStream;
@@ -483,6 +566,16 @@ pub mod testutils {
         }
     }
 
+    /// Resolves names from a fixed table.
+    impl Resolver for HashMap<&str, LookupResult> {
+        fn lookup_ip(
+            &self,
+            hostname: &str,
+        ) -> impl Future<Output = Result<LookupResult, DnsError>> {
+            std::future::ready(self.get(hostname).ok_or(DnsError::LookupFailed).cloned())
+        }
+    }
+
     impl FakeResponder {
         pub fn hostname(&self) -> &str {
             &self.hostname
@@ -506,6 +599,7 @@ mod test {
     use super::*;
     use crate::OverrideNagleAlgorithm;
     use crate::certs::RootCertificates;
+    use crate::dns::lookup_result::ServiceBinding;
     use crate::host::Host;
     use crate::route::resolve::testutils::{FakeResolver, FakeResponder};
     use crate::route::{
@@ -603,17 +697,17 @@ mod test {
         responders
             .remove("host-1")
             .unwrap()
-            .respond(Ok(LookupResult {
-                ipv4: vec![],
-                ipv6: vec![ip_addr!(v6, "3fff::11")],
-            }));
+            .respond(Ok(LookupResult::new(
+                vec![],
+                vec![ip_addr!(v6, "3fff::11")],
+            )));
         responders
             .remove("host-3")
             .unwrap()
-            .respond(Ok(LookupResult {
-                ipv4: vec![ip_addr!(v4, "192.0.2.55")],
-                ipv6: vec![ip_addr!(v6, "3fff::22")],
-            }));
+            .respond(Ok(LookupResult::new(
+                vec![ip_addr!(v4, "192.0.2.55")],
+                vec![ip_addr!(v6, "3fff::22")],
+            )));
 
         let () = tokio::select! {
             biased;
@@ -624,10 +718,10 @@ mod test {
         responders
             .remove("host-2")
             .unwrap()
-            .respond(Ok(LookupResult {
-                ipv4: vec![],
-                ipv6: vec![ip_addr!(v6, "3fff::33")],
-            }));
+            .respond(Ok(LookupResult::new(
+                vec![],
+                vec![ip_addr!(v6, "3fff::33")],
+            )));
         let result = resolve.await.expect("finished");
 
         pretty_assertions::assert_eq!(
@@ -647,31 +741,22 @@ mod test {
         );
     }
 
-    impl Resolver for HashMap<&str, LookupResult> {
-        fn lookup_ip(
-            &self,
-            hostname: &str,
-        ) -> impl Future<Output = Result<LookupResult, DnsError>> {
-            std::future::ready(self.get(hostname).ok_or(DnsError::LookupFailed).cloned())
-        }
-    }
-
     #[test]
     fn resolve_hostnames_in_real_route() {
         let dns = HashMap::from([
             (
                 "proxy-domain",
-                LookupResult {
-                    ipv4: vec![ip_addr!(v4, "192.0.2.100")],
-                    ipv6: vec![ip_addr!(v6, "3fff::ffff")],
-                },
+                LookupResult::new(
+                    vec![ip_addr!(v4, "192.0.2.100")],
+                    vec![ip_addr!(v6, "3fff::ffff")],
+                ),
             ),
             (
                 "target-domain",
-                LookupResult {
-                    ipv4: vec![ip_addr!(v4, "192.0.2.1"), ip_addr!(v4, "192.0.2.2")],
-                    ipv6: vec![ip_addr!(v6, "3fff::1234")],
-                },
+                LookupResult::new(
+                    vec![ip_addr!(v4, "192.0.2.1"), ip_addr!(v4, "192.0.2.2")],
+                    vec![ip_addr!(v6, "3fff::1234")],
+                ),
             ),
         ]);
 
@@ -687,6 +772,7 @@ mod test {
             sni: Host::Domain("target-domain".into()),
             alpn: None,
             min_protocol_version: None,
+            ech_config_list: None,
         };
 
         fn socks_route<A>(proxy: A, target: A) -> ConnectionProxyRoute<A> {
@@ -789,4 +875,68 @@ mod test {
 
         pretty_assertions::assert_eq!(resolved, expected_routes);
     }
+
+    #[test]
+    fn resolved_tls_route_uses_published_ech_configs() {
+        let service_binding = |ech: &[u8], target_name: Option<&str>| ServiceBinding {
+            priority: 1,
+            target_name: target_name.map(String::from),
+            alpn: vec![],
+            no_default_alpn: false,
+            port: None,
+            ipv4_hint: vec![],
+            ipv6_hint: vec![],
+            ech_config_list: Some(ech.into()),
+        };
+        let dns = HashMap::from([
+            (
+                "target-domain",
+                LookupResult::new(vec![ip_addr!(v4, "192.0.2.1")], vec![])
+                    .with_service_bindings(vec![service_binding(b"published", None)]),
+            ),
+            (
+                "other-domain",
+                LookupResult::new(vec![ip_addr!(v4, "192.0.2.2")], vec![]).with_service_bindings(
+                    vec![service_binding(b"elsewhere", Some("another-target"))],
+                ),
+            ),
+        ]);
+
+        let tls_route = |sni: &str, address: &str| TlsRoute {
+            inner: TcpRoute {
+                address: Host::Domain(UnresolvedHost(address.into())),
+                port: TARGET_PORT,
+                override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+            },
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::Native,
+                sni: Host::Domain(sni.into()),
+                alpn: None,
+                min_protocol_version: None,
+                ech_config_list: Some(b"preconfigured".as_slice().into()),
+            },
+        };
+        let resolved_ech = |route| {
+            resolve_route(&dns, route)
+                .now_or_never()
+                .expect("all resolution is static")
+                .expect("all hostnames are resolvable")
+                .map(|route: TlsRoute<TcpRoute<IpAddr>>| route.fragment.ech_config_list)
+                .collect_vec()
+        };
+
+        assert_eq!(
+            resolved_ech(tls_route("target-domain", "target-domain")),
+            [Some(b"published".as_slice().into())]
+        );
+        // Configs for a different target, or for a name other than the SNI, aren't used.
+        assert_eq!(
+            resolved_ech(tls_route("other-domain", "other-domain")),
+            [Some(b"preconfigured".as_slice().into())]
+        );
+        assert_eq!(
+            resolved_ech(tls_route("some-front", "target-domain")),
+            [Some(b"preconfigured".as_slice().into())]
+        );
+    }
 }
@@ -9,18 +9,22 @@ use std::future::Future;
 use std::hash::Hash;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use derive_where::derive_where;
 use futures_util::stream::{FusedStream, FuturesUnordered};
 use futures_util::{Stream, StreamExt};
+use itertools::Itertools as _;
 use pin_project::pin_project;
 use rangemap::RangeSet;
 use tokio::time::{Duration, Instant};
 
 use crate::dns::DnsError;
 use crate::dns::dns_utils::log_safe_domain;
-use crate::route::{ResolveHostnames, ResolvedRoute, Resolver, TransportRoute, UsesTransport};
+use crate::route::{
+    EncodeForFingerprint, ResolveHostnames, ResolvedRoute, Resolver, RouteFingerprint,
+    TransportRoute, UsesTransport,
+};
 use crate::utils::NetworkChangeEvent;
 use crate::utils::binary_heap::{MinKeyValueQueue, Queue};
 use crate::utils::future::SomeOrPending;
@@ -87,6 +91,16 @@ pub struct Schedule<S, R, SP> {
 pub struct ConnectionOutcomes<R> {
     params: ConnectionOutcomeParams,
     recent_failures: HashMap<R, ConnectionFailureRecord>,
+    /// Failures loaded from a previous process by [`ConnectionOutcomes::restore`].
+    ///
+    /// The routes themselves aren't saved, so these are looked up by fingerprint. Once a route is
+    /// attempted again, its entry moves to `recent_failures`.
+    restored_failures: HashMap<RouteFingerprint, ConnectionFailureRecord>,
+    /// How to look up a route in `restored_failures`.
+    ///
+    /// Set by [`ConnectionOutcomes::restore`], which is the only way for there to be restored
+    /// failures, so that routes that are never saved don't need to be fingerprintable.
+    fingerprint: Option<fn(&R) -> RouteFingerprint>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -107,6 +121,27 @@ struct ConnectionFailureRecord {
     failure_count: u8,
 }
 
+/// Failure history from [`ConnectionOutcomes::save`], for persisting across process restarts.
+///
+/// Use [`SavedConnectionOutcomes::serialize`] and [`SavedConnectionOutcomes::deserialize`] to
+/// convert to and from bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SavedConnectionOutcomes {
+    entries: Vec<SavedFailure>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SavedFailure {
+    route: RouteFingerprint,
+    outcome: UnsuccessfulOutcome,
+    failed_at: SystemTime,
+    failure_count: u8,
+}
+
+/// saved connection outcomes are malformed or from an unsupported version
+#[derive(Debug, displaydoc::Display, thiserror::Error)]
+pub struct InvalidSavedOutcomes;
+
 impl Default for RouteResolver {
     fn default() -> Self {
         Self { allow_ipv6: true }
@@ -336,6 +371,8 @@ impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
         Self {
             params,
             recent_failures: Default::default(),
+            restored_failures: Default::default(),
+            fingerprint: None,
         }
     }
 
@@ -363,26 +400,35 @@ impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
         let Self {
             params,
             recent_failures,
+            restored_failures,
+            fingerprint,
         } = self;
 
         // Age out any old entries.
-        recent_failures.retain(|_route, record| {
+        let is_recent = |record: &ConnectionFailureRecord| {
             let ConnectionFailureRecord {
                 outcome,
                 started: last_time,
                 wall_clock: _,
                 failure_count: _,
             } = record;
-            let age_cutoff = match outcome {
-                UnsuccessfulOutcome::ShortTerm => params.short_term_age_cutoff,
-                UnsuccessfulOutcome::LongTerm => params.long_term_age_cutoff,
-            };
-            now.saturating_duration_since(*last_time) < age_cutoff
-        });
+            now.saturating_duration_since(*last_time) < params.age_cutoff(*outcome)
+        };
+        recent_failures.retain(|_route, record| is_recent(record));
+        restored_failures.retain(|_route, record| is_recent(record));
 
         for (route, outcome) in updates {
             let AttemptOutcome { started, result } = outcome;
 
+            // A new outcome for the route supersedes anything carried over from a previous
+            // process.
+            let restored = match fingerprint {
+                Some(fingerprint) if !restored_failures.is_empty() => {
+                    restored_failures.remove(&fingerprint(&route))
+                }
+                _ => None,
+            };
+
             match result {
                 Ok(()) => {
                     let _ = recent_failures.remove(&route);
@@ -397,11 +443,23 @@ impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
                         );
                     }
                     Entry::Vacant(entry) => {
-                        entry.insert(ConnectionFailureRecord::new(
-                            unsuccessful_outcome,
-                            started,
-                            wall_clock,
-                        ));
+                        let record = match restored {
+                            Some(mut record) => {
+                                record.update(
+                                    unsuccessful_outcome,
+                                    started,
+                                    wall_clock,
+                                    params.max_count,
+                                );
+                                record
+                            }
+                            None => ConnectionFailureRecord::new(
+                                unsuccessful_outcome,
+                                started,
+                                wall_clock,
+                            ),
+                        };
+                        entry.insert(record);
                     }
                 },
             }
@@ -414,6 +472,8 @@ impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
     pub fn reset(&mut self, cutoff: Instant) {
         self.recent_failures
             .retain(|_route, record| cutoff < record.started);
+        self.restored_failures
+            .retain(|_route, record| cutoff < record.started);
     }
 
     /// Clear any outcomes that should have expired according to the wall clock.
@@ -431,12 +491,176 @@ impl<R: Hash + Eq + Clone> ConnectionOutcomes<R> {
         let Self {
             params,
             recent_failures,
+            restored_failures,
+            fingerprint: _,
         } = self;
         let Some(cutoff) = now.checked_sub(params.long_term_age_cutoff) else {
             // Give up with too long a cutoff.
             return;
         };
         recent_failures.retain(|_route, record| cutoff < record.wall_clock);
+        restored_failures.retain(|_route, record| cutoff < record.wall_clock);
+    }
+}
+
+impl<R: Hash + Eq + Clone + EncodeForFingerprint> ConnectionOutcomes<R> {
+    /// Captures the current failure history so that it can be persisted and passed to
+    /// [`Self::restore`] in a later process.
+    pub fn save(&self) -> SavedConnectionOutcomes {
+        let Self {
+            params: _,
+            recent_failures,
+            restored_failures,
+            fingerprint: _,
+        } = self;
+
+        let entries = recent_failures
+            .iter()
+            .map(|(route, record)| (RouteFingerprint::of(route), record))
+            .chain(
+                restored_failures
+                    .iter()
+                    .map(|(route, record)| (*route, record)),
+            )
+            // Outcomes from this process come first, so they win over restored ones.
+            .unique_by(|(route, _record)| *route)
+            .map(|(route, record)| SavedFailure {
+                route,
+                outcome: record.outcome,
+                failed_at: record.wall_clock,
+                failure_count: record.failure_count,
+            })
+            .sorted_by_key(|entry| entry.route)
+            .collect();
+        SavedConnectionOutcomes { entries }
+    }
+
+    /// Seeds the failure history with outcomes from [`Self::save`], typically from a previous run
+    /// of the process.
+    ///
+    /// Since [`Instant`]s don't carry over between processes, saved entries are aged using the
+    /// wall clock, and any that would already have expired are dropped. Outcomes recorded by this
+    /// process take precedence over restored ones for the same route.
+    pub fn restore(
+        &mut self,
+        saved: &SavedConnectionOutcomes,
+        now: Instant,
+        wall_clock: SystemTime,
+    ) {
+        let Self {
+            params,
+            recent_failures: _,
+            restored_failures,
+            fingerprint,
+        } = self;
+
+        *fingerprint = Some(RouteFingerprint::of::<R>);
+        for entry in &saved.entries {
+            let SavedFailure {
+                route,
+                outcome,
+                failed_at,
+                failure_count,
+            } = *entry;
+            // If the failure appears to be in the future, the clock was adjusted; treat it as
+            // having just happened.
+            let age = wall_clock.duration_since(failed_at).unwrap_or_default();
+            if age >= params.age_cutoff(outcome) {
+                continue;
+            }
+            let Some(started) = now.checked_sub(age) else {
+                continue;
+            };
+            restored_failures
+                .entry(route)
+                .or_insert(ConnectionFailureRecord {
+                    outcome,
+                    started,
+                    wall_clock: failed_at,
+                    failure_count: failure_count.min(params.max_count),
+                });
+        }
+    }
+}
+
+impl SavedConnectionOutcomes {
+    const FORMAT_VERSION: u8 = 1;
+    /// Fingerprint, outcome, failure time in seconds since the epoch, and failure count.
+    const ENTRY_LEN: usize = 8 + 1 + 8 + 1;
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Encodes the outcomes in a compact, versioned binary format.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.entries.len() * Self::ENTRY_LEN);
+        bytes.push(Self::FORMAT_VERSION);
+        for entry in &self.entries {
+            let SavedFailure {
+                route,
+                outcome,
+                failed_at,
+                failure_count,
+            } = entry;
+            let failed_at_secs = failed_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+
+            bytes.extend_from_slice(&route.0.to_be_bytes());
+            bytes.push(match outcome {
+                UnsuccessfulOutcome::ShortTerm => 0,
+                UnsuccessfulOutcome::LongTerm => 1,
+            });
+            bytes.extend_from_slice(&failed_at_secs.to_be_bytes());
+            bytes.push(*failure_count);
+        }
+        bytes
+    }
+
+    /// Decodes outcomes produced by [`Self::serialize`].
+    ///
+    /// Outcomes saved by a different version of the format are rejected; callers should treat that
+    /// the same as having nothing saved.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, InvalidSavedOutcomes> {
+        let Some((&Self::FORMAT_VERSION, entries)) = bytes.split_first() else {
+            return Err(InvalidSavedOutcomes);
+        };
+        let chunks = entries.chunks_exact(Self::ENTRY_LEN);
+        if !chunks.remainder().is_empty() {
+            return Err(InvalidSavedOutcomes);
+        }
+
+        let entries = chunks
+            .map(|chunk| {
+                let (route, rest) = chunk.split_at(8);
+                let (&outcome, rest) = rest.split_first().expect("checked length");
+                let (failed_at_secs, &[failure_count]) = rest.split_at(8) else {
+                    unreachable!("checked length");
+                };
+
+                let outcome = match outcome {
+                    0 => UnsuccessfulOutcome::ShortTerm,
+                    1 => UnsuccessfulOutcome::LongTerm,
+                    _ => return Err(InvalidSavedOutcomes),
+                };
+                let failed_at_secs =
+                    u64::from_be_bytes(failed_at_secs.try_into().expect("checked length"));
+                let failed_at = UNIX_EPOCH
+                    .checked_add(Duration::from_secs(failed_at_secs))
+                    .ok_or(InvalidSavedOutcomes)?;
+                Ok(SavedFailure {
+                    route: RouteFingerprint(u64::from_be_bytes(
+                        route.try_into().expect("checked length"),
+                    )),
+                    outcome,
+                    failed_at,
+                    failure_count,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
     }
 }
 
@@ -500,14 +724,22 @@ impl<R: Hash + Eq> RouteDelayPolicy<R> for ConnectionOutcomes<R> {
         let Self {
             recent_failures,
             params,
+            restored_failures,
+            fingerprint,
         } = self;
 
+        let record = recent_failures.get(route).or_else(|| match fingerprint {
+            Some(fingerprint) if !restored_failures.is_empty() => {
+                restored_failures.get(&fingerprint(route))
+            }
+            _ => None,
+        });
         let Some(ConnectionFailureRecord {
             outcome,
             started,
             wall_clock: _,
             failure_count,
-        }) = recent_failures.get(route)
+        }) = record
         else {
             return Duration::ZERO;
         };
@@ -521,6 +753,13 @@ impl<R: Hash + Eq> RouteDelayPolicy<R> for ConnectionOutcomes<R> {
 }
 
 impl ConnectionOutcomeParams {
+    fn age_cutoff(&self, outcome: UnsuccessfulOutcome) -> Duration {
+        match outcome {
+            UnsuccessfulOutcome::ShortTerm => self.short_term_age_cutoff,
+            UnsuccessfulOutcome::LongTerm => self.long_term_age_cutoff,
+        }
+    }
+
     /// Compute the delay given the time since the last failure and count of
     /// repeated failures.
     ///
@@ -919,10 +1158,10 @@ mod test {
         let resolver = RouteResolver { allow_ipv6: true };
         let name_resolver = HashMap::from([(
             "domain-name",
-            LookupResult {
-                ipv4: vec![ip_addr!(v4, "192.0.2.1")],
-                ipv6: vec![ip_addr!(v6, "3fff::1234")],
-            },
+            LookupResult::new(
+                vec![ip_addr!(v4, "192.0.2.1")],
+                vec![ip_addr!(v6, "3fff::1234")],
+            ),
         )]);
 
         let unresolved_routes = [FakeRoute(UnresolvedHost("domain-name".into()))];
@@ -954,17 +1193,17 @@ mod test {
         let name_resolver = HashMap::from([
             (
                 "name-1",
-                LookupResult {
-                    ipv4: vec![ip_addr!(v4, "192.0.2.11")],
-                    ipv6: vec![ip_addr!(v6, "3fff::1234")],
-                },
+                LookupResult::new(
+                    vec![ip_addr!(v4, "192.0.2.11")],
+                    vec![ip_addr!(v6, "3fff::1234")],
+                ),
             ),
             (
                 "name-2",
-                LookupResult {
-                    ipv4: vec![ip_addr!(v4, "192.0.2.22")],
-                    ipv6: vec![ip_addr!(v6, "3fff::5678")],
-                },
+                LookupResult::new(
+                    vec![ip_addr!(v4, "192.0.2.22")],
+                    vec![ip_addr!(v6, "3fff::5678")],
+                ),
             ),
         ]);
 
@@ -1360,6 +1599,158 @@ mod test {
         assert_eq!(outcomes.compute_delay(&ROUTE, start).as_secs(), 0);
     }
 
+    #[test]
+    fn connection_outcomes_survive_save_and_restore() {
+        const MAX_DELAY: Duration = Duration::from_secs(100);
+        const AGE_CUTOFF: Duration = Duration::from_secs(1000);
+        const MAX_COUNT: u8 = 5;
+        const PARAMS: ConnectionOutcomeParams = ConnectionOutcomeParams {
+            short_term_age_cutoff: AGE_CUTOFF,
+            long_term_age_cutoff: AGE_CUTOFF,
+            cooldown_growth_factor: 2.0,
+            count_growth_factor: 10.0,
+            max_count: MAX_COUNT,
+            max_delay: MAX_DELAY,
+        };
+
+        const FAILING_ROUTE: &str = "failing";
+        const OTHER_ROUTE: &str = "other";
+        let start = Instant::now();
+        let wall_clock_start = SystemTime::now();
+
+        let mut outcomes = ConnectionOutcomes::new(PARAMS);
+        for _ in 0..2 {
+            outcomes.apply_outcome_updates(
+                [(
+                    FAILING_ROUTE,
+                    AttemptOutcome {
+                        started: start,
+                        result: Err(UnsuccessfulOutcome::default()),
+                    },
+                )],
+                start,
+                wall_clock_start,
+            );
+        }
+        assert_eq!(outcomes.compute_delay(&FAILING_ROUTE, start).as_secs(), 16);
+
+        let saved = SavedConnectionOutcomes::deserialize(&outcomes.save().serialize())
+            .expect("can round-trip");
+        assert_eq!(saved.serialize(), outcomes.save().serialize());
+
+        // Restore in a "new process" some time later, where Instants aren't comparable.
+        let new_start = start + Duration::from_secs(12345);
+        let mut restored = ConnectionOutcomes::new(PARAMS);
+        restored.restore(&saved, new_start, wall_clock_start + AGE_CUTOFF / 2);
+        assert_eq!(
+            restored.compute_delay(&FAILING_ROUTE, new_start).as_secs(),
+            outcomes
+                .compute_delay(&FAILING_ROUTE, start + AGE_CUTOFF / 2)
+                .as_secs()
+        );
+        assert_eq!(
+            restored.compute_delay(&OTHER_ROUTE, new_start),
+            Duration::ZERO
+        );
+
+        // Another failure builds on the restored count.
+        restored.apply_outcome_updates(
+            [(
+                FAILING_ROUTE,
+                AttemptOutcome {
+                    started: new_start,
+                    result: Err(UnsuccessfulOutcome::default()),
+                },
+            )],
+            new_start,
+            wall_clock_start + AGE_CUTOFF / 2,
+        );
+        assert_eq!(
+            restored.compute_delay(&FAILING_ROUTE, new_start).as_secs(),
+            33
+        );
+
+        // A success clears the route, including from the saved history.
+        restored.apply_outcome_updates(
+            [(
+                FAILING_ROUTE,
+                AttemptOutcome {
+                    started: new_start,
+                    result: Ok(()),
+                },
+            )],
+            new_start,
+            wall_clock_start + AGE_CUTOFF / 2,
+        );
+        assert_eq!(
+            restored.compute_delay(&FAILING_ROUTE, new_start),
+            Duration::ZERO
+        );
+        assert!(restored.save().is_empty());
+    }
+
+    #[test]
+    fn connection_outcomes_drop_expired_saved_outcomes() {
+        const AGE_CUTOFF: Duration = Duration::from_secs(1000);
+        let params = ConnectionOutcomeParams {
+            short_term_age_cutoff: AGE_CUTOFF,
+            long_term_age_cutoff: AGE_CUTOFF,
+            cooldown_growth_factor: 2.0,
+            count_growth_factor: 10.0,
+            max_count: 5,
+            max_delay: Duration::from_secs(100),
+        };
+
+        const ROUTE: &str = "route";
+        let start = Instant::now();
+
+        let mut outcomes = ConnectionOutcomes::new(params.clone());
+        outcomes.record_outcome(
+            ROUTE,
+            start,
+            Duration::ZERO,
+            Err(UnsuccessfulOutcome::default()),
+        );
+        let saved = outcomes.save();
+        assert!(!saved.is_empty());
+
+        let mut restored = ConnectionOutcomes::new(params);
+        restored.restore(&saved, start, SystemTime::now() + AGE_CUTOFF);
+        assert_eq!(restored.compute_delay(&ROUTE, start), Duration::ZERO);
+        assert!(restored.save().is_empty());
+    }
+
+    #[test]
+    fn saved_connection_outcomes_reject_malformed_input() {
+        assert_matches!(
+            SavedConnectionOutcomes::deserialize(&[]),
+            Err(InvalidSavedOutcomes)
+        );
+        assert_matches!(
+            SavedConnectionOutcomes::deserialize(&[2]),
+            Err(InvalidSavedOutcomes)
+        );
+        assert_matches!(
+            SavedConnectionOutcomes::deserialize(&[1, 0, 0]),
+            Err(InvalidSavedOutcomes)
+        );
+        // Unknown outcome.
+        let mut bytes = vec![1];
+        bytes.extend([0; 8]);
+        bytes.push(7);
+        bytes.extend([0; 8]);
+        bytes.push(1);
+        assert_matches!(
+            SavedConnectionOutcomes::deserialize(&bytes),
+            Err(InvalidSavedOutcomes)
+        );
+
+        assert_eq!(
+            SavedConnectionOutcomes::deserialize(&[1]).expect("valid"),
+            SavedConnectionOutcomes::default()
+        );
+    }
+
     #[tokio::test(start_paused = true)]
     async fn min_kvq_stream_debounce() {
         use std::task::Poll;
@@ -18,13 +18,26 @@ pub struct TlsRouteFragment {
     pub sni: Host<Arc<str>>,
     pub alpn: Option<Alpn>,
     pub min_protocol_version: Option<SslVersion>,
+    /// If present, the client hello will be encrypted using one of these configs.
+    ///
+    /// The `sni` is then only sent in the encrypted inner hello; observers see the public name
+    /// from the config instead.
+    pub ech_config_list: Option<EchConfigList>,
 }
 
+/// A serialized `ECHConfigList`, as defined in the [ECH draft][] and carried in the `ech`
+/// parameter of HTTPS DNS records.
+///
+/// [ECH draft]: https://datatracker.ietf.org/doc/html/draft-ietf-tls-esni-22#section-4
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct EchConfigList(Arc<[u8]>);
+
 impl std::hash::Hash for TlsRouteFragment {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.root_certs.hash(state);
         self.sni.hash(state);
         self.alpn.hash(state);
+        self.ech_config_list.hash(state);
         // Ignore SslVersion, an opaque enum. Unfortunate, but a valid hash implementation.
     }
 }
@@ -36,6 +49,7 @@ pub struct TlsRouteProvider<P> {
     pub(crate) sni: Host<Arc<str>>,
     pub(crate) certs: RootCertificates,
     pub(crate) min_protocol_version: Option<SslVersion>,
+    pub(crate) ech_config_list: Option<EchConfigList>,
     pub(crate) inner: P,
 }
 
@@ -50,9 +64,44 @@ impl<T> TlsRouteProvider<T> {
             sni,
             certs,
             min_protocol_version,
+            ech_config_list: None,
             inner,
         }
     }
+
+    /// Sets the ECH configs to use for all produced routes.
+    pub fn with_ech_config_list(self, ech_config_list: Option<EchConfigList>) -> Self {
+        Self {
+            ech_config_list,
+            ..self
+        }
+    }
+}
+
+impl EchConfigList {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&[u8]> for EchConfigList {
+    fn from(value: &[u8]) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<Vec<u8>> for EchConfigList {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value.into())
+    }
+}
+
+impl std::fmt::Debug for EchConfigList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EchConfigList")
+            .field(&format_args!("<{} bytes>", self.0.len()))
+            .finish()
+    }
 }
 
 /// Sets the [`Alpn`] value for a route or route fragment.
@@ -72,6 +121,7 @@ impl<P: RouteProvider> RouteProvider for TlsRouteProvider<P> {
             sni,
             certs,
             min_protocol_version,
+            ech_config_list,
             inner,
         } = self;
 
@@ -81,6 +131,7 @@ impl<P: RouteProvider> RouteProvider for TlsRouteProvider<P> {
                 sni: sni.clone(),
                 alpn: None,
                 min_protocol_version: *min_protocol_version,
+                ech_config_list: ech_config_list.clone(),
             },
             inner: route,
         })
@@ -8,13 +8,13 @@ use std::net::IpAddr;
 use std::time::Duration;
 
 use boring_signal::ssl::{ConnectConfiguration, SslConnector, SslMethod, SslSignatureAlgorithm};
-use tokio_boring_signal::SslStream;
+use tokio_boring_signal::{HandshakeError, SslStream};
 
 use crate::certs::RootCertificates;
 use crate::dns::DnsResolver;
 use crate::errors::TransportConnectError;
 use crate::host::Host;
-use crate::route::{Connector, DirectOrProxyMode, TcpRoute, TlsRouteFragment};
+use crate::route::{Connector, DirectOrProxyMode, EchConfigList, TcpRoute, TlsRouteFragment};
 #[cfg(feature = "dev-util")]
 #[allow(unused_imports)]
 use crate::utils::development_only_enable_nss_standard_debug_interop;
@@ -157,10 +157,18 @@ where
             sni,
             alpn,
             min_protocol_version,
+            ech_config_list,
         } = fragment;
         let host = sni;
 
-        let ssl_config = ssl_config(&root_certs, host.as_deref(), alpn, min_protocol_version);
+        let ssl_config = ssl_config(
+            &root_certs,
+            host.as_deref(),
+            alpn,
+            min_protocol_version,
+            ech_config_list.as_ref(),
+        );
+        let offered_ech = ech_config_list.is_some() && matches!(host, Host::Domain(_));
 
         async move {
             let domain = match &host {
@@ -171,7 +179,10 @@ where
 
             tokio_boring_signal::connect(ssl_config, &domain, inner)
                 .await
-                .map_err(TransportConnectError::from)
+                .map_err(|e| {
+                    let ech_rejected = offered_ech.then(|| ech_rejection(&e)).flatten();
+                    ech_rejected.unwrap_or_else(|| TransportConnectError::from(e))
+                })
         }
     }
 }
@@ -187,6 +198,7 @@ fn ssl_config(
     host: Host<&str>,
     alpn: Option<Alpn>,
     min_required_tls_version: Option<boring_signal::ssl::SslVersion>,
+    ech_config_list: Option<&EchConfigList>,
 ) -> Result<ConnectConfiguration, TransportConnectError> {
     let mut ssl = SslConnector::builder(SslMethod::tls_client())?;
     certs.apply_to_connector(&mut ssl, host)?;
@@ -215,7 +227,33 @@ fn ssl_config(
     // #[cfg(feature = "dev-util")]
     // development_only_enable_nss_standard_debug_interop(&mut ssl)?;
 
-    Ok(ssl.build().configure()?)
+    let mut config = ssl.build().configure()?;
+    match (ech_config_list, host) {
+        (Some(ech_config_list), Host::Domain(_)) => {
+            config.set_ech_config_list(ech_config_list.as_bytes())?
+        }
+        // ECH protects the server name; there's nothing to hide when connecting by IP address.
+        (Some(_), Host::Ip(_)) | (None, _) => {}
+    }
+
+    Ok(config)
+}
+
+/// Checks whether a handshake failed because the server rejected our Encrypted Client Hello.
+fn ech_rejection<S>(error: &HandshakeError<S>) -> Option<TransportConnectError> {
+    let retry_configs = error
+        .ssl()?
+        .get_ech_retry_configs()
+        .filter(|configs| !configs.is_empty())
+        .map(EchConfigList::from);
+    let rejected = retry_configs.is_some()
+        || error.as_ssl_error_stack().is_some_and(|stack| {
+            stack
+                .errors()
+                .iter()
+                .any(|e| e.reason() == Some("ECH_REJECTED"))
+        });
+    rejected.then_some(TransportConnectError::EchRejected { retry_configs })
 }
 
 #[cfg(test)]
@@ -420,19 +458,27 @@ pub(crate) mod testutil {
 #[cfg(test)]
 mod test {
     use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
     use std::num::NonZero;
 
     use assert_matches::assert_matches;
     use boring_signal::x509::X509VerifyError;
+    use const_str::concat_bytes;
     use futures_util::future::Either;
+    use itertools::Itertools as _;
     use test_case::test_case;
+    use tokio::io::AsyncReadExt as _;
     use warp::Filter as _;
 
     use super::testutil::*;
     use super::*;
     use crate::OverrideNagleAlgorithm;
+    use crate::dns::lookup_result::LookupResult;
     use crate::errors::FailedHandshakeReason;
-    use crate::route::{ComposedConnector, ConnectorExt as _, TlsRoute};
+    use crate::route::{
+        ComposedConnector, ConnectorExt as _, TlsRoute, UnresolvedHost, resolve_route,
+    };
     use crate::tcp_ssl::proxy::testutil::PROXY_CERTIFICATE;
 
     #[test_case(Alpn::Http1_1, Alpn::Http2)]
@@ -460,6 +506,7 @@ mod test {
                     sni: Host::Domain(SERVER_HOSTNAME.into()),
                     alpn: Some(client_alpn),
                     min_protocol_version: None,
+                    ech_config_list: None,
                 },
                 inner: TcpRoute {
                     address: addr.ip(),
@@ -509,6 +556,7 @@ mod test {
                     sni: Host::Domain(SERVER_HOSTNAME.into()),
                     alpn: None,
                     min_protocol_version: None,
+                    ech_config_list: None,
                 },
                 inner: TcpRoute {
                     address: addr.ip(),
@@ -531,4 +579,117 @@ mod test {
             ))
         );
     }
+
+    #[test_log::test(tokio::test)]
+    async fn ech_config_from_dns_answer_is_offered_in_client_hello() {
+        const PUBLIC_NAME: &str = "public.example";
+        // A single ECHConfig for DHKEM(X25519, HKDF-SHA256) with HKDF-SHA256 and AES-128-GCM.
+        // Any 32 bytes are a valid X25519 public key.
+        const ECH_CONFIG_LIST: &[u8] = concat_bytes!(
+            [0, 65],             // ECHConfigList length
+            [0xfe, 0x0d, 0, 61], // version, length
+            7,                   // config_id
+            [0x00, 0x20],        // kem_id
+            [0, 32],
+            [0x55; 32],         // public_key
+            [0, 4, 0, 1, 0, 1], // cipher_suites
+            0,                  // maximum_name_length
+            14,
+            PUBLIC_NAME, // public_name
+            [0, 0],      // extensions
+        );
+        // The RDATA of an HTTPS record as it would appear in a DNS answer.
+        const RDATA: &[u8] = concat_bytes!(
+            [0, 1], // priority
+            0,      // target name: the owner name
+            [0, 5, 0, 67],
+            ECH_CONFIG_LIST, // ech
+        );
+        let binding = crate::dns::dns_message::parse_svcb_record(RDATA).expect("valid record");
+
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("can bind");
+        let port = listener.local_addr().expect("bound").port();
+
+        let dns = HashMap::from([(
+            SERVER_HOSTNAME,
+            LookupResult::new(vec![Ipv4Addr::LOCALHOST], vec![])
+                .with_service_bindings(vec![binding]),
+        )]);
+        let unresolved = TlsRoute {
+            fragment: TlsRouteFragment {
+                root_certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+                sni: Host::Domain(SERVER_HOSTNAME.into()),
+                alpn: None,
+                min_protocol_version: None,
+                ech_config_list: None,
+            },
+            inner: TcpRoute {
+                address: Host::Domain(UnresolvedHost(SERVER_HOSTNAME.into())),
+                port: NonZero::new(port).expect("successful listener has a valid port"),
+                override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+            },
+        };
+        let route = resolve_route(&dns, unresolved)
+            .await
+            .expect("can resolve")
+            .exactly_one()
+            .expect("one address");
+
+        type StatelessTlsConnector = ComposedConnector<StatelessTls, StatelessTcp>;
+        let connector = StatelessTlsConnector::default();
+        let client = connector.connect(route, "transport");
+        let server = async {
+            let (mut stream, _) = listener.accept().await.expect("incoming connection");
+            let mut header = [0; 5];
+            stream.read_exact(&mut header).await.expect("record header");
+            let mut record = vec![0; u16::from_be_bytes([header[3], header[4]]).into()];
+            stream.read_exact(&mut record).await.expect("record body");
+            record
+        };
+        let (_client_result, client_hello) = tokio::join!(client, server);
+
+        let extensions = client_hello_extensions(&client_hello);
+        assert!(
+            extensions.contains_key(&0xfe0d),
+            "no encrypted_client_hello extension in {:?}",
+            extensions.keys()
+        );
+        // Only the public name is visible outside the encrypted inner hello.
+        let server_name = extensions.get(&0).expect("has server_name");
+        assert_eq!(&server_name[5..], PUBLIC_NAME.as_bytes());
+    }
+
+    /// Splits the extensions out of a ClientHello handshake message.
+    fn client_hello_extensions(mut message: &[u8]) -> HashMap<u16, &[u8]> {
+        fn take<'a>(input: &mut &'a [u8], len: usize) -> &'a [u8] {
+            let (taken, rest) = input.split_at(len);
+            *input = rest;
+            taken
+        }
+        fn take_u16(input: &mut &[u8]) -> u16 {
+            let bytes = take(input, 2);
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        }
+
+        assert_eq!(take(&mut message, 4)[0], 1, "not a ClientHello");
+        let _version_and_random = take(&mut message, 2 + 32);
+        let session_id_len = take(&mut message, 1)[0].into();
+        let _session_id = take(&mut message, session_id_len);
+        let cipher_suites_len = take_u16(&mut message).into();
+        let _cipher_suites = take(&mut message, cipher_suites_len);
+        let compression_methods_len = take(&mut message, 1)[0].into();
+        let _compression_methods = take(&mut message, compression_methods_len);
+        let extensions_len = take_u16(&mut message).into();
+        let mut extensions = take(&mut message, extensions_len);
+
+        let mut result = HashMap::new();
+        while !extensions.is_empty() {
+            let extension_type = take_u16(&mut extensions);
+            let len = take_u16(&mut extensions).into();
+            result.insert(extension_type, take(&mut extensions, len));
+        }
+        result
+    }
 }
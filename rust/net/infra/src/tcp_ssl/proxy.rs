@@ -16,6 +16,7 @@ use crate::route::{
 };
 
 pub mod https;
+pub mod pluggable;
 pub mod socks;
 
 mod stream;
@@ -93,6 +94,16 @@ impl Connector<ConnectionProxyRoute<IpAddr>, ()> for StatelessProxied {
                 .map_ok(Into::into)
                 .await
             }
+            ConnectionProxyRoute::Pluggable(route) => {
+                LoggingConnector::new(
+                    self,
+                    pluggable::LONG_FULL_CONNECT_THRESHOLD,
+                    "Proxy-TCP+Pluggable",
+                )
+                .connect(route, log_tag)
+                .map_ok(Into::into)
+                .await
+            }
         }
     }
 }
@@ -381,6 +392,7 @@ mod test {
                     sni: Host::Domain(PROXY_HOSTNAME.into()),
                     alpn: None,
                     min_protocol_version: None,
+                    ech_config_list: None,
                 },
                 inner: TcpRoute {
                     address: proxy_addr.ip(),
@@ -407,6 +419,7 @@ mod test {
                     sni: Host::Domain(SERVER_HOSTNAME.into()),
                     alpn: Some(Alpn::Http1_1),
                     min_protocol_version: None,
+                    ech_config_list: None,
                 },
                 "tcp proxy test",
             )
@@ -453,6 +466,7 @@ mod test {
                     sni: Host::Domain(SERVER_HOSTNAME.into()),
                     alpn: Some(Alpn::Http1_1),
                     min_protocol_version: None,
+                    ech_config_list: None,
                 },
                 "tcp proxy test",
             )
@@ -0,0 +1,254 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::errors::TransportConnectError;
+use crate::route::{
+    ComposedConnector, Connector, ConnectorExt as _, LoggingConnector, PluggableTransportFragment,
+    PluggableTransportRoute, TcpRoute,
+};
+use crate::{AsyncDuplexStream, Connection, TransportInfo};
+
+/// Allows a little extra time on top of the TCP connection for the transport's own handshake.
+pub(crate) const LONG_FULL_CONNECT_THRESHOLD: Duration =
+    super::LONG_TCP_HANDSHAKE_THRESHOLD.saturating_add(Duration::from_secs(3));
+
+/// A connection whose bytes may have been transformed by a
+/// [`PluggableTransport`](crate::route::PluggableTransport).
+///
+/// Keeps the addresses of the underlying connection, so that it can still report its
+/// [`TransportInfo`] after being wrapped.
+pub struct PluggableStream {
+    stream: Box<dyn AsyncDuplexStream>,
+    transport_info: TransportInfo,
+}
+
+impl PluggableStream {
+    pub fn new(stream: impl AsyncDuplexStream + Connection + 'static) -> Self {
+        let transport_info = stream.transport_info();
+        Self {
+            stream: Box::new(stream),
+            transport_info,
+        }
+    }
+
+    /// Replaces the stream with a transformed version of it.
+    pub fn map<S: AsyncDuplexStream + 'static>(
+        self,
+        f: impl FnOnce(Box<dyn AsyncDuplexStream>) -> S,
+    ) -> Self {
+        let Self {
+            stream,
+            transport_info,
+        } = self;
+        Self {
+            stream: Box::new(f(stream)),
+            transport_info,
+        }
+    }
+}
+
+impl std::fmt::Debug for PluggableStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluggableStream")
+            .field("transport_info", &self.transport_info)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Connection for PluggableStream {
+    fn transport_info(&self) -> TransportInfo {
+        self.transport_info.clone()
+    }
+}
+
+impl AsyncRead for PluggableStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for PluggableStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl<Inner> Connector<PluggableTransportFragment, Inner> for super::StatelessProxied
+where
+    Inner: AsyncDuplexStream + Connection + 'static,
+{
+    type Connection = PluggableStream;
+
+    type Error = TransportConnectError;
+
+    async fn connect_over(
+        &self,
+        over: Inner,
+        fragment: PluggableTransportFragment,
+        log_tag: &str,
+    ) -> Result<Self::Connection, Self::Error> {
+        let PluggableTransportFragment(transport) = fragment;
+        log::info!(
+            "[{log_tag}] establishing {} pluggable transport",
+            transport.name()
+        );
+        transport.wrap(PluggableStream::new(over), log_tag).await
+    }
+}
+
+impl Connector<PluggableTransportRoute<TcpRoute<IpAddr>>, ()> for super::StatelessProxied {
+    type Connection = PluggableStream;
+
+    type Error = TransportConnectError;
+
+    async fn connect_over(
+        &self,
+        (): (),
+        route: PluggableTransportRoute<TcpRoute<IpAddr>>,
+        log_tag: &str,
+    ) -> Result<Self::Connection, Self::Error> {
+        let tcp = LoggingConnector::new(
+            crate::tcp_ssl::StatelessTcp,
+            super::LONG_TCP_HANDSHAKE_THRESHOLD,
+            "Proxy-TCP",
+        );
+        ComposedConnector::new(self, tcp)
+            .connect(route, log_tag)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use futures_util::FutureExt as _;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::OverrideNagleAlgorithm;
+    use crate::route::PluggableTransport;
+    use crate::tcp_ssl::proxy::testutil::TcpServer;
+
+    /// Flips every bit, which is about the simplest possible "obfuscation".
+    #[derive(Debug)]
+    struct Inverted;
+
+    struct InvertingStream(Box<dyn AsyncDuplexStream>);
+
+    #[async_trait]
+    impl PluggableTransport for Inverted {
+        fn name(&self) -> &'static str {
+            "inverted"
+        }
+
+        fn configuration(&self) -> &[u8] {
+            &[]
+        }
+
+        async fn wrap(
+            &self,
+            stream: PluggableStream,
+            _log_tag: &str,
+        ) -> Result<PluggableStream, TransportConnectError> {
+            Ok(stream.map(InvertingStream))
+        }
+    }
+
+    impl AsyncRead for InvertingStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let start = buf.filled().len();
+            let result = Pin::new(&mut self.0).poll_read(cx, buf);
+            buf.filled_mut()[start..].iter_mut().for_each(|b| *b = !*b);
+            result
+        }
+    }
+
+    impl AsyncWrite for InvertingStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let inverted = buf.iter().map(|b| !b).collect::<Vec<_>>();
+            Pin::new(&mut self.0).poll_write(cx, &inverted)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_shutdown(cx)
+        }
+    }
+
+    #[tokio::test]
+    async fn pluggable_transport_wraps_connection() {
+        let server = TcpServer::bind_localhost();
+        let route = PluggableTransportRoute {
+            fragment: PluggableTransportFragment(Arc::new(Inverted)),
+            inner: TcpRoute {
+                address: server.listen_addr.ip(),
+                port: server.listen_addr.port().try_into().expect("nonzero"),
+                override_nagle_algorithm: OverrideNagleAlgorithm::UseSystemDefault,
+            },
+        };
+
+        let (mut connection, (mut accepted, _)) = tokio::try_join!(
+            super::super::StatelessProxied.connect(route, "test"),
+            server.accept().map(Ok),
+        )
+        .expect("can connect");
+        assert_eq!(connection.transport_info().remote_addr, server.listen_addr);
+
+        connection.write_all(b"hello").await.expect("can write");
+        let mut received = [0; 5];
+        accepted.read_exact(&mut received).await.expect("can read");
+        assert_eq!(received, [!b'h', !b'e', !b'l', !b'l', !b'o']);
+
+        accepted
+            .write_all(&[!b'h', !b'i'])
+            .await
+            .expect("can write");
+        let mut received = [0; 2];
+        connection
+            .read_exact(&mut received)
+            .await
+            .expect("can read");
+        assert_eq!(&received, b"hi");
+    }
+}
@@ -37,10 +37,12 @@ impl FakeTransportTarget {
                 host: None,
                 port: DEFAULT_HTTPS_PORT,
             },
-            ConnectionProxyRoute::Tls { .. } => Self::TcpThroughProxy {
-                host: None,
-                port: DEFAULT_HTTPS_PORT,
-            },
+            ConnectionProxyRoute::Tls { .. } | ConnectionProxyRoute::Pluggable(_) => {
+                Self::TcpThroughProxy {
+                    host: None,
+                    port: DEFAULT_HTTPS_PORT,
+                }
+            }
             ConnectionProxyRoute::Socks(SocksRoute {
                 target_addr: target_host,
                 target_port,
@@ -35,8 +35,10 @@ use crate::proto;
 mod error;
 pub use error::{ConnectError, SendError};
 
+pub mod envelope;
 pub mod fake;
 pub mod server_requests;
+pub mod supervisor;
 pub mod ws;
 
 pub type MessageProto = proto::chat_websocket::WebSocketMessage;
@@ -451,7 +453,7 @@ pub mod test_support {
 
         let connect = ConnectState::new_with_transport_connector(
             SUGGESTED_CONNECT_CONFIG,
-            PreconnectingFactory::new(DefaultConnectorFactory, Duration::ZERO),
+            PreconnectingFactory::new(DefaultConnectorFactory::default(), Duration::ZERO),
         );
         let user_agent = UserAgent::with_libsignal_version("test_simple_chat_connection");
 
@@ -736,6 +738,7 @@ pub(crate) mod test {
                         sni: Host::Domain(CHAT_DOMAIN.into()),
                         alpn: Some(Alpn::Http1_1),
                         min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_3),
+                        ech_config_list: None,
                     },
                     inner: DirectOrProxyRoute::Direct(TcpRoute {
                         address: UnresolvedHost(CHAT_DOMAIN.into()),
@@ -802,6 +805,7 @@ pub(crate) mod test {
                     sni: Host::Domain(CHAT_DOMAIN.into()),
                     alpn: Some(Alpn::Http1_1),
                     min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_3),
+                    ech_config_list: None,
                 },
                 inner: DirectOrProxyRoute::Direct(TcpRoute {
                     address: UnresolvedHost(CHAT_DOMAIN.into()),
@@ -0,0 +1,903 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Typed handling of envelopes delivered as [`ServerEvent::IncomingMessage`].
+//!
+//! [`IncomingEnvelope::parse`] decodes and classifies the raw envelope bytes, and
+//! [`decrypt_envelope`] dispatches to the right decryption routine from `libsignal_protocol`.
+//! Neither one acknowledges the message to the server: that's left to the caller via
+//! [`PendingAck::send`], which should only happen once the decrypted result has been durably
+//! committed. Otherwise a crash between decryption and storage would lose the message.
+//!
+//! [`ServerEvent::IncomingMessage`]: super::server_requests::ServerEvent::IncomingMessage
+
+use bytes::Bytes;
+use libsignal_core::{DeviceId, ProtocolAddress, ServiceId};
+use libsignal_protocol::{
+    CiphertextMessageType, IdentityKeyStore, InMemSignalProtocolStore, KyberPreKeyStore,
+    PlaintextContent, PreKeySignalMessage, PreKeyStore, PublicKey, SenderKeyStore, SessionStore,
+    SignalMessage, SignalProtocolError, SignedPreKeyStore, Timestamp, group_decrypt,
+    message_decrypt_prekey, message_decrypt_signal, sealed_sender_decrypt_to_usmc,
+};
+use prost::Message as _;
+use rand::TryRngCore as _;
+use rand::rngs::OsRng;
+
+use crate::chat::SendError;
+use crate::chat::server_requests::ResponseEnvelopeSender;
+pub use crate::proto::chat_envelope::Envelope;
+use crate::proto::chat_envelope::envelope::Type as EnvelopeType;
+
+/// The kind of payload carried by an [`Envelope`], as indicated by its `type` field.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EnvelopeKind {
+    /// A [`SignalMessage`] for an existing session.
+    Ciphertext,
+    /// A [`PreKeySignalMessage`], which may establish a new session.
+    PreKeyBundle,
+    /// A [`SenderKeyMessage`](libsignal_protocol::SenderKeyMessage) sent to a group without
+    /// sealed sender.
+    SenderKey,
+    /// A sealed sender message; the inner message type is only known after decryption.
+    UnidentifiedSender,
+    /// A [`PlaintextContent`] message, such as a decryption error report.
+    PlaintextContent,
+    /// A receipt generated by the server (historically just "receipt"); it has no content.
+    ServerDeliveryReceipt,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum EnvelopeError {
+    /// envelope is not a valid protobuf
+    Malformed,
+    /// envelope has unknown type {0}
+    UnknownType(i32),
+    /// envelope has no content
+    MissingContent,
+    /// envelope has no valid source service ID
+    InvalidSource,
+    /// envelope has no valid source device ID
+    InvalidSourceDevice,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum DecryptError {
+    /// {0}
+    Envelope(#[from] EnvelopeError),
+    /// {0}
+    Protocol(#[from] SignalProtocolError),
+}
+
+/// Acknowledgement for an incoming message that hasn't been sent yet.
+///
+/// Dropping this without calling [`send`](Self::send) leaves the message on the server, which
+/// will deliver it again on the next connection.
+#[must_use]
+pub struct PendingAck(ResponseEnvelopeSender);
+
+impl PendingAck {
+    pub fn new(send_ack: ResponseEnvelopeSender) -> Self {
+        Self(send_ack)
+    }
+
+    /// Tells the server the message has been handled and can be removed from the queue.
+    pub fn send(self) -> Result<(), SendError> {
+        (self.0)(http::StatusCode::OK)
+    }
+}
+
+impl std::fmt::Debug for PendingAck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PendingAck").finish_non_exhaustive()
+    }
+}
+
+/// A parsed and classified [`ServerEvent::IncomingMessage`].
+///
+/// [`ServerEvent::IncomingMessage`]: super::server_requests::ServerEvent::IncomingMessage
+#[derive(Debug)]
+pub struct IncomingEnvelope {
+    pub request_id: u64,
+    pub envelope: Envelope,
+    pub kind: EnvelopeKind,
+    pub server_delivery_timestamp: Timestamp,
+    pub ack: PendingAck,
+}
+
+/// A plaintext message from a sender, or a server receipt.
+#[derive(Debug)]
+pub enum DecryptedEnvelope {
+    Content {
+        sender: ProtocolAddress,
+        /// Whether the sender's identity came from a sealed sender certificate.
+        sealed_sender: bool,
+        /// The inner message type that was decrypted.
+        message_type: CiphertextMessageType,
+        /// The serialized `Content`, including any padding.
+        content: Vec<u8>,
+    },
+    ServerDeliveryReceipt {
+        sender: ProtocolAddress,
+        timestamp: Timestamp,
+    },
+}
+
+/// The stores needed to decrypt any kind of envelope.
+///
+/// These are separate references rather than a single
+/// [`ProtocolStore`](libsignal_protocol::ProtocolStore) because decryption needs several of them
+/// mutably at once.
+pub struct DecryptionStores<'a> {
+    pub session_store: &'a mut dyn SessionStore,
+    pub identity_store: &'a mut dyn IdentityKeyStore,
+    pub pre_key_store: &'a mut dyn PreKeyStore,
+    pub signed_pre_key_store: &'a dyn SignedPreKeyStore,
+    pub kyber_pre_key_store: &'a mut dyn KyberPreKeyStore,
+    pub sender_key_store: &'a mut dyn SenderKeyStore,
+}
+
+impl<'a> From<&'a mut InMemSignalProtocolStore> for DecryptionStores<'a> {
+    fn from(store: &'a mut InMemSignalProtocolStore) -> Self {
+        let InMemSignalProtocolStore {
+            session_store,
+            pre_key_store,
+            signed_pre_key_store,
+            kyber_pre_key_store,
+            identity_store,
+            sender_key_store,
+        } = store;
+        Self {
+            session_store,
+            identity_store,
+            pre_key_store,
+            signed_pre_key_store,
+            kyber_pre_key_store,
+            sender_key_store,
+        }
+    }
+}
+
+/// Information about the local device needed to decrypt sealed sender messages.
+pub struct DecryptionContext<'a> {
+    pub trust_root: &'a PublicKey,
+    pub local_address: &'a ProtocolAddress,
+}
+
+impl TryFrom<i32> for EnvelopeKind {
+    type Error = EnvelopeError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Ok(
+            match EnvelopeType::try_from(value).map_err(|_| EnvelopeError::UnknownType(value))? {
+                EnvelopeType::Ciphertext => Self::Ciphertext,
+                EnvelopeType::PrekeyBundle => Self::PreKeyBundle,
+                EnvelopeType::SenderkeyMessage => Self::SenderKey,
+                EnvelopeType::UnidentifiedSender => Self::UnidentifiedSender,
+                EnvelopeType::PlaintextContent => Self::PlaintextContent,
+                EnvelopeType::ServerDeliveryReceipt => Self::ServerDeliveryReceipt,
+                EnvelopeType::Unknown => return Err(EnvelopeError::UnknownType(value)),
+            },
+        )
+    }
+}
+
+impl IncomingEnvelope {
+    /// Decodes the fields of a [`ServerEvent::IncomingMessage`].
+    ///
+    /// On failure, the acknowledgement is handed back along with the error. Malformed envelopes
+    /// should still be acknowledged, or the server will keep delivering them.
+    ///
+    /// [`ServerEvent::IncomingMessage`]: super::server_requests::ServerEvent::IncomingMessage
+    pub fn parse(
+        request_id: u64,
+        envelope: Bytes,
+        server_delivery_timestamp: Timestamp,
+        send_ack: ResponseEnvelopeSender,
+    ) -> Result<Self, (EnvelopeError, PendingAck)> {
+        let ack = PendingAck::new(send_ack);
+        let parsed = Envelope::decode(envelope)
+            .map_err(|_| EnvelopeError::Malformed)
+            .and_then(|envelope| {
+                let kind = EnvelopeKind::try_from(envelope.r#type.unwrap_or_default())?;
+                Ok((envelope, kind))
+            });
+        match parsed {
+            Ok((envelope, kind)) => Ok(Self {
+                request_id,
+                envelope,
+                kind,
+                server_delivery_timestamp,
+                ack,
+            }),
+            Err(e) => Err((e, ack)),
+        }
+    }
+
+    /// The sender recorded by the server, for envelopes not using sealed sender.
+    pub fn source_address(&self) -> Result<ProtocolAddress, EnvelopeError> {
+        let service_id = match &self.envelope.source_service_id_binary {
+            Some(binary) => ServiceId::parse_from_service_id_binary(binary),
+            None => self
+                .envelope
+                .source_service_id
+                .as_deref()
+                .and_then(ServiceId::parse_from_service_id_string),
+        }
+        .ok_or(EnvelopeError::InvalidSource)?;
+        let device_id = self
+            .envelope
+            .source_device
+            .and_then(|device| DeviceId::try_from(device).ok())
+            .ok_or(EnvelopeError::InvalidSourceDevice)?;
+        Ok(service_id.to_protocol_address(device_id))
+    }
+
+    fn content(&self) -> Result<&[u8], EnvelopeError> {
+        self.envelope
+            .content
+            .as_deref()
+            .filter(|content| !content.is_empty())
+            .ok_or(EnvelopeError::MissingContent)
+    }
+
+    /// The time to validate sealed sender certificates against.
+    ///
+    /// Prefers the server's receive timestamp, falling back to the delivery timestamp.
+    fn validation_timestamp(&self) -> Timestamp {
+        self.envelope
+            .server_timestamp
+            .map(Timestamp::from_epoch_millis)
+            .unwrap_or(self.server_delivery_timestamp)
+    }
+}
+
+/// Decrypts `envelope` using the appropriate protocol operation for its kind.
+///
+/// This updates `stores` but does not acknowledge the message; see the [module-level
+/// documentation](self).
+pub async fn decrypt_envelope(
+    envelope: &IncomingEnvelope,
+    context: &DecryptionContext<'_>,
+    mut stores: DecryptionStores<'_>,
+) -> Result<DecryptedEnvelope, DecryptError> {
+    let message_type = match envelope.kind {
+        EnvelopeKind::ServerDeliveryReceipt => {
+            return Ok(DecryptedEnvelope::ServerDeliveryReceipt {
+                sender: envelope.source_address()?,
+                timestamp: Timestamp::from_epoch_millis(
+                    envelope.envelope.timestamp.unwrap_or_default(),
+                ),
+            });
+        }
+        EnvelopeKind::UnidentifiedSender => {
+            return decrypt_sealed_sender(envelope, context, &mut stores).await;
+        }
+        EnvelopeKind::Ciphertext => CiphertextMessageType::Whisper,
+        EnvelopeKind::PreKeyBundle => CiphertextMessageType::PreKey,
+        EnvelopeKind::SenderKey => CiphertextMessageType::SenderKey,
+        EnvelopeKind::PlaintextContent => CiphertextMessageType::Plaintext,
+    };
+
+    let sender = envelope.source_address()?;
+    let content = decrypt_message(message_type, envelope.content()?, &sender, &mut stores).await?;
+    Ok(DecryptedEnvelope::Content {
+        sender,
+        sealed_sender: false,
+        message_type,
+        content,
+    })
+}
+
+async fn decrypt_sealed_sender(
+    envelope: &IncomingEnvelope,
+    context: &DecryptionContext<'_>,
+    stores: &mut DecryptionStores<'_>,
+) -> Result<DecryptedEnvelope, DecryptError> {
+    let usmc = sealed_sender_decrypt_to_usmc(envelope.content()?, stores.identity_store).await?;
+
+    let certificate = usmc.sender()?;
+    if !certificate.validate(context.trust_root, envelope.validation_timestamp())? {
+        return Err(SignalProtocolError::InvalidSealedSenderMessage(
+            "trust root validation failed".to_owned(),
+        )
+        .into());
+    }
+
+    let sender = ProtocolAddress::new(
+        certificate.sender_uuid()?.to_owned(),
+        certificate.sender_device_id()?,
+    );
+    if sender == *context.local_address {
+        return Err(SignalProtocolError::SealedSenderSelfSend.into());
+    }
+
+    let message_type = usmc.msg_type()?;
+    let content = decrypt_message(message_type, usmc.contents()?, &sender, stores).await?;
+    Ok(DecryptedEnvelope::Content {
+        sender,
+        sealed_sender: true,
+        message_type,
+        content,
+    })
+}
+
+async fn decrypt_message(
+    message_type: CiphertextMessageType,
+    message: &[u8],
+    sender: &ProtocolAddress,
+    stores: &mut DecryptionStores<'_>,
+) -> Result<Vec<u8>, SignalProtocolError> {
+    let mut rng = OsRng.unwrap_err();
+    match message_type {
+        CiphertextMessageType::Whisper => {
+            message_decrypt_signal(
+                &SignalMessage::try_from(message)?,
+                sender,
+                stores.session_store,
+                stores.identity_store,
+                &mut rng,
+            )
+            .await
+        }
+        CiphertextMessageType::PreKey => {
+            message_decrypt_prekey(
+                &PreKeySignalMessage::try_from(message)?,
+                sender,
+                stores.session_store,
+                stores.identity_store,
+                stores.pre_key_store,
+                stores.signed_pre_key_store,
+                stores.kyber_pre_key_store,
+                &mut rng,
+            )
+            .await
+        }
+        CiphertextMessageType::SenderKey => {
+            group_decrypt(message, stores.sender_key_store, sender).await
+        }
+        CiphertextMessageType::Plaintext => {
+            Ok(PlaintextContent::try_from(message)?.body().to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::SystemTime;
+
+    use assert_matches::assert_matches;
+    use libsignal_core::Aci;
+    use libsignal_protocol::{
+        DecryptionErrorMessage, GenericSignedPreKey as _, IdentityKeyPair, KeyPair,
+        KyberPreKeyRecord, PreKeyBundle, PreKeyRecord, SenderCertificate, ServerCertificate,
+        SignedPreKeyRecord, create_sender_key_distribution_message, group_encrypt, kem,
+        message_encrypt, process_prekey_bundle, process_sender_key_distribution_message,
+        sealed_sender_encrypt,
+    };
+    use test_case::test_case;
+
+    use super::*;
+
+    const SENDER_UUID: [u8; 16] = [0x11; 16];
+
+    fn ack_tracker() -> (ResponseEnvelopeSender, Arc<AtomicBool>) {
+        let acked = Arc::new(AtomicBool::new(false));
+        let sender: ResponseEnvelopeSender = Box::new({
+            let acked = acked.clone();
+            move |status| {
+                assert_eq!(status, http::StatusCode::OK);
+                acked.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        (sender, acked)
+    }
+
+    fn envelope(envelope_type: EnvelopeType) -> Envelope {
+        Envelope {
+            r#type: Some(envelope_type.into()),
+            source_service_id_binary: Some(
+                Aci::from_uuid_bytes(SENDER_UUID).service_id_binary().into(),
+            ),
+            source_device: Some(2),
+            timestamp: Some(1234),
+            ..Default::default()
+        }
+    }
+
+    #[test_case(EnvelopeType::Ciphertext => EnvelopeKind::Ciphertext)]
+    #[test_case(EnvelopeType::PrekeyBundle => EnvelopeKind::PreKeyBundle)]
+    #[test_case(EnvelopeType::SenderkeyMessage => EnvelopeKind::SenderKey)]
+    #[test_case(EnvelopeType::UnidentifiedSender => EnvelopeKind::UnidentifiedSender)]
+    #[test_case(EnvelopeType::PlaintextContent => EnvelopeKind::PlaintextContent)]
+    #[test_case(EnvelopeType::ServerDeliveryReceipt => EnvelopeKind::ServerDeliveryReceipt)]
+    fn classify(envelope_type: EnvelopeType) -> EnvelopeKind {
+        let (send_ack, _) = ack_tracker();
+        IncomingEnvelope::parse(
+            1,
+            envelope(envelope_type).encode_to_vec().into(),
+            Timestamp::from_epoch_millis(5678),
+            send_ack,
+        )
+        .expect("valid")
+        .kind
+    }
+
+    #[test]
+    fn malformed_envelope_returns_ack() {
+        let (send_ack, acked) = ack_tracker();
+        let (error, ack) = IncomingEnvelope::parse(
+            1,
+            Bytes::from_static(b"\xff\xff not a protobuf"),
+            Timestamp::from_epoch_millis(5678),
+            send_ack,
+        )
+        .expect_err("malformed");
+        assert_matches!(error, EnvelopeError::Malformed);
+
+        assert!(!acked.load(Ordering::SeqCst));
+        ack.send().expect("can ack");
+        assert!(acked.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unknown_type() {
+        let (send_ack, _) = ack_tracker();
+        let unknown = Envelope {
+            r#type: Some(100),
+            ..Default::default()
+        };
+        let (error, _ack) = IncomingEnvelope::parse(
+            1,
+            unknown.encode_to_vec().into(),
+            Timestamp::from_epoch_millis(5678),
+            send_ack,
+        )
+        .expect_err("unknown type");
+        assert_matches!(error, EnvelopeError::UnknownType(100));
+    }
+
+    #[tokio::test]
+    async fn server_delivery_receipt_is_not_acked_by_decryption() {
+        let (send_ack, acked) = ack_tracker();
+        let incoming = IncomingEnvelope::parse(
+            1,
+            envelope(EnvelopeType::ServerDeliveryReceipt)
+                .encode_to_vec()
+                .into(),
+            Timestamp::from_epoch_millis(5678),
+            send_ack,
+        )
+        .expect("valid");
+
+        let identity = libsignal_protocol::IdentityKeyPair::generate(&mut rand::rng());
+        let mut store = InMemSignalProtocolStore::new(identity, 1).expect("can create store");
+        let local_address = ProtocolAddress::new(
+            "local".to_owned(),
+            DeviceId::new(1).expect("valid device ID"),
+        );
+        let trust_root = *identity.public_key();
+        let decrypted = decrypt_envelope(
+            &incoming,
+            &DecryptionContext {
+                trust_root: &trust_root,
+                local_address: &local_address,
+            },
+            (&mut store).into(),
+        )
+        .await
+        .expect("can decrypt");
+
+        let (sender, timestamp) = assert_matches!(
+            decrypted,
+            DecryptedEnvelope::ServerDeliveryReceipt { sender, timestamp } => (sender, timestamp)
+        );
+        assert_eq!(
+            sender,
+            Aci::from_uuid_bytes(SENDER_UUID)
+                .to_protocol_address(DeviceId::new(2).expect("valid device ID"))
+        );
+        assert_eq!(timestamp, Timestamp::from_epoch_millis(1234));
+
+        assert!(!acked.load(Ordering::SeqCst));
+        incoming.ack.send().expect("can ack");
+        assert!(acked.load(Ordering::SeqCst));
+    }
+
+    const ALICE_UUID: [u8; 16] = [0xaa; 16];
+    const BOB_UUID: [u8; 16] = [0xbb; 16];
+
+    struct Party {
+        address: ProtocolAddress,
+        store: InMemSignalProtocolStore,
+    }
+
+    impl Party {
+        fn new(uuid: [u8; 16], device_id: u8) -> Self {
+            let identity = IdentityKeyPair::generate(&mut rand::rng());
+            Self {
+                address: Aci::from_uuid_bytes(uuid)
+                    .to_protocol_address(DeviceId::new(device_id).expect("valid device ID")),
+                store: InMemSignalProtocolStore::new(identity, 1).expect("can create store"),
+            }
+        }
+
+        fn envelope(&self, envelope_type: EnvelopeType, content: &[u8]) -> IncomingEnvelope {
+            let aci = Aci::parse_from_service_id_string(self.address.name()).expect("ACI");
+            let envelope = Envelope {
+                r#type: Some(envelope_type.into()),
+                source_service_id_binary: Some(aci.service_id_binary()),
+                source_device: Some(self.address.device_id().into()),
+                content: Some(content.to_vec()),
+                ..Default::default()
+            };
+            incoming(envelope)
+        }
+
+        async fn pre_key_bundle(&mut self) -> PreKeyBundle {
+            let mut rng = rand::rng();
+            let identity = self
+                .store
+                .get_identity_key_pair()
+                .await
+                .expect("has identity");
+            let pre_key_pair = KeyPair::generate(&mut rng);
+            let signed_pre_key_pair = KeyPair::generate(&mut rng);
+            let kyber_pre_key_pair = kem::KeyPair::generate(kem::KeyType::Kyber1024, &mut rng);
+            let signed_pre_key_signature = identity
+                .private_key()
+                .calculate_signature(&signed_pre_key_pair.public_key.serialize(), &mut rng)
+                .expect("can sign");
+            let kyber_pre_key_signature = identity
+                .private_key()
+                .calculate_signature(&kyber_pre_key_pair.public_key.serialize(), &mut rng)
+                .expect("can sign");
+
+            self.store
+                .save_pre_key(1u32.into(), &PreKeyRecord::new(1u32.into(), &pre_key_pair))
+                .await
+                .expect("can save");
+            self.store
+                .save_signed_pre_key(
+                    2u32.into(),
+                    &SignedPreKeyRecord::new(
+                        2u32.into(),
+                        Timestamp::from_epoch_millis(42),
+                        &signed_pre_key_pair,
+                        &signed_pre_key_signature,
+                    ),
+                )
+                .await
+                .expect("can save");
+            self.store
+                .save_kyber_pre_key(
+                    3u32.into(),
+                    &KyberPreKeyRecord::new(
+                        3u32.into(),
+                        Timestamp::from_epoch_millis(42),
+                        &kyber_pre_key_pair,
+                        &kyber_pre_key_signature,
+                    ),
+                )
+                .await
+                .expect("can save");
+
+            PreKeyBundle::new(
+                1,
+                self.address.device_id(),
+                Some((1u32.into(), pre_key_pair.public_key)),
+                2u32.into(),
+                signed_pre_key_pair.public_key,
+                signed_pre_key_signature.to_vec(),
+                3u32.into(),
+                kyber_pre_key_pair.public_key,
+                kyber_pre_key_signature.to_vec(),
+                *identity.identity_key(),
+            )
+            .expect("valid bundle")
+        }
+
+        async fn decrypt(
+            &mut self,
+            incoming: &IncomingEnvelope,
+            trust_root: &PublicKey,
+        ) -> Result<DecryptedEnvelope, DecryptError> {
+            decrypt_envelope(
+                incoming,
+                &DecryptionContext {
+                    trust_root,
+                    local_address: &self.address,
+                },
+                (&mut self.store).into(),
+            )
+            .await
+        }
+    }
+
+    fn incoming(envelope: Envelope) -> IncomingEnvelope {
+        let (send_ack, _) = ack_tracker();
+        IncomingEnvelope::parse(
+            1,
+            envelope.encode_to_vec().into(),
+            Timestamp::from_epoch_millis(5678),
+            send_ack,
+        )
+        .expect("valid")
+    }
+
+    fn unused_trust_root() -> PublicKey {
+        KeyPair::generate(&mut rand::rng()).public_key
+    }
+
+    /// Has `sender` start a session with `recipient`, returning the first message's ciphertext.
+    async fn start_session(sender: &mut Party, recipient: &mut Party, plaintext: &[u8]) -> Vec<u8> {
+        let bundle = recipient.pre_key_bundle().await;
+        process_prekey_bundle(
+            &recipient.address,
+            &mut sender.store.session_store,
+            &mut sender.store.identity_store,
+            &bundle,
+            SystemTime::now(),
+            &mut rand::rng(),
+        )
+        .await
+        .expect("valid bundle");
+        let message = message_encrypt(
+            plaintext,
+            &recipient.address,
+            &mut sender.store.session_store,
+            &mut sender.store.identity_store,
+            SystemTime::now(),
+            &mut rand::rng(),
+        )
+        .await
+        .expect("can encrypt");
+        assert_eq!(message.message_type(), CiphertextMessageType::PreKey);
+        message.serialize().to_vec()
+    }
+
+    fn assert_content(
+        decrypted: DecryptedEnvelope,
+        expected_sender: &ProtocolAddress,
+        expected_sealed_sender: bool,
+        expected_type: CiphertextMessageType,
+        expected_content: &[u8],
+    ) {
+        let (sender, sealed_sender, message_type, content) = assert_matches!(
+            decrypted,
+            DecryptedEnvelope::Content { sender, sealed_sender, message_type, content } =>
+                (sender, sealed_sender, message_type, content)
+        );
+        assert_eq!(&sender, expected_sender);
+        assert_eq!(sealed_sender, expected_sealed_sender);
+        assert_eq!(message_type, expected_type);
+        assert_eq!(content, expected_content);
+    }
+
+    #[tokio::test]
+    async fn decrypt_pre_key_message() {
+        let mut alice = Party::new(ALICE_UUID, 1);
+        let mut bob = Party::new(BOB_UUID, 2);
+
+        let ciphertext = start_session(&mut alice, &mut bob, b"hello bob").await;
+        let decrypted = bob
+            .decrypt(
+                &alice.envelope(EnvelopeType::PrekeyBundle, &ciphertext),
+                &unused_trust_root(),
+            )
+            .await
+            .expect("can decrypt");
+        assert_content(
+            decrypted,
+            &alice.address,
+            false,
+            CiphertextMessageType::PreKey,
+            b"hello bob",
+        );
+    }
+
+    #[tokio::test]
+    async fn decrypt_whisper_message() {
+        let mut alice = Party::new(ALICE_UUID, 1);
+        let mut bob = Party::new(BOB_UUID, 2);
+
+        let ciphertext = start_session(&mut alice, &mut bob, b"hello bob").await;
+        bob.decrypt(
+            &alice.envelope(EnvelopeType::PrekeyBundle, &ciphertext),
+            &unused_trust_root(),
+        )
+        .await
+        .expect("can decrypt");
+
+        // Bob's reply uses the established session.
+        let reply = message_encrypt(
+            b"hello alice",
+            &alice.address,
+            &mut bob.store.session_store,
+            &mut bob.store.identity_store,
+            SystemTime::now(),
+            &mut rand::rng(),
+        )
+        .await
+        .expect("can encrypt");
+        assert_eq!(reply.message_type(), CiphertextMessageType::Whisper);
+
+        let decrypted = alice
+            .decrypt(
+                &bob.envelope(EnvelopeType::Ciphertext, reply.serialize()),
+                &unused_trust_root(),
+            )
+            .await
+            .expect("can decrypt");
+        assert_content(
+            decrypted,
+            &bob.address,
+            false,
+            CiphertextMessageType::Whisper,
+            b"hello alice",
+        );
+    }
+
+    #[tokio::test]
+    async fn decrypt_sender_key_message() {
+        let mut alice = Party::new(ALICE_UUID, 1);
+        let mut bob = Party::new(BOB_UUID, 2);
+        let distribution_id = uuid::Uuid::from_bytes([0xdd; 16]);
+
+        let skdm = create_sender_key_distribution_message(
+            &alice.address,
+            distribution_id,
+            &mut alice.store.sender_key_store,
+            &mut rand::rng(),
+        )
+        .await
+        .expect("can create");
+        process_sender_key_distribution_message(
+            &alice.address,
+            &skdm,
+            &mut bob.store.sender_key_store,
+        )
+        .await
+        .expect("valid distribution message");
+
+        let message = group_encrypt(
+            &mut alice.store.sender_key_store,
+            &alice.address,
+            distribution_id,
+            b"hello group",
+            &mut rand::rng(),
+        )
+        .await
+        .expect("can encrypt");
+
+        let decrypted = bob
+            .decrypt(
+                &alice.envelope(EnvelopeType::SenderkeyMessage, message.serialized()),
+                &unused_trust_root(),
+            )
+            .await
+            .expect("can decrypt");
+        assert_content(
+            decrypted,
+            &alice.address,
+            false,
+            CiphertextMessageType::SenderKey,
+            b"hello group",
+        );
+    }
+
+    #[tokio::test]
+    async fn decrypt_plaintext_content() {
+        let mut alice = Party::new(ALICE_UUID, 1);
+        let mut bob = Party::new(BOB_UUID, 2);
+
+        let ciphertext = start_session(&mut alice, &mut bob, b"hello bob").await;
+        let error_message = DecryptionErrorMessage::for_original(
+            &ciphertext,
+            CiphertextMessageType::PreKey,
+            Timestamp::from_epoch_millis(1234),
+            1,
+        )
+        .expect("valid original");
+        let plaintext = PlaintextContent::from(error_message);
+
+        let decrypted = alice
+            .decrypt(
+                &bob.envelope(EnvelopeType::PlaintextContent, plaintext.serialized()),
+                &unused_trust_root(),
+            )
+            .await
+            .expect("can decrypt");
+        assert_content(
+            decrypted,
+            &bob.address,
+            false,
+            CiphertextMessageType::Plaintext,
+            plaintext.body(),
+        );
+    }
+
+    #[tokio::test]
+    async fn decrypt_sealed_sender_message() {
+        let mut rng = rand::rng();
+        let mut alice = Party::new(ALICE_UUID, 1);
+        let mut bob = Party::new(BOB_UUID, 2);
+
+        let trust_root = KeyPair::generate(&mut rng);
+        let server_key = KeyPair::generate(&mut rng);
+        let server_certificate =
+            ServerCertificate::new(1, server_key.public_key, &trust_root.private_key, &mut rng)
+                .expect("can sign");
+        let alice_identity = alice
+            .store
+            .get_identity_key_pair()
+            .await
+            .expect("has identity");
+        let sender_certificate = SenderCertificate::new(
+            alice.address.name().to_owned(),
+            None,
+            *alice_identity.public_key(),
+            alice.address.device_id(),
+            Timestamp::from_epoch_millis(1_000_000),
+            server_certificate,
+            &server_key.private_key,
+            &mut rng,
+        )
+        .expect("can sign");
+
+        // Establish the session so the sealed message is a PreKey message for Bob.
+        let bundle = bob.pre_key_bundle().await;
+        process_prekey_bundle(
+            &bob.address,
+            &mut alice.store.session_store,
+            &mut alice.store.identity_store,
+            &bundle,
+            SystemTime::now(),
+            &mut rng,
+        )
+        .await
+        .expect("valid bundle");
+        let sealed = sealed_sender_encrypt(
+            &bob.address,
+            &sender_certificate,
+            b"hello from nobody",
+            &mut alice.store.session_store,
+            &mut alice.store.identity_store,
+            SystemTime::now(),
+            &mut rng,
+        )
+        .await
+        .expect("can encrypt");
+
+        // Sealed sender envelopes carry no source; the sender comes from the certificate.
+        let envelope = Envelope {
+            r#type: Some(EnvelopeType::UnidentifiedSender.into()),
+            content: Some(sealed),
+            server_timestamp: Some(5678),
+            ..Default::default()
+        };
+        let decrypted = bob
+            .decrypt(&incoming(envelope.clone()), &trust_root.public_key)
+            .await
+            .expect("can decrypt");
+        assert_content(
+            decrypted,
+            &alice.address,
+            true,
+            CiphertextMessageType::PreKey,
+            b"hello from nobody",
+        );
+
+        // The certificate must chain to the trust root.
+        assert_matches!(
+            bob.decrypt(&incoming(envelope), &unused_trust_root()).await,
+            Err(DecryptError::Protocol(
+                SignalProtocolError::InvalidSealedSenderMessage(_)
+            ))
+        );
+    }
+}
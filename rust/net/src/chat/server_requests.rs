@@ -95,7 +95,9 @@ impl TryFrom<ws::ListenerEvent> for ServerEvent {
     }
 }
 
-fn convert_finished_reason(reason: Result<ws::FinishReason, ws::FinishError>) -> DisconnectCause {
+pub(crate) fn convert_finished_reason(
+    reason: Result<ws::FinishReason, ws::FinishError>,
+) -> DisconnectCause {
     match reason {
         Ok(ws::FinishReason::LocalDisconnect) => DisconnectCause::LocalDisconnect,
         Ok(ws::FinishReason::RemoteDisconnect) => {
@@ -0,0 +1,585 @@
+//
+// Copyright 2025 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Keeps chat connections alive across transport failures.
+//!
+//! A [`ChatConnection`] represents a single websocket; once it finishes, it stays finished. A
+//! [`SupervisedConnection`] runs a background task that connects, waits for the connection to end,
+//! and then connects again, with jittered exponential backoff between failed attempts. It stops
+//! for good when the server says the connection can't be used anymore ([`StopReason`]), or when
+//! it's stopped or dropped locally.
+//!
+//! The supervisor doesn't hold on to a [`ConnectState`](crate::connect_state::ConnectState)
+//! itself; connecting is delegated to a [`ConnectFn`], which will usually call
+//! [`ChatConnection::start_connect_with`] and [`ChatConnection::finish_connect`]. When the app
+//! reports a network change (which should also call
+//! [`ConnectState::network_changed`](crate::connect_state::ConnectState::network_changed)), the
+//! supervisor cuts short any backoff in progress and tries again right away.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use libsignal_net_infra::errors::LogSafeDisplay;
+use libsignal_net_infra::utils::NetworkChangeEvent;
+use tokio::sync::{oneshot, watch};
+use tokio::time::Instant;
+
+use crate::chat::server_requests::{DisconnectCause, convert_finished_reason};
+use crate::chat::{ChatConnection, ConnectError, SendError, ws};
+
+/// Establishes a new connection, delivering its events to the provided listener.
+pub type ConnectFn = Box<
+    dyn FnMut(ws::EventListener) -> BoxFuture<'static, Result<ChatConnection, ConnectError>> + Send,
+>;
+
+#[derive(Clone, Debug)]
+pub struct ReconnectConfig {
+    /// The delay after the first failure, before jitter is applied.
+    pub initial_backoff: Duration,
+    /// The longest delay between attempts, before jitter is applied.
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The state of a [`SupervisedConnection`].
+#[derive(Clone)]
+pub enum ConnectionState {
+    /// A connection attempt is in progress.
+    Connecting,
+    /// The connection is established and can be used to send requests.
+    Connected(Arc<ChatConnection>),
+    /// The last attempt failed (or the connection was lost); another will be made at `retry_at`.
+    WaitingToReconnect {
+        consecutive_failures: u32,
+        retry_at: Instant,
+    },
+    /// The supervisor will not connect again.
+    Stopped(StopReason),
+}
+
+/// Why a [`SupervisedConnection`] stopped for good.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, displaydoc::Display)]
+pub enum StopReason {
+    /// stopped locally
+    Requested,
+    /// the server explicitly disconnected us because we connected elsewhere with the same credentials
+    ConnectedElsewhere,
+    /// the server explicitly disconnected us for some reason other than that we connected elsewhere
+    ConnectionInvalidated,
+    /// app version is too old
+    AppExpired,
+    /// device was deregistered
+    DeviceDeregistered,
+}
+
+/// A chat connection that's automatically re-established when it's lost.
+///
+/// Dropping this stops the supervisor and disconnects any active connection.
+pub struct SupervisedConnection {
+    state: watch::Receiver<ConnectionState>,
+    stop: oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// Supervisors for the pair of connections an app normally keeps open.
+pub struct ChatSupervisor {
+    pub authenticated: SupervisedConnection,
+    pub unauthenticated: SupervisedConnection,
+}
+
+impl ChatSupervisor {
+    pub fn spawn(
+        tokio_runtime: &tokio::runtime::Handle,
+        config: ReconnectConfig,
+        network_change_event: NetworkChangeEvent,
+        (connect_authenticated, authenticated_listener): (ConnectFn, ws::EventListener),
+        (connect_unauthenticated, unauthenticated_listener): (ConnectFn, ws::EventListener),
+    ) -> Self {
+        Self {
+            authenticated: SupervisedConnection::spawn(
+                tokio_runtime,
+                "auth chat",
+                config.clone(),
+                network_change_event.clone(),
+                connect_authenticated,
+                authenticated_listener,
+            ),
+            unauthenticated: SupervisedConnection::spawn(
+                tokio_runtime,
+                "unauth chat",
+                config,
+                network_change_event,
+                connect_unauthenticated,
+                unauthenticated_listener,
+            ),
+        }
+    }
+
+    /// Stops both connections and waits for them to disconnect.
+    pub async fn stop(self) {
+        let Self {
+            authenticated,
+            unauthenticated,
+        } = self;
+        tokio::join!(authenticated.stop(), unauthenticated.stop());
+    }
+}
+
+impl SupervisedConnection {
+    /// Starts connecting in the background.
+    ///
+    /// `listener` receives alerts and incoming requests from every connection made. It does *not*
+    /// receive [`ws::ListenerEvent::Finished`]; watch [`Self::state`] instead.
+    pub fn spawn(
+        tokio_runtime: &tokio::runtime::Handle,
+        log_tag: &'static str,
+        config: ReconnectConfig,
+        network_change_event: NetworkChangeEvent,
+        connect: ConnectFn,
+        listener: ws::EventListener,
+    ) -> Self {
+        let (state_tx, state) = watch::channel(ConnectionState::Connecting);
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio_runtime.spawn(
+            Supervisor {
+                log_tag,
+                config,
+                network_change_event,
+                connect,
+                listener: Arc::new(Mutex::new(listener)),
+                state: state_tx,
+            }
+            .run(stop_rx),
+        );
+        Self { state, stop, task }
+    }
+
+    /// Returns a receiver that observes every state change.
+    pub fn state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// The active connection, if any.
+    pub fn connection(&self) -> Option<Arc<ChatConnection>> {
+        match &*self.state.borrow() {
+            ConnectionState::Connected(connection) => Some(connection.clone()),
+            ConnectionState::Connecting
+            | ConnectionState::WaitingToReconnect { .. }
+            | ConnectionState::Stopped(_) => None,
+        }
+    }
+
+    /// Stops reconnecting and waits for any active connection to disconnect.
+    pub async fn stop(self) {
+        let Self {
+            state: _,
+            stop,
+            task,
+        } = self;
+        // If the task has already finished, there's nothing to stop.
+        _ = stop.send(());
+        if let Err(e) = task.await {
+            log::warn!("chat supervisor task failed: {e}");
+        }
+    }
+}
+
+impl std::fmt::Debug for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connecting => write!(f, "Connecting"),
+            Self::Connected(connection) => f
+                .debug_tuple("Connected")
+                .field(connection.connection_info())
+                .finish(),
+            Self::WaitingToReconnect {
+                consecutive_failures,
+                retry_at,
+            } => f
+                .debug_struct("WaitingToReconnect")
+                .field("consecutive_failures", consecutive_failures)
+                .field("retry_at", retry_at)
+                .finish(),
+            Self::Stopped(reason) => f.debug_tuple("Stopped").field(reason).finish(),
+        }
+    }
+}
+
+struct Supervisor {
+    log_tag: &'static str,
+    config: ReconnectConfig,
+    network_change_event: NetworkChangeEvent,
+    connect: ConnectFn,
+    listener: Arc<Mutex<ws::EventListener>>,
+    state: watch::Sender<ConnectionState>,
+}
+
+/// What to do after a connection attempt or connection ends.
+enum Next {
+    Retry { delay: Duration },
+    Stop(StopReason),
+}
+
+impl Supervisor {
+    async fn run(mut self, mut stop: oneshot::Receiver<()>) {
+        let log_tag = self.log_tag;
+        let mut consecutive_failures = 0;
+        let reason = loop {
+            self.state.send_replace(ConnectionState::Connecting);
+
+            let (finished_tx, finished_rx) = oneshot::channel();
+            let listener = self.forwarding_listener(finished_tx);
+            let connected = tokio::select! {
+                result = (self.connect)(listener) => result,
+                _ = &mut stop => break StopReason::Requested,
+            };
+
+            let next = match connected {
+                Ok(connection) => {
+                    log::info!("[{log_tag}] connected");
+                    consecutive_failures = 0;
+                    let connection = Arc::new(connection);
+                    self.state
+                        .send_replace(ConnectionState::Connected(connection.clone()));
+                    let finished = tokio::select! {
+                        finished = finished_rx => finished,
+                        _ = &mut stop => {
+                            connection.disconnect().await;
+                            break StopReason::Requested;
+                        }
+                    };
+                    match finished {
+                        Ok(DisconnectCause::LocalDisconnect) => {
+                            log::info!("[{log_tag}] disconnected locally; not reconnecting");
+                            Next::Stop(StopReason::Requested)
+                        }
+                        Ok(DisconnectCause::Error(e)) => self.after_disconnect(e),
+                        // The listener was dropped without reporting why.
+                        Err(_) => self.after_disconnect(SendError::Disconnected),
+                    }
+                }
+                Err(e) => {
+                    consecutive_failures += 1;
+                    self.after_connect_failure(e, consecutive_failures)
+                }
+            };
+
+            let delay = match next {
+                Next::Stop(reason) => break reason,
+                Next::Retry { delay } => delay,
+            };
+            let retry_at = Instant::now() + delay;
+            self.state
+                .send_replace(ConnectionState::WaitingToReconnect {
+                    consecutive_failures,
+                    retry_at,
+                });
+            tokio::select! {
+                () = tokio::time::sleep_until(retry_at) => {}
+                () = network_changed(&mut self.network_change_event) => {
+                    log::info!("[{log_tag}] network changed; reconnecting now");
+                    consecutive_failures = 0;
+                }
+                _ = &mut stop => break StopReason::Requested,
+            }
+        };
+
+        log::info!("[{log_tag}] stopped: {reason}");
+        self.state.send_replace(ConnectionState::Stopped(reason));
+    }
+
+    fn after_disconnect(&self, error: SendError) -> Next {
+        let log_tag = self.log_tag;
+        match error {
+            SendError::ConnectedElsewhere => Next::Stop(StopReason::ConnectedElsewhere),
+            SendError::ConnectionInvalidated => Next::Stop(StopReason::ConnectionInvalidated),
+            e @ (SendError::RequestTimedOut
+            | SendError::Disconnected
+            | SendError::WebSocket(_)
+            | SendError::IncomingDataInvalid
+            | SendError::RequestHasInvalidHeader) => {
+                log::info!("[{log_tag}] connection lost: {}", &e as &dyn LogSafeDisplay);
+                // Wait a little before reconnecting, in case the server is dropping connections
+                // as soon as they're made.
+                Next::Retry {
+                    delay: self.backoff(1),
+                }
+            }
+        }
+    }
+
+    fn after_connect_failure(&self, error: ConnectError, consecutive_failures: u32) -> Next {
+        let log_tag = self.log_tag;
+        match error {
+            ConnectError::AppExpired => Next::Stop(StopReason::AppExpired),
+            ConnectError::DeviceDeregistered => Next::Stop(StopReason::DeviceDeregistered),
+            ConnectError::RetryLater(retry_later) => {
+                log::info!(
+                    "[{log_tag}] server asked to retry after {}s",
+                    retry_later.retry_after_seconds
+                );
+                Next::Retry {
+                    delay: Duration::from_secs(retry_later.retry_after_seconds.into()),
+                }
+            }
+            e @ (ConnectError::Timeout
+            | ConnectError::AllAttemptsFailed
+            | ConnectError::InvalidConnectionConfiguration
+            | ConnectError::WebSocket(_)) => {
+                log::warn!(
+                    "[{log_tag}] connect attempt {consecutive_failures} failed: {}",
+                    &e as &dyn LogSafeDisplay
+                );
+                Next::Retry {
+                    delay: self.backoff(consecutive_failures),
+                }
+            }
+        }
+    }
+
+    /// Exponential backoff with "equal jitter": a random delay between half and all of the
+    /// nominal delay, so that many clients failing at once don't all retry at once.
+    fn backoff(&self, consecutive_failures: u32) -> Duration {
+        let exponent = consecutive_failures.saturating_sub(1).min(16);
+        let nominal = self
+            .config
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.config.max_backoff);
+        nominal.mul_f64(rand::random_range(0.5..=1.0))
+    }
+
+    /// Wraps the shared listener for a single connection, splitting off the event that says the
+    /// connection has finished.
+    fn forwarding_listener(
+        &self,
+        finished_tx: oneshot::Sender<DisconnectCause>,
+    ) -> ws::EventListener {
+        let listener = self.listener.clone();
+        let mut finished_tx = Some(finished_tx);
+        Box::new(move |event| match event {
+            ws::ListenerEvent::Finished(reason) => {
+                if let Some(finished_tx) = finished_tx.take() {
+                    // If the supervisor has stopped, nobody needs to know.
+                    _ = finished_tx.send(convert_finished_reason(reason));
+                }
+            }
+            event @ (ws::ListenerEvent::ReceivedAlerts(_)
+            | ws::ListenerEvent::ReceivedMessage(_, _)) => {
+                (listener.lock().expect("not poisoned"))(event)
+            }
+        })
+    }
+}
+
+/// Resolves when the network changes; never resolves if nobody can report changes anymore.
+async fn network_changed(network_change_event: &mut NetworkChangeEvent) {
+    if network_change_event.changed().await.is_err() {
+        std::future::pending::<()>().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use assert_matches::assert_matches;
+    use futures_util::FutureExt as _;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::chat::fake::FakeChatRemote;
+    use crate::env::{CONNECTED_ELSEWHERE_CLOSE_CODE, CONNECTION_INVALIDATED_CLOSE_CODE};
+
+    /// A [`ConnectFn`] that fails with the given errors in turn, then produces fake connections.
+    fn fake_connect(
+        failures: Vec<ConnectError>,
+    ) -> (
+        ConnectFn,
+        tokio::sync::mpsc::UnboundedReceiver<FakeChatRemote>,
+    ) {
+        let (remote_tx, remote_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut failures = VecDeque::from(failures);
+        let connect: ConnectFn = Box::new(move |listener| {
+            let result = match failures.pop_front() {
+                Some(e) => Err(e),
+                None => {
+                    let (chat, remote) =
+                        ChatConnection::new_fake(tokio::runtime::Handle::current(), listener, []);
+                    remote_tx.send(remote).expect("test still running");
+                    Ok(chat)
+                }
+            };
+            std::future::ready(result).boxed()
+        });
+        (connect, remote_rx)
+    }
+
+    fn spawn(connect: ConnectFn, network_change_event: NetworkChangeEvent) -> SupervisedConnection {
+        SupervisedConnection::spawn(
+            &tokio::runtime::Handle::current(),
+            "test",
+            ReconnectConfig::default(),
+            network_change_event,
+            connect,
+            Box::new(|_| {}),
+        )
+    }
+
+    async fn wait_for(
+        state: &mut watch::Receiver<ConnectionState>,
+        mut predicate: impl FnMut(&ConnectionState) -> bool,
+    ) -> ConnectionState {
+        state
+            .wait_for(|s| predicate(s))
+            .await
+            .expect("supervisor still running")
+            .clone()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnects_after_connection_lost() {
+        let (connect, mut remotes) = fake_connect(vec![]);
+        let supervised = spawn(
+            connect,
+            libsignal_net_infra::utils::no_network_change_events(),
+        );
+        let mut state = supervised.state();
+
+        let first = remotes.recv().await.expect("connected");
+        wait_for(&mut state, |s| matches!(s, ConnectionState::Connected(_))).await;
+
+        first.send_close(None).expect("still connected");
+        wait_for(&mut state, |s| {
+            matches!(s, ConnectionState::WaitingToReconnect { .. })
+        })
+        .await;
+
+        let _second = remotes.recv().await.expect("reconnected");
+        wait_for(&mut state, |s| matches!(s, ConnectionState::Connected(_))).await;
+        assert!(supervised.connection().is_some());
+
+        supervised.stop().await;
+        assert_matches!(
+            *state.borrow(),
+            ConnectionState::Stopped(StopReason::Requested)
+        );
+    }
+
+    #[test_case(CONNECTED_ELSEWHERE_CLOSE_CODE, StopReason::ConnectedElsewhere)]
+    #[test_case(CONNECTION_INVALIDATED_CLOSE_CODE, StopReason::ConnectionInvalidated)]
+    #[tokio::test(start_paused = true)]
+    async fn stops_when_server_invalidates_connection(close_code: u16, expected: StopReason) {
+        let (connect, mut remotes) = fake_connect(vec![]);
+        let supervised = spawn(
+            connect,
+            libsignal_net_infra::utils::no_network_change_events(),
+        );
+        let mut state = supervised.state();
+
+        let remote = remotes.recv().await.expect("connected");
+        wait_for(&mut state, |s| matches!(s, ConnectionState::Connected(_))).await;
+        remote
+            .send_close(Some(close_code))
+            .expect("still connected");
+
+        let stopped = wait_for(&mut state, |s| matches!(s, ConnectionState::Stopped(_))).await;
+        assert_matches!(stopped, ConnectionState::Stopped(reason) if reason == expected);
+
+        // No further attempts.
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_matches!(remotes.try_recv(), Err(_));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_between_failed_attempts() {
+        let (connect, mut remotes) = fake_connect(vec![
+            ConnectError::AllAttemptsFailed,
+            ConnectError::Timeout,
+            ConnectError::AllAttemptsFailed,
+        ]);
+        let supervised = spawn(
+            connect,
+            libsignal_net_infra::utils::no_network_change_events(),
+        );
+        let mut state = supervised.state();
+
+        let start = Instant::now();
+        let _remote = remotes.recv().await.expect("eventually connected");
+        // 1s, 2s, and 4s nominal delays, each at least halved by jitter.
+        assert!(start.elapsed() >= Duration::from_millis(3500));
+        assert!(start.elapsed() <= Duration::from_secs(7));
+        wait_for(&mut state, |s| matches!(s, ConnectionState::Connected(_))).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn network_change_skips_backoff() {
+        let (connect, mut remotes) =
+            fake_connect((0..5).map(|_| ConnectError::AllAttemptsFailed).collect());
+        let (network_change_tx, network_change_rx) = watch::channel(());
+        let supervised = spawn(connect, network_change_rx);
+        let mut state = supervised.state();
+
+        // Let the backoff grow, then report a network change.
+        let waiting = wait_for(&mut state, |s| {
+            matches!(
+                s,
+                ConnectionState::WaitingToReconnect {
+                    consecutive_failures: 4,
+                    ..
+                }
+            )
+        })
+        .await;
+        let retry_at = assert_matches!(waiting, ConnectionState::WaitingToReconnect { retry_at, .. } => retry_at);
+        // 8s nominal, at least 4s with jitter.
+        assert!(retry_at >= Instant::now() + Duration::from_secs(4));
+        let network_changed_at = Instant::now();
+        network_change_tx.send_replace(());
+
+        // The fifth attempt happens immediately and fails, then there's at most a 1s delay.
+        let _remote = remotes.recv().await.expect("connected");
+        assert!(Instant::now() <= network_changed_at + Duration::from_secs(1));
+    }
+
+    #[test_case(ConnectError::AppExpired, StopReason::AppExpired)]
+    #[test_case(ConnectError::DeviceDeregistered, StopReason::DeviceDeregistered)]
+    #[tokio::test(start_paused = true)]
+    async fn stops_on_fatal_connect_error(error: ConnectError, expected: StopReason) {
+        let (connect, _remotes) = fake_connect(vec![error]);
+        let supervised = spawn(
+            connect,
+            libsignal_net_infra::utils::no_network_change_events(),
+        );
+        let mut state = supervised.state();
+        let stopped = wait_for(&mut state, |s| matches!(s, ConnectionState::Stopped(_))).await;
+        assert_matches!(stopped, ConnectionState::Stopped(reason) if reason == expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropping_stops_supervisor() {
+        let (connect, mut remotes) = fake_connect(vec![]);
+        let supervised = spawn(
+            connect,
+            libsignal_net_infra::utils::no_network_change_events(),
+        );
+        let mut state = supervised.state();
+        let remote = remotes.recv().await.expect("connected");
+        wait_for(&mut state, |s| matches!(s, ConnectionState::Connected(_))).await;
+
+        drop(supervised);
+        // The connection is closed from the client side.
+        assert_matches!(remote.receive_request().await, Ok(None) | Err(_));
+    }
+}
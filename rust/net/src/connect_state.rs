@@ -20,13 +20,14 @@ use libsignal_net_infra::http_client::HttpConnectError;
 use libsignal_net_infra::route::{
     AttemptOutcome, ComposedConnector, ConnectError, ConnectionOutcomeParams, ConnectionOutcomes,
     ConnectionProxyConfig, Connector, ConnectorFactory, DelayBasedOnTransport, DescribeForLog,
-    DescribedRouteConnector, DirectOrProxy, DirectOrProxyMode, DirectOrProxyRoute, ErrorHandling,
-    HttpRouteFragment, HttpsServiceRoute, InterfaceChangedOr, InterfaceMonitor, LoggingConnector,
-    ResettingConnectionOutcomes, ResolveHostnames, ResolveWithSavedDescription, ResolvedRoute,
-    RouteProvider, RouteProviderContext, RouteProviderExt as _, RouteResolver,
-    StaticTcpTimeoutConnector, ThrottlingConnector, TransportRoute, UnresolvedRouteDescription,
-    UnresolvedTransportRoute, UnresolvedWebsocketServiceRoute, UnsuccessfulOutcome, UsePreconnect,
-    UsesTransport, VariableTlsTimeoutConnector, WebSocketRouteFragment, WebSocketServiceRoute,
+    DescribedRouteConnector, DirectOrProxy, DirectOrProxyMode, DirectOrProxyRoute, EchRetryConfigs,
+    EchRetryConnector, ErrorHandling, HttpRouteFragment, HttpsServiceRoute, InterfaceChangedOr,
+    InterfaceMonitor, LoggingConnector, ResettingConnectionOutcomes, ResolveHostnames,
+    ResolveWithSavedDescription, ResolvedRoute, RouteProvider, RouteProviderContext,
+    RouteProviderExt as _, RouteResolver, SavedConnectionOutcomes, StaticTcpTimeoutConnector,
+    ThrottlingConnector, TransportRoute, UnresolvedRouteDescription, UnresolvedTransportRoute,
+    UnresolvedWebsocketServiceRoute, UnsuccessfulOutcome, UsePreconnect, UsesTransport,
+    VariableTlsTimeoutConnector, WebSocketRouteFragment, WebSocketServiceRoute,
 };
 use libsignal_net_infra::tcp_ssl::{LONG_TCP_HANDSHAKE_THRESHOLD, LONG_TLS_HANDSHAKE_THRESHOLD};
 use libsignal_net_infra::timeouts::{
@@ -110,14 +111,16 @@ pub struct ConnectState<ConnectorFactory = DefaultConnectorFactory> {
     route_provider_context: RouteProviderContextImpl,
 }
 
-pub type DefaultTransportConnector = VariableTlsTimeoutConnector<
-    ThrottlingConnector<LoggingConnector<crate::infra::tcp_ssl::StatelessTls>>,
-    crate::infra::route::DirectOrProxy<
-        LoggingConnector<StaticTcpTimeoutConnector<crate::infra::tcp_ssl::StatelessTcp>>,
-        crate::infra::tcp_ssl::proxy::StatelessProxied,
+pub type DefaultTransportConnector = EchRetryConnector<
+    VariableTlsTimeoutConnector<
+        ThrottlingConnector<LoggingConnector<crate::infra::tcp_ssl::StatelessTls>>,
+        crate::infra::route::DirectOrProxy<
+            LoggingConnector<StaticTcpTimeoutConnector<crate::infra::tcp_ssl::StatelessTcp>>,
+            crate::infra::tcp_ssl::proxy::StatelessProxied,
+            TransportConnectError,
+        >,
         TransportConnectError,
     >,
-    TransportConnectError,
 >;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -135,7 +138,15 @@ pub struct ConnectionResources<'a, TC> {
     pub confirmation_header_name: Option<HeaderName>,
 }
 
-pub struct DefaultConnectorFactory;
+/// Makes the standard TCP+TLS connector stack.
+///
+/// Connectors made by the same factory share what they learn about servers'
+/// ECH configs.
+#[derive(Debug, Default)]
+pub struct DefaultConnectorFactory {
+    ech_retry_configs: Arc<EchRetryConfigs>,
+}
+
 impl<R> ConnectorFactory<R> for DefaultConnectorFactory
 where
     DefaultTransportConnector: Connector<R, ()>,
@@ -157,17 +168,20 @@ where
             // Proxy connectors use LoggingConnector internally
             Default::default(),
         );
-        VariableTlsTimeoutConnector::new(
-            throttle_tls_connections,
-            proxy_or_direct_connector,
-            MIN_TLS_HANDSHAKE_TIMEOUT,
+        EchRetryConnector::with_retry_configs(
+            VariableTlsTimeoutConnector::new(
+                throttle_tls_connections,
+                proxy_or_direct_connector,
+                MIN_TLS_HANDSHAKE_TIMEOUT,
+            ),
+            Arc::clone(&self.ech_retry_configs),
         )
     }
 }
 
 impl ConnectState {
     pub fn new(config: Config) -> std::sync::Mutex<Self> {
-        Self::new_with_transport_connector(config, DefaultConnectorFactory)
+        Self::new_with_transport_connector(config, DefaultConnectorFactory::default())
     }
 }
 
@@ -197,6 +211,21 @@ impl<ConnectorFactory> ConnectState<ConnectorFactory> {
     pub fn network_changed(&mut self, network_change_time: Instant) {
         self.attempts_record.reset(network_change_time);
     }
+
+    /// Captures which routes have recently failed, so that the app can persist the history and
+    /// pass it to [`Self::restore_route_outcomes`] on its next launch.
+    pub fn save_route_outcomes(&self) -> SavedConnectionOutcomes {
+        self.attempts_record.save()
+    }
+
+    /// Seeds the route history with outcomes saved by a previous process.
+    ///
+    /// This lets routes that were failing (for example, on a censored network) be deprioritized
+    /// right away, instead of each cold start having to rediscover that they're slow to fail.
+    pub fn restore_route_outcomes(&mut self, saved: &SavedConnectionOutcomes) {
+        self.attempts_record
+            .restore(saved, Instant::now(), SystemTime::now());
+    }
 }
 
 #[non_exhaustive]
@@ -835,6 +864,7 @@ mod test {
             sni: Host::Domain("fake-sni".into()),
             alpn: Some(Alpn::Http1_1),
             min_protocol_version: Some(boring_signal::ssl::SslVersion::TLS1_3),
+            ech_config_list: None,
         },
         inner: DirectOrProxyRoute::Direct(TcpRoute {
             address: UnresolvedHost::from(Arc::from(FAKE_HOST_NAME)),
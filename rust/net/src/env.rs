@@ -17,8 +17,8 @@ use libsignal_net_infra::certs::RootCertificates;
 use libsignal_net_infra::dns::lookup_result::LookupResult;
 use libsignal_net_infra::host::Host;
 use libsignal_net_infra::route::{
-    DirectTcpRouteProvider, DomainFrontConfig, DomainFrontRouteProvider, HttpVersion,
-    HttpsProvider, TlsRouteProvider,
+    DirectTcpRouteProvider, DomainFrontConfig, DomainFrontRouteProvider, EchConfigList,
+    HttpVersion, HttpsProvider, TlsRouteProvider,
 };
 use libsignal_net_infra::{
     AsStaticHttpHeader, ConnectionParams, EnableDomainFronting, EnforceMinimumTls,
@@ -52,6 +52,7 @@ const DOMAIN_CONFIG_CHAT: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: Some(TIMESTAMP_HEADER_NAME),
         proxy: Some(ConnectionProxyConfig {
@@ -75,6 +76,7 @@ const DOMAIN_CONFIG_CHAT_STAGING: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: Some(TIMESTAMP_HEADER_NAME),
         proxy: Some(ConnectionProxyConfig {
@@ -90,6 +92,7 @@ const DOMAIN_CONFIG_CDSI: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
@@ -107,6 +110,7 @@ const DOMAIN_CONFIG_CDSI_STAGING: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
@@ -124,6 +128,7 @@ const DOMAIN_CONFIG_SVR2: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
@@ -157,6 +162,7 @@ const DOMAIN_CONFIG_SVR2_STAGING: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
@@ -186,6 +192,7 @@ const DOMAIN_CONFIG_SVRB_STAGING: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
@@ -215,6 +222,7 @@ const DOMAIN_CONFIG_SVRB_PROD: DomainConfig = DomainConfig {
         port: DEFAULT_HTTPS_PORT,
         cert: SIGNAL_ROOT_CERTIFICATES,
         min_tls_version: Some(SslVersion::TLS1_3),
+        ech_config_list: None,
         http_version: Some(HttpVersion::Http1_1),
         confirmation_header_name: None,
         proxy: Some(ConnectionProxyConfig {
@@ -360,6 +368,12 @@ pub struct ConnectionConfig {
     pub cert: RootCertificates,
     /// Which minimum version of TLS to require when connecting to the resource.
     pub min_tls_version: Option<SslVersion>,
+    /// A serialized `ECHConfigList` to use when connecting to the resource directly.
+    ///
+    /// This hides the hostname from network observers, who will instead see the public name
+    /// from the config. If the server has rotated its keys, it will provide new ones during the
+    /// handshake, so this doesn't need to be kept perfectly up to date.
+    pub ech_config_list: Option<&'static [u8]>,
     /// Which version of HTTP to expect when connecting to the resource.
     ///
     /// This may be `None` for a non-HTTP resource.
@@ -480,6 +494,7 @@ impl ConnectionConfig {
             port,
             cert,
             min_tls_version,
+            ech_config_list,
             http_version,
             confirmation_header_name: _,
             proxy,
@@ -537,7 +552,8 @@ impl ConnectionConfig {
                 *min_tls_version,
                 Host::Domain(Arc::clone(&hostname)),
                 direct_tcp_provider,
-            ),
+            )
+            .with_ech_config_list(ech_config_list.map(EchConfigList::from)),
         )
     }
 
@@ -885,6 +901,7 @@ mod test {
             port: PORT,
             cert: RootCertificates::Native,
             min_tls_version: Some(SslVersion::TLS1_2),
+            ech_config_list: None,
             http_version: Some(HttpVersion::Http1_1),
             confirmation_header_name: None,
             proxy: Some(ConnectionProxyConfig {
@@ -928,6 +945,7 @@ mod test {
                     sni: Host::Domain("host".into()),
                     alpn: Some(Alpn::Http1_1),
                     min_protocol_version: Some(SslVersion::TLS1_2),
+                    ech_config_list: None,
                 },
                 inner: TcpRoute {
                     address: UnresolvedHost::from(Arc::from("host")),
@@ -974,6 +992,7 @@ mod test {
             .resolve(DnsLookupRequest {
                 hostname: Arc::from(hostname),
                 ipv6_enabled: true,
+                service_bindings: false,
             })
             .await
             .unwrap_or_else(|_| panic!("Unable to resolve {hostname}"))
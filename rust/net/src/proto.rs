@@ -4,5 +4,6 @@
 //
 
 pub(crate) mod cds2;
+pub mod chat_envelope;
 pub(crate) mod chat_provisioning;
 pub mod chat_websocket;
@@ -14,6 +14,7 @@ use libsignal_net_infra::dns::build_custom_resolver_cloudflare_doh;
 use libsignal_net_infra::dns::custom_resolver::CustomDnsResolver;
 use libsignal_net_infra::dns::dns_lookup::{DnsLookup, DnsLookupRequest, SystemDnsLookup};
 use libsignal_net_infra::dns::dns_transport_udp::UdpTransportConnectorFactory;
+use libsignal_net_infra::dns::dnssec::TrustAnchor;
 use libsignal_net_infra::route::UdpRoute;
 use libsignal_net_infra::timeouts::DNS_LATER_RESPONSE_GRACE_PERIOD;
 use libsignal_net_infra::utils::no_network_change_events;
@@ -35,6 +36,7 @@ async fn system_dns_lookup() {
         .dns_lookup(DnsLookupRequest {
             hostname: "localhost".into(),
             ipv6_enabled: true,
+            service_bindings: false,
         })
         .await
         .expect("can look up");
@@ -55,7 +57,7 @@ async fn udp_dns_lookup() {
             address: ip_addr!("1.1.1.1"),
             port: nonzero!(53u16),
         }],
-        UdpTransportConnectorFactory,
+        UdpTransportConnectorFactory::default(),
         &no_network_change_events(),
         DNS_LATER_RESPONSE_GRACE_PERIOD,
     );
@@ -64,6 +66,7 @@ async fn udp_dns_lookup() {
         .resolve(DnsLookupRequest {
             hostname: "signal.org".into(),
             ipv6_enabled: true,
+            service_bindings: false,
         })
         .await
         .expect("can look up");
@@ -74,6 +77,33 @@ async fn udp_dns_lookup() {
     assert!(!v6.is_empty());
 }
 
+#[tokio::test]
+async fn udp_dnssec_lookup() {
+    skip_unless_nonhermetic!();
+    let dns = CustomDnsResolver::new(
+        vec![UdpRoute {
+            address: ip_addr!("1.1.1.1"),
+            port: nonzero!(53u16),
+        }],
+        UdpTransportConnectorFactory::validating(TrustAnchor::iana_root()),
+        &no_network_change_events(),
+        DNS_LATER_RESPONSE_GRACE_PERIOD,
+    );
+
+    // Use a name in a zone that is known to be signed.
+    let result = dns
+        .resolve(DnsLookupRequest {
+            hostname: "cloudflare.com".into(),
+            ipv6_enabled: true,
+            service_bindings: false,
+        })
+        .await
+        .expect("can look up");
+
+    println!("found {result:?}");
+    assert!(result.iter().next().is_some());
+}
+
 #[tokio::test]
 async fn dns_over_https_lookup() {
     skip_unless_nonhermetic!();
@@ -88,6 +118,7 @@ async fn dns_over_https_lookup() {
         .resolve(DnsLookupRequest {
             hostname: "signal.org".into(),
             ipv6_enabled: true,
+            service_bindings: false,
         })
         .await
         .expect("can look up");
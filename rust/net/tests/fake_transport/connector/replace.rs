@@ -4,8 +4,8 @@
 //
 
 use libsignal_net_infra::route::{
-    ComposedConnector, DirectOrProxy, LoggingConnector, StaticTcpTimeoutConnector,
-    ThrottlingConnector, VariableTlsTimeoutConnector,
+    ComposedConnector, DirectOrProxy, EchRetryConnector, LoggingConnector,
+    StaticTcpTimeoutConnector, ThrottlingConnector, VariableTlsTimeoutConnector,
 };
 
 use super::FakeTransportConnector;
@@ -53,6 +53,16 @@ where
     }
 }
 
+impl<C: ReplaceStatelessConnectorsWithFake> ReplaceStatelessConnectorsWithFake
+    for EchRetryConnector<C>
+{
+    type Replacement = EchRetryConnector<C::Replacement>;
+
+    fn replace_with_fake(self, fake: FakeTransportConnector) -> Self::Replacement {
+        EchRetryConnector::new(self.into_inner().replace_with_fake(fake))
+    }
+}
+
 impl<D, P, E> ReplaceStatelessConnectorsWithFake for DirectOrProxy<D, P, E>
 where
     D: ReplaceStatelessConnectorsWithFake,
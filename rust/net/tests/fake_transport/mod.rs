@@ -64,6 +64,7 @@ pub fn only_direct_routes(
                 hostname,
                 cert: _,
                 min_tls_version: _,
+                ech_config_list: _,
                 http_version: _,
                 confirmation_header_name: _,
                 proxy: _,
@@ -110,6 +111,7 @@ pub fn allow_domain_fronting(
                 hostname: _,
                 cert: _,
                 min_tls_version: _,
+                ech_config_list: _,
                 http_version: _,
                 confirmation_header_name: _,
                 proxy,
@@ -177,8 +179,10 @@ impl FakeDeps {
     ) -> (Self, UnboundedReceiverStream<FakeTargetAndStream>) {
         let (transport_connector, incoming_streams) = FakeTransportConnector::new([]);
 
-        let connector_factory =
-            ReplacingConnectorFactory(transport_connector.clone(), DefaultConnectorFactory);
+        let connector_factory = ReplacingConnectorFactory(
+            transport_connector.clone(),
+            DefaultConnectorFactory::default(),
+        );
         let connect_state =
             ConnectState::new_with_transport_connector(SUGGESTED_CONNECT_CONFIG, connector_factory);
         let resolved_names = fake_ips_for_names(chat_domain_config);
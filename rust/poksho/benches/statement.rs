@@ -0,0 +1,95 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+#![allow(non_snake_case)]
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use poksho::{PointArgs, ScalarArgs, Statement};
+
+const GENERATOR_COUNT: u64 = 5;
+
+/// A statement shaped like a credential issuance proof: one equation over system generators `G_i`,
+/// and one over per-proof points `M_i`.
+fn statement() -> Statement {
+    let names: Vec<_> = (0..GENERATOR_COUNT)
+        .map(|i| (format!("x{i}"), format!("G_{i}"), format!("M_{i}")))
+        .collect();
+    let mut st = Statement::new();
+    st.add(
+        "A",
+        &names
+            .iter()
+            .map(|(x, G, _)| (x.as_str(), G.as_str()))
+            .collect::<Vec<_>>(),
+    );
+    st.add(
+        "B",
+        &names
+            .iter()
+            .map(|(x, _, M)| (x.as_str(), M.as_str()))
+            .collect::<Vec<_>>(),
+    );
+    st
+}
+
+fn args() -> (ScalarArgs, PointArgs, PointArgs) {
+    let mut scalar_args = ScalarArgs::new();
+    let mut fixed_args = PointArgs::new();
+    let mut dynamic_args = PointArgs::new();
+    let mut A = RistrettoPoint::identity();
+    let mut B = RistrettoPoint::identity();
+    for i in 0..GENERATOR_COUNT {
+        let x = Scalar::from(i + 2);
+        let G = Scalar::from(i + 100) * RISTRETTO_BASEPOINT_POINT;
+        let M = Scalar::from(i + 200) * RISTRETTO_BASEPOINT_POINT;
+        scalar_args.add(format!("x{i}"), x);
+        fixed_args.add(format!("G_{i}"), G);
+        dynamic_args.add(format!("M_{i}"), M);
+        A += x * G;
+        B += x * M;
+    }
+    dynamic_args.add("A", A);
+    dynamic_args.add("B", B);
+    (scalar_args, fixed_args, dynamic_args)
+}
+
+fn bench_statement(c: &mut Criterion) {
+    let (scalar_args, fixed_args, dynamic_args) = args();
+    let mut all_args = dynamic_args.clone();
+    all_args.0.extend(fixed_args.0.clone());
+
+    let uncompiled = statement();
+    let compiled = statement().compile(&fixed_args).expect("valid");
+    let randomness = [0x42; 32];
+    let proof = uncompiled
+        .prove(&scalar_args, &all_args, b"", &randomness)
+        .expect("valid");
+
+    let mut group = c.benchmark_group("Statement");
+    group.bench_function("prove", |b| {
+        b.iter(|| uncompiled.prove(&scalar_args, &all_args, b"", &randomness))
+    });
+    group.bench_function("verify_proof", |b| {
+        b.iter(|| uncompiled.verify_proof(&proof, &all_args, b""))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("CompiledStatement");
+    group.bench_function("compile", |b| b.iter(|| statement().compile(&fixed_args)));
+    group.bench_function("prove", |b| {
+        b.iter(|| compiled.prove(&scalar_args, &dynamic_args, b"", &randomness))
+    });
+    group.bench_function("verify_proof", |b| {
+        b.iter(|| compiled.verify_proof(&proof, &dynamic_args, b""))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_statement);
+criterion_main!(benches);
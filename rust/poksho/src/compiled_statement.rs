@@ -0,0 +1,393 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A [`Statement`] prepared once for repeated proving and verification.
+//!
+//! Building a `Statement` and looking up its arguments by name is cheap compared to the curve
+//! arithmetic, but the arithmetic itself can be sped up when some of the points never change, such
+//! as generators from a set of system parameters. [`Statement::compile`] takes those points up front
+//! and precomputes:
+//!
+//! - the transcript state after absorbing the statement description,
+//! - the encodings of the fixed points,
+//! - a constant-time multiplication table for each fixed point, for proving, and
+//! - a variable-time multiscalar precomputation for each equation's fixed points, for verifying.
+//!
+//! The resulting proofs are byte-for-byte identical to the ones produced by the original
+//! `Statement`, so either one can verify proofs made by the other.
+
+use PokshoError::*;
+use curve25519_dalek::constants::{RISTRETTO_BASEPOINT_POINT, RISTRETTO_BASEPOINT_TABLE};
+use curve25519_dalek::ristretto::{
+    CompressedRistretto, RistrettoBasepointTable, RistrettoPoint, VartimeRistrettoPrecomputation,
+};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{MultiscalarMul, VartimePrecomputedMultiscalarMul};
+
+use crate::args::*;
+use crate::errors::*;
+use crate::proof::*;
+use crate::shoapi::ShoApi;
+use crate::shohmacsha256::ShoHmacSha256;
+use crate::simple_types::*;
+use crate::statement::{
+    Equation, PointIndex, Statement, Term, check_challenge, prove_with_transcript,
+};
+
+struct FixedPoint {
+    point: RistrettoPoint,
+    compressed: CompressedRistretto,
+    // Only present for points that appear on the right-hand side of some equation, since those are
+    // the only ones multiplied while proving. The base point uses the library's own table instead.
+    table: Option<Box<RistrettoBasepointTable>>,
+}
+
+struct CompiledEquation {
+    lhs: PointIndex,
+    fixed_terms: Vec<Term>,
+    dynamic_terms: Vec<Term>,
+    // The points of fixed_terms, followed by the left-hand side if that's fixed too
+    precomputation: VartimeRistrettoPrecomputation,
+}
+
+/// A [`Statement`] with some of its points fixed, ready to be used many times.
+///
+/// Created with [`Statement::compile`].
+pub struct CompiledStatement {
+    statement: Statement,
+    // Indexed by point index; None for points that are passed in with each proof
+    fixed_points: Vec<Option<FixedPoint>>,
+    equations: Vec<CompiledEquation>,
+    // The protocol label L and statement description D, already absorbed
+    description: ShoHmacSha256,
+}
+
+impl Statement {
+    /// Prepares this statement for repeated use with the given points fixed.
+    ///
+    /// The base point `G` is always fixed and should not be included in `fixed_points`. Any other
+    /// points in the statement must be passed in each time a proof is created or verified.
+    pub fn compile(self, fixed_points: &PointArgs) -> Result<CompiledStatement, PokshoError> {
+        let mut fixed: Vec<Option<RistrettoPoint>> = vec![None; self.point_vec.len()];
+        fixed[0] = Some(RISTRETTO_BASEPOINT_POINT);
+        for (name, point) in &fixed_points.0 {
+            match self.point_map.get(name) {
+                Some(&index) if index != 0 => fixed[usize::from(index)] = Some(*point),
+                _ => return Err(BadArgs),
+            }
+        }
+
+        let is_multiplied = |index: usize| {
+            self.equations
+                .iter()
+                .flat_map(|e| &e.rhs)
+                .any(|term| usize::from(term.point) == index)
+        };
+        let fixed_points = fixed
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                point.map(|point| FixedPoint {
+                    point,
+                    compressed: point.compress(),
+                    table: (index != 0 && is_multiplied(index))
+                        .then(|| Box::new(RistrettoBasepointTable::create(&point))),
+                })
+            })
+            .collect();
+
+        let equations = self
+            .equations
+            .iter()
+            .map(|Equation { lhs, rhs }| {
+                let (fixed_terms, dynamic_terms): (Vec<Term>, Vec<Term>) = rhs
+                    .iter()
+                    .partition(|term| fixed[usize::from(term.point)].is_some());
+                let static_points = fixed_terms
+                    .iter()
+                    .map(|term| term.point)
+                    .chain(Some(*lhs).filter(|lhs| fixed[usize::from(*lhs)].is_some()))
+                    .map(|index| fixed[usize::from(index)].expect("fixed"));
+                CompiledEquation {
+                    lhs: *lhs,
+                    fixed_terms,
+                    dynamic_terms,
+                    precomputation: VartimeRistrettoPrecomputation::new(static_points),
+                }
+            })
+            .collect();
+
+        let description = self.absorb_description();
+        Ok(CompiledStatement {
+            statement: self,
+            fixed_points,
+            equations,
+            description,
+        })
+    }
+}
+
+impl CompiledStatement {
+    /// Like [`Statement::prove`], but `point_args` must contain only the points that weren't fixed
+    /// at compile time.
+    pub fn prove(
+        &self,
+        scalar_args: &ScalarArgs,
+        point_args: &PointArgs,
+        message: &[u8],
+        randomness: &[u8], // must be 32 bytes
+    ) -> Result<Vec<u8>, PokshoError> {
+        if randomness.len() != 32 {
+            return Err(BadArgs);
+        }
+        let g1 = self.statement.sort_scalars(scalar_args)?;
+        let all_points = self.sort_points(point_args)?;
+        let sho = self.absorb_points(&all_points);
+
+        let proof = prove_with_transcript(sho, g1, message, randomness, |nonce| {
+            self.homomorphism(nonce, &all_points)
+        });
+
+        // Verify before returning, since a bad proof could indicate
+        // a glitched/faulty response that leaks private keys, or incorrect inputs
+        let proof_bytes = proof.to_bytes();
+        match self.verify_proof(&proof_bytes, point_args, message) {
+            Err(VerificationFailure) => Err(ProofCreationVerificationFailure),
+            Err(e) => Err(e),
+            Ok(_) => Ok(proof_bytes),
+        }
+    }
+
+    /// Like [`Statement::verify_proof`], but `point_args` must contain only the points that weren't
+    /// fixed at compile time.
+    pub fn verify_proof(
+        &self,
+        proof_bytes: &[u8],
+        point_args: &PointArgs,
+        message: &[u8],
+    ) -> Result<(), PokshoError> {
+        let proof = Proof::from_slice(proof_bytes).ok_or(VerificationFailure)?;
+        if proof.response.len() != self.statement.scalar_vec.len() {
+            return Err(VerificationFailure);
+        }
+        let all_points = self.sort_points(point_args)?;
+        let sho = self.absorb_points(&all_points);
+
+        // commitment R = F(s) - h*A; see Statement::verify_proof
+        let commitment = self.vartime_homomorphism_with_subtraction(
+            &proof.response,
+            &all_points,
+            proof.challenge,
+        );
+
+        check_challenge(sho, &proof, &commitment, message)
+    }
+
+    // Absorbs the point values A, reusing the encodings of fixed points
+    fn absorb_points(&self, all_points: &[RistrettoPoint]) -> ShoHmacSha256 {
+        let mut sho = self.description.clone();
+        for (point, fixed) in all_points.iter().zip(&self.fixed_points) {
+            match fixed {
+                Some(fixed) => sho.absorb(fixed.compressed.as_bytes()),
+                None => sho.absorb(&point.compress().to_bytes()),
+            }
+        }
+        sho.ratchet();
+        sho
+    }
+
+    // Applies the homomorphism from G1 -> G2 in constant time, using the tables for fixed points
+    fn homomorphism(&self, g1: &[Scalar], all_points: &[RistrettoPoint]) -> G2 {
+        self.equations
+            .iter()
+            .map(|e| {
+                let fixed_sum: RistrettoPoint = e
+                    .fixed_terms
+                    .iter()
+                    .map(|Term { scalar, point }| {
+                        let table = match &self.fixed_points[usize::from(*point)] {
+                            Some(FixedPoint {
+                                table: Some(table), ..
+                            }) => &**table,
+                            // Every other fixed point that gets multiplied has its own table
+                            _ => RISTRETTO_BASEPOINT_TABLE,
+                        };
+                        table * &g1[usize::from(*scalar)]
+                    })
+                    .sum();
+                let dynamic_sum = RistrettoPoint::multiscalar_mul(
+                    e.dynamic_terms
+                        .iter()
+                        .map(|term| g1[usize::from(term.scalar)]),
+                    e.dynamic_terms
+                        .iter()
+                        .map(|term| all_points[usize::from(term.point)]),
+                );
+                fixed_sum + dynamic_sum
+            })
+            .collect()
+    }
+
+    // Recovers the Schnorr commitment from a proof, using the precomputations for fixed points
+    fn vartime_homomorphism_with_subtraction(
+        &self,
+        g1: &[Scalar],
+        all_points: &[RistrettoPoint],
+        challenge: Scalar,
+    ) -> G2 {
+        self.equations
+            .iter()
+            .map(|e| {
+                let lhs_is_fixed = self.fixed_points[usize::from(e.lhs)].is_some();
+                let (fixed_lhs, dynamic_lhs) = if lhs_is_fixed {
+                    (Some(-challenge), None)
+                } else {
+                    (None, Some((-challenge, all_points[usize::from(e.lhs)])))
+                };
+                let (dynamic_lhs_scalar, dynamic_lhs_point) = dynamic_lhs.unzip();
+                e.precomputation.vartime_mixed_multiscalar_mul(
+                    e.fixed_terms
+                        .iter()
+                        .map(|term| g1[usize::from(term.scalar)])
+                        .chain(fixed_lhs),
+                    e.dynamic_terms
+                        .iter()
+                        .map(|term| g1[usize::from(term.scalar)])
+                        .chain(dynamic_lhs_scalar),
+                    e.dynamic_terms
+                        .iter()
+                        .map(|term| all_points[usize::from(term.point)])
+                        .chain(dynamic_lhs_point),
+                )
+            })
+            .collect()
+    }
+
+    // Combines the fixed points with the ones passed in, in point index order
+    fn sort_points(&self, point_args: &PointArgs) -> Result<Vec<RistrettoPoint>, PokshoError> {
+        let dynamic_count = self.fixed_points.iter().filter(|p| p.is_none()).count();
+        if point_args.0.len() != dynamic_count {
+            return Err(BadArgsWrongNumberOfPointArgs);
+        }
+        self.statement
+            .point_vec
+            .iter()
+            .zip(&self.fixed_points)
+            .map(|(point_name, fixed)| match fixed {
+                Some(fixed) => Ok(fixed.point),
+                None => point_args
+                    .0
+                    .get(point_name)
+                    .copied()
+                    .ok_or(BadArgsMissingPointArg),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+
+    use super::*;
+
+    fn statement() -> Statement {
+        let mut st = Statement::new();
+        st.add("A", &[("a", "G"), ("b", "H")]);
+        st.add("B", &[("a", "H"), ("c", "I")]);
+        st.add("H", &[("d", "G")]);
+        st
+    }
+
+    fn args() -> (ScalarArgs, PointArgs, PointArgs) {
+        let a = Scalar::from(3u8);
+        let b = Scalar::from(5u8);
+        let c = Scalar::from(7u8);
+        let d = Scalar::from(11u8);
+        let G = RISTRETTO_BASEPOINT_POINT;
+        let H = d * G;
+        let I = Scalar::from(13u8) * G;
+
+        let mut scalar_args = ScalarArgs::new();
+        scalar_args.add("a", a);
+        scalar_args.add("b", b);
+        scalar_args.add("c", c);
+        scalar_args.add("d", d);
+
+        let mut fixed_args = PointArgs::new();
+        fixed_args.add("H", H);
+        fixed_args.add("B", a * H + c * I);
+
+        let mut dynamic_args = PointArgs::new();
+        dynamic_args.add("A", a * G + b * H);
+        dynamic_args.add("I", I);
+
+        (scalar_args, fixed_args, dynamic_args)
+    }
+
+    #[test]
+    fn test_matches_statement() {
+        let (scalar_args, fixed_args, dynamic_args) = args();
+        let mut all_args = dynamic_args.clone();
+        all_args.0.extend(fixed_args.0.clone());
+        let randomness = [0x42; 32];
+
+        let compiled = statement()
+            .compile(&fixed_args)
+            .expect("valid fixed points");
+        let proof = compiled
+            .prove(&scalar_args, &dynamic_args, b"message", &randomness)
+            .expect("valid proof");
+        let expected = statement()
+            .prove(&scalar_args, &all_args, b"message", &randomness)
+            .expect("valid proof");
+        assert_eq!(proof, expected);
+
+        compiled
+            .verify_proof(&proof, &dynamic_args, b"message")
+            .expect("valid");
+        statement()
+            .verify_proof(&proof, &all_args, b"message")
+            .expect("valid");
+        assert!(matches!(
+            compiled.verify_proof(&proof, &dynamic_args, b"different"),
+            Err(VerificationFailure)
+        ));
+
+        let mut wrong_args = dynamic_args.clone();
+        wrong_args.add("I", RISTRETTO_BASEPOINT_POINT);
+        assert!(matches!(
+            compiled.verify_proof(&proof, &wrong_args, b"message"),
+            Err(VerificationFailure)
+        ));
+    }
+
+    #[test]
+    fn test_bad_args() {
+        let (_, fixed_args, dynamic_args) = args();
+
+        let mut unknown = fixed_args.clone();
+        unknown.add("J", RISTRETTO_BASEPOINT_POINT);
+        assert!(matches!(statement().compile(&unknown), Err(BadArgs)));
+        let mut base_point = fixed_args.clone();
+        base_point.add("G", RISTRETTO_BASEPOINT_POINT);
+        assert!(matches!(statement().compile(&base_point), Err(BadArgs)));
+
+        let compiled = statement()
+            .compile(&fixed_args)
+            .expect("valid fixed points");
+        let proof = [0u8; 5 * 32];
+        assert!(matches!(
+            compiled.verify_proof(&proof, &fixed_args, b""),
+            Err(BadArgsMissingPointArg)
+        ));
+        let mut extra = dynamic_args.clone();
+        extra.add("H", RISTRETTO_BASEPOINT_POINT);
+        assert!(matches!(
+            compiled.verify_proof(&proof, &extra, b""),
+            Err(BadArgsWrongNumberOfPointArgs)
+        ));
+    }
+}
@@ -6,6 +6,7 @@
 #![warn(clippy::unwrap_used)]
 
 pub mod args;
+pub mod compiled_statement;
 pub mod errors;
 pub mod proof;
 pub mod scalar;
@@ -17,6 +18,7 @@ mod simple_types;
 pub mod statement;
 
 pub use args::{PointArgs, ScalarArgs};
+pub use compiled_statement::CompiledStatement;
 pub use errors::PokshoError;
 pub use proof::Proof;
 #[allow(deprecated)]
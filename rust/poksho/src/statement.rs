@@ -98,17 +98,18 @@ use crate::shoapi::{ShoApi, ShoApiExt as _};
 use crate::shohmacsha256::ShoHmacSha256;
 use crate::simple_types::*;
 
-type ScalarIndex = u8;
-type PointIndex = u8;
+pub(crate) type ScalarIndex = u8;
+pub(crate) type PointIndex = u8;
 
-struct Term {
-    scalar: ScalarIndex,
-    point: PointIndex,
+#[derive(Clone, Copy)]
+pub(crate) struct Term {
+    pub(crate) scalar: ScalarIndex,
+    pub(crate) point: PointIndex,
 }
 
-struct Equation {
-    lhs: PointIndex,
-    rhs: Vec<Term>,
+pub(crate) struct Equation {
+    pub(crate) lhs: PointIndex,
+    pub(crate) rhs: Vec<Term>,
 }
 
 pub struct Statement {
@@ -121,11 +122,11 @@ pub struct Statement {
     // the reverse map.  The former map is used when adding new equations,
     // and the latter is used when instantiating these indices with
     // concrete values.
-    equations: Vec<Equation>,
+    pub(crate) equations: Vec<Equation>,
     scalar_map: HashMap<Cow<'static, str>, ScalarIndex>,
-    scalar_vec: Vec<Cow<'static, str>>,
-    point_map: HashMap<Cow<'static, str>, PointIndex>,
-    point_vec: Vec<Cow<'static, str>>,
+    pub(crate) scalar_vec: Vec<Cow<'static, str>>,
+    pub(crate) point_map: HashMap<Cow<'static, str>, PointIndex>,
+    pub(crate) point_vec: Vec<Cow<'static, str>>,
 }
 
 impl Statement {
@@ -184,8 +185,7 @@ impl Statement {
         let all_points = self.sort_points(point_args)?;
 
         // Absorb the protocol label L, description of statement D, and point values A
-        let mut sho = ShoHmacSha256::new(b"POKSHO_Ristretto_SHOHMACSHA256"); // L
-        sho.absorb(&self.to_bytes()); // D
+        let mut sho = self.absorb_description(); // L, D
         for point in &all_points {
             // A
             sho.absorb(&point.compress().to_bytes());
@@ -193,48 +193,9 @@ impl Statement {
 
         sho.ratchet(); // Ratchet
 
-        // Random nonce
-        // "Synthetic" nonce based on hashing randomness, witness (private scalars) and message
-        let mut sho2 = sho.clone();
-        sho2.absorb(randomness); // Z
-        for scalar in &g1 {
-            sho2.absorb(&scalar.to_bytes()); // a
-        }
-        sho2.ratchet(); // Ratchet
-        sho2.absorb_and_ratchet(message); // M
-        let blinding_scalar_bytes = sho2.squeeze_and_ratchet(g1.len() * 64);
-
-        // TODO use array_chunks once that's stabilized.
-        // See https://github.com/rust-lang/rust/issues/74985.
-        let nonce: G1 = blinding_scalar_bytes
-            .chunks_exact(64)
-            .map(|chunk| {
-                let chunk = chunk.try_into().expect("correct width");
-                Scalar::from_bytes_mod_order_wide(chunk)
-            })
-            .collect();
-
-        // Commitment from nonce by applying homomorphism F: commitment = F(nonce)
-        let commitment = self.homomorphism_with_subtraction(&nonce, &all_points, None);
-
-        // Challenge from commitment and message
-        for point in &commitment {
-            sho.absorb(&point.compress().to_bytes());
-        }
-        sho.absorb_and_ratchet(message);
-        let challenge = Scalar::from_bytes_mod_order_wide(&sho.squeeze_and_ratchet_as_array());
-
-        // Response
-        let response = nonce
-            .into_iter()
-            .zip(g1)
-            .map(|(nonce, g1)| nonce + (g1 * challenge))
-            .collect();
-
-        let proof = Proof {
-            challenge,
-            response,
-        };
+        let proof = prove_with_transcript(sho, g1, message, randomness, |nonce| {
+            self.homomorphism_with_subtraction(nonce, &all_points, None)
+        });
 
         // Verify before returning, since a bad proof could indicate
         // a glitched/faulty response that leaks private keys, or incorrect inputs
@@ -259,8 +220,7 @@ impl Statement {
         let all_points = self.sort_points(point_args)?;
 
         // Absorb the protocol label L, statement description D, and point values A
-        let mut sho = ShoHmacSha256::new(b"POKSHO_Ristretto_SHOHMACSHA256"); // L
-        sho.absorb(&self.to_bytes()); // D
+        let mut sho = self.absorb_description(); // L, D
         for point in &all_points {
             // A
             sho.absorb(&point.compress().to_bytes());
@@ -278,20 +238,14 @@ impl Statement {
         let commitment =
             self.homomorphism_with_subtraction(&proof.response, &all_points, Some(proof.challenge));
 
-        // Reconstruct challenge from commitment and message
-        for point in &commitment {
-            // R
-            sho.absorb(&point.compress().to_bytes());
-        }
-        sho.absorb_and_ratchet(message); // M
-        let challenge = Scalar::from_bytes_mod_order_wide(&sho.squeeze_and_ratchet_as_array());
+        check_challenge(sho, &proof, &commitment, message)
+    }
 
-        // Check challenge (const time)
-        if challenge == proof.challenge {
-            Ok(())
-        } else {
-            Err(VerificationFailure)
-        }
+    // Absorbs the protocol label L and statement description D
+    pub(crate) fn absorb_description(&self) -> ShoHmacSha256 {
+        let mut sho = ShoHmacSha256::new(b"POKSHO_Ristretto_SHOHMACSHA256"); // L
+        sho.absorb(&self.to_bytes()); // D
+        sho
     }
 
     fn add_scalar(
@@ -389,7 +343,7 @@ impl Statement {
             .collect()
     }
 
-    fn sort_scalars(&self, scalar_args: &ScalarArgs) -> Result<G1, PokshoError> {
+    pub(crate) fn sort_scalars(&self, scalar_args: &ScalarArgs) -> Result<G1, PokshoError> {
         if scalar_args.0.len() != self.scalar_vec.len() {
             return Err(BadArgsWrongNumberOfScalarArgs);
         }
@@ -431,6 +385,83 @@ impl Default for Statement {
     }
 }
 
+// Produces a proof of knowledge of the witness g1, given a transcript that has absorbed L, D, and A
+// and been ratcheted, and the homomorphism F (which must not subtract anything)
+pub(crate) fn prove_with_transcript(
+    mut sho: ShoHmacSha256,
+    g1: G1,
+    message: &[u8],
+    randomness: &[u8],
+    homomorphism: impl FnOnce(&[Scalar]) -> G2,
+) -> Proof {
+    // Random nonce
+    // "Synthetic" nonce based on hashing randomness, witness (private scalars) and message
+    let mut sho2 = sho.clone();
+    sho2.absorb(randomness); // Z
+    for scalar in &g1 {
+        sho2.absorb(&scalar.to_bytes()); // a
+    }
+    sho2.ratchet(); // Ratchet
+    sho2.absorb_and_ratchet(message); // M
+    let blinding_scalar_bytes = sho2.squeeze_and_ratchet(g1.len() * 64);
+
+    // TODO use array_chunks once that's stabilized.
+    // See https://github.com/rust-lang/rust/issues/74985.
+    let nonce: G1 = blinding_scalar_bytes
+        .chunks_exact(64)
+        .map(|chunk| {
+            let chunk = chunk.try_into().expect("correct width");
+            Scalar::from_bytes_mod_order_wide(chunk)
+        })
+        .collect();
+
+    // Commitment from nonce by applying homomorphism F: commitment = F(nonce)
+    let commitment = homomorphism(&nonce);
+
+    // Challenge from commitment and message
+    for point in &commitment {
+        sho.absorb(&point.compress().to_bytes());
+    }
+    sho.absorb_and_ratchet(message);
+    let challenge = Scalar::from_bytes_mod_order_wide(&sho.squeeze_and_ratchet_as_array());
+
+    // Response
+    let response = nonce
+        .into_iter()
+        .zip(g1)
+        .map(|(nonce, g1)| nonce + (g1 * challenge))
+        .collect();
+
+    Proof {
+        challenge,
+        response,
+    }
+}
+
+// Checks a proof's challenge against a transcript that has absorbed L, D, and A and been
+// ratcheted, and the commitment reconstructed from the proof
+pub(crate) fn check_challenge(
+    mut sho: ShoHmacSha256,
+    proof: &Proof,
+    commitment: &G2,
+    message: &[u8],
+) -> Result<(), PokshoError> {
+    // Reconstruct challenge from commitment and message
+    for point in commitment {
+        // R
+        sho.absorb(&point.compress().to_bytes());
+    }
+    sho.absorb_and_ratchet(message); // M
+    let challenge = Scalar::from_bytes_mod_order_wide(&sho.squeeze_and_ratchet_as_array());
+
+    // Check challenge (const time)
+    if challenge == proof.challenge {
+        Ok(())
+    } else {
+        Err(VerificationFailure)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(non_snake_case)]
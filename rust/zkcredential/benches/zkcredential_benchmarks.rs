@@ -7,7 +7,12 @@ use std::sync::OnceLock;
 
 use criterion::{Criterion, criterion_group, criterion_main};
 use poksho::ShoApi;
+use poksho::shoapi::ShoApiExt as _;
 use zkcredential::attributes::{Domain, KeyPair, derive_default_generator_points};
+use zkcredential::credentials::CredentialKeyPair;
+use zkcredential::issuance::IssuanceProofBuilder;
+use zkcredential::presentation::{PresentationProofBuilder, PresentationProofVerifier};
+use zkcredential::sho::ShoExt as _;
 
 struct ExampleDomain;
 impl Domain for ExampleDomain {
@@ -40,5 +45,68 @@ fn attribute_key_inversion(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, attribute_key_inversion,);
+fn issuance_and_presentation(c: &mut Criterion) {
+    let mut sho = poksho::ShoSha256::new(b"test");
+    let credential_key = CredentialKeyPair::generate(sho.squeeze_and_ratchet_as_array());
+    let attr_key = KeyPair::<ExampleDomain>::derive_from(&mut sho);
+    let attrs: [[curve25519_dalek::RistrettoPoint; 2]; 3] =
+        std::array::from_fn(|_| [sho.get_point(), sho.get_point()]);
+    let label = b"20260101_ExampleCredential";
+
+    let issuance_builder = || {
+        IssuanceProofBuilder::new(label)
+            .add_public_attribute(&[1, 2, 3])
+            .add_attribute(&attrs[0])
+            .add_attribute(&attrs[1])
+            .add_attribute(&attrs[2])
+    };
+    let issuance_proof =
+        issuance_builder().issue(&credential_key, sho.squeeze_and_ratchet_as_array());
+
+    c.bench_function("IssuanceProofBuilder::issue", |b| {
+        b.iter(|| issuance_builder().issue(&credential_key, [0x42; 32]))
+    });
+    c.bench_function("IssuanceProofBuilder::verify", |b| {
+        b.iter(|| {
+            issuance_builder()
+                .verify(credential_key.public_key(), issuance_proof.clone())
+                .expect("valid")
+        })
+    });
+
+    let credential = issuance_builder()
+        .verify(credential_key.public_key(), issuance_proof)
+        .expect("valid");
+    let presentation_builder = || {
+        PresentationProofBuilder::new(label)
+            .add_attribute(&attrs[0], &attr_key)
+            .add_attribute(&attrs[1], &attr_key)
+            .add_attribute(&attrs[2], &attr_key)
+    };
+    let presentation = presentation_builder().present(
+        credential_key.public_key(),
+        &credential,
+        sho.squeeze_and_ratchet_as_array(),
+    );
+    let ciphertexts = attrs.map(|attr| attr_key.encrypt(&attr));
+
+    c.bench_function("PresentationProofBuilder::present", |b| {
+        b.iter(|| {
+            presentation_builder().present(credential_key.public_key(), &credential, [0x42; 32])
+        })
+    });
+    c.bench_function("PresentationProofVerifier::verify", |b| {
+        b.iter(|| {
+            PresentationProofVerifier::new(label)
+                .add_public_attribute(&[1, 2, 3])
+                .add_attribute(&ciphertexts[0], &attr_key.public_key)
+                .add_attribute(&ciphertexts[1], &attr_key.public_key)
+                .add_attribute(&ciphertexts[2], &attr_key.public_key)
+                .verify(&credential_key, &presentation)
+                .expect("valid")
+        })
+    });
+}
+
+criterion_group!(benches, attribute_key_inversion, issuance_and_presentation);
 criterion_main!(benches);
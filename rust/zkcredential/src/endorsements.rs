@@ -56,6 +56,7 @@
 //! [HMAC]: https://en.wikipedia.org/wiki/HMAC
 
 use std::fmt::Debug;
+use std::sync::LazyLock;
 
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::traits::{MultiscalarMul, VartimeMultiscalarMul};
@@ -379,7 +380,19 @@ impl EndorsementResponse {
         EndorsementResponse { R, proof }
     }
 
-    fn proof_statement() -> poksho::Statement {
+    fn proof_statement() -> &'static poksho::CompiledStatement {
+        // Unlike issuance and presentation, this statement involves no system generators: every
+        // point besides the base point depends on the key or the points being endorsed. Compiling
+        // still saves re-absorbing the statement description for every proof.
+        static STATEMENT: LazyLock<poksho::CompiledStatement> = LazyLock::new(|| {
+            EndorsementResponse::uncompiled_proof_statement()
+                .compile(&poksho::PointArgs::new())
+                .expect("only the base point is fixed")
+        });
+        &STATEMENT
+    }
+
+    fn uncompiled_proof_statement() -> poksho::Statement {
         let mut statement = poksho::Statement::new();
         // We use a weighted sum where the weights are generated by hashing the inputs (a "random
         // linear combination"), like PrivacyPass does. Checking every signature individually would
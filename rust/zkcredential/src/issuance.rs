@@ -15,6 +15,8 @@
 
 pub mod blind;
 
+use std::sync::OnceLock;
+
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::traits::Identity;
 use partial_default::PartialDefault;
@@ -131,6 +133,52 @@ impl<'a> IssuanceProofBuilder<'a> {
         st
     }
 
+    /// Returns the statement from [`get_poksho_statement`](Self::get_poksho_statement), compiled
+    /// with the system points fixed.
+    ///
+    /// Compiled statements are cached by attribute count.
+    fn compiled_poksho_statement(&self) -> &'static poksho::CompiledStatement {
+        static STATEMENTS: [OnceLock<poksho::CompiledStatement>; NUM_SUPPORTED_ATTRS] =
+            [const { OnceLock::new() }; NUM_SUPPORTED_ATTRS];
+        let attr_count = self.attr_points.len();
+        STATEMENTS[attr_count - 1].get_or_init(|| {
+            self.get_poksho_statement()
+                .compile(&Self::system_point_args(attr_count))
+                .expect("statement uses all system points")
+        })
+    }
+
+    /// Generates a [`poksho::PointArgs`] containing the system points used in the proof.
+    ///
+    /// These are the same for every proof with the same number of attributes, so they are fixed
+    /// when the statement is compiled rather than passed with each proof. `total_attr_count` is
+    /// passed in for [blind issuance](blind::BlindedIssuanceProofBuilder), which uses the same
+    /// points.
+    fn system_point_args(total_attr_count: usize) -> poksho::PointArgs {
+        let system = SystemParams::get_hardcoded();
+        assert!(
+            total_attr_count <= NUM_SUPPORTED_ATTRS,
+            "should have been enforced by the caller"
+        );
+
+        let mut point_args = poksho::PointArgs::new();
+        point_args.add("G_w", system.G_w);
+        point_args.add("G_wprime", system.G_wprime);
+        point_args.add("G_x0", system.G_x0);
+        point_args.add("G_x1", system.G_x1);
+
+        let G_y_names: [_; NUM_SUPPORTED_ATTRS] =
+            ["G_y0", "G_y1", "G_y2", "G_y3", "G_y4", "G_y5", "G_y6"];
+        for (name, value) in G_y_names
+            .into_iter()
+            .take(total_attr_count)
+            .zip(system.G_y.iter())
+        {
+            point_args.add(name, *value);
+        }
+        point_args
+    }
+
     fn finalize_public_attrs(&mut self) {
         debug_assert!(self.attr_points[0] == RistrettoPoint::identity());
         self.attr_points[0] = self.public_attrs.get_point();
@@ -167,7 +215,8 @@ impl<'a> IssuanceProofBuilder<'a> {
         scalar_args
     }
 
-    /// Generates a [`poksho::PointArgs`] to be used in the final proof.
+    /// Generates a [`poksho::PointArgs`] to be used in the final proof, excluding the system points
+    /// from [`system_point_args`](Self::system_point_args).
     ///
     /// The `credential` argument may be `None` when used for [blind
     /// issuance](blind::BlindedIssuanceProofBuilder), in which case the caller is responsible for
@@ -186,21 +235,7 @@ impl<'a> IssuanceProofBuilder<'a> {
 
         let mut point_args = poksho::PointArgs::new();
         point_args.add("C_W", key.C_W);
-        point_args.add("G_w", system.G_w);
-        point_args.add("G_wprime", system.G_wprime);
         point_args.add("G_V-I", system.G_V - key.I(total_attr_count));
-        point_args.add("G_x0", system.G_x0);
-        point_args.add("G_x1", system.G_x1);
-
-        let G_y_names: [_; NUM_SUPPORTED_ATTRS] =
-            ["G_y0", "G_y1", "G_y2", "G_y3", "G_y4", "G_y5", "G_y6"];
-        for (name, value) in G_y_names
-            .into_iter()
-            .take(total_attr_count)
-            .zip(system.G_y.iter())
-        {
-            point_args.add(name, *value);
-        }
 
         if let Some(credential) = credential {
             point_args.add("V", credential.V);
@@ -245,7 +280,7 @@ impl<'a> IssuanceProofBuilder<'a> {
         );
 
         let poksho_proof = self
-            .get_poksho_statement()
+            .compiled_poksho_statement()
             .prove(
                 &scalar_args,
                 &point_args,
@@ -273,7 +308,7 @@ impl<'a> IssuanceProofBuilder<'a> {
         self.finalize_public_attrs();
         let point_args =
             self.prepare_point_args(public_key, self.attr_points.len(), Some(&proof.credential));
-        match self.get_poksho_statement().verify_proof(
+        match self.compiled_poksho_statement().verify_proof(
             &proof.poksho_proof,
             &point_args,
             self.authenticated_message,
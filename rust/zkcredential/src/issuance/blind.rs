@@ -51,6 +51,8 @@
 //!
 //! Clients should use a new, one-off blinding key for every request.
 
+use std::sync::OnceLock;
+
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
@@ -369,6 +371,25 @@ impl BlindedIssuanceProofBuilder<'_> {
         st
     }
 
+    /// Returns the statement from [`get_poksho_statement`](Self::get_poksho_statement), compiled
+    /// with the system points fixed.
+    ///
+    /// Compiled statements are cached by the number of unblinded and blinded attribute points.
+    fn compiled_poksho_statement(&self) -> &'static poksho::CompiledStatement {
+        static STATEMENTS: [[OnceLock<poksho::CompiledStatement>; NUM_SUPPORTED_ATTRS];
+            NUM_SUPPORTED_ATTRS] =
+            [const { [const { OnceLock::new() }; NUM_SUPPORTED_ATTRS] }; NUM_SUPPORTED_ATTRS];
+        let unblinded_count = self.inner.attr_points.len();
+        let blinded_count = self.blinded_attr_points.len();
+        STATEMENTS[unblinded_count][blinded_count].get_or_init(|| {
+            self.get_poksho_statement()
+                .compile(&IssuanceProofBuilder::system_point_args(
+                    unblinded_count + blinded_count,
+                ))
+                .expect("statement uses all system points")
+        })
+    }
+
     fn finalize_public_attrs(&mut self) {
         self.inner.finalize_public_attrs()
     }
@@ -484,7 +505,7 @@ impl BlindedIssuanceProofBuilder<'_> {
         let point_args = self.prepare_point_args(key_pair.public_key(), blinding_key, &credential);
 
         let poksho_proof = self
-            .get_poksho_statement()
+            .compiled_poksho_statement()
             .prove(
                 &scalar_args,
                 &point_args,
@@ -512,7 +533,7 @@ impl BlindedIssuanceProofBuilder<'_> {
         self.finalize_public_attrs();
         let point_args =
             self.prepare_point_args(public_key, blinding_key.public_key(), &proof.credential);
-        self.get_poksho_statement()
+        self.compiled_poksho_statement()
             .verify_proof(
                 &proof.poksho_proof,
                 &point_args,
@@ -22,6 +22,9 @@
 //! Credential presentation is defined in Chase-Perrin-Zaverucha section 3.2; proofs for verifiable
 //! encryption are defined in section 4.1.
 
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
 use curve25519_dalek::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::traits::Identity;
@@ -55,6 +58,7 @@ pub struct PresentationProof {
     poksho_proof: Vec<u8>,
 }
 
+#[derive(Clone, PartialEq, Eq, Hash)]
 struct AttributeRef {
     key_index: Option<usize>,
     first_point_index: usize,
@@ -305,12 +309,61 @@ impl<'a, T: MayHavePublicKey> PresentationProofBuilderCore<'a, T> {
         st
     }
 
+    /// Returns the statement from [`get_poksho_statement`](Self::get_poksho_statement), compiled
+    /// with the system points `G_x0`, `G_x1`, and all `G_y{i}` fixed.
+    ///
+    /// Compiled statements are cached by the shape of the presentation: which encryption keys are
+    /// used, whether their public keys are checked, and how attributes are laid out. Each kind of
+    /// credential only has a handful of shapes, so the cache stays small.
+    fn compiled_poksho_statement(&self) -> Arc<poksho::CompiledStatement> {
+        type Shape = (Vec<(&'static str, bool)>, Vec<AttributeRef>);
+        static STATEMENTS: LazyLock<RwLock<HashMap<Shape, Arc<poksho::CompiledStatement>>>> =
+            LazyLock::new(Default::default);
+
+        let shape: Shape = (
+            self.encryption_keys
+                .iter()
+                .map(|key| (key.id(), key.public_key().is_some()))
+                .collect(),
+            self.attributes.clone(),
+        );
+        if let Some(statement) = STATEMENTS.read().expect("not poisoned").get(&shape) {
+            return statement.clone();
+        }
+
+        let credentials_system = SystemParams::get_hardcoded();
+        let mut fixed_points = poksho::PointArgs::new();
+        fixed_points.add("G_x0", credentials_system.G_x0);
+        fixed_points.add("G_x1", credentials_system.G_x1);
+        let G_y_names: [_; NUM_SUPPORTED_ATTRS] =
+            ["G_y0", "G_y1", "G_y2", "G_y3", "G_y4", "G_y5", "G_y6"];
+        for (G_y_name, G_yn) in G_y_names
+            .into_iter()
+            .take(self.attr_points.len())
+            .zip(credentials_system.G_y)
+        {
+            fixed_points.add(G_y_name, G_yn)
+        }
+        let statement = Arc::new(
+            self.get_poksho_statement()
+                .compile(&fixed_points)
+                .expect("statement uses all system points"),
+        );
+
+        STATEMENTS
+            .write()
+            .expect("not poisoned")
+            .entry(shape)
+            .or_insert(statement)
+            .clone()
+    }
+
     /// Generates [`poksho::PointArgs`] containing all points not derived from attributes.
     ///
-    /// This includes the credential key commitments `C_x0`, `C_x1`, and `C_y0`; the system points
-    /// `G_x0`, `G_x1`, and all `G_y{i}`; the appropriate issuing parameter point `I`; and the
-    /// points necessary to prove the validity of encryption keys: `0`, `G_a1_{key}`, `G_a2_{key}`,
-    /// and `sum(A)`.
+    /// This includes the credential key commitments `C_x0`, `C_x1`, and `C_y0`; the appropriate
+    /// issuing parameter point `I`; and the points necessary to prove the validity of encryption
+    /// keys: `0`, `G_a1_{key}`, `G_a2_{key}`, and `sum(A)`. The system points are fixed in
+    /// [`compiled_poksho_statement`](Self::compiled_poksho_statement) instead.
     ///
     /// The caller is responsible for handling the presenter's one-off public point `Z` (which the
     /// verifier derives from the commitments and public attributes); the appropriate `C_y{i}` for
@@ -321,15 +374,11 @@ impl<'a, T: MayHavePublicKey> PresentationProofBuilderCore<'a, T> {
         I: RistrettoPoint,
         commitments: &PresentationProofCommitments,
     ) -> poksho::PointArgs {
-        let credentials_system = SystemParams::get_hardcoded();
-
         let mut point_args = poksho::PointArgs::new();
         point_args.add("I", I);
 
         point_args.add("C_x0", commitments.C_x0);
         point_args.add("C_x1", commitments.C_x1);
-        point_args.add("G_x0", credentials_system.G_x0);
-        point_args.add("G_x1", credentials_system.G_x1);
 
         if !self.encryption_keys.is_empty() {
             point_args.add("0", RistrettoPoint::identity());
@@ -347,16 +396,6 @@ impl<'a, T: MayHavePublicKey> PresentationProofBuilderCore<'a, T> {
             }
         }
 
-        let G_y_names: [_; NUM_SUPPORTED_ATTRS] =
-            ["G_y0", "G_y1", "G_y2", "G_y3", "G_y4", "G_y5", "G_y6"];
-        for (G_y_name, G_yn) in G_y_names
-            .into_iter()
-            .take(self.attr_points.len())
-            .zip(credentials_system.G_y)
-        {
-            point_args.add(G_y_name, G_yn)
-        }
-
         point_args.add("C_y0", commitments.C_y[0]);
         // Other C_y depend on the form of the attribute.
 
@@ -540,7 +579,7 @@ impl<'a> PresentationProofBuilder<'a> {
 
         let poksho_proof = self
             .core
-            .get_poksho_statement()
+            .compiled_poksho_statement()
             .prove(
                 &scalar_args,
                 &point_args,
@@ -699,7 +738,7 @@ impl<'a> PresentationProofVerifier<'a> {
 
         point_args.add("Z", Z);
 
-        match self.core.get_poksho_statement().verify_proof(
+        match self.core.compiled_poksho_statement().verify_proof(
             &proof.poksho_proof,
             &point_args,
             self.core.authenticated_message,
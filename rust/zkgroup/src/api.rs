@@ -6,11 +6,14 @@
 pub mod auth;
 pub mod backups;
 pub mod call_links;
+pub mod describe;
 pub mod groups;
 pub mod profiles;
+pub mod rate_limits;
 pub mod receipts;
 
 pub mod generic_server_params;
+pub mod key_rotation;
 pub mod server_params;
 
 pub use server_params::{
@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use zkcredential::credentials::{CredentialKeyPair, CredentialPublicKey};
 
 use crate::api::auth::auth_credential_with_pni::AuthCredentialWithPniVersion;
+use crate::api::describe::{Describe, Description};
 use crate::common::constants::PRESENTATION_VERSION_4;
 use crate::common::serialization::VersionByte;
 use crate::common::simple_types::{RandomnessBytes, Timestamp};
@@ -224,6 +225,22 @@ impl AuthCredentialWithPniZkcPresentation {
     }
 }
 
+impl Describe for AuthCredentialWithPniZkcResponse {
+    fn describe(&self, _description: &mut Description) {}
+}
+
+impl Describe for AuthCredentialWithPniZkc {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.redemption_time);
+    }
+}
+
+impl Describe for AuthCredentialWithPniZkcPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.redemption_time);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use zkcredential::RANDOMNESS_LEN;
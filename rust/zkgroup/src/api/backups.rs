@@ -4,9 +4,14 @@
 //
 
 mod auth_credential;
+mod credential_wallet;
 
 pub use auth_credential::{
     BackupAuthCredential, BackupAuthCredentialPresentation, BackupAuthCredentialRequest,
     BackupAuthCredentialRequestContext, BackupAuthCredentialResponse, BackupCredentialType,
     BackupLevel,
 };
+pub use credential_wallet::{
+    BACKUP_CREDENTIAL_DAYS_STORED, BackupAuthCredentialWallet, BackupAuthCredentialWalletError,
+    BackupLevelChange,
+};
@@ -20,6 +20,7 @@ use partial_default::PartialDefault;
 use poksho::ShoApi;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::sho::Sho;
 use crate::common::simple_types::*;
@@ -266,6 +267,10 @@ impl BackupAuthCredential {
     pub fn credential_type(&self) -> BackupCredentialType {
         self.credential_type
     }
+
+    pub fn redemption_time(&self) -> Timestamp {
+        self.redemption_time
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialDefault)]
@@ -311,6 +316,36 @@ impl BackupAuthCredentialPresentation {
     }
 }
 
+impl Describe for BackupAuthCredentialRequest {
+    fn describe(&self, _description: &mut Description) {}
+}
+
+impl Describe for BackupAuthCredentialResponse {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.redemption_time);
+        description.backup_level = Some(self.backup_level);
+        description.credential_type = Some(self.credential_type);
+    }
+}
+
+impl Describe for BackupAuthCredential {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.redemption_time);
+        description.backup_level = Some(self.backup_level);
+        description.credential_type = Some(self.credential_type);
+        description.redact("backup_id");
+    }
+}
+
+impl Describe for BackupAuthCredentialPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.redemption_time);
+        description.backup_level = Some(self.backup_level);
+        description.credential_type = Some(self.credential_type);
+        description.reveal("backup_id", self.backup_id.0);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
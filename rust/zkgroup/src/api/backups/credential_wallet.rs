@@ -0,0 +1,206 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides BackupAuthCredentialWallet, which holds a client's daily backup credentials.
+//!
+//! The chat server hands out backup credentials a batch at a time, one per day for the coming week,
+//! separately for each [`BackupCredentialType`]. The wallet keeps those batches, hands back the
+//! credential that the verifying server will accept at a given time, and notices when a new batch
+//! was issued at a different [`BackupLevel`], which happens when a subscription starts or lapses.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    BackupAuthCredential, BackupAuthCredentialPresentation, BackupAuthCredentialRequestContext,
+    BackupAuthCredentialResponse, BackupCredentialType, BackupLevel,
+};
+use crate::common::constants::SECONDS_PER_DAY;
+use crate::common::serialization::ReservedByte;
+use crate::generic_server_params::GenericServerPublicParams;
+use crate::{RandomnessBytes, Timestamp};
+
+/// How many daily credentials of each type are kept, starting from the current day.
+pub const BACKUP_CREDENTIAL_DAYS_STORED: u64 = 7;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum BackupAuthCredentialWalletError {
+    /// credential response failed verification
+    VerificationFailure,
+    /// credentials in a batch must all have the same type and level
+    InconsistentBatch,
+    /// no stored credential can be redeemed at this time
+    NoCredential,
+}
+
+/// A change in backup level detected when receiving a new batch of credentials.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BackupLevelChange {
+    pub credential_type: BackupCredentialType,
+    pub previous: BackupLevel,
+    pub current: BackupLevel,
+}
+
+/// Stores a week's worth of daily backup credentials for each credential type.
+///
+/// All credentials stored for a given type have the same backup level, namely the level of the most
+/// recently received batch.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct BackupAuthCredentialWallet {
+    reserved: ReservedByte,
+    messages: BTreeMap<Timestamp, BackupAuthCredential>,
+    media: BTreeMap<Timestamp, BackupAuthCredential>,
+}
+
+impl BackupAuthCredentialWallet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn credentials(
+        &self,
+        credential_type: BackupCredentialType,
+    ) -> &BTreeMap<Timestamp, BackupAuthCredential> {
+        match credential_type {
+            BackupCredentialType::Messages => &self.messages,
+            BackupCredentialType::Media => &self.media,
+        }
+    }
+
+    fn credentials_mut(
+        &mut self,
+        credential_type: BackupCredentialType,
+    ) -> &mut BTreeMap<Timestamp, BackupAuthCredential> {
+        match credential_type {
+            BackupCredentialType::Messages => &mut self.messages,
+            BackupCredentialType::Media => &mut self.media,
+        }
+    }
+
+    /// Validates a batch of responses for one credential type and stores the resulting credentials.
+    ///
+    /// `responses` pairs each response with the redemption time it was issued for. The batch is
+    /// accepted or rejected as a whole. If its level differs from the level of the credentials
+    /// already stored for that type, the old credentials are discarded and the change is returned.
+    /// Credentials are then pruned as in [`Self::prune`].
+    pub fn receive(
+        &mut self,
+        context: &BackupAuthCredentialRequestContext,
+        responses: impl IntoIterator<Item = (Timestamp, BackupAuthCredentialResponse)>,
+        params: &GenericServerPublicParams,
+        now: Timestamp,
+    ) -> Result<Option<BackupLevelChange>, BackupAuthCredentialWalletError> {
+        let received = responses
+            .into_iter()
+            .map(|(redemption_time, response)| {
+                context
+                    .clone()
+                    .receive(response, params, redemption_time)
+                    .map_err(|_| BackupAuthCredentialWalletError::VerificationFailure)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let Some(first) = received.first() else {
+            return Ok(None);
+        };
+        let credential_type = first.credential_type();
+        let current = first.backup_level();
+        if received.iter().any(|credential| {
+            credential.credential_type() != credential_type || credential.backup_level() != current
+        }) {
+            return Err(BackupAuthCredentialWalletError::InconsistentBatch);
+        }
+
+        let change = self
+            .backup_level(credential_type)
+            .filter(|previous| *previous != current)
+            .map(|previous| BackupLevelChange {
+                credential_type,
+                previous,
+                current,
+            });
+
+        let credentials = self.credentials_mut(credential_type);
+        if change.is_some() {
+            credentials.clear();
+        }
+        credentials.extend(
+            received
+                .into_iter()
+                .map(|credential| (credential.redemption_time(), credential)),
+        );
+        self.prune(now);
+
+        Ok(change)
+    }
+
+    /// The level of the credentials stored for `credential_type`, if there are any.
+    pub fn backup_level(&self, credential_type: BackupCredentialType) -> Option<BackupLevel> {
+        self.credentials(credential_type)
+            .values()
+            .next()
+            .map(BackupAuthCredential::backup_level)
+    }
+
+    /// Returns the credential to present at `now`.
+    ///
+    /// Prefers the credential for the current day. Failing that, uses the most recent earlier one,
+    /// then the earliest later one, as long as the verifying server would still accept it.
+    pub fn get(
+        &self,
+        credential_type: BackupCredentialType,
+        now: Timestamp,
+    ) -> Option<&BackupAuthCredential> {
+        // Mirrors the window accepted by BackupAuthCredentialPresentation::verify.
+        let earliest =
+            Timestamp::from_epoch_seconds(now.epoch_seconds().saturating_sub(2 * SECONDS_PER_DAY));
+        let latest = now.checked_add_seconds(SECONDS_PER_DAY)?;
+        let credentials = self.credentials(credential_type);
+        credentials
+            .range(earliest..=now)
+            .next_back()
+            .or_else(|| credentials.range(now..=latest).next())
+            .map(|(_, credential)| credential)
+    }
+
+    /// Presents the credential returned by [`Self::get`].
+    pub fn present(
+        &self,
+        credential_type: BackupCredentialType,
+        now: Timestamp,
+        params: &GenericServerPublicParams,
+        randomness: RandomnessBytes,
+    ) -> Result<BackupAuthCredentialPresentation, BackupAuthCredentialWalletError> {
+        self.get(credential_type, now)
+            .map(|credential| credential.present(params, randomness))
+            .ok_or(BackupAuthCredentialWalletError::NoCredential)
+    }
+
+    /// Whether a new batch should be fetched for `credential_type`, because the credential for the
+    /// current day or the next one is missing.
+    pub fn needs_refresh(&self, credential_type: BackupCredentialType, now: Timestamp) -> bool {
+        let today = start_of_day(now);
+        let credentials = self.credentials(credential_type);
+        !credentials.contains_key(&today)
+            || !credentials.contains_key(&today.add_seconds(SECONDS_PER_DAY))
+    }
+
+    /// Drops credentials that can no longer be redeemed, and any more than
+    /// [`BACKUP_CREDENTIAL_DAYS_STORED`] days ahead.
+    pub fn prune(&mut self, now: Timestamp) {
+        let start =
+            Timestamp::from_epoch_seconds(now.epoch_seconds().saturating_sub(2 * SECONDS_PER_DAY));
+        let end = start_of_day(now).add_seconds(BACKUP_CREDENTIAL_DAYS_STORED * SECONDS_PER_DAY);
+        for credentials in [&mut self.messages, &mut self.media] {
+            credentials.retain(|redemption_time, _| (start..end).contains(redemption_time));
+        }
+    }
+}
+
+fn start_of_day(timestamp: Timestamp) -> Timestamp {
+    let seconds = timestamp.epoch_seconds();
+    Timestamp::from_epoch_seconds(seconds - seconds % SECONDS_PER_DAY)
+}
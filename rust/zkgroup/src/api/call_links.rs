@@ -6,6 +6,8 @@
 mod auth_credential;
 mod create_credential;
 mod params;
+mod root_key;
+mod state;
 
 pub use auth_credential::{
     CallLinkAuthCredential, CallLinkAuthCredentialPresentation, CallLinkAuthCredentialResponse,
@@ -16,3 +18,11 @@ pub use create_credential::{
     CreateCallLinkCredentialResponse,
 };
 pub use params::{CallLinkPublicParams, CallLinkSecretParams};
+pub use root_key::{
+    CALL_LINK_ROOM_ID_LEN, CALL_LINK_ROOT_KEY_LEN, CallLinkEpoch, CallLinkParseError,
+    CallLinkRootKey, CallLinkUrl,
+};
+pub use state::{
+    CALL_LINK_ADMIN_PASSKEY_LEN, CallLinkAdminPasskey, CallLinkRestrictions, CallLinkState,
+    EncryptedCallLinkState,
+};
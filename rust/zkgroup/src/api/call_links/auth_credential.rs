@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 
 use super::{CallLinkPublicParams, CallLinkSecretParams};
 use crate::ZkGroupVerificationFailure;
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::simple_types::*;
 use crate::crypto::uid_encryption;
@@ -134,3 +135,17 @@ impl CallLinkAuthCredentialPresentation {
         }
     }
 }
+
+impl Describe for CallLinkAuthCredentialResponse {
+    fn describe(&self, _description: &mut Description) {}
+}
+
+impl Describe for CallLinkAuthCredential {
+    fn describe(&self, _description: &mut Description) {}
+}
+
+impl Describe for CallLinkAuthCredentialPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.redemption_time);
+    }
+}
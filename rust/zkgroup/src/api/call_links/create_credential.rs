@@ -17,6 +17,7 @@ use serde::{Deserialize, Serialize};
 
 use super::{CallLinkPublicParams, CallLinkSecretParams};
 use crate::ZkGroupVerificationFailure;
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::sho::Sho;
 use crate::common::simple_types::*;
@@ -222,3 +223,25 @@ impl CreateCallLinkCredentialPresentation {
         }
     }
 }
+
+impl Describe for CreateCallLinkCredentialRequest {
+    fn describe(&self, _description: &mut Description) {}
+}
+
+impl Describe for CreateCallLinkCredentialResponse {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.timestamp);
+    }
+}
+
+impl Describe for CreateCallLinkCredential {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.timestamp);
+    }
+}
+
+impl Describe for CreateCallLinkCredentialPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.redemption_time = Some(self.timestamp);
+    }
+}
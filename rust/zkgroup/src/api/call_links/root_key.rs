@@ -0,0 +1,271 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides CallLinkRootKey and the user-facing call link format.
+//!
+//! Everything about a call link is derived from its root key: the room ID known to the calling
+//! server and the [`CallLinkSecretParams`] used for credentials and to protect the link's state
+//! (see [`super::CallLinkState`]). The root key and the link's epoch are shared as text, with
+//! each byte written as two letters from a consonant-only alphabet so the result can't spell
+//! anything, in hyphen-separated groups of four:
+//!
+//! ```text
+//! https://signal.link/call/#key=bcdf-ghkm-npqr-stxz-bcdf-ghkm-npqr-stxz&epoch=bcdf-ghkm
+//! ```
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use partial_default::PartialDefault;
+use serde::{Deserialize, Serialize};
+
+use super::CallLinkSecretParams;
+use crate::RandomnessBytes;
+use crate::common::sho::Sho;
+
+pub const CALL_LINK_ROOT_KEY_LEN: usize = 16;
+pub const CALL_LINK_ROOM_ID_LEN: usize = 32;
+
+const CALL_LINK_URL_PREFIX: &str = "https://signal.link/call/#";
+const ALPHABET: &[u8; 16] = b"bcdfghkmnpqrstxz";
+const CHARS_PER_GROUP: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum CallLinkParseError {
+    /// invalid character in call link component
+    InvalidCharacter,
+    /// call link component has the wrong length
+    WrongLength,
+    /// not a call link
+    NotACallLink,
+    /// call link is missing its root key
+    MissingKey,
+}
+
+/// The secret from which everything else about a call link is derived.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct CallLinkRootKey([u8; CALL_LINK_ROOT_KEY_LEN]);
+
+impl CallLinkRootKey {
+    pub fn generate(randomness: RandomnessBytes) -> Self {
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_Random_CallLinkRootKey_Generate",
+            &randomness,
+        );
+        Self(sho.squeeze_as_array())
+    }
+
+    pub fn from_bytes(bytes: [u8; CALL_LINK_ROOT_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; CALL_LINK_ROOT_KEY_LEN] {
+        &self.0
+    }
+
+    /// The ID the calling server knows the room by.
+    pub fn derive_room_id(&self) -> [u8; CALL_LINK_ROOM_ID_LEN] {
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_CallLinkRootKey_DeriveRoomId",
+            &self.0,
+        );
+        sho.squeeze_as_array()
+    }
+
+    pub fn derive_secret_params(&self) -> CallLinkSecretParams {
+        CallLinkSecretParams::derive_from_root_key(&self.0)
+    }
+}
+
+impl Display for CallLinkRootKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(&self.0))
+    }
+}
+
+impl FromStr for CallLinkRootKey {
+    type Err = CallLinkParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode(s).map(Self)
+    }
+}
+
+/// Distinguishes successive links for the same room, so that a link can be reset without changing
+/// the room's root key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialDefault)]
+#[serde(transparent)]
+pub struct CallLinkEpoch(u32);
+
+impl CallLinkEpoch {
+    pub fn generate(randomness: RandomnessBytes) -> Self {
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_Random_CallLinkEpoch_Generate",
+            &randomness,
+        );
+        Self(u32::from_be_bytes(sho.squeeze_as_array()))
+    }
+}
+
+impl From<u32> for CallLinkEpoch {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<CallLinkEpoch> for u32 {
+    fn from(value: CallLinkEpoch) -> Self {
+        value.0
+    }
+}
+
+impl Display for CallLinkEpoch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(&self.0.to_be_bytes()))
+    }
+}
+
+impl FromStr for CallLinkEpoch {
+    type Err = CallLinkParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode(s).map(|bytes| Self(u32::from_be_bytes(bytes)))
+    }
+}
+
+/// The user-facing form of a call link.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CallLinkUrl {
+    pub root_key: CallLinkRootKey,
+    /// Absent for links created before epochs were introduced.
+    pub epoch: Option<CallLinkEpoch>,
+}
+
+impl Display for CallLinkUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{CALL_LINK_URL_PREFIX}key={}", self.root_key)?;
+        if let Some(epoch) = self.epoch {
+            write!(f, "&epoch={epoch}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CallLinkUrl {
+    type Err = CallLinkParseError;
+
+    /// Parses a call link, ignoring any parameters other than the key and epoch.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fragment = s
+            .strip_prefix(CALL_LINK_URL_PREFIX)
+            .ok_or(CallLinkParseError::NotACallLink)?;
+        let mut root_key = None;
+        let mut epoch = None;
+        for parameter in fragment.split('&') {
+            match parameter.split_once('=') {
+                Some(("key", value)) => root_key = Some(value.parse()?),
+                Some(("epoch", value)) => epoch = Some(value.parse()?),
+                _ => {}
+            }
+        }
+        Ok(Self {
+            root_key: root_key.ok_or(CallLinkParseError::MissingKey)?,
+            epoch,
+        })
+    }
+}
+
+// Keeps the key out of logs.
+impl fmt::Debug for CallLinkRootKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallLinkRootKey").finish_non_exhaustive()
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    let chars: Vec<char> = bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xF])
+        .map(|nibble| char::from(ALPHABET[usize::from(nibble)]))
+        .collect();
+    chars
+        .chunks(CHARS_PER_GROUP)
+        .map(String::from_iter)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn decode<const N: usize>(s: &str) -> Result<[u8; N], CallLinkParseError> {
+    let nibbles = s
+        .bytes()
+        .filter(|c| *c != b'-')
+        .map(|c| {
+            ALPHABET
+                .iter()
+                .position(|a| *a == c)
+                .and_then(|nibble| u8::try_from(nibble).ok())
+                .ok_or(CallLinkParseError::InvalidCharacter)
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    if nibbles.len() != 2 * N {
+        return Err(CallLinkParseError::WrongLength);
+    }
+    let mut bytes = [0; N];
+    for (byte, pair) in bytes.iter_mut().zip(nibbles.chunks_exact(2)) {
+        *byte = (pair[0] << 4) | pair[1];
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::constants::*;
+
+    #[test]
+    fn root_key_encoding() {
+        let root_key = CallLinkRootKey::from_bytes(
+            *b"\x01\x23\x45\x67\x89\xab\xcd\xef\x01\x23\x45\x67\x89\xab\xcd\xef",
+        );
+        let encoded = root_key.to_string();
+        assert_eq!(encoded, "bcdf-ghkm-npqr-stxz-bcdf-ghkm-npqr-stxz");
+        assert_eq!(encoded.parse(), Ok(root_key));
+
+        assert_eq!(
+            "bcdf-ghkm-npqr-stxz-bcdf-ghkm-npqr-stxa".parse::<CallLinkRootKey>(),
+            Err(CallLinkParseError::InvalidCharacter)
+        );
+        assert_eq!(
+            "bcdf-ghkm".parse::<CallLinkRootKey>(),
+            Err(CallLinkParseError::WrongLength)
+        );
+    }
+
+    #[test]
+    fn url_round_trip() {
+        let url = CallLinkUrl {
+            root_key: CallLinkRootKey::generate(TEST_ARRAY_32),
+            epoch: Some(CallLinkEpoch::generate(TEST_ARRAY_32_1)),
+        };
+        let formatted = url.to_string();
+        assert!(formatted.starts_with(CALL_LINK_URL_PREFIX));
+        assert_eq!(formatted.parse(), Ok(url));
+
+        let without_epoch = CallLinkUrl { epoch: None, ..url };
+        assert_eq!(without_epoch.to_string().parse(), Ok(without_epoch));
+
+        let with_extra = format!("{formatted}&future=1");
+        assert_eq!(with_extra.parse(), Ok(url));
+
+        assert_eq!(
+            "https://signal.link/call/#epoch=bcdf-ghkm".parse::<CallLinkUrl>(),
+            Err(CallLinkParseError::MissingKey)
+        );
+        assert_eq!(
+            "https://example.com/".parse::<CallLinkUrl>(),
+            Err(CallLinkParseError::NotACallLink)
+        );
+    }
+}
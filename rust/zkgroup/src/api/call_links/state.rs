@@ -0,0 +1,225 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides CallLinkState, the settings of a call link, and its encrypted form.
+//!
+//! The calling server stores each link's state on behalf of its clients. Only the name is secret;
+//! it's encrypted with a key derived from the link's [`CallLinkSecretParams`], and padded so that
+//! the server only learns roughly how long it is. Changes to the state are authorized by the
+//! [`CallLinkAdminPasskey`] chosen by the link's creator.
+
+use aes_gcm_siv::aead::Aead;
+use aes_gcm_siv::aead::generic_array::GenericArray;
+use aes_gcm_siv::{Aes256GcmSiv, KeyInit};
+use partial_default::PartialDefault;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use super::{CallLinkEpoch, CallLinkSecretParams};
+use crate::ZkGroupVerificationFailure;
+use crate::common::constants::*;
+use crate::common::serialization::ReservedByte;
+use crate::common::sho::Sho;
+use crate::common::simple_types::*;
+
+pub const CALL_LINK_ADMIN_PASSKEY_LEN: usize = 16;
+
+/// Names are prefixed with their length and padded to a multiple of this many bytes before
+/// encryption.
+const NAME_PADDING_BLOCK_LEN: usize = 32;
+const NAME_LENGTH_PREFIX_LEN: usize = 4;
+
+/// Who may join a call using the link.
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, PartialDefault, derive_more::TryFrom,
+)]
+#[serde(into = "u8", try_from = "u8")]
+#[repr(u8)]
+#[try_from(repr)]
+pub enum CallLinkRestrictions {
+    #[partial_default]
+    None = 0,
+    AdminApproval = 1,
+}
+
+impl From<CallLinkRestrictions> for u8 {
+    fn from(restrictions: CallLinkRestrictions) -> Self {
+        restrictions as u8
+    }
+}
+
+/// The decrypted settings of a call link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallLinkState {
+    pub name: String,
+    pub restrictions: CallLinkRestrictions,
+    pub revoked: bool,
+    pub expiration: Timestamp,
+    pub epoch: Option<CallLinkEpoch>,
+}
+
+/// A call link's settings as stored by the calling server.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, PartialDefault)]
+pub struct EncryptedCallLinkState {
+    reserved: ReservedByte,
+    /// Empty if the link has no name.
+    pub encrypted_name: Vec<u8>,
+    pub restrictions: CallLinkRestrictions,
+    pub revoked: bool,
+    pub expiration: Timestamp,
+    pub epoch: Option<CallLinkEpoch>,
+}
+
+/// The secret that authorizes changes to a call link's state.
+#[derive(Copy, Clone)]
+pub struct CallLinkAdminPasskey([u8; CALL_LINK_ADMIN_PASSKEY_LEN]);
+
+impl CallLinkAdminPasskey {
+    pub fn generate(randomness: RandomnessBytes) -> Self {
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_Random_CallLinkAdminPasskey_Generate",
+            &randomness,
+        );
+        Self(sho.squeeze_as_array())
+    }
+
+    pub fn from_bytes(bytes: [u8; CALL_LINK_ADMIN_PASSKEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; CALL_LINK_ADMIN_PASSKEY_LEN] {
+        &self.0
+    }
+
+    /// Checks a passkey presented with an update, in constant time.
+    pub fn matches(&self, presented: &[u8]) -> bool {
+        self.0.as_slice().ct_eq(presented).into()
+    }
+}
+
+impl CallLinkSecretParams {
+    fn derive_name_key(&self) -> AesKeyBytes {
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_CallLinkSecretParams_DeriveNameKey",
+            &[
+                self.uid_enc_key_pair.a1.to_bytes(),
+                self.uid_enc_key_pair.a2.to_bytes(),
+            ]
+            .concat(),
+        );
+        sho.squeeze_as_array()
+    }
+
+    /// Encrypts a link name, producing `ciphertext || nonce || reserved byte` like
+    /// [`GroupSecretParams::encrypt_blob`](crate::groups::GroupSecretParams::encrypt_blob).
+    ///
+    /// The name is prefixed with its length as a big-endian u32 and padded with zeros. An empty
+    /// name produces an empty ciphertext.
+    pub fn encrypt_name(&self, randomness: RandomnessBytes, name: &str) -> Vec<u8> {
+        if name.is_empty() {
+            return Vec::new();
+        }
+
+        let name_len = u32::try_from(name.len()).expect("name length fits in u32");
+        let padded_len =
+            (NAME_LENGTH_PREFIX_LEN + name.len()).next_multiple_of(NAME_PADDING_BLOCK_LEN);
+        let mut padded = Vec::with_capacity(padded_len);
+        padded.extend_from_slice(&name_len.to_be_bytes());
+        padded.extend_from_slice(name.as_bytes());
+        padded.resize(padded_len, 0);
+
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_Random_CallLinkSecretParams_EncryptName",
+            &randomness,
+        );
+        let nonce = sho.squeeze_as_array::<AESGCM_NONCE_LEN>();
+        let key = self.derive_name_key();
+        let mut ciphertext = Aes256GcmSiv::new(GenericArray::from_slice(&key))
+            .encrypt(GenericArray::from_slice(&nonce), padded.as_slice())
+            .expect("aead encrypt failure");
+        ciphertext.extend(nonce);
+        ciphertext.push(0); // reserved byte
+        ciphertext
+    }
+
+    pub fn decrypt_name(
+        &self,
+        encrypted_name: &[u8],
+    ) -> Result<String, ZkGroupVerificationFailure> {
+        if encrypted_name.is_empty() {
+            return Ok(String::new());
+        }
+
+        let (reserved, encrypted_name) = encrypted_name
+            .split_last()
+            .ok_or(ZkGroupVerificationFailure)?;
+        if *reserved != 0 || encrypted_name.len() < AESGCM_TAG_LEN + AESGCM_NONCE_LEN {
+            return Err(ZkGroupVerificationFailure);
+        }
+        let (ciphertext, nonce) = encrypted_name.split_at(encrypted_name.len() - AESGCM_NONCE_LEN);
+
+        let key = self.derive_name_key();
+        let padded = Aes256GcmSiv::new(GenericArray::from_slice(&key))
+            .decrypt(GenericArray::from_slice(nonce), ciphertext)
+            .map_err(|_| ZkGroupVerificationFailure)?;
+
+        let (name_len, padded_name) = padded
+            .split_first_chunk::<NAME_LENGTH_PREFIX_LEN>()
+            .ok_or(ZkGroupVerificationFailure)?;
+        let name_len = usize::try_from(u32::from_be_bytes(*name_len))
+            .map_err(|_| ZkGroupVerificationFailure)?;
+        if name_len > padded_name.len() {
+            return Err(ZkGroupVerificationFailure);
+        }
+        let (name, padding) = padded_name.split_at(name_len);
+        if padding.iter().any(|byte| *byte != 0) {
+            return Err(ZkGroupVerificationFailure);
+        }
+        String::from_utf8(name.to_vec()).map_err(|_| ZkGroupVerificationFailure)
+    }
+
+    pub fn encrypt_state(
+        &self,
+        randomness: RandomnessBytes,
+        state: &CallLinkState,
+    ) -> EncryptedCallLinkState {
+        let CallLinkState {
+            name,
+            restrictions,
+            revoked,
+            expiration,
+            epoch,
+        } = state;
+        EncryptedCallLinkState {
+            reserved: Default::default(),
+            encrypted_name: self.encrypt_name(randomness, name),
+            restrictions: *restrictions,
+            revoked: *revoked,
+            expiration: *expiration,
+            epoch: *epoch,
+        }
+    }
+
+    pub fn decrypt_state(
+        &self,
+        state: &EncryptedCallLinkState,
+    ) -> Result<CallLinkState, ZkGroupVerificationFailure> {
+        let EncryptedCallLinkState {
+            reserved: _,
+            encrypted_name,
+            restrictions,
+            revoked,
+            expiration,
+            epoch,
+        } = state;
+        Ok(CallLinkState {
+            name: self.decrypt_name(encrypted_name)?,
+            restrictions: *restrictions,
+            revoked: *revoked,
+            expiration: *expiration,
+            epoch: *epoch,
+        })
+    }
+}
@@ -0,0 +1,273 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides human-readable descriptions of serialized zkgroup values, for debugging.
+//!
+//! A serialized credential, presentation, request, or response is an opaque run of bytes, which
+//! makes it hard to tell from a log line why a `verify_*` call rejected it. [`describe`] decodes
+//! such a value and reports its public details: the version byte, expiration or redemption time,
+//! levels and types, and any attributes that are revealed to the server anyway. Proofs, points,
+//! and ciphertexts are never included, and neither are client-side secrets like profile keys.
+//!
+//! Credentials held by clients carry attributes, such as a receipt serial or backup ID, that only
+//! become public once the credential is presented. Those are listed by name under
+//! [`Description::redacted_attributes`] without their values.
+//!
+//! [`Description`] implements [`serde::Serialize`], so it can be logged as JSON.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use partial_default::PartialDefault;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::backups::{BackupCredentialType, BackupLevel};
+use crate::{ReceiptLevel, Timestamp, ZkGroupDeserializationFailure};
+
+/// Implemented by every type listed in [`DescribedKind`].
+pub(crate) trait Describe: for<'a> Deserialize<'a> + PartialDefault {
+    /// Fills in the non-secret details carried by this value.
+    fn describe(&self, description: &mut Description);
+}
+
+macro_rules! described_kinds {
+    ($($kind:ident => $ty:ty,)+) => {
+        /// The kinds of serialized values that can be passed to [`describe`].
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+        pub enum DescribedKind {
+            $($kind,)+
+        }
+
+        impl DescribedKind {
+            pub const ALL: &[Self] = &[$(Self::$kind,)+];
+
+            /// The name of the corresponding Rust type.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$kind => stringify!($kind),)+
+                }
+            }
+
+            fn describe(self, bytes: &[u8]) -> Result<Description, ZkGroupDeserializationFailure> {
+                match self {
+                    $(Self::$kind => describe_as::<$ty>(self, bytes),)+
+                }
+            }
+        }
+    };
+}
+
+described_kinds! {
+    AuthCredentialWithPniZkcResponse => crate::auth::AuthCredentialWithPniZkcResponse,
+    AuthCredentialWithPniZkc => crate::auth::AuthCredentialWithPniZkc,
+    AuthCredentialWithPniZkcPresentation => crate::auth::AuthCredentialWithPniZkcPresentation,
+    ProfileKeyCredentialRequest => crate::profiles::ProfileKeyCredentialRequest,
+    ExpiringProfileKeyCredentialResponse => crate::profiles::ExpiringProfileKeyCredentialResponse,
+    ExpiringProfileKeyCredential => crate::profiles::ExpiringProfileKeyCredential,
+    ExpiringProfileKeyCredentialPresentation =>
+        crate::profiles::ExpiringProfileKeyCredentialPresentation,
+    ReceiptCredentialRequest => crate::receipts::ReceiptCredentialRequest,
+    ReceiptCredentialResponse => crate::receipts::ReceiptCredentialResponse,
+    ReceiptCredential => crate::receipts::ReceiptCredential,
+    ReceiptCredentialPresentation => crate::receipts::ReceiptCredentialPresentation,
+    BackupAuthCredentialRequest => crate::backups::BackupAuthCredentialRequest,
+    BackupAuthCredentialResponse => crate::backups::BackupAuthCredentialResponse,
+    BackupAuthCredential => crate::backups::BackupAuthCredential,
+    BackupAuthCredentialPresentation => crate::backups::BackupAuthCredentialPresentation,
+    CreateCallLinkCredentialRequest => crate::call_links::CreateCallLinkCredentialRequest,
+    CreateCallLinkCredentialResponse => crate::call_links::CreateCallLinkCredentialResponse,
+    CreateCallLinkCredential => crate::call_links::CreateCallLinkCredential,
+    CreateCallLinkCredentialPresentation => crate::call_links::CreateCallLinkCredentialPresentation,
+    CallLinkAuthCredentialResponse => crate::call_links::CallLinkAuthCredentialResponse,
+    CallLinkAuthCredential => crate::call_links::CallLinkAuthCredential,
+    CallLinkAuthCredentialPresentation => crate::call_links::CallLinkAuthCredentialPresentation,
+    GroupSendEndorsementsResponse => crate::groups::GroupSendEndorsementsResponse,
+    GroupSendFullToken => crate::groups::GroupSendFullToken,
+    RateLimitTokenRequest => crate::rate_limits::RateLimitTokenRequest,
+    RateLimitTokenResponse => crate::rate_limits::RateLimitTokenResponse,
+    RateLimitToken => crate::rate_limits::RateLimitToken,
+    RateLimitTokenPresentation => crate::rate_limits::RateLimitTokenPresentation,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+/// unknown kind of zkgroup value
+pub struct UnknownDescribedKind;
+
+impl FromStr for DescribedKind {
+    type Err = UnknownDescribedKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or(UnknownDescribedKind)
+    }
+}
+
+/// The public details of a serialized zkgroup value.
+///
+/// Fields that don't apply to a particular kind are left empty and omitted from the serialized
+/// form.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Description {
+    pub kind: DescribedKind,
+    /// The leading version byte (zero for types that use a reserved byte instead).
+    pub version: u8,
+    pub len: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<Timestamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redemption_time: Option<Timestamp>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_debug_name"
+    )]
+    pub backup_level: Option<BackupLevel>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_debug_name"
+    )]
+    pub credential_type: Option<BackupCredentialType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt_level: Option<ReceiptLevel>,
+    /// The number of endorsements, blinded tokens, etc. in a batch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// Attributes revealed to the verifying server, hex-encoded.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub revealed_attributes: BTreeMap<&'static str, String>,
+    /// Attributes present in a client-held credential that are withheld until it's presented.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub redacted_attributes: BTreeSet<&'static str>,
+}
+
+impl Description {
+    fn new(kind: DescribedKind, version: u8, len: usize) -> Self {
+        Self {
+            kind,
+            version,
+            len,
+            expiration: None,
+            redemption_time: None,
+            backup_level: None,
+            credential_type: None,
+            receipt_level: None,
+            count: None,
+            revealed_attributes: BTreeMap::new(),
+            redacted_attributes: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn reveal(&mut self, name: &'static str, value: impl AsRef<[u8]>) {
+        self.revealed_attributes.insert(name, hex::encode(value));
+    }
+
+    pub(crate) fn redact(&mut self, name: &'static str) {
+        self.redacted_attributes.insert(name);
+    }
+}
+
+fn serialize_debug_name<T: Debug, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(&format_args!("{value:?}")),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn describe_as<T: Describe>(
+    kind: DescribedKind,
+    bytes: &[u8],
+) -> Result<Description, ZkGroupDeserializationFailure> {
+    let value: T = crate::deserialize(bytes)?;
+    // Every kind starts with a version or reserved byte, so a successful decode means it's there.
+    let version = *bytes.first().expect("checked by deserialization");
+    let mut description = Description::new(kind, version, bytes.len());
+    value.describe(&mut description);
+    Ok(description)
+}
+
+/// Decodes `bytes` as a `kind` value and reports its public details.
+pub fn describe(
+    kind: DescribedKind,
+    bytes: &[u8],
+) -> Result<Description, ZkGroupDeserializationFailure> {
+    kind.describe(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::constants::*;
+    use crate::{RandomnessBytes, ServerSecretParams};
+
+    #[test]
+    fn kind_names_round_trip() {
+        for kind in DescribedKind::ALL {
+            assert_eq!(kind.name().parse(), Ok(*kind));
+        }
+        assert_eq!(
+            "ServerSecretParams".parse::<DescribedKind>(),
+            Err(UnknownDescribedKind)
+        );
+    }
+
+    #[test]
+    fn receipt_presentation() {
+        let server_secret_params = ServerSecretParams::generate(TEST_ARRAY_32);
+        let server_public_params = server_secret_params.get_public_params();
+        let receipt_serial_bytes = [0xa5; RECEIPT_SERIAL_LEN];
+        let randomness: RandomnessBytes = TEST_ARRAY_32_1;
+
+        let context = server_public_params
+            .create_receipt_credential_request_context(randomness, receipt_serial_bytes);
+        let response = server_secret_params.issue_receipt_credential(
+            TEST_ARRAY_32_2,
+            &context.get_request(),
+            Timestamp::from_epoch_seconds(86400 * 30),
+            3,
+        );
+        let credential = server_public_params
+            .receive_receipt_credential(&context, &response)
+            .expect("valid response");
+        let presentation = server_public_params
+            .create_receipt_credential_presentation(TEST_ARRAY_32_3, &credential);
+        let bytes = crate::serialize(&presentation);
+
+        let description =
+            describe(DescribedKind::ReceiptCredentialPresentation, &bytes).expect("valid");
+        assert_eq!(description.version, 0);
+        assert_eq!(description.len, bytes.len());
+        assert_eq!(
+            description.expiration,
+            Some(Timestamp::from_epoch_seconds(86400 * 30))
+        );
+        assert_eq!(description.receipt_level, Some(3));
+        assert_eq!(
+            description.revealed_attributes.get("receipt_serial"),
+            Some(&hex::encode(receipt_serial_bytes))
+        );
+
+        describe(DescribedKind::ReceiptCredentialResponse, &bytes).expect_err("not a response");
+        describe(DescribedKind::ReceiptCredentialPresentation, &bytes[1..]).expect_err("truncated");
+
+        // The client's credential carries the serial too, but doesn't reveal it.
+        let description = describe(
+            DescribedKind::ReceiptCredential,
+            &crate::serialize(&credential),
+        )
+        .expect("valid");
+        assert_eq!(description.receipt_level, Some(3));
+        assert!(description.revealed_attributes.is_empty());
+        assert_eq!(
+            description.redacted_attributes,
+            BTreeSet::from(["receipt_serial"])
+        );
+    }
+}
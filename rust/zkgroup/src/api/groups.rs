@@ -5,6 +5,8 @@
 
 pub mod group_params;
 mod group_send_endorsement;
+mod group_send_endorsement_cache;
+pub mod group_state;
 pub mod profile_key_ciphertext;
 pub mod uuid_ciphertext;
 
@@ -13,5 +15,6 @@ pub use group_send_endorsement::{
     GroupSendDerivedKeyPair, GroupSendEndorsement, GroupSendEndorsementsResponse,
     GroupSendFullToken, GroupSendToken,
 };
+pub use group_send_endorsement_cache::{GroupSendEndorsementCache, GroupSendEndorsementCacheError};
 pub use profile_key_ciphertext::ProfileKeyCiphertext;
 pub use uuid_ciphertext::UuidCiphertext;
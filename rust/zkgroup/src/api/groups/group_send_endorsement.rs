@@ -20,6 +20,7 @@ use rayon::iter::{IndexedParallelIterator as _, ParallelIterator as _};
 use serde::{Deserialize, Serialize};
 use zkcredential::attributes::Attribute as _;
 
+use crate::api::describe::{Describe, Description};
 use crate::common::array_utils;
 use crate::common::serialization::ReservedByte;
 use crate::crypto::uid_encryption;
@@ -590,3 +591,15 @@ impl GroupSendFullToken {
             .map_err(|_| ZkGroupVerificationFailure)
     }
 }
+
+impl Describe for GroupSendEndorsementsResponse {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.expiration);
+    }
+}
+
+impl Describe for GroupSendFullToken {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.expiration);
+    }
+}
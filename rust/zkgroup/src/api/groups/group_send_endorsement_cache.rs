@@ -0,0 +1,164 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides GroupSendEndorsementCache, which manages a single group's endorsements between
+//! refreshes from the server.
+
+use std::collections::{HashMap, HashSet};
+
+use libsignal_core::ServiceId;
+
+use crate::groups::{
+    GroupSecretParams, GroupSendEndorsement, GroupSendEndorsementsResponse, GroupSendFullToken,
+};
+use crate::{Timestamp, ZkGroupVerificationFailure};
+
+/// How long before expiration the cache asks for new endorsements.
+///
+/// This matches the margin used when accepting endorsements from the server, so a refresh will
+/// never be asked for and then rejected for expiring too soon.
+const REFRESH_MARGIN_SECONDS: u64 = 2 * 60 * 60;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum GroupSendEndorsementCacheError {
+    /// endorsements have expired
+    Expired,
+    /// no endorsement for a recipient
+    MissingEndorsement,
+}
+
+/// Tracks the endorsements for the members of one group.
+///
+/// The combined endorsement for every other member is kept up to date as members leave, so that
+/// tokens for large subsets of the group can be produced by removing the few excluded members
+/// rather than recombining everyone else. Members who join after the endorsements were issued
+/// don't have endorsements until the next refresh; see [`Self::needs_refresh`].
+#[derive(Clone)]
+pub struct GroupSendEndorsementCache {
+    local_user: ServiceId,
+    expiration: Timestamp,
+    // Excludes the local user.
+    endorsements: HashMap<ServiceId, GroupSendEndorsement>,
+    combined: GroupSendEndorsement,
+    missing: HashSet<ServiceId>,
+}
+
+impl GroupSendEndorsementCache {
+    /// Validates the endorsements in `response`, which must have been issued for exactly
+    /// `members` (including `local_user`).
+    pub fn receive(
+        response: GroupSendEndorsementsResponse,
+        members: Vec<ServiceId>,
+        local_user: ServiceId,
+        now: Timestamp,
+        group_params: &GroupSecretParams,
+        root_public_key: impl AsRef<zkcredential::endorsements::ServerRootPublicKey>,
+    ) -> Result<Self, ZkGroupVerificationFailure> {
+        let expiration = response.expiration();
+        let received = response.receive_with_service_ids(
+            members.clone(),
+            now,
+            group_params,
+            root_public_key,
+        )?;
+
+        let endorsements: HashMap<_, _> = members
+            .into_iter()
+            .zip(received)
+            .filter(|(member, _)| *member != local_user)
+            .map(|(member, received)| (member, received.decompressed))
+            .collect();
+        let combined = GroupSendEndorsement::combine(endorsements.values().copied());
+
+        Ok(Self {
+            local_user,
+            expiration,
+            endorsements,
+            combined,
+            missing: HashSet::new(),
+        })
+    }
+
+    pub fn expiration(&self) -> Timestamp {
+        self.expiration
+    }
+
+    /// Whether new endorsements should be fetched from the server, because the current ones are
+    /// about to expire or some members don't have one.
+    pub fn needs_refresh(&self, now: Timestamp) -> bool {
+        !self.missing.is_empty()
+            || self.expiration.saturating_seconds_since(now) < REFRESH_MARGIN_SECONDS
+    }
+
+    /// Brings the cache in line with the group's current membership.
+    pub fn update_members(&mut self, members: impl IntoIterator<Item = ServiceId>) {
+        let members: HashSet<_> = members.into_iter().collect();
+
+        let departed: Vec<_> = self
+            .endorsements
+            .keys()
+            .filter(|member| !members.contains(member))
+            .copied()
+            .collect();
+        for member in departed {
+            self.remove_member(&member);
+        }
+
+        self.missing.retain(|member| members.contains(member));
+        self.missing.extend(members.into_iter().filter(|member| {
+            *member != self.local_user && !self.endorsements.contains_key(member)
+        }));
+    }
+
+    pub fn remove_member(&mut self, member: &ServiceId) {
+        if let Some(endorsement) = self.endorsements.remove(member) {
+            self.combined = self.combined.remove(&endorsement);
+        }
+        self.missing.remove(member);
+    }
+
+    /// Produces a token for sending to `recipients`, who must be members of the group other than
+    /// the local user.
+    pub fn token_for(
+        &self,
+        recipients: &[ServiceId],
+        now: Timestamp,
+        group_params: &GroupSecretParams,
+    ) -> Result<GroupSendFullToken, GroupSendEndorsementCacheError> {
+        if self.expiration <= now {
+            return Err(GroupSendEndorsementCacheError::Expired);
+        }
+
+        let recipients: HashSet<_> = recipients.iter().collect();
+        if !recipients
+            .iter()
+            .all(|recipient| self.endorsements.contains_key(recipient))
+        {
+            return Err(GroupSendEndorsementCacheError::MissingEndorsement);
+        }
+
+        // Do whichever takes fewer operations: combining the recipients' endorsements, or removing
+        // everyone else's from the full combination.
+        let endorsement = if recipients.len() * 2 <= self.endorsements.len() {
+            GroupSendEndorsement::combine(
+                recipients
+                    .iter()
+                    .map(|recipient| self.endorsements[*recipient]),
+            )
+        } else {
+            self.combined.remove(&GroupSendEndorsement::combine(
+                self.endorsements
+                    .iter()
+                    .filter_map(|(member, endorsement)| {
+                        (!recipients.contains(member)).then_some(*endorsement)
+                    }),
+            ))
+        };
+
+        Ok(endorsement
+            .to_token(group_params)
+            .into_full_token(self.expiration))
+    }
+}
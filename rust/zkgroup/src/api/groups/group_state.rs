@@ -0,0 +1,538 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides a reference, in-memory implementation of server-side group state.
+//!
+//! The server never learns who is in a group: members are recorded by their [`UuidCiphertext`]
+//! and [`ProfileKeyCiphertext`], and requests are authenticated with an auth credential
+//! presentation whose ACI ciphertext must match a member's. Because encryption under a group's
+//! keys is deterministic, matching is just a comparison of ciphertexts.
+//!
+//! This is suitable for tests and small deployments; it does not persist anything, and only covers
+//! membership, roles, an encrypted title, and access control.
+
+use crate::api::auth::AnyAuthCredentialPresentation;
+use crate::api::groups::{GroupPublicParams, ProfileKeyCiphertext, UuidCiphertext};
+use crate::api::profiles::ExpiringProfileKeyCredentialPresentation;
+use crate::{ServerSecretParams, Timestamp};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemberRole {
+    Default,
+    Administrator,
+}
+
+/// Who may perform a particular kind of change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AccessRequired {
+    Member,
+    Administrator,
+    Unsatisfiable,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AccessControl {
+    /// Who may change the group's attributes, such as its title.
+    pub attributes: AccessRequired,
+    /// Who may add new members.
+    pub members: AccessRequired,
+}
+
+impl Default for AccessControl {
+    fn default() -> Self {
+        Self {
+            attributes: AccessRequired::Member,
+            members: AccessRequired::Member,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GroupMember {
+    pub user_id: UuidCiphertext,
+    pub profile_key: ProfileKeyCiphertext,
+    pub role: MemberRole,
+    pub joined_at_version: u32,
+}
+
+/// A single modification to a group, applied as part of [`GroupState::apply`].
+pub enum GroupChange {
+    /// Adds the holder of a profile key credential, who must not already be a member.
+    ///
+    /// Only administrators may add other administrators.
+    AddMember {
+        presentation: ExpiringProfileKeyCredentialPresentation,
+        role: MemberRole,
+    },
+    /// Removes a member. Members may always remove themselves; otherwise this requires an
+    /// administrator.
+    RemoveMember(UuidCiphertext),
+    /// Changes a member's role. Requires an administrator.
+    ModifyMemberRole {
+        user_id: UuidCiphertext,
+        role: MemberRole,
+    },
+    /// Replaces the encrypted title.
+    ModifyTitle(Vec<u8>),
+    /// Requires an administrator.
+    ModifyAttributesAccess(AccessRequired),
+    /// Requires an administrator.
+    ModifyMembersAccess(AccessRequired),
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum GroupStateError {
+    /// presentation failed verification
+    InvalidPresentation,
+    /// the author is not a member of the group
+    NotAMember,
+    /// change {0} is not permitted for the author
+    Forbidden(usize),
+    /// change {0} adds someone who is already a member
+    AlreadyAMember(usize),
+    /// change {0} refers to someone who is not a member
+    UnknownMember(usize),
+    /// the group would be left without an administrator
+    NoAdministrator,
+}
+
+/// The server's view of a single group.
+#[derive(Clone)]
+pub struct GroupState {
+    public_params: GroupPublicParams,
+    version: u32,
+    title: Vec<u8>,
+    access_control: AccessControl,
+    members: Vec<GroupMember>,
+}
+
+impl GroupState {
+    /// Creates a group at version 0 whose only member is its founder, as an administrator.
+    pub fn new(
+        server_params: &ServerSecretParams,
+        public_params: GroupPublicParams,
+        founder: &ExpiringProfileKeyCredentialPresentation,
+        title: Vec<u8>,
+        now: Timestamp,
+    ) -> Result<Self, GroupStateError> {
+        server_params
+            .verify_expiring_profile_key_credential_presentation(public_params, founder, now)
+            .map_err(|_| GroupStateError::InvalidPresentation)?;
+        Ok(Self {
+            public_params,
+            version: 0,
+            title,
+            access_control: AccessControl::default(),
+            members: vec![GroupMember {
+                user_id: founder.get_uuid_ciphertext(),
+                profile_key: founder.get_profile_key_ciphertext(),
+                role: MemberRole::Administrator,
+                joined_at_version: 0,
+            }],
+        })
+    }
+
+    pub fn public_params(&self) -> GroupPublicParams {
+        self.public_params
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn title(&self) -> &[u8] {
+        &self.title
+    }
+
+    pub fn access_control(&self) -> AccessControl {
+        self.access_control
+    }
+
+    pub fn members(&self) -> &[GroupMember] {
+        &self.members
+    }
+
+    pub fn member(&self, user_id: &UuidCiphertext) -> Option<&GroupMember> {
+        self.members.iter().find(|m| m.user_id == *user_id)
+    }
+
+    /// Checks that `presentation` is valid for this group and belongs to a current member.
+    ///
+    /// Returns that member.
+    pub fn authenticate(
+        &self,
+        server_params: &ServerSecretParams,
+        presentation: &AnyAuthCredentialPresentation,
+        now: Timestamp,
+    ) -> Result<&GroupMember, GroupStateError> {
+        server_params
+            .verify_auth_credential_presentation(self.public_params, presentation, now)
+            .map_err(|_| GroupStateError::InvalidPresentation)?;
+        self.member(&presentation.get_aci_ciphertext())
+            .ok_or(GroupStateError::NotAMember)
+    }
+
+    /// Applies `changes` on behalf of the member who made `author`, producing a new version.
+    ///
+    /// Changes are applied in order, and either all of them take effect or none do. On success,
+    /// returns the new version number.
+    pub fn apply(
+        &mut self,
+        server_params: &ServerSecretParams,
+        author: &AnyAuthCredentialPresentation,
+        changes: &[GroupChange],
+        now: Timestamp,
+    ) -> Result<u32, GroupStateError> {
+        let author = self.authenticate(server_params, author, now)?.user_id;
+
+        let mut next = self.clone();
+        next.version += 1;
+        for (index, change) in changes.iter().enumerate() {
+            next.apply_one(server_params, &author, index, change, now)?;
+        }
+
+        if !next.members.is_empty()
+            && !next
+                .members
+                .iter()
+                .any(|m| m.role == MemberRole::Administrator)
+        {
+            return Err(GroupStateError::NoAdministrator);
+        }
+
+        *self = next;
+        Ok(self.version)
+    }
+
+    fn apply_one(
+        &mut self,
+        server_params: &ServerSecretParams,
+        author: &UuidCiphertext,
+        index: usize,
+        change: &GroupChange,
+        now: Timestamp,
+    ) -> Result<(), GroupStateError> {
+        // The author may have removed themselves or changed their own role earlier in the batch;
+        // later changes are checked against that.
+        let author_role = self.member(author).map(|m| m.role);
+        let has_access = |required: AccessRequired| {
+            matches!(
+                (required, author_role),
+                (AccessRequired::Member, Some(_))
+                    | (
+                        AccessRequired::Administrator,
+                        Some(MemberRole::Administrator)
+                    )
+            )
+        };
+        let is_admin = has_access(AccessRequired::Administrator);
+        let forbidden = GroupStateError::Forbidden(index);
+
+        match change {
+            GroupChange::AddMember { presentation, role } => {
+                if !has_access(self.access_control.members)
+                    || (*role == MemberRole::Administrator && !is_admin)
+                {
+                    return Err(forbidden);
+                }
+                server_params
+                    .verify_expiring_profile_key_credential_presentation(
+                        self.public_params,
+                        presentation,
+                        now,
+                    )
+                    .map_err(|_| GroupStateError::InvalidPresentation)?;
+                let user_id = presentation.get_uuid_ciphertext();
+                if self.member(&user_id).is_some() {
+                    return Err(GroupStateError::AlreadyAMember(index));
+                }
+                self.members.push(GroupMember {
+                    user_id,
+                    profile_key: presentation.get_profile_key_ciphertext(),
+                    role: *role,
+                    joined_at_version: self.version,
+                });
+            }
+            GroupChange::RemoveMember(user_id) => {
+                if !is_admin && !(author_role.is_some() && user_id == author) {
+                    return Err(forbidden);
+                }
+                let position = self
+                    .members
+                    .iter()
+                    .position(|m| m.user_id == *user_id)
+                    .ok_or(GroupStateError::UnknownMember(index))?;
+                self.members.remove(position);
+            }
+            GroupChange::ModifyMemberRole { user_id, role } => {
+                if !is_admin {
+                    return Err(forbidden);
+                }
+                let member = self
+                    .members
+                    .iter_mut()
+                    .find(|m| m.user_id == *user_id)
+                    .ok_or(GroupStateError::UnknownMember(index))?;
+                member.role = *role;
+            }
+            GroupChange::ModifyTitle(title) => {
+                if !has_access(self.access_control.attributes) {
+                    return Err(forbidden);
+                }
+                self.title.clone_from(title);
+            }
+            GroupChange::ModifyAttributesAccess(required) => {
+                if !is_admin {
+                    return Err(forbidden);
+                }
+                self.access_control.attributes = *required;
+            }
+            GroupChange::ModifyMembersAccess(required) => {
+                if !is_admin {
+                    return Err(forbidden);
+                }
+                self.access_control.members = *required;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libsignal_core::{Aci, Pni};
+
+    use super::*;
+    use crate::auth::AuthCredentialWithPniZkcResponse;
+    use crate::common::constants::*;
+    use crate::groups::{GroupMasterKey, GroupSecretParams};
+    use crate::profiles::ProfileKey;
+    use crate::{SECONDS_PER_DAY, ServerPublicParams};
+
+    const NOW: Timestamp = Timestamp::from_epoch_seconds(16 * SECONDS_PER_DAY);
+
+    struct User {
+        aci: Aci,
+        profile_key: ProfileKey,
+    }
+
+    impl User {
+        fn new(seed: u8) -> Self {
+            Self {
+                aci: Aci::from_uuid_bytes([seed; 16]),
+                profile_key: ProfileKey::create([seed; 32]),
+            }
+        }
+
+        fn auth(
+            &self,
+            server: &ServerSecretParams,
+            group: &GroupSecretParams,
+        ) -> AnyAuthCredentialPresentation {
+            let public = server.get_public_params();
+            let pni = Pni::from_uuid_bytes([0xFF; 16]);
+            AuthCredentialWithPniZkcResponse::issue_credential(
+                self.aci,
+                pni,
+                NOW,
+                server,
+                TEST_ARRAY_32_2,
+            )
+            .receive(self.aci, pni, NOW, &public)
+            .expect("valid response")
+            .present(&public, group, TEST_ARRAY_32_5)
+            .into()
+        }
+
+        fn profile(
+            &self,
+            server: &ServerSecretParams,
+            group: &GroupSecretParams,
+        ) -> ExpiringProfileKeyCredentialPresentation {
+            let public: ServerPublicParams = server.get_public_params();
+            let context = public.create_profile_key_credential_request_context(
+                TEST_ARRAY_32_3,
+                self.aci,
+                self.profile_key,
+            );
+            let response = server
+                .issue_expiring_profile_key_credential(
+                    TEST_ARRAY_32_4,
+                    &context.get_request(),
+                    self.aci,
+                    self.profile_key.get_commitment(self.aci),
+                    NOW.add_seconds(SECONDS_PER_DAY),
+                )
+                .expect("valid request");
+            let credential = public
+                .receive_expiring_profile_key_credential(&context, &response, NOW)
+                .expect("valid response");
+            public.create_expiring_profile_key_credential_presentation(
+                TEST_ARRAY_32_5,
+                *group,
+                credential,
+            )
+        }
+    }
+
+    #[test]
+    fn membership_and_access_control() {
+        let server = ServerSecretParams::generate(TEST_ARRAY_32);
+        let group = GroupSecretParams::derive_from_master_key(GroupMasterKey::new(TEST_ARRAY_32_1));
+        let [alice, bob, carol] = [1, 2, 3].map(User::new);
+
+        let mut state = GroupState::new(
+            &server,
+            group.get_public_params(),
+            &alice.profile(&server, &group),
+            b"title".to_vec(),
+            NOW,
+        )
+        .expect("valid founder");
+        let alice_id = group.encrypt_service_id(alice.aci.into());
+        let bob_id = group.encrypt_service_id(bob.aci.into());
+
+        // Non-members can't make changes.
+        assert_eq!(
+            state.apply(
+                &server,
+                &bob.auth(&server, &group),
+                &[GroupChange::ModifyTitle(vec![])],
+                NOW
+            ),
+            Err(GroupStateError::NotAMember)
+        );
+
+        // Alice adds Bob and restricts who can change the title.
+        let version = state
+            .apply(
+                &server,
+                &alice.auth(&server, &group),
+                &[
+                    GroupChange::AddMember {
+                        presentation: bob.profile(&server, &group),
+                        role: MemberRole::Default,
+                    },
+                    GroupChange::ModifyAttributesAccess(AccessRequired::Administrator),
+                ],
+                NOW,
+            )
+            .expect("allowed");
+        assert_eq!(version, 1);
+        assert_eq!(
+            state.member(&bob_id).map(|m| (m.role, m.joined_at_version)),
+            Some((MemberRole::Default, 1))
+        );
+
+        // Bob may add Carol, but may no longer change the title.
+        let bob_auth = bob.auth(&server, &group);
+        assert_eq!(
+            state.apply(
+                &server,
+                &bob_auth,
+                &[
+                    GroupChange::AddMember {
+                        presentation: carol.profile(&server, &group),
+                        role: MemberRole::Default,
+                    },
+                    GroupChange::ModifyTitle(b"mine now".to_vec()),
+                ],
+                NOW
+            ),
+            Err(GroupStateError::Forbidden(1))
+        );
+        // The whole batch was rejected.
+        assert_eq!(state.version(), 1);
+        assert_eq!(state.members().len(), 2);
+        assert_eq!(state.title(), b"title");
+
+        // Alice can't leave without leaving an administrator behind...
+        let alice_auth = alice.auth(&server, &group);
+        assert_eq!(
+            state.apply(
+                &server,
+                &alice_auth,
+                &[GroupChange::RemoveMember(alice_id)],
+                NOW
+            ),
+            Err(GroupStateError::NoAdministrator)
+        );
+
+        // ...but she can after promoting Bob.
+        state
+            .apply(
+                &server,
+                &alice_auth,
+                &[
+                    GroupChange::ModifyMemberRole {
+                        user_id: bob_id,
+                        role: MemberRole::Administrator,
+                    },
+                    GroupChange::RemoveMember(alice_id),
+                ],
+                NOW,
+            )
+            .expect("allowed");
+        assert!(state.member(&alice_id).is_none());
+        assert_eq!(
+            state.authenticate(&server, &alice_auth, NOW).err(),
+            Some(GroupStateError::NotAMember)
+        );
+        assert_eq!(
+            state
+                .authenticate(&server, &bob_auth, NOW)
+                .map(|m| m.role)
+                .ok(),
+            Some(MemberRole::Administrator)
+        );
+    }
+
+    #[test]
+    fn presentations_must_be_for_this_group() {
+        let server = ServerSecretParams::generate(TEST_ARRAY_32);
+        let group = GroupSecretParams::derive_from_master_key(GroupMasterKey::new(TEST_ARRAY_32_1));
+        let other_group =
+            GroupSecretParams::derive_from_master_key(GroupMasterKey::new(TEST_ARRAY_32_2));
+        let [alice, bob] = [1, 2].map(User::new);
+
+        assert!(matches!(
+            GroupState::new(
+                &server,
+                group.get_public_params(),
+                &alice.profile(&server, &other_group),
+                vec![],
+                NOW,
+            ),
+            Err(GroupStateError::InvalidPresentation)
+        ));
+
+        let mut state = GroupState::new(
+            &server,
+            group.get_public_params(),
+            &alice.profile(&server, &group),
+            vec![],
+            NOW,
+        )
+        .expect("valid founder");
+        assert_eq!(
+            state
+                .authenticate(&server, &alice.auth(&server, &other_group), NOW)
+                .err(),
+            Some(GroupStateError::InvalidPresentation)
+        );
+        assert_eq!(
+            state.apply(
+                &server,
+                &alice.auth(&server, &group),
+                &[GroupChange::AddMember {
+                    presentation: bob.profile(&server, &other_group),
+                    role: MemberRole::Default,
+                }],
+                NOW
+            ),
+            Err(GroupStateError::InvalidPresentation)
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides support for rotating server keys without invalidating outstanding credentials.
+//!
+//! A server keeps its current key set along with a limited number of previous generations in a
+//! [`ServerKeyGenerations`]. Issued credentials don't record which key set was used, so responses
+//! and presentations travel as [`KeyTagged`] values that carry the [`KeyId`] of the parameters
+//! they were created with. Clients keep the public parameters of every generation they might still
+//! need in a [`PublicKeyGenerations`], and look them up by that key ID.
+//!
+//! The key ID is part of a tagged value's serialized form: a version byte, then the key ID, then
+//! the value's own serialization, which is unchanged. Wrapping rather than changing each
+//! credential's format is enough because the key ID only selects which parameters to verify
+//! against. It isn't covered by any proof, but a presentation with the wrong key ID is checked
+//! against parameters it wasn't created with and fails, just like one from a retired generation.
+
+use std::collections::VecDeque;
+
+use partial_default::PartialDefault;
+use poksho::ShoApi;
+use poksho::shoapi::ShoApiExt as _;
+use serde::{Deserialize, Serialize};
+
+use crate::common::serialization::{ReservedByte, serialize};
+use crate::generic_server_params::{GenericServerPublicParams, GenericServerSecretParams};
+use crate::{ServerPublicParams, ServerSecretParams, ZkGroupVerificationFailure};
+
+pub const KEY_ID_LEN: usize = 8;
+
+/// Identifies a generation of server parameters.
+///
+/// Derived from the public parameters, so the server and its clients always agree on it without
+/// any extra coordination.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialDefault)]
+pub struct KeyId([u8; KEY_ID_LEN]);
+
+impl KeyId {
+    pub fn for_public_params(public_params: &impl Serialize) -> Self {
+        let mut sho = poksho::ShoHmacSha256::new(b"20261018_Signal_ServerParamsKeyId");
+        sho.absorb_and_ratchet(&serialize(public_params));
+        Self(sho.squeeze_and_ratchet_as_array())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_ID_LEN] {
+        &self.0
+    }
+}
+
+/// Server parameters that can be rotated using [`ServerKeyGenerations`].
+pub trait RotatableServerParams {
+    type PublicParams: Serialize;
+
+    fn public_params(&self) -> Self::PublicParams;
+}
+
+impl RotatableServerParams for ServerSecretParams {
+    type PublicParams = ServerPublicParams;
+
+    fn public_params(&self) -> Self::PublicParams {
+        self.get_public_params()
+    }
+}
+
+impl RotatableServerParams for GenericServerSecretParams {
+    type PublicParams = GenericServerPublicParams;
+
+    fn public_params(&self) -> Self::PublicParams {
+        self.get_public_params()
+    }
+}
+
+/// A value created with a particular generation of server parameters.
+///
+/// Serializes as a version byte and the [`KeyId`], followed by the value.
+#[derive(Clone, Serialize, Deserialize, PartialDefault)]
+#[partial_default(bound = "T: PartialDefault")]
+pub struct KeyTagged<T> {
+    version: ReservedByte,
+    key_id: KeyId,
+    value: T,
+}
+
+impl<T> KeyTagged<T> {
+    pub fn new(key_id: KeyId, value: T) -> Self {
+        Self {
+            version: ReservedByte::default(),
+            key_id,
+            value,
+        }
+    }
+
+    pub fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Transforms the value while keeping its key ID.
+    ///
+    /// Useful for clients turning a tagged response into a tagged credential.
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<KeyTagged<U>, E> {
+        Ok(KeyTagged::new(self.key_id, f(self.value)?))
+    }
+}
+
+/// The current generation of a server's parameters, plus a bounded number of previous ones that
+/// are still accepted.
+pub struct ServerKeyGenerations<S> {
+    // Newest first.
+    generations: VecDeque<(KeyId, S)>,
+    max_previous_generations: usize,
+}
+
+impl<S: RotatableServerParams> ServerKeyGenerations<S> {
+    /// Starts with `current` as the only generation.
+    ///
+    /// To restore a saved set of generations, start with the oldest and [`rotate`](Self::rotate)
+    /// through the rest.
+    pub fn new(current: S, max_previous_generations: usize) -> Self {
+        let key_id = KeyId::for_public_params(&current.public_params());
+        Self {
+            generations: VecDeque::from([(key_id, current)]),
+            max_previous_generations,
+        }
+    }
+
+    /// Makes `next` the current generation, retiring the oldest one if there are now too many.
+    pub fn rotate(&mut self, next: S) {
+        let key_id = KeyId::for_public_params(&next.public_params());
+        self.generations.push_front((key_id, next));
+        self.generations
+            .truncate(self.max_previous_generations.saturating_add(1));
+    }
+
+    pub fn current(&self) -> &S {
+        let (_key_id, params) = self.generations.front().expect("always at least one");
+        params
+    }
+
+    pub fn current_key_id(&self) -> KeyId {
+        let (key_id, _params) = self.generations.front().expect("always at least one");
+        *key_id
+    }
+
+    /// Returns the generation identified by `key_id`, if it hasn't been retired.
+    pub fn get(&self, key_id: KeyId) -> Option<&S> {
+        self.generations
+            .iter()
+            .find_map(|(id, params)| (*id == key_id).then_some(params))
+    }
+
+    /// Issues a value using the current generation, tagging it with that generation's key ID.
+    pub fn issue<T>(&self, issue: impl FnOnce(&S) -> T) -> KeyTagged<T> {
+        KeyTagged::new(self.current_key_id(), issue(self.current()))
+    }
+
+    /// Verifies a tagged value using the generation it was created with.
+    ///
+    /// Values from retired or unknown generations always fail verification.
+    pub fn verify<T, R>(
+        &self,
+        tagged: &KeyTagged<T>,
+        verify: impl FnOnce(&S, &T) -> Result<R, ZkGroupVerificationFailure>,
+    ) -> Result<R, ZkGroupVerificationFailure> {
+        let params = self.get(tagged.key_id).ok_or(ZkGroupVerificationFailure)?;
+        verify(params, &tagged.value)
+    }
+
+    /// The public parameters of every generation still accepted, for distribution to clients.
+    pub fn public_params(&self) -> PublicKeyGenerations<S::PublicParams> {
+        PublicKeyGenerations {
+            generations: self
+                .generations
+                .iter()
+                .map(|(key_id, params)| (*key_id, params.public_params()))
+                .collect(),
+        }
+    }
+}
+
+/// The public parameters for each generation of server keys a client may need.
+#[derive(Clone)]
+pub struct PublicKeyGenerations<P> {
+    // Newest first.
+    generations: Vec<(KeyId, P)>,
+}
+
+impl<P> Default for PublicKeyGenerations<P> {
+    fn default() -> Self {
+        Self {
+            generations: Vec::new(),
+        }
+    }
+}
+
+impl<P: Serialize> PublicKeyGenerations<P> {
+    /// Adds a newly-published generation, making it the current one.
+    ///
+    /// Returns its key ID. Adding a generation that's already present just makes it current.
+    pub fn insert(&mut self, public_params: P) -> KeyId {
+        let key_id = KeyId::for_public_params(&public_params);
+        self.generations.retain(|(id, _)| *id != key_id);
+        self.generations.insert(0, (key_id, public_params));
+        key_id
+    }
+
+    /// Keeps only the current generation and up to `max_previous_generations` before it.
+    pub fn retain_newest(&mut self, max_previous_generations: usize) {
+        self.generations
+            .truncate(max_previous_generations.saturating_add(1));
+    }
+
+    /// Returns the newest generation and its key ID, to be used for new requests.
+    pub fn current(&self) -> Option<(KeyId, &P)> {
+        self.generations
+            .first()
+            .map(|(key_id, params)| (*key_id, params))
+    }
+
+    pub fn get(&self, key_id: KeyId) -> Option<&P> {
+        self.generations
+            .iter()
+            .find_map(|(id, params)| (*id == key_id).then_some(params))
+    }
+
+    /// Processes a tagged value using the generation it was created with.
+    ///
+    /// Fails if this client doesn't know about that generation.
+    pub fn receive<T, R>(
+        &self,
+        tagged: KeyTagged<T>,
+        receive: impl FnOnce(&P, T) -> Result<R, ZkGroupVerificationFailure>,
+    ) -> Result<KeyTagged<R>, ZkGroupVerificationFailure> {
+        let params = self.get(tagged.key_id).ok_or(ZkGroupVerificationFailure)?;
+        tagged.try_map(|value| receive(params, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::constants::*;
+    use crate::receipts::{ReceiptCredential, ReceiptCredentialPresentation};
+    use crate::{RANDOMNESS_LEN, Timestamp};
+
+    #[test]
+    fn key_ids_are_stable_and_distinct() {
+        let first = ServerSecretParams::generate(TEST_ARRAY_32);
+        let second = ServerSecretParams::generate(TEST_ARRAY_32_1);
+        let first_id = KeyId::for_public_params(&first.get_public_params());
+        assert_eq!(
+            first_id,
+            KeyId::for_public_params(&first.get_public_params())
+        );
+        assert_ne!(
+            first_id,
+            KeyId::for_public_params(&second.get_public_params())
+        );
+    }
+
+    #[test]
+    fn tagged_values_carry_their_key_id_when_serialized() {
+        let params = ServerSecretParams::generate(TEST_ARRAY_32);
+        let key_id = KeyId::for_public_params(&params.get_public_params());
+        let value = vec![1u8, 2, 3];
+
+        let serialized = serialize(&KeyTagged::new(key_id, value.clone()));
+        let (version, rest) = serialized.split_first().expect("not empty");
+        let (serialized_key_id, serialized_value) = rest.split_at(KEY_ID_LEN);
+        assert_eq!(*version, 0);
+        assert_eq!(serialized_key_id, key_id.as_bytes());
+        assert_eq!(serialized_value, serialize(&value));
+
+        let deserialized: KeyTagged<Vec<u8>> = crate::deserialize(&serialized).expect("valid");
+        assert_eq!(deserialized.key_id(), key_id);
+        assert_eq!(deserialized.into_value(), value);
+
+        let mut wrong_version = serialized;
+        wrong_version[0] = 1;
+        assert!(crate::deserialize::<KeyTagged<Vec<u8>>>(&wrong_version).is_err());
+    }
+
+    #[test]
+    fn rotation_keeps_previous_generations() {
+        let mut server = ServerKeyGenerations::new(ServerSecretParams::generate(TEST_ARRAY_32), 1);
+        let mut client = server.public_params();
+        let receipt_serial_bytes = [0x84; RECEIPT_SERIAL_LEN];
+
+        // Issue a credential under the first generation.
+        let (_, public_params) = client.current().expect("has a generation");
+        let context = public_params
+            .create_receipt_credential_request_context(TEST_ARRAY_32_1, receipt_serial_bytes);
+        let response = server.issue(|params| {
+            params.issue_receipt_credential(
+                TEST_ARRAY_32_2,
+                &context.get_request(),
+                Timestamp::from_epoch_seconds(31337),
+                3,
+            )
+        });
+        let credential: KeyTagged<ReceiptCredential> = client
+            .receive(response, |params, response| {
+                params.receive_receipt_credential(&context, &response)
+            })
+            .expect("valid response");
+        let present = |client: &PublicKeyGenerations<ServerPublicParams>| {
+            let params = client.get(credential.key_id()).expect("known generation");
+            KeyTagged::new(
+                credential.key_id(),
+                params.create_receipt_credential_presentation(
+                    [0x45; RANDOMNESS_LEN],
+                    credential.value(),
+                ),
+            )
+        };
+        let verify =
+            |server: &ServerKeyGenerations<ServerSecretParams>,
+             presentation: &KeyTagged<ReceiptCredentialPresentation>| {
+                server.verify(presentation, |params, presentation| {
+                    params.verify_receipt_credential_presentation(presentation)
+                })
+            };
+
+        // The credential keeps working through one rotation...
+        server.rotate(ServerSecretParams::generate(TEST_ARRAY_32_3));
+        client.insert(server.current().get_public_params());
+        assert_ne!(credential.key_id(), server.current_key_id());
+        verify(&server, &present(&client)).expect("previous generation still accepted");
+
+        // ...but not two.
+        server.rotate(ServerSecretParams::generate(TEST_ARRAY_32_4));
+        client.insert(server.current().get_public_params());
+        assert!(server.get(credential.key_id()).is_none());
+        verify(&server, &present(&client)).expect_err("generation was retired");
+
+        client.retain_newest(0);
+        assert!(client.get(credential.key_id()).is_none());
+        assert_eq!(
+            client.current().map(|(key_id, _)| key_id),
+            Some(server.current_key_id())
+        );
+    }
+}
@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::simple_types::*;
 use crate::crypto;
@@ -28,3 +29,9 @@ impl ExpiringProfileKeyCredential {
         self.credential_expiration_time
     }
 }
+
+impl Describe for ExpiringProfileKeyCredential {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.credential_expiration_time);
+    }
+}
@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::simple_types::*;
 use crate::crypto;
@@ -17,3 +18,9 @@ pub struct ExpiringProfileKeyCredentialResponse {
     pub(crate) credential_expiration_time: Timestamp,
     pub(crate) proof: crypto::proofs::ExpiringProfileKeyCredentialIssuanceProof,
 }
+
+impl Describe for ExpiringProfileKeyCredentialResponse {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.credential_expiration_time);
+    }
+}
@@ -0,0 +1,511 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides ProfileCipher, which encrypts the fields of a user's profile with their [`ProfileKey`].
+//!
+//! Each field is encrypted with AES-256-GCM under the profile key itself, producing
+//! `nonce || ciphertext || tag`. Before encryption, text fields are padded with zeros to the
+//! smallest of a fixed set of lengths that fits, so that the server only learns roughly how long
+//! they are. Avatars are padded to a length that grows by 5% per step, and payment addresses are
+//! length-prefixed and padded to a single fixed length.
+
+use libsignal_core::curve::{PrivateKey, PublicKey};
+use prost::Message as _;
+use rand::{CryptoRng, Rng};
+use signal_crypto::{Aes256GcmDecryption, Aes256GcmEncryption};
+
+use super::ProfileKey;
+use crate::common::constants::*;
+use crate::common::sho::Sho;
+use crate::common::simple_types::*;
+
+/// The lengths an encoded [`ProfileName`] may be padded to.
+pub const PROFILE_NAME_PADDED_LENGTHS: &[usize] = &[53, 257];
+/// The lengths an "about" text may be padded to.
+pub const PROFILE_ABOUT_PADDED_LENGTHS: &[usize] = &[128, 254, 512];
+/// The lengths an "about" emoji may be padded to.
+pub const PROFILE_ABOUT_EMOJI_PADDED_LENGTHS: &[usize] = &[32];
+/// The length an encoded payment address is padded to, including its length prefix.
+pub const PROFILE_PAYMENT_ADDRESS_PADDED_LENGTH: usize = 554;
+
+const MIN_AVATAR_PADDED_LENGTH: usize = 541;
+const PAYMENT_ADDRESS_LENGTH_PREFIX_LEN: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, thiserror::Error, displaydoc::Display)]
+pub enum ProfileCipherError {
+    /// value is too long to be padded
+    InputTooLong,
+    /// decryption failed
+    DecryptionFailure,
+    /// decrypted value is malformed
+    InvalidEncoding,
+    /// payment address signature is invalid
+    InvalidSignature,
+}
+
+/// A profile name, encoded as `given_name` and `family_name` separated by a NUL byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileName {
+    pub given_name: String,
+    pub family_name: Option<String>,
+}
+
+impl ProfileName {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.given_name.as_bytes().to_vec();
+        if let Some(family_name) = &self.family_name {
+            encoded.push(0);
+            encoded.extend_from_slice(family_name.as_bytes());
+        }
+        encoded
+    }
+
+    fn decode(encoded: Vec<u8>) -> Result<Self, ProfileCipherError> {
+        let encoded =
+            String::from_utf8(encoded).map_err(|_| ProfileCipherError::InvalidEncoding)?;
+        Ok(match encoded.split_once('\0') {
+            Some((given_name, family_name)) => Self {
+                given_name: given_name.to_owned(),
+                family_name: Some(family_name.to_owned()),
+            },
+            None => Self {
+                given_name: encoded,
+                family_name: None,
+            },
+        })
+    }
+}
+
+/// A MobileCoin public address, signed by the owner's identity key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MobileCoinAddress {
+    pub address: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl MobileCoinAddress {
+    pub fn sign<R: Rng + CryptoRng>(
+        address: Vec<u8>,
+        identity_key: &PrivateKey,
+        csprng: &mut R,
+    ) -> Self {
+        let signature = identity_key
+            .calculate_signature(&address, csprng)
+            .expect("identity keys can sign")
+            .into_vec();
+        Self { address, signature }
+    }
+
+    pub fn verify(&self, identity_key: &PublicKey) -> bool {
+        identity_key.verify_signature(&self.address, &self.signature)
+    }
+
+    /// Encodes the address as a `PaymentAddress` protobuf message.
+    fn encode(&self) -> Vec<u8> {
+        proto::PaymentAddress {
+            address: Some(proto::payment_address::Address::MobileCoinAddress(
+                proto::MobileCoinAddress {
+                    address: Some(self.address.clone()),
+                    signature: Some(self.signature.clone()),
+                },
+            )),
+        }
+        .encode_to_vec()
+    }
+
+    fn decode(encoded: &[u8]) -> Result<Self, ProfileCipherError> {
+        let proto::PaymentAddress { address } = proto::PaymentAddress::decode(encoded)
+            .map_err(|_| ProfileCipherError::InvalidEncoding)?;
+        let Some(proto::payment_address::Address::MobileCoinAddress(address)) = address else {
+            return Err(ProfileCipherError::InvalidEncoding);
+        };
+        Ok(Self {
+            address: address.address.unwrap_or_default(),
+            signature: address.signature.unwrap_or_default(),
+        })
+    }
+}
+
+/// The padded length of an avatar of `len` bytes.
+///
+/// This is `floor(1.05^ceil(log_1.05(len)))`, but at least 541, computed the same way as the
+/// original client implementations so that all clients produce the same lengths.
+pub fn avatar_padded_length(len: usize) -> usize {
+    let exponent = ((len as f64).ln() / 1.05f64.ln()).ceil();
+    // A zero-length avatar produces an exponent of -inf, and thus a bucket of 0.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let bucket = 1.05f64.powf(exponent).floor() as usize;
+    bucket.max(MIN_AVATAR_PADDED_LENGTH)
+}
+
+/// Encrypts and decrypts profile fields with a [`ProfileKey`].
+#[derive(Copy, Clone)]
+pub struct ProfileCipher {
+    key: ProfileKeyBytes,
+}
+
+impl ProfileCipher {
+    pub fn new(profile_key: ProfileKey) -> Self {
+        Self {
+            key: profile_key.get_bytes(),
+        }
+    }
+
+    pub fn encrypt_name(
+        &self,
+        randomness: RandomnessBytes,
+        name: &ProfileName,
+    ) -> Result<Vec<u8>, ProfileCipherError> {
+        self.encrypt_padded(randomness, name.encode(), PROFILE_NAME_PADDED_LENGTHS)
+    }
+
+    pub fn decrypt_name(&self, encrypted: &[u8]) -> Result<ProfileName, ProfileCipherError> {
+        ProfileName::decode(self.decrypt_padded(encrypted)?)
+    }
+
+    pub fn encrypt_about(
+        &self,
+        randomness: RandomnessBytes,
+        about: &str,
+    ) -> Result<Vec<u8>, ProfileCipherError> {
+        self.encrypt_padded(randomness, about.into(), PROFILE_ABOUT_PADDED_LENGTHS)
+    }
+
+    pub fn decrypt_about(&self, encrypted: &[u8]) -> Result<String, ProfileCipherError> {
+        String::from_utf8(self.decrypt_padded(encrypted)?)
+            .map_err(|_| ProfileCipherError::InvalidEncoding)
+    }
+
+    pub fn encrypt_about_emoji(
+        &self,
+        randomness: RandomnessBytes,
+        emoji: &str,
+    ) -> Result<Vec<u8>, ProfileCipherError> {
+        self.encrypt_padded(randomness, emoji.into(), PROFILE_ABOUT_EMOJI_PADDED_LENGTHS)
+    }
+
+    pub fn decrypt_about_emoji(&self, encrypted: &[u8]) -> Result<String, ProfileCipherError> {
+        self.decrypt_about(encrypted)
+    }
+
+    /// Encrypts an avatar image, padded to [`avatar_padded_length`].
+    pub fn encrypt_avatar(&self, randomness: RandomnessBytes, avatar: &[u8]) -> Vec<u8> {
+        let mut padded = avatar.to_vec();
+        padded.resize(avatar_padded_length(avatar.len()), 0);
+        self.encrypt(randomness, padded)
+    }
+
+    /// Decrypts an avatar image.
+    ///
+    /// The padding is left in place; image formats ignore trailing data.
+    pub fn decrypt_avatar(&self, encrypted: &[u8]) -> Result<Vec<u8>, ProfileCipherError> {
+        self.decrypt(encrypted)
+    }
+
+    pub fn encrypt_payment_address(
+        &self,
+        randomness: RandomnessBytes,
+        address: &MobileCoinAddress,
+    ) -> Result<Vec<u8>, ProfileCipherError> {
+        let encoded = address.encode();
+        let encoded_len =
+            u32::try_from(encoded.len()).map_err(|_| ProfileCipherError::InputTooLong)?;
+        if PAYMENT_ADDRESS_LENGTH_PREFIX_LEN + encoded.len() > PROFILE_PAYMENT_ADDRESS_PADDED_LENGTH
+        {
+            return Err(ProfileCipherError::InputTooLong);
+        }
+        let mut padded = Vec::with_capacity(PROFILE_PAYMENT_ADDRESS_PADDED_LENGTH);
+        padded.extend_from_slice(&encoded_len.to_le_bytes());
+        padded.extend_from_slice(&encoded);
+        padded.resize(PROFILE_PAYMENT_ADDRESS_PADDED_LENGTH, 0);
+        Ok(self.encrypt(randomness, padded))
+    }
+
+    /// Decrypts a payment address and checks that it was signed by `identity_key`.
+    pub fn decrypt_payment_address(
+        &self,
+        encrypted: &[u8],
+        identity_key: &PublicKey,
+    ) -> Result<MobileCoinAddress, ProfileCipherError> {
+        let padded = self.decrypt(encrypted)?;
+        let (encoded_len, rest) = padded
+            .split_first_chunk::<PAYMENT_ADDRESS_LENGTH_PREFIX_LEN>()
+            .ok_or(ProfileCipherError::InvalidEncoding)?;
+        let encoded = usize::try_from(u32::from_le_bytes(*encoded_len))
+            .ok()
+            .and_then(|len| rest.get(..len))
+            .ok_or(ProfileCipherError::InvalidEncoding)?;
+        let address = MobileCoinAddress::decode(encoded)?;
+        if !address.verify(identity_key) {
+            return Err(ProfileCipherError::InvalidSignature);
+        }
+        Ok(address)
+    }
+
+    fn encrypt_padded(
+        &self,
+        randomness: RandomnessBytes,
+        mut plaintext: Vec<u8>,
+        padded_lengths: &[usize],
+    ) -> Result<Vec<u8>, ProfileCipherError> {
+        let padded_len = padded_lengths
+            .iter()
+            .copied()
+            .find(|len| *len >= plaintext.len())
+            .ok_or(ProfileCipherError::InputTooLong)?;
+        plaintext.resize(padded_len, 0);
+        Ok(self.encrypt(randomness, plaintext))
+    }
+
+    fn decrypt_padded(&self, encrypted: &[u8]) -> Result<Vec<u8>, ProfileCipherError> {
+        let mut plaintext = self.decrypt(encrypted)?;
+        let len = plaintext
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |last| last + 1);
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+
+    fn encrypt(&self, randomness: RandomnessBytes, mut buf: Vec<u8>) -> Vec<u8> {
+        let mut sho = Sho::new(
+            b"Signal_ZKGroup_20261018_Random_ProfileCipher_Encrypt",
+            &randomness,
+        );
+        let nonce = sho.squeeze_as_array::<AESGCM_NONCE_LEN>();
+        let mut gcm =
+            Aes256GcmEncryption::new(&self.key, &nonce, &[]).expect("valid key and nonce sizes");
+        gcm.encrypt(&mut buf);
+
+        let mut encrypted = Vec::with_capacity(AESGCM_NONCE_LEN + buf.len() + AESGCM_TAG_LEN);
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&buf);
+        encrypted.extend_from_slice(&gcm.compute_tag());
+        encrypted
+    }
+
+    fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, ProfileCipherError> {
+        if encrypted.len() < AESGCM_NONCE_LEN + AESGCM_TAG_LEN {
+            return Err(ProfileCipherError::DecryptionFailure);
+        }
+        let (nonce, rest) = encrypted.split_at(AESGCM_NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - AESGCM_TAG_LEN);
+
+        let mut gcm =
+            Aes256GcmDecryption::new(&self.key, nonce, &[]).expect("valid key and nonce sizes");
+        let mut buf = ciphertext.to_vec();
+        gcm.decrypt(&mut buf);
+        gcm.verify_tag(tag)
+            .map_err(|_| ProfileCipherError::DecryptionFailure)?;
+        Ok(buf)
+    }
+}
+
+/// The payment address messages from the service's profile protos.
+///
+/// ```protobuf
+/// message PaymentAddress {
+///   oneof Address {
+///     MobileCoinAddress mobileCoinAddress = 1;
+///   }
+///   message MobileCoinAddress {
+///     optional bytes address = 1;
+///     optional bytes signature = 2;
+///   }
+/// }
+/// ```
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PaymentAddress {
+        #[prost(oneof = "payment_address::Address", tags = "1")]
+        pub address: Option<payment_address::Address>,
+    }
+
+    pub mod payment_address {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Address {
+            #[prost(message, tag = "1")]
+            MobileCoinAddress(super::MobileCoinAddress),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MobileCoinAddress {
+        #[prost(bytes = "vec", optional, tag = "1")]
+        pub address: Option<Vec<u8>>,
+        #[prost(bytes = "vec", optional, tag = "2")]
+        pub signature: Option<Vec<u8>>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> ProfileCipher {
+        ProfileCipher::new(ProfileKey::create(TEST_ARRAY_32))
+    }
+
+    #[test]
+    fn name_round_trip() {
+        let cipher = cipher();
+        for name in [
+            ProfileName {
+                given_name: "Alice".to_owned(),
+                family_name: None,
+            },
+            ProfileName {
+                given_name: "Alice".to_owned(),
+                family_name: Some("Liddell".to_owned()),
+            },
+        ] {
+            let encrypted = cipher
+                .encrypt_name(TEST_ARRAY_32_1, &name)
+                .expect("short enough");
+            assert_eq!(
+                encrypted.len(),
+                AESGCM_NONCE_LEN + PROFILE_NAME_PADDED_LENGTHS[0] + AESGCM_TAG_LEN
+            );
+            assert_eq!(cipher.decrypt_name(&encrypted), Ok(name));
+        }
+
+        let long_name = ProfileName {
+            given_name: "A".repeat(100),
+            family_name: None,
+        };
+        let encrypted = cipher
+            .encrypt_name(TEST_ARRAY_32_1, &long_name)
+            .expect("short enough");
+        assert_eq!(
+            encrypted.len(),
+            AESGCM_NONCE_LEN + PROFILE_NAME_PADDED_LENGTHS[1] + AESGCM_TAG_LEN
+        );
+
+        let too_long_name = ProfileName {
+            given_name: "A".repeat(300),
+            family_name: None,
+        };
+        assert_eq!(
+            cipher.encrypt_name(TEST_ARRAY_32_1, &too_long_name),
+            Err(ProfileCipherError::InputTooLong)
+        );
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let encrypted = cipher()
+            .encrypt_about(TEST_ARRAY_32_1, "hello")
+            .expect("short enough");
+        let other = ProfileCipher::new(ProfileKey::create(TEST_ARRAY_32_2));
+        assert_eq!(
+            other.decrypt_about(&encrypted),
+            Err(ProfileCipherError::DecryptionFailure)
+        );
+        assert_eq!(
+            cipher().decrypt_about(&encrypted[..AESGCM_NONCE_LEN]),
+            Err(ProfileCipherError::DecryptionFailure)
+        );
+    }
+
+    #[test]
+    fn avatar_padding() {
+        // Computed as max(541, floor(1.05^ceil(ln(n) / ln(1.05)))) with Python's math module.
+        for (len, padded) in [
+            (0, 541),
+            (1, 541),
+            (541, 541),
+            (542, 568),
+            (1000, 1020),
+            (12345, 12903),
+            (100000, 100155),
+            (1 << 20, 1093831),
+            (10_000_000, 10319484),
+        ] {
+            assert_eq!(avatar_padded_length(len), padded, "{len}");
+        }
+
+        let avatar = [0xa5; 1000];
+        let encrypted = cipher().encrypt_avatar(TEST_ARRAY_32_1, &avatar);
+        let decrypted = cipher().decrypt_avatar(&encrypted).expect("valid");
+        assert_eq!(decrypted.len(), avatar_padded_length(avatar.len()));
+        assert_eq!(&decrypted[..avatar.len()], avatar);
+    }
+
+    // These ciphertexts weren't captured from a shipping client. They were produced independently
+    // of this implementation, using the AESGCM class from Python's `cryptography` package with the
+    // key TEST_ARRAY_32 and the nonce a0a1...ab, following the client format: the plaintext padded
+    // with zeros to the first bucket, encrypted as `nonce || ciphertext || tag`.
+    #[test]
+    fn decrypts_known_ciphertexts() {
+        let cipher = cipher();
+        assert_eq!(
+            cipher.decrypt_name(&hex::decode(
+                "a0a1a2a3a4a5a6a7a8a9aaaba774154e20cb4ed60601e2bf6b7ac0de70ac591092b7426c9c0e26867fab7501d27647ffaf22533d5f9c04c8097a83f9471b464862373f7abcc40501ed20a16f48fcc1e7f7"
+            ).expect("valid hex")),
+            Ok(ProfileName {
+                given_name: "Alice".to_owned(),
+                family_name: Some("Liddell".to_owned()),
+            })
+        );
+        assert_eq!(
+            cipher
+                .decrypt_about(&hex::decode(
+                    "a0a1a2a3a4a5a6a7a8a9aaaba2770b4365bf6ada4217e6b16513b4fe18c33575b247ddfc1b0e26867fab7501d27647ffaf22533d5f9c04c8097a83f9471b464862d01a7e415e0b5ea47085b0b4bc856f30a5e4e0f4e2ff188fceccbacd0baba4c5c1997aee753e9ab19a4d948f842bab03ae8f9b9edb06508588ed28245061aede359b3c2fcfdea4b455f24d6db3acb4d1d21a4f90a8fa642282cbff"
+                ).expect("valid hex"))
+                .as_deref(),
+            Ok("Down the rabbit hole \u{1f407}")
+        );
+        assert_eq!(
+            cipher
+                .decrypt_about_emoji(&hex::decode(
+                    "a0a1a2a3a4a5a6a7a8a9aaab1687ecaa45cb02bf626587d3077ac0de70ac591092b7426c9c0e26867fab7501756a9aabc5cf3fbb5426d13591e6af76"
+                ).expect("valid hex"))
+                .as_deref(),
+            Ok("\u{1f407}")
+        );
+    }
+
+    #[test]
+    fn payment_address_encoding() {
+        // Hand-assembled from the PaymentAddress definition: field 1 (the MobileCoinAddress)
+        // containing field 1 (address) and field 2 (signature).
+        let encoded = hex::decode("0a0b0a045a5a5a5a1203a5a5a5").expect("valid hex");
+        let address = MobileCoinAddress {
+            address: vec![0x5a; 4],
+            signature: vec![0xa5; 3],
+        };
+        assert_eq!(address.encode(), encoded);
+        assert_eq!(MobileCoinAddress::decode(&encoded), Ok(address));
+        assert_eq!(
+            MobileCoinAddress::decode(&[]),
+            Err(ProfileCipherError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn payment_address_round_trip() {
+        let mut rng = rand::rng();
+        let identity_key = libsignal_core::curve::KeyPair::generate(&mut rng);
+        let address = MobileCoinAddress::sign(vec![0x5a; 300], &identity_key.private_key, &mut rng);
+
+        let encrypted = cipher()
+            .encrypt_payment_address(TEST_ARRAY_32_1, &address)
+            .expect("short enough");
+        assert_eq!(
+            encrypted.len(),
+            AESGCM_NONCE_LEN + PROFILE_PAYMENT_ADDRESS_PADDED_LENGTH + AESGCM_TAG_LEN
+        );
+        assert_eq!(
+            cipher().decrypt_payment_address(&encrypted, &identity_key.public_key),
+            Ok(address)
+        );
+
+        let other_key = libsignal_core::curve::KeyPair::generate(&mut rng);
+        assert_eq!(
+            cipher().decrypt_payment_address(&encrypted, &other_key.public_key),
+            Err(ProfileCipherError::InvalidSignature)
+        );
+    }
+}
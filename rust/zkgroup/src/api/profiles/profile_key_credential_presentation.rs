@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize, Serializer};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::constants::*;
 use crate::common::errors::*;
 use crate::common::serialization::VersionByte;
@@ -178,3 +179,9 @@ impl Serialize for AnyProfileKeyCredentialPresentation {
         }
     }
 }
+
+impl Describe for ExpiringProfileKeyCredentialPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.credential_expiration_time);
+    }
+}
@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::crypto;
 
@@ -16,3 +17,7 @@ pub struct ProfileKeyCredentialRequest {
     pub(crate) ciphertext: crypto::profile_key_credential_request::Ciphertext,
     pub(crate) proof: crypto::proofs::ProfileKeyCredentialRequestProof,
 }
+
+impl Describe for ProfileKeyCredentialRequest {
+    fn describe(&self, _description: &mut Description) {}
+}
@@ -0,0 +1,11 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+mod token;
+
+pub use token::{
+    RateLimitToken, RateLimitTokenNullifier, RateLimitTokenPresentation, RateLimitTokenRequest,
+    RateLimitTokenRequestContext, RateLimitTokenResponse,
+};
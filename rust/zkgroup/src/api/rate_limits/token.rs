@@ -0,0 +1,402 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Provides RateLimitToken and related types.
+//!
+//! RateLimitToken is a MAC over:
+//! - a serial number (chosen randomly by the client, blinded at issuance, revealed for verification)
+//! - an expiration timestamp, truncated to day granularity (chosen by the issuing server, passed publicly to the verifying server)
+//!
+//! Tokens are issued in batches, one per blinded serial in the request. Because serials are hidden
+//! from the issuing server, a presented token can't be linked back to the request (or account) it
+//! was issued to. Each presentation reveals its serial as a [`RateLimitTokenNullifier`], which the
+//! verifying server must remember until the token expires in order to reject a second use.
+
+use curve25519_dalek_signal::ristretto::RistrettoPoint;
+use partial_default::PartialDefault;
+use poksho::ShoApi;
+use poksho::shoapi::ShoApiExt as _;
+use serde::{Deserialize, Serialize};
+
+use crate::ZkGroupVerificationFailure;
+use crate::api::describe::{Describe, Description};
+use crate::common::serialization::ReservedByte;
+use crate::common::sho::Sho;
+use crate::common::simple_types::*;
+use crate::generic_server_params::{GenericServerPublicParams, GenericServerSecretParams};
+
+const SERIAL_LEN: usize = 16;
+
+/// Identifies a single presented token, to detect reuse.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialDefault)]
+pub struct RateLimitTokenNullifier([u8; SERIAL_LEN]);
+
+impl RateLimitTokenNullifier {
+    pub fn as_bytes(&self) -> &[u8; SERIAL_LEN] {
+        &self.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct SerialPoint(RistrettoPoint);
+
+impl SerialPoint {
+    fn new(serial: &RateLimitTokenNullifier) -> Self {
+        Self(Sho::new(b"20261018_Signal_RateLimitTokenSerial", &serial.0).get_point())
+    }
+}
+
+impl zkcredential::attributes::RevealedAttribute for SerialPoint {
+    fn as_point(&self) -> RistrettoPoint {
+        self.0
+    }
+}
+
+const CREDENTIAL_LABEL: &[u8] = b"20261018_Signal_RateLimitToken";
+
+#[derive(Clone, Serialize, Deserialize, PartialDefault)]
+pub struct RateLimitTokenRequestContext {
+    reserved: ReservedByte,
+    serials: Vec<RateLimitTokenNullifier>,
+    blinded_serials: Vec<zkcredential::issuance::blind::BlindedPoint>,
+    key_pair: zkcredential::issuance::blind::BlindingKeyPair,
+}
+
+impl RateLimitTokenRequestContext {
+    /// Prepares a request for `count` tokens.
+    pub fn new(count: usize, randomness: RandomnessBytes) -> Self {
+        let mut sho = poksho::ShoHmacSha256::new(b"20261018_Signal_RateLimitTokenRequest");
+        sho.absorb_and_ratchet(&randomness);
+
+        let key_pair = zkcredential::issuance::blind::BlindingKeyPair::generate(&mut sho);
+
+        let serials: Vec<_> = (0..count)
+            .map(|_| RateLimitTokenNullifier(sho.squeeze_and_ratchet_as_array()))
+            .collect();
+        let blinded_serials = serials
+            .iter()
+            .map(|serial| key_pair.blind(&SerialPoint::new(serial), &mut sho).into())
+            .collect();
+
+        Self {
+            reserved: Default::default(),
+            serials,
+            blinded_serials,
+            key_pair,
+        }
+    }
+
+    pub fn get_request(&self) -> RateLimitTokenRequest {
+        RateLimitTokenRequest {
+            reserved: Default::default(),
+            blinded_serials: self.blinded_serials.clone(),
+            public_key: *self.key_pair.public_key(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialDefault)]
+pub struct RateLimitTokenRequest {
+    reserved: ReservedByte,
+    blinded_serials: Vec<zkcredential::issuance::blind::BlindedPoint>,
+    public_key: zkcredential::issuance::blind::BlindingPublicKey,
+}
+
+impl RateLimitTokenRequest {
+    /// The number of tokens requested.
+    ///
+    /// The issuing server is responsible for deciding how many tokens an account may have.
+    pub fn len(&self) -> usize {
+        self.blinded_serials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blinded_serials.is_empty()
+    }
+
+    pub fn issue(
+        &self,
+        expiration: Timestamp,
+        params: &GenericServerSecretParams,
+        randomness: RandomnessBytes,
+    ) -> RateLimitTokenResponse {
+        let mut sho = Sho::new(b"20261018_Signal_RateLimitTokenIssue", &randomness);
+        RateLimitTokenResponse {
+            reserved: Default::default(),
+            expiration,
+            blinded_credentials: self
+                .blinded_serials
+                .iter()
+                .map(|blinded_serial| {
+                    zkcredential::issuance::IssuanceProofBuilder::new(CREDENTIAL_LABEL)
+                        .add_public_attribute(&expiration)
+                        .add_blinded_revealed_attribute(blinded_serial)
+                        .issue(
+                            &params.credential_key,
+                            &self.public_key,
+                            sho.squeeze_as_array(),
+                        )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialDefault)]
+pub struct RateLimitTokenResponse {
+    reserved: ReservedByte,
+    expiration: Timestamp,
+    blinded_credentials: Vec<zkcredential::issuance::blind::BlindedIssuanceProof>,
+}
+
+impl RateLimitTokenRequestContext {
+    pub fn receive(
+        self,
+        response: RateLimitTokenResponse,
+        params: &GenericServerPublicParams,
+        expected_expiration: Timestamp,
+    ) -> Result<Vec<RateLimitToken>, ZkGroupVerificationFailure> {
+        if response.expiration != expected_expiration
+            || !response.expiration.is_day_aligned()
+            || response.blinded_credentials.len() != self.serials.len()
+        {
+            return Err(ZkGroupVerificationFailure);
+        }
+
+        self.serials
+            .into_iter()
+            .zip(self.blinded_serials)
+            .zip(response.blinded_credentials)
+            .map(|((serial, blinded_serial), blinded_credential)| {
+                Ok(RateLimitToken {
+                    reserved: Default::default(),
+                    expiration: response.expiration,
+                    credential: zkcredential::issuance::IssuanceProofBuilder::new(CREDENTIAL_LABEL)
+                        .add_public_attribute(&response.expiration)
+                        .add_blinded_revealed_attribute(&blinded_serial)
+                        .verify(&params.credential_key, &self.key_pair, blinded_credential)
+                        .map_err(|_| ZkGroupVerificationFailure)?,
+                    serial,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialDefault)]
+pub struct RateLimitToken {
+    reserved: ReservedByte,
+    expiration: Timestamp,
+    credential: zkcredential::credentials::Credential,
+    serial: RateLimitTokenNullifier,
+}
+
+impl RateLimitToken {
+    pub fn present(
+        &self,
+        server_params: &GenericServerPublicParams,
+        randomness: RandomnessBytes,
+    ) -> RateLimitTokenPresentation {
+        RateLimitTokenPresentation {
+            version: Default::default(),
+            expiration: self.expiration,
+            serial: self.serial,
+            proof: zkcredential::presentation::PresentationProofBuilder::new(CREDENTIAL_LABEL)
+                .add_revealed_attribute(&SerialPoint::new(&self.serial))
+                .present(&server_params.credential_key, &self.credential, randomness),
+        }
+    }
+
+    pub fn expiration(&self) -> Timestamp {
+        self.expiration
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialDefault)]
+pub struct RateLimitTokenPresentation {
+    version: ReservedByte,
+    expiration: Timestamp,
+    serial: RateLimitTokenNullifier,
+    proof: zkcredential::presentation::PresentationProof,
+}
+
+impl RateLimitTokenPresentation {
+    /// Checks that the token is valid and unexpired.
+    ///
+    /// This does not check for reuse; the caller must also check that [`Self::nullifier`] hasn't
+    /// been seen before, and remember it until [`Self::expiration`].
+    pub fn verify(
+        &self,
+        current_time: Timestamp,
+        server_params: &GenericServerSecretParams,
+    ) -> Result<(), ZkGroupVerificationFailure> {
+        if self.expiration <= current_time {
+            return Err(ZkGroupVerificationFailure);
+        }
+
+        zkcredential::presentation::PresentationProofVerifier::new(CREDENTIAL_LABEL)
+            .add_public_attribute(&self.expiration)
+            .add_revealed_attribute(&SerialPoint::new(&self.serial))
+            .verify(&server_params.credential_key, &self.proof)
+            .map_err(|_| ZkGroupVerificationFailure)
+    }
+
+    pub fn nullifier(&self) -> RateLimitTokenNullifier {
+        self.serial
+    }
+
+    pub fn expiration(&self) -> Timestamp {
+        self.expiration
+    }
+}
+
+impl Describe for RateLimitTokenRequest {
+    fn describe(&self, description: &mut Description) {
+        description.count = Some(self.blinded_serials.len());
+    }
+}
+
+impl Describe for RateLimitTokenResponse {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.expiration);
+        description.count = Some(self.blinded_credentials.len());
+    }
+}
+
+impl Describe for RateLimitToken {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.expiration);
+        description.redact("nullifier");
+    }
+}
+
+impl Describe for RateLimitTokenPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.expiration);
+        description.reveal("nullifier", self.serial.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{RANDOMNESS_LEN, SECONDS_PER_DAY};
+
+    const DAY_ALIGNED_TIMESTAMP: Timestamp = Timestamp::from_epoch_seconds(1681344000); // 2023-04-13 00:00:00 UTC
+    const SERVER_SECRET_RAND: RandomnessBytes = [0xA0; RANDOMNESS_LEN];
+    const REQUEST_RAND: RandomnessBytes = [0xA1; RANDOMNESS_LEN];
+    const ISSUE_RAND: RandomnessBytes = [0xA2; RANDOMNESS_LEN];
+    const PRESENT_RAND: RandomnessBytes = [0xA3; RANDOMNESS_LEN];
+
+    fn server_secret_params() -> GenericServerSecretParams {
+        GenericServerSecretParams::generate(SERVER_SECRET_RAND)
+    }
+
+    fn generate_tokens(count: usize) -> Vec<RateLimitToken> {
+        let context = RateLimitTokenRequestContext::new(count, REQUEST_RAND);
+        let request = context.get_request();
+        assert_eq!(request.len(), count);
+
+        let response = request.issue(DAY_ALIGNED_TIMESTAMP, &server_secret_params(), ISSUE_RAND);
+        context
+            .receive(
+                response,
+                &server_secret_params().get_public_params(),
+                DAY_ALIGNED_TIMESTAMP,
+            )
+            .expect("tokens should be valid")
+    }
+
+    #[test]
+    fn test_tokens_have_distinct_nullifiers() {
+        let server_public_params = server_secret_params().get_public_params();
+        let tokens = generate_tokens(3);
+
+        let mut seen = HashSet::new();
+        for token in &tokens {
+            let presentation = token.present(&server_public_params, PRESENT_RAND);
+            presentation
+                .verify(
+                    DAY_ALIGNED_TIMESTAMP.sub_seconds(1),
+                    &server_secret_params(),
+                )
+                .expect("presentation should be valid");
+            assert!(seen.insert(presentation.nullifier()));
+        }
+
+        // Presenting the same token again produces the same nullifier.
+        let again = tokens[0].present(&server_public_params, [0xA4; RANDOMNESS_LEN]);
+        assert!(seen.contains(&again.nullifier()));
+    }
+
+    #[test]
+    fn test_server_verify_expiration() {
+        let tokens = generate_tokens(1);
+        let presentation =
+            tokens[0].present(&server_secret_params().get_public_params(), PRESENT_RAND);
+
+        presentation
+            .verify(
+                DAY_ALIGNED_TIMESTAMP.sub_seconds(SECONDS_PER_DAY),
+                &server_secret_params(),
+            )
+            .expect("presentation should be valid");
+        presentation
+            .verify(DAY_ALIGNED_TIMESTAMP, &server_secret_params())
+            .expect_err("token should not be valid once expired");
+    }
+
+    #[test]
+    fn test_server_verify_wrong_serial() {
+        let tokens = generate_tokens(2);
+        let server_public_params = server_secret_params().get_public_params();
+        let valid_presentation = tokens[0].present(&server_public_params, PRESENT_RAND);
+        let invalid_presentation = RateLimitTokenPresentation {
+            serial: tokens[1]
+                .present(&server_public_params, PRESENT_RAND)
+                .serial,
+            ..valid_presentation
+        };
+        invalid_presentation
+            .verify(
+                DAY_ALIGNED_TIMESTAMP.sub_seconds(1),
+                &server_secret_params(),
+            )
+            .expect_err("token should not be valid with a different serial");
+    }
+
+    #[test]
+    fn test_client_rejects_wrong_expiration_or_count() {
+        let context = RateLimitTokenRequestContext::new(2, REQUEST_RAND);
+        let response =
+            context
+                .get_request()
+                .issue(DAY_ALIGNED_TIMESTAMP, &server_secret_params(), ISSUE_RAND);
+        let server_public_params = server_secret_params().get_public_params();
+
+        assert!(
+            context
+                .clone()
+                .receive(
+                    response.clone(),
+                    &server_public_params,
+                    DAY_ALIGNED_TIMESTAMP.add_seconds(SECONDS_PER_DAY),
+                )
+                .is_err()
+        );
+
+        let truncated = RateLimitTokenResponse {
+            blinded_credentials: response.blinded_credentials[..1].to_vec(),
+            ..response
+        };
+        assert!(
+            context
+                .receive(truncated, &server_public_params, DAY_ALIGNED_TIMESTAMP)
+                .is_err()
+        );
+    }
+}
@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::simple_types::*;
 use crate::crypto;
@@ -28,3 +29,11 @@ impl ReceiptCredential {
         self.receipt_level
     }
 }
+
+impl Describe for ReceiptCredential {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.receipt_expiration_time);
+        description.receipt_level = Some(self.receipt_level);
+        description.redact("receipt_serial");
+    }
+}
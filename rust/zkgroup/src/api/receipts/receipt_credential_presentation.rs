@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::crypto::receipt_struct::ReceiptStruct;
 use crate::{ReceiptLevel, ReceiptSerialBytes, Timestamp, crypto};
@@ -43,3 +44,11 @@ impl ReceiptCredentialPresentation {
         self.receipt_serial_bytes
     }
 }
+
+impl Describe for ReceiptCredentialPresentation {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.receipt_expiration_time);
+        description.receipt_level = Some(self.receipt_level);
+        description.reveal("receipt_serial", self.receipt_serial_bytes);
+    }
+}
@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::crypto;
 
@@ -25,3 +26,7 @@ pub struct ReceiptCredentialRequest {
     // wants to waste everybody's time by getting the server to issue a credential that it can't
     // use, so be it.)
 }
+
+impl Describe for ReceiptCredentialRequest {
+    fn describe(&self, _description: &mut Description) {}
+}
@@ -6,6 +6,7 @@
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
 
+use crate::api::describe::{Describe, Description};
 use crate::common::serialization::ReservedByte;
 use crate::common::simple_types::*;
 use crate::crypto;
@@ -18,3 +19,10 @@ pub struct ReceiptCredentialResponse {
     pub(crate) blinded_credential: crypto::credentials::BlindedReceiptCredential,
     pub(crate) proof: crypto::proofs::ReceiptCredentialIssuanceProof,
 }
+
+impl Describe for ReceiptCredentialResponse {
+    fn describe(&self, description: &mut Description) {
+        description.expiration = Some(self.receipt_expiration_time);
+        description.receipt_level = Some(self.receipt_level);
+    }
+}
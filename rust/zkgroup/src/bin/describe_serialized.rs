@@ -0,0 +1,44 @@
+//
+// Copyright 2026 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Prints the public details of a base64-encoded zkgroup value as JSON.
+//!
+//! Usage: `describe_serialized [KIND] < value.b64`
+//!
+//! If KIND is omitted, every kind the value can be decoded as is printed.
+
+use std::io::Read;
+
+use base64::prelude::{BASE64_STANDARD, Engine};
+use zkgroup::describe::{DescribedKind, describe};
+
+fn main() {
+    let kinds = match std::env::args().nth(1) {
+        Some(kind) => vec![kind.parse().unwrap_or_else(|_| {
+            let names: Vec<_> = DescribedKind::ALL.iter().map(|kind| kind.name()).collect();
+            panic!(
+                "unknown kind {kind:?}; expected one of {}",
+                names.join(", ")
+            )
+        })],
+        None => DescribedKind::ALL.to_vec(),
+    };
+
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let bytes = BASE64_STANDARD.decode(input.trim()).unwrap();
+
+    let descriptions: Vec<_> = kinds
+        .into_iter()
+        .filter_map(|kind| describe(kind, &bytes).ok())
+        .collect();
+    if descriptions.is_empty() {
+        eprintln!("could not decode as any requested kind");
+        std::process::exit(1);
+    }
+    for description in descriptions {
+        println!("{}", serde_json::to_string_pretty(&description).unwrap());
+    }
+}
@@ -59,3 +59,190 @@ fn test_backup_auth_request_response() {
         .verify(redemption_time, &server_secret_params)
         .expect("presentation should be valid");
 }
+
+#[test]
+fn test_describe_backup_presentation() {
+    use zkgroup::describe::{DescribedKind, describe};
+
+    let backup_key = libsignal_account_keys::BackupKey([0x46u8; 32]);
+    let aci: libsignal_core::Aci = uuid::uuid!("c0fc16e4-bae5-4343-9f0d-e7ecf4251343").into();
+    let request_context =
+        zkgroup::backups::BackupAuthCredentialRequestContext::new(&backup_key, aci);
+    let server_secret_params = zkgroup::generic_server_params::GenericServerSecretParams::generate(
+        [0x43u8; RANDOMNESS_LEN],
+    );
+    let server_public_params = server_secret_params.get_public_params();
+    let response = request_context.get_request().issue(
+        DAY_ALIGNED_TIMESTAMP,
+        zkgroup::backups::BackupLevel::Paid,
+        zkgroup::backups::BackupCredentialType::Media,
+        &server_secret_params,
+        [0x44u8; RANDOMNESS_LEN],
+    );
+    let credential = request_context
+        .receive(response, &server_public_params, DAY_ALIGNED_TIMESTAMP)
+        .expect("credential should be valid");
+    let presentation = credential.present(&server_public_params, [0x45u8; RANDOMNESS_LEN]);
+
+    let description = describe(
+        DescribedKind::BackupAuthCredentialPresentation,
+        &zkgroup::serialize(&presentation),
+    )
+    .expect("valid presentation");
+    let json = serde_json::to_value(&description).expect("can serialize");
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "kind": "BackupAuthCredentialPresentation",
+            "version": 0,
+            "len": description.len,
+            "redemption_time": DAY_ALIGNED_TIMESTAMP.epoch_seconds(),
+            "backup_level": "Paid",
+            "credential_type": "Media",
+            "revealed_attributes": {
+                "backup_id": hex::encode(presentation.backup_id().0),
+            },
+        })
+    );
+}
+
+#[test]
+fn test_backup_credential_wallet() {
+    use zkgroup::SECONDS_PER_DAY;
+    use zkgroup::backups::{
+        BackupAuthCredentialRequestContext, BackupAuthCredentialWallet,
+        BackupAuthCredentialWalletError, BackupCredentialType, BackupLevel, BackupLevelChange,
+    };
+
+    let backup_key = libsignal_account_keys::BackupKey([0x46u8; 32]);
+    let aci: libsignal_core::Aci = uuid::uuid!("c0fc16e4-bae5-4343-9f0d-e7ecf4251343").into();
+    let context = BackupAuthCredentialRequestContext::new(&backup_key, aci);
+    let request = context.get_request();
+    let server_secret_params = zkgroup::generic_server_params::GenericServerSecretParams::generate(
+        [0x43u8; RANDOMNESS_LEN],
+    );
+    let server_public_params = server_secret_params.get_public_params();
+
+    let day = |n: u64| DAY_ALIGNED_TIMESTAMP.add_seconds(n * SECONDS_PER_DAY);
+    let batch = |days: std::ops::Range<u64>, level, credential_type| {
+        days.map(|n| {
+            let response = request.issue(
+                day(n),
+                level,
+                credential_type,
+                &server_secret_params,
+                [0x44u8; RANDOMNESS_LEN],
+            );
+            (day(n), response)
+        })
+        .collect::<Vec<_>>()
+    };
+
+    let mut wallet = BackupAuthCredentialWallet::new();
+    let now = day(0).add_seconds(3600);
+    assert!(wallet.needs_refresh(BackupCredentialType::Messages, now));
+
+    // Ten days are issued, but only a week is kept.
+    for credential_type in [BackupCredentialType::Messages, BackupCredentialType::Media] {
+        let change = wallet
+            .receive(
+                &context,
+                batch(0..10, BackupLevel::Paid, credential_type),
+                &server_public_params,
+                now,
+            )
+            .expect("valid batch");
+        assert_eq!(change, None);
+    }
+    assert!(!wallet.needs_refresh(BackupCredentialType::Messages, now));
+    assert_eq!(
+        wallet.backup_level(BackupCredentialType::Media),
+        Some(BackupLevel::Paid)
+    );
+    assert!(wallet.get(BackupCredentialType::Media, day(6)).is_some());
+    assert!(wallet.get(BackupCredentialType::Media, day(9)).is_none());
+
+    // The presentation uses the credential for the current day, and is accepted.
+    let later = day(3).add_seconds(7200);
+    let presentation = wallet
+        .present(
+            BackupCredentialType::Media,
+            later,
+            &server_public_params,
+            [0x45u8; RANDOMNESS_LEN],
+        )
+        .expect("has a credential");
+    assert_eq!(presentation.credential_type(), BackupCredentialType::Media);
+    presentation
+        .verify(later, &server_secret_params)
+        .expect("presentation should be valid");
+    assert_eq!(
+        wallet
+            .get(BackupCredentialType::Media, later)
+            .expect("has a credential")
+            .redemption_time(),
+        day(3)
+    );
+
+    // A mixed batch is rejected without changing anything.
+    let mut mixed = batch(1..3, BackupLevel::Free, BackupCredentialType::Messages);
+    mixed.extend(batch(
+        3..4,
+        BackupLevel::Paid,
+        BackupCredentialType::Messages,
+    ));
+    assert_eq!(
+        wallet
+            .receive(&context, mixed, &server_public_params, later)
+            .map(|_| ()),
+        Err(BackupAuthCredentialWalletError::InconsistentBatch)
+    );
+
+    // The subscription lapses; the paid credentials are replaced with free ones.
+    let change = wallet
+        .receive(
+            &context,
+            batch(3..10, BackupLevel::Free, BackupCredentialType::Messages),
+            &server_public_params,
+            later,
+        )
+        .expect("valid batch");
+    assert_eq!(
+        change,
+        Some(BackupLevelChange {
+            credential_type: BackupCredentialType::Messages,
+            previous: BackupLevel::Paid,
+            current: BackupLevel::Free,
+        })
+    );
+    assert_eq!(
+        wallet
+            .get(BackupCredentialType::Messages, later)
+            .expect("has a credential")
+            .backup_level(),
+        BackupLevel::Free
+    );
+    assert_eq!(
+        wallet.backup_level(BackupCredentialType::Media),
+        Some(BackupLevel::Paid)
+    );
+
+    // Stored credentials survive serialization.
+    let wallet: BackupAuthCredentialWallet =
+        zkgroup::deserialize(&zkgroup::serialize(&wallet)).expect("valid wallet");
+    assert!(wallet.get(BackupCredentialType::Messages, later).is_some());
+
+    // Once everything has expired, there's nothing left to present.
+    let much_later = day(20);
+    assert_eq!(
+        wallet
+            .present(
+                BackupCredentialType::Messages,
+                much_later,
+                &server_public_params,
+                [0x45u8; RANDOMNESS_LEN],
+            )
+            .map(|_| ()),
+        Err(BackupAuthCredentialWalletError::NoCredential)
+    );
+}
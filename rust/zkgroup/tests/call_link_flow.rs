@@ -250,3 +250,119 @@ fn test_auth_credential_enforces_timestamp_granularity() {
         "client should reject timestamp"
     );
 }
+
+#[test]
+fn test_call_link_state() {
+    use zkgroup::call_links::{
+        CallLinkAdminPasskey, CallLinkEpoch, CallLinkRestrictions, CallLinkRootKey, CallLinkState,
+        CallLinkUrl, EncryptedCallLinkState,
+    };
+
+    // The creator makes a new link and shares it.
+    let root_key = CallLinkRootKey::generate([0x42u8; RANDOMNESS_LEN]);
+    let epoch = CallLinkEpoch::generate([0x43u8; RANDOMNESS_LEN]);
+    let admin_passkey = CallLinkAdminPasskey::generate([0x44u8; RANDOMNESS_LEN]);
+    let link = CallLinkUrl {
+        root_key,
+        epoch: Some(epoch),
+    }
+    .to_string();
+    let secret_params = root_key.derive_secret_params();
+
+    let state = CallLinkState {
+        name: "Weekly sync".to_string(),
+        restrictions: CallLinkRestrictions::AdminApproval,
+        revoked: false,
+        expiration: DAY_ALIGNED_TIMESTAMP.add_seconds(90 * SECONDS_PER_DAY),
+        epoch: Some(epoch),
+    };
+    let encrypted = secret_params.encrypt_state([0x45u8; RANDOMNESS_LEN], &state);
+    assert_eq!(encrypted.restrictions, state.restrictions);
+    assert!(
+        !encrypted
+            .encrypted_name
+            .windows(state.name.len())
+            .any(|window| window == state.name.as_bytes())
+    );
+    // Names are padded, so similar names can't be told apart by length.
+    let renamed = CallLinkState {
+        name: "Weekly sync!".to_string(),
+        ..state.clone()
+    };
+    assert_eq!(
+        secret_params
+            .encrypt_state([0x45u8; RANDOMNESS_LEN], &renamed)
+            .encrypted_name
+            .len(),
+        encrypted.encrypted_name.len()
+    );
+
+    // The server only accepts updates with the right passkey.
+    assert!(admin_passkey.matches(admin_passkey.as_bytes()));
+    assert!(
+        !CallLinkAdminPasskey::generate([0x46u8; RANDOMNESS_LEN]).matches(admin_passkey.as_bytes())
+    );
+
+    // The server stores the encrypted state as-is.
+    let stored = zkgroup::serialize(&encrypted);
+    let encrypted: EncryptedCallLinkState = zkgroup::deserialize(&stored).expect("valid state");
+
+    // Anyone with the link derives the same room and can read the state.
+    let joined: CallLinkUrl = link.parse().expect("valid link");
+    assert_eq!(joined.epoch, Some(epoch));
+    assert_eq!(joined.root_key.derive_room_id(), root_key.derive_room_id());
+    let joined_secret_params = joined.root_key.derive_secret_params();
+    assert_eq!(
+        joined_secret_params
+            .decrypt_state(&encrypted)
+            .expect("valid state"),
+        state
+    );
+    let user_id = libsignal_core::Aci::from_uuid_bytes([0x04u8; UUID_LEN]);
+    assert_eq!(
+        secret_params
+            .decrypt_uid(joined_secret_params.encrypt_uid(user_id))
+            .expect("same params"),
+        user_id
+    );
+
+    // Secret params that were saved without the root key can still read the state.
+    let saved_secret_params: zkgroup::call_links::CallLinkSecretParams =
+        zkgroup::deserialize(&zkgroup::serialize(&secret_params)).expect("valid params");
+    assert_eq!(
+        saved_secret_params
+            .decrypt_state(&encrypted)
+            .expect("valid state"),
+        state
+    );
+
+    // A different link can't.
+    let other = CallLinkRootKey::generate([0x48u8; RANDOMNESS_LEN]).derive_secret_params();
+    assert!(other.decrypt_state(&encrypted).is_err());
+
+    // Links without a name round-trip as empty.
+    let unnamed = CallLinkState {
+        name: String::new(),
+        ..state
+    };
+    let encrypted_unnamed = secret_params.encrypt_state([0x45u8; RANDOMNESS_LEN], &unnamed);
+    assert!(encrypted_unnamed.encrypted_name.is_empty());
+    assert_eq!(
+        secret_params
+            .decrypt_state(&encrypted_unnamed)
+            .expect("valid state"),
+        unnamed
+    );
+
+    // Names are length-prefixed, so trailing NULs aren't mistaken for padding.
+    let nul_terminated = CallLinkState {
+        name: "Weekly sync\0".to_string(),
+        ..unnamed
+    };
+    assert_eq!(
+        secret_params
+            .decrypt_state(&secret_params.encrypt_state([0x45u8; RANDOMNESS_LEN], &nul_terminated))
+            .expect("valid state"),
+        nul_terminated
+    );
+}
@@ -228,3 +228,88 @@ fn test_client_rejects_bad_expirations() {
         DAY_ALIGNED_TIMESTAMP.add_seconds(1000 * SECONDS_PER_DAY),
     );
 }
+
+#[test]
+fn test_endorsement_cache() {
+    let randomness1: RandomnessBytes = [0x43u8; RANDOMNESS_LEN];
+    let randomness2: RandomnessBytes = [0x44u8; RANDOMNESS_LEN];
+    let randomness3: RandomnessBytes = [0x45u8; RANDOMNESS_LEN];
+
+    let client_user_id: libsignal_core::ServiceId =
+        libsignal_core::Aci::from_uuid_bytes([0x04u8; UUID_LEN]).into();
+    let others: Vec<libsignal_core::ServiceId> = (0x10..0x14u8)
+        .map(|i| libsignal_core::Aci::from_uuid_bytes([i; UUID_LEN]).into())
+        .collect();
+    let group_members: Vec<_> = std::iter::once(client_user_id)
+        .chain(others.iter().copied())
+        .collect();
+
+    let group_secret_params = zkgroup::groups::GroupSecretParams::generate(randomness1);
+    let server_secret_params = zkgroup::ServerSecretParams::generate(randomness2);
+    let todays_key = zkgroup::groups::GroupSendDerivedKeyPair::for_expiration(
+        DAY_ALIGNED_TIMESTAMP.add_seconds(SECONDS_PER_DAY),
+        &server_secret_params,
+    );
+    let response = zkgroup::groups::GroupSendEndorsementsResponse::issue(
+        group_members
+            .iter()
+            .map(|member| group_secret_params.encrypt_service_id(*member)),
+        &todays_key,
+        randomness3,
+    );
+
+    let mut cache = zkgroup::groups::GroupSendEndorsementCache::receive(
+        response,
+        group_members.clone(),
+        client_user_id,
+        DAY_ALIGNED_TIMESTAMP,
+        &group_secret_params,
+        server_secret_params.get_public_params(),
+    )
+    .expect("issued endorsements should be valid");
+    assert!(!cache.needs_refresh(DAY_ALIGNED_TIMESTAMP));
+
+    let check = |cache: &zkgroup::groups::GroupSendEndorsementCache,
+                 recipients: &[libsignal_core::ServiceId]| {
+        cache
+            .token_for(recipients, DAY_ALIGNED_TIMESTAMP, &group_secret_params)
+            .expect("has endorsements")
+            .verify(
+                recipients.iter().copied(),
+                DAY_ALIGNED_TIMESTAMP,
+                &todays_key,
+            )
+            .expect("token should be valid for exactly these recipients");
+    };
+
+    // Small subsets are combined directly; large ones are derived from the full combination.
+    check(&cache, &others[..1]);
+    check(&cache, &others[1..]);
+    check(&cache, &others);
+
+    // Removing a member updates the full combination.
+    cache.update_members(group_members[..4].iter().copied());
+    check(&cache, &others[..3]);
+    check(&cache, &others[1..3]);
+    assert_eq!(
+        cache
+            .token_for(&others, DAY_ALIGNED_TIMESTAMP, &group_secret_params)
+            .err(),
+        Some(zkgroup::groups::GroupSendEndorsementCacheError::MissingEndorsement)
+    );
+
+    // Adding one back requires a refresh before it can be sent to.
+    cache.update_members(group_members.iter().copied());
+    assert!(cache.needs_refresh(DAY_ALIGNED_TIMESTAMP));
+    check(&cache, &others[..3]);
+
+    cache.update_members(group_members[..4].iter().copied());
+    assert!(!cache.needs_refresh(DAY_ALIGNED_TIMESTAMP));
+    assert!(cache.needs_refresh(cache.expiration().sub_seconds(60)));
+    assert_eq!(
+        cache
+            .token_for(&others[..1], cache.expiration(), &group_secret_params)
+            .err(),
+        Some(zkgroup::groups::GroupSendEndorsementCacheError::Expired)
+    );
+}
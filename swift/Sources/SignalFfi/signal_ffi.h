@@ -1791,6 +1791,8 @@ SignalFfiError *signal_connection_proxy_config_destroy(SignalMutPointerConnectio
 
 SignalFfiError *signal_connection_proxy_config_new(SignalMutPointerConnectionProxyConfig *out, const char *scheme, const char *host, int32_t port, const char *username, const char *password);
 
+SignalFfiError *signal_connection_proxy_config_new_domain_front(SignalMutPointerConnectionProxyConfig *out, const char *host, int32_t port, const char *sni, const char *http_host);
+
 SignalFfiError *signal_create_call_link_credential_check_valid_contents(SignalBorrowedBuffer params_bytes);
 
 SignalFfiError *signal_create_call_link_credential_present_deterministic(SignalOwnedBuffer *out, SignalBorrowedBuffer credential_bytes, SignalBorrowedBuffer room_id, const SignalServiceIdFixedWidthBinaryBytes *user_id, SignalBorrowedBuffer server_params_bytes, SignalBorrowedBuffer call_link_params_bytes, const uint8_t (*randomness)[SignalRANDOMNESS_LEN]);